@@ -0,0 +1,106 @@
+//! Local cache of generate responses
+//!
+//! Responses are stored under `.vibetap/cache/`, keyed by a hash of the diff
+//! hunks, the generation options (test runner included) and the project
+//! instructions. Pre-commit hooks
+//! re-run generate on the same staged diff all the time; a hit returns the
+//! earlier suggestions without calling the API.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::commands::generate::compute_hash;
+use vibetap_core::api::{GenerateRequest, GenerateResponse};
+
+/// Entries older than this are ignored and removed
+const MAX_AGE_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Entries kept on disk; the oldest go first
+const MAX_ENTRIES: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheEntry {
+    created_at: i64,
+    response: GenerateResponse,
+}
+
+fn cache_dir() -> PathBuf {
+    Path::new(".vibetap").join("cache")
+}
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Cache key for a request
+///
+/// Project instructions and the policy pack are part of the key so changing
+/// them takes effect right away. Answers from a local backend are keyed by
+/// its `backend` label as well.
+pub fn key(request: &GenerateRequest, backend: Option<&str>) -> String {
+    let mut input = serde_json::to_string(&(
+        &request.diff.hunks,
+        &request.options,
+        &request.project_instructions,
+        &request.policy_pack_id,
+    ))
+    .unwrap_or_default();
+    if let Some(backend) = backend {
+        input.push_str(backend);
+    }
+    compute_hash(&input)
+}
+
+/// The cached response for `key`, if there is a fresh one
+pub fn load(key: &str) -> Option<GenerateResponse> {
+    let path = cache_dir().join(format!("{}.json", key));
+    let content = std::fs::read_to_string(&path).ok()?;
+    let entry: CacheEntry = serde_json::from_str(&content).ok()?;
+    if now() - entry.created_at > MAX_AGE_SECS {
+        let _ = std::fs::remove_file(path);
+        return None;
+    }
+    Some(entry.response)
+}
+
+/// Store `response` under `key`, pruning old entries
+pub fn store(key: &str, response: &GenerateResponse) -> anyhow::Result<()> {
+    let dir = cache_dir();
+    std::fs::create_dir_all(&dir)?;
+
+    let entry = CacheEntry {
+        created_at: now(),
+        response: response.clone(),
+    };
+    std::fs::write(
+        dir.join(format!("{}.json", key)),
+        serde_json::to_string(&entry)?,
+    )?;
+
+    prune(&dir);
+    Ok(())
+}
+
+/// Drop the oldest entries beyond `MAX_ENTRIES`
+fn prune(dir: &Path) {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<(std::time::SystemTime, PathBuf)> = read_dir
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .collect();
+    if entries.len() <= MAX_ENTRIES {
+        return;
+    }
+
+    entries.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    for (_, path) in entries.into_iter().skip(MAX_ENTRIES) {
+        let _ = std::fs::remove_file(path);
+    }
+}
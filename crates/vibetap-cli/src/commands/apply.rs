@@ -1,14 +1,30 @@
 use clap::Args;
 use colored::Colorize;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::io::{self, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use syntect::easy::HighlightLines;
 use syntect::highlighting::{Style, ThemeSet};
 use syntect::parsing::SyntaxSet;
 use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
 
-use super::generate::{compute_hash, load_suggestions, SavedSuggestions};
+use super::generate::{
+    compute_hash, load_branch_suggestions, load_suggestions, load_suggestions_file,
+    SavedSuggestions,
+};
+use super::history;
+use super::run::detect_test_runner;
+use crate::feedback;
+use crate::lint;
+use crate::merge;
+use crate::placement;
+use crate::sandbox::{CheckOutcome, Sandbox};
+use crate::ui;
+use vibetap_core::api::{FeedbackAction, TestSuggestion};
+use vibetap_core::Config;
+use vibetap_git::FileMerge;
 
 #[derive(Args)]
 pub struct ApplyArgs {
@@ -16,23 +32,145 @@ pub struct ApplyArgs {
     #[arg()]
     selections: Vec<String>,
 
+    /// Suggestion to apply by its ID, e.g. sug_abc123 (repeatable)
+    #[arg(long = "id", value_name = "ID")]
+    ids: Vec<String>,
+
     /// Skip confirmation prompt
     #[arg(short, long)]
     yes: bool,
 
-    /// Force apply even if source files have changed
+    /// Force apply even if source files have changed, the set belongs to another branch,
+    /// a test file was changed since in a way that conflicts or has uncommitted edits, or a
+    /// suggestion fails its check
     #[arg(short, long)]
     force: bool,
+
+    /// Apply the suggestion set saved for this branch instead of the current one
+    #[arg(long, value_name = "NAME")]
+    branch: Option<String>,
+
+    /// Apply a suggestion set from this file, e.g. one saved by CI or on another machine
+    #[arg(long, value_name = "FILE", conflicts_with = "branch")]
+    from: Option<PathBuf>,
+
+    /// Skip linting suggestions in the preview
+    #[arg(long)]
+    no_lint: bool,
+
+    /// Skip the syntax/type check of each suggestion before it is written
+    #[arg(long)]
+    no_check: bool,
+
+    /// Add the new tests and imports to test files that already exist instead of overwriting them
+    #[arg(long)]
+    merge: bool,
+
+    /// Apply only these tests from the selected suggestion, numbered in file order (e.g. 1,3)
+    #[arg(long, value_name = "N", value_delimiter = ',')]
+    tests: Vec<usize>,
+
+    /// Open each suggestion in $EDITOR first and apply what you save
+    #[arg(long)]
+    edit: bool,
+
+    /// Stage the written test files, like 'git add' (also apply.stage in the project config)
+    #[arg(long, overrides_with = "no_stage")]
+    stage: bool,
+
+    /// Don't stage the written test files, even with apply.stage set
+    #[arg(long, overrides_with = "stage")]
+    no_stage: bool,
+
+    /// Commit the written test files on their own, with a message naming the suggestions
+    #[arg(long)]
+    commit: bool,
+
+    /// Run each applied test and roll it back if it doesn't compile or fails; tests run on a
+    /// copy of the project in a container, so this needs Docker
+    #[arg(long)]
+    verify: bool,
 }
 
+/// Where tests rolled back by --verify are kept
+const REJECTED_DIR: &str = ".vibetap/rejected";
+
 /// Record of an applied suggestion for revert tracking
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppliedRecord {
     pub suggestion_id: String,
     pub file_path: String,
     pub created_file: bool,
+    /// Content before applying, inline as records written before
+    /// `original_object` existed have it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub original_content: Option<String>,
+    /// Hash of the content before applying, saved under `.vibetap/objects/`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_object: Option<String>,
     pub applied_at: i64,
+    /// Model that produced the suggestion
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Confidence reported for the suggestion
+    #[serde(default)]
+    pub confidence: Option<f64>,
+    /// Generation session the suggestion came from
+    #[serde(default)]
+    pub session: Option<String>,
+    /// Commit the record was attached to as a git note
+    #[serde(default)]
+    pub noted_commit: Option<String>,
+    /// The code was edited before it was applied
+    #[serde(default)]
+    pub modified: bool,
+    /// The apply run the record came from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<String>,
+    /// How the test did the last time it ran
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification: Option<Verification>,
+}
+
+/// Outcome of the last run of an applied test, by `vibetap run` or
+/// `apply --verify`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Verification {
+    pub passed: bool,
+    pub ran_at: i64,
+    /// The start of the failure output, when the runner reported it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+}
+
+impl AppliedRecord {
+    /// The file's content before the suggestion was applied, if it existed
+    pub fn original(&self) -> io::Result<Option<String>> {
+        match (&self.original_content, &self.original_object) {
+            (Some(content), _) => Ok(Some(content.clone())),
+            (None, Some(hash)) => history::read_object(hash).map(Some),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// ID of the apply run the record came from; records from before
+    /// batches had IDs are grouped by their timestamp, as `@<applied_at>`
+    pub fn batch_id(&self) -> String {
+        self.batch
+            .clone()
+            .unwrap_or_else(|| format!("@{}", self.applied_at))
+    }
+}
+
+/// A suggestion ready to be written, with what its file held before
+struct Pending<'a> {
+    suggestion: &'a TestSuggestion,
+    content: String,
+    original: Option<String>,
+    original_object: Option<String>,
+    modified: bool,
+    /// Note shown after the file name, e.g. how it was merged
+    outcome: String,
 }
 
 /// History of applied suggestions
@@ -42,8 +180,18 @@ pub struct ApplyHistory {
 }
 
 pub async fn execute(args: ApplyArgs) -> anyhow::Result<()> {
-    // Load the last suggestions
-    let saved = load_suggestions()?;
+    // Load the suggestions for this branch (or the set asked for)
+    let mut saved = match (&args.from, &args.branch) {
+        (Some(path), _) => load_suggestions_file(path)?,
+        (None, Some(branch)) => load_branch_suggestions(branch)?,
+        (None, None) => load_suggestions()?,
+    };
+    let apply_config = Config::load()
+        .ok()
+        .and_then(|c| c.project)
+        .and_then(|p| p.apply)
+        .unwrap_or_default();
+    relocate_suggestions(&mut saved, &apply_config.test_path_templates);
     let response = &saved.response;
 
     if response.suggestions.is_empty() {
@@ -51,6 +199,37 @@ pub async fn execute(args: ApplyArgs) -> anyhow::Result<()> {
         return Ok(());
     }
 
+    // Tests generated for another branch's code rarely belong here
+    let current_branch = vibetap_git::current_branch().ok().flatten();
+    if let (Some(generated_on), Some(current)) = (saved.branch.as_deref(), current_branch.as_deref()) {
+        if generated_on != current {
+            let message = format!(
+                "These suggestions were generated on branch '{}', but '{}' is checked out.",
+                generated_on, current
+            );
+            if !args.force {
+                println!("\n{} {}", ui::icon("✗", "Error:").red(), message.red());
+                println!("Options:");
+                println!(
+                    "  {} - Apply them on the branch they were generated for",
+                    format!("git checkout {}", generated_on).cyan()
+                );
+                println!("  {} - Generate suggestions for '{}'", "vibetap generate".cyan(), current);
+                println!("  {} - Apply them here anyway", "vibetap apply --force".cyan());
+                return Ok(());
+            }
+            println!("\n{} {}", ui::icon("⚠", "Warning:").yellow(), message.yellow());
+        }
+    }
+
+    if let Some(warning) = check_head_moved(&saved) {
+        println!("\n{} {}", ui::icon("⚠", "Warning:").yellow(), warning.yellow());
+        println!(
+            "{}",
+            "Test files may land on a different branch than the code they cover.".dimmed()
+        );
+    }
+
     // Check if source files have changed since suggestions were generated
     if !args.force && !saved.source_files.is_empty() {
         let changed_files = check_file_changes(&saved);
@@ -86,15 +265,16 @@ pub async fn execute(args: ApplyArgs) -> anyhow::Result<()> {
     let max = response.suggestions.len();
 
     // Determine which suggestions to apply
-    let to_apply: Vec<usize> = if args.selections.is_empty() {
+    let to_apply: Vec<usize> = if args.selections.is_empty() && args.ids.is_empty() {
         // Interactive mode - show list and prompt
         println!("\n{}", "Available suggestions:".bold());
         for (i, suggestion) in response.suggestions.iter().enumerate() {
             println!(
-                "  {} {} ({})",
+                "  {} {} ({}, {})",
                 format!("{}.", i + 1).bold(),
                 suggestion.file_path.cyan(),
-                suggestion.category.dimmed()
+                suggestion.category.dimmed(),
+                suggestion.id.dimmed()
             );
         }
         println!();
@@ -107,7 +287,19 @@ pub async fn execute(args: ApplyArgs) -> anyhow::Result<()> {
 
         parse_selections(&[input.trim().to_string()], max)?
     } else {
-        parse_selections(&args.selections, max)?
+        let mut to_apply = parse_selections(&args.selections, max)?;
+        for id in &args.ids {
+            let Some(idx) = response.suggestions.iter().position(|s| s.id == *id) else {
+                anyhow::bail!(
+                    "No suggestion with ID '{}' in this set. Run 'vibetap generate' to see the current IDs.",
+                    id
+                );
+            };
+            if !to_apply.contains(&idx) {
+                to_apply.push(idx);
+            }
+        }
+        to_apply
     };
 
     if to_apply.is_empty() {
@@ -115,19 +307,98 @@ pub async fn execute(args: ApplyArgs) -> anyhow::Result<()> {
         return Ok(());
     }
 
-    // Show preview and confirm
+    // What became of each selected suggestion, reported at the end
+    let mut feedback_events = Vec::new();
+
+    // Picking tests by number only makes sense within one suggestion
+    let picked = if args.tests.is_empty() {
+        None
+    } else {
+        let [idx] = to_apply[..] else {
+            anyhow::bail!("--tests picks tests from one suggestion; select exactly one");
+        };
+        let suggestion = &response.suggestions[idx];
+        let code = merge::keep_tests(&suggestion.file_path, &suggestion.code, &args.tests)
+            .map_err(|e| {
+                anyhow::anyhow!("Can't pick tests from {}: {}", suggestion.file_path, e)
+            })?;
+        let names = merge::list_tests(&suggestion.file_path, &code).unwrap_or_default();
+        Some((code, names))
+    };
+
+    // The code to apply for each selection, and whether it was edited
+    let mut selected: Vec<(usize, String, bool)> = Vec::new();
     for &idx in &to_apply {
         let suggestion = &response.suggestions[idx];
-        println!("\n{}", format!("─── {} ───", suggestion.file_path).bold());
+        let code = match &picked {
+            Some((code, _)) => code.clone(),
+            None => suggestion.code.clone(),
+        };
+        if !args.edit {
+            let modified = code != suggestion.code;
+            selected.push((idx, code, modified));
+            continue;
+        }
+        let code = edit_in_editor(&code, &suggestion.file_path)?;
+        if code.trim().is_empty() {
+            println!(
+                "{} {} {}",
+                "•".dimmed(),
+                suggestion.file_path,
+                "(saved empty; skipped)".dimmed()
+            );
+            feedback_events.push(feedback::skipped(&suggestion.id, "emptied_in_editor"));
+            continue;
+        }
+        let modified = code != suggestion.code;
+        selected.push((idx, code, modified));
+    }
+    if selected.is_empty() {
+        println!("{}", "No suggestions selected.".yellow());
+        feedback::report(feedback_events).await;
+        return Ok(());
+    }
+
+    // Broken code is caught in a copy of the project, before anything is written
+    let checks = if args.no_check {
+        Vec::new()
+    } else {
+        check_selected(&response.suggestions, &selected).await
+    };
+
+    // Show preview and confirm
+    for (i, (idx, code, modified)) in selected.iter().enumerate() {
+        let suggestion = &response.suggestions[*idx];
+        if ui::is_accessible() {
+            println!("\nSuggestion for {}", suggestion.file_path);
+        } else {
+            println!("\n{}", format!("─── {} ───", suggestion.file_path).bold());
+        }
         println!("{}", suggestion.description.dimmed());
+        if let Some((_, names)) = &picked {
+            println!("{}", format!("(only: {})", names.join(", ")).dimmed());
+        }
+        if *modified && (args.edit || picked.is_none()) {
+            println!("{}", "(edited)".dimmed());
+        }
+        if let Some(CheckOutcome::Failed(reason)) = checks.get(i) {
+            println!(
+                "{} {}",
+                ui::icon("✗", "Failed:").red(),
+                format!("Doesn't compile: {}", reason).red()
+            );
+        }
+        if !args.no_lint {
+            lint::print_issues(&lint::lint(code, &suggestion.file_path).await);
+        }
         println!();
-        print_code_block(&suggestion.code, &suggestion.file_path);
+        print_code_block(code, &suggestion.file_path);
     }
 
     if !args.yes {
         print!(
             "\n{} ",
-            format!("Apply {} suggestion(s)? [y/N]:", to_apply.len()).yellow()
+            format!("Apply {} suggestion(s)? [y/N]:", selected.len()).yellow()
         );
         io::stdout().flush()?;
 
@@ -136,59 +407,401 @@ pub async fn execute(args: ApplyArgs) -> anyhow::Result<()> {
 
         if !confirm.trim().eq_ignore_ascii_case("y") {
             println!("{}", "Cancelled.".dimmed());
+            for (idx, _, _) in &selected {
+                let id = &response.suggestions[*idx].id;
+                feedback_events.push(feedback::skipped(id, "declined"));
+            }
+            feedback::report(feedback_events).await;
             return Ok(());
         }
     }
 
     // Apply the suggestions
+    // Without a runner nothing could be verified, so stop before writing anything
+    let verify_runner = if args.verify {
+        Some(detect_test_runner()?)
+    } else {
+        None
+    };
+
     let mut history = load_history()?;
+    let batch = history::new_batch_id();
     let mut applied_count = 0;
+    let mut rejected_count = 0;
+    let mut written: Vec<&TestSuggestion> = Vec::new();
 
-    for &idx in &to_apply {
+    // Work out every file's new content before writing any of them
+    let mut pending: Vec<Pending> = Vec::new();
+    for (i, (idx, code, modified)) in selected.into_iter().enumerate() {
         let suggestion = &response.suggestions[idx];
         let file_path = Path::new(&suggestion.file_path);
 
-        // Track if file existed before
-        let (created_file, original_content) = if file_path.exists() {
-            (false, Some(std::fs::read_to_string(file_path)?))
-        } else {
-            // Create parent directories if needed
-            if let Some(parent) = file_path.parent() {
-                std::fs::create_dir_all(parent)?;
+        if let Some(CheckOutcome::Failed(reason)) = checks.get(i) {
+            if !args.force {
+                println!(
+                    "  {} {} not applied: it doesn't compile ({})",
+                    ui::icon("⚠", "Warning:").yellow(),
+                    suggestion.file_path,
+                    reason
+                );
+                println!(
+                    "    {}",
+                    "Fix it with --edit, or use --force to apply it anyway.".dimmed()
+                );
+                feedback_events.push(feedback::skipped(&suggestion.id, "failed_check"));
+                continue;
             }
-            (true, None)
+        }
+
+        // A file another selection writes to is taken as that one leaves it
+        let planned = pending
+            .iter()
+            .rev()
+            .find(|p| p.suggestion.file_path == suggestion.file_path);
+        let original_content = match planned {
+            Some(p) => Some(p.content.clone()),
+            None if file_path.exists() => Some(std::fs::read_to_string(file_path)?),
+            None => None,
         };
+        // Saved before anything is written, so `vibetap revert` can always bring it back
+        let original_object = original_content
+            .as_deref()
+            .map(history::store_object)
+            .transpose()?;
+        let uncommitted = planned.is_none()
+            && original_content.is_some()
+            && vibetap_git::has_unstaged_changes(file_path).unwrap_or(false);
 
-        // Write the test file
-        std::fs::write(file_path, &suggestion.code)?;
+        // The test file as suggested, or the one there with the new tests added
+        let mut outcome = String::new();
+        let content = match original_content {
+            Some(ref existing) if args.merge => {
+                match merge::merge(&suggestion.file_path, existing, &code) {
+                    Ok(merged) if merged.added.is_empty() && merged.content == *existing => {
+                        println!(
+                            "  {} {} {}",
+                            "•".dimmed(),
+                            suggestion.file_path,
+                            "(already has these tests)".dimmed()
+                        );
+                        feedback_events.push(feedback::skipped(&suggestion.id, "already_present"));
+                        continue;
+                    }
+                    Ok(merged) => {
+                        outcome = format!(" (merged: {} test(s) added", merged.added.len());
+                        if !merged.skipped.is_empty() {
+                            outcome.push_str(&format!(", {} already present", merged.skipped.len()));
+                        }
+                        outcome.push(')');
+                        merged.content
+                    }
+                    Err(e) => {
+                        println!(
+                            "  {} {} not applied: {}",
+                            ui::icon("⚠", "Warning:").yellow(),
+                            suggestion.file_path,
+                            e
+                        );
+                        println!(
+                            "    {}",
+                            "Run without --merge to overwrite the file instead.".dimmed()
+                        );
+                        feedback_events.push(feedback::skipped(&suggestion.id, "merge_failed"));
+                        continue;
+                    }
+                }
+            }
+            // Edits made to the test file since generating are kept where they don't overlap
+            Some(ref existing) => match saved.test_file_bases.get(&suggestion.file_path) {
+                Some(base) if base != existing => {
+                    match vibetap_git::merge_file(base, existing, &code) {
+                        Ok(FileMerge::Clean(merged)) => {
+                            outcome = " (merged with changes made since generating)".to_string();
+                            merged
+                        }
+                        Ok(_) | Err(_) if args.force => {
+                            if uncommitted {
+                                outcome = " (overwrote uncommitted edits; revert restores them)"
+                                    .to_string();
+                            }
+                            code
+                        }
+                        result => {
+                            let reason = match result {
+                                Err(e) => e.to_string(),
+                                _ => "it was changed since the suggestions were generated, and the changes overlap"
+                                    .to_string(),
+                            };
+                            println!(
+                                "  {} {} not applied: {}",
+                                ui::icon("⚠", "Warning:").yellow(),
+                                suggestion.file_path,
+                                reason
+                            );
+                            print_clobbered(&suggestion.file_path, existing, &code);
+                            println!(
+                                "    {}",
+                                "Use --merge to add just the new tests, or --force to overwrite the file."
+                                    .dimmed()
+                            );
+                            feedback_events.push(feedback::skipped(&suggestion.id, "conflict"));
+                            continue;
+                        }
+                    }
+                }
+                // Nothing to merge from, so changes made since generating would be lost
+                None if !args.force
+                    && *existing != code
+                    && saved
+                        .test_file_hashes
+                        .get(&suggestion.file_path)
+                        .is_some_and(|hash| *hash != compute_hash(existing)) =>
+                {
+                    let reason = if saved.test_file_hashes[&suggestion.file_path].is_empty() {
+                        "the file already exists"
+                    } else {
+                        "it was changed since the suggestions were generated"
+                    };
+                    println!(
+                        "  {} {} not applied: {}",
+                        ui::icon("⚠", "Warning:").yellow(),
+                        suggestion.file_path,
+                        reason
+                    );
+                    print_clobbered(&suggestion.file_path, existing, &code);
+                    println!(
+                        "    {}",
+                        "Use --merge to add just the new tests, or --force to overwrite the file."
+                            .dimmed()
+                    );
+                    feedback_events.push(feedback::skipped(&suggestion.id, "conflict"));
+                    continue;
+                }
+                // Edits git doesn't have yet can't be recovered from it
+                _ if uncommitted && !args.force && *existing != code => {
+                    println!(
+                        "  {} {} not applied: it has uncommitted edits",
+                        ui::icon("⚠", "Warning:").yellow(),
+                        suggestion.file_path
+                    );
+                    print_clobbered(&suggestion.file_path, existing, &code);
+                    println!(
+                        "    {}",
+                        "Stage or commit them first, use --merge to add just the new tests, or --force to overwrite the file (revert restores it)."
+                            .dimmed()
+                    );
+                    feedback_events.push(feedback::skipped(&suggestion.id, "uncommitted_edits"));
+                    continue;
+                }
+                _ => {
+                    if uncommitted && *existing != code {
+                        outcome =
+                            " (overwrote uncommitted edits; revert restores them)".to_string();
+                    }
+                    code
+                }
+            },
+            None => code,
+        };
+
+        pending.push(Pending {
+            suggestion,
+            content,
+            original: original_content,
+            original_object,
+            modified,
+            outcome,
+        });
+    }
+
+    // All or nothing, so a failed write doesn't leave part of the batch behind
+    if let Err((failed, e)) = write_all(&pending) {
+        println!(
+            "  {} Could not write {}: {}",
+            ui::icon("✗", "Failed:").red(),
+            failed.display(),
+            e
+        );
+        for file in &pending {
+            println!(
+                "  {} {} {}",
+                "•".dimmed(),
+                file.suggestion.file_path,
+                "(not applied)".dimmed()
+            );
+        }
+        history::collect_garbage(&history)?;
+        anyhow::bail!(
+            "Nothing was applied because {} could not be written",
+            failed.display()
+        );
+    }
+
+    // Suggested tests are generated code: they run on a copy of the project,
+    // in a container, and never in the tree itself
+    let verify_sandbox = verify_runner
+        .as_ref()
+        .map(|_| Sandbox::create().map_err(|e| e.to_string()));
+
+    let mut applied: Vec<&Pending> = Vec::new();
+    for file in &pending {
+        let suggestion = file.suggestion;
+        let file_path = Path::new(&suggestion.file_path);
+        let mut outcome = file.outcome.clone();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+
+        let mut verified = None;
+        if let (Some(runner), Some(sandbox)) = (&verify_runner, &verify_sandbox) {
+            let verification = match sandbox {
+                Ok(sandbox) => sandbox.run_test(&suggestion.file_path, runner).await,
+                Err(e) => CheckOutcome::Skipped(format!("could not copy the project: {}", e)),
+            };
+            match verification {
+                CheckOutcome::Passed => {
+                    outcome.push_str(" (verified)");
+                    verified = Some(Verification {
+                        passed: true,
+                        ran_at: now,
+                        failure: None,
+                    });
+                }
+                CheckOutcome::Failed(reason) => {
+                    let kept = reject(file_path, file.original.as_deref())?;
+                    let reason = if reason.is_empty() {
+                        "the test failed".to_string()
+                    } else {
+                        reason
+                    };
+                    println!(
+                        "  {} {} rolled back: {}",
+                        ui::icon("✗", "Failed:").red(),
+                        suggestion.file_path,
+                        reason
+                    );
+                    println!(
+                        "    {}",
+                        format!("Kept a copy at {}", kept.display()).dimmed()
+                    );
+                    rejected_count += 1;
+                    feedback_events.push(feedback::skipped(&suggestion.id, "failed_verification"));
+                    continue;
+                }
+                CheckOutcome::Skipped(reason) => println!(
+                    "  {} {} could not be verified: {}",
+                    ui::icon("⚠", "Warning:").yellow(),
+                    suggestion.file_path,
+                    reason
+                ),
+            }
+        }
 
         // Record in history
         history.records.push(AppliedRecord {
             suggestion_id: suggestion.id.clone(),
             file_path: suggestion.file_path.clone(),
-            created_file,
-            original_content,
-            applied_at: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .map(|d| d.as_secs() as i64)
-                .unwrap_or(0),
+            created_file: file.original.is_none(),
+            original_content: None,
+            original_object: file.original_object.clone(),
+            applied_at: now,
+            model: Some(response.model_used.clone()),
+            confidence: Some(suggestion.confidence),
+            session: Some(saved.session_id()),
+            noted_commit: None,
+            modified: file.modified,
+            batch: Some(batch.clone()),
+            verification: verified,
         });
+        applied.push(file);
 
         println!(
-            "  {} {}",
+            "  {} {}{}",
             "✓".green(),
-            suggestion.file_path
+            suggestion.file_path,
+            outcome.dimmed()
         );
+        let action = if file.modified {
+            FeedbackAction::Edited
+        } else {
+            FeedbackAction::Applied
+        };
+        feedback_events.push(feedback::event(&suggestion.id, action));
+        written.push(suggestion);
         applied_count += 1;
     }
 
-    // Save history
-    save_history(&history)?;
+    // Save history, dropping the oldest records past the limit
+    history::truncate(
+        &mut history,
+        apply_config
+            .history_limit
+            .unwrap_or(history::DEFAULT_HISTORY_LIMIT),
+    );
+    // Files revert can't find would be stuck, so take them back out
+    if let Err(e) = save_history(&history) {
+        for file in applied.iter().rev() {
+            let _ = restore(
+                Path::new(&file.suggestion.file_path),
+                file.original.as_deref(),
+            );
+        }
+        return Err(
+            e.context("Could not save the apply history, so the test files were taken back out")
+        );
+    }
+    history::collect_garbage(&history)?;
+    feedback::report(feedback_events).await;
+
+    let paths: Vec<&Path> = written.iter().map(|s| Path::new(&s.file_path)).collect();
+    // Before staging, so what gets committed is what the formatter left
+    format_files(apply_config.format_command.as_deref(), &paths);
+
+    let stage = match (args.stage, args.no_stage) {
+        (true, _) => true,
+        (_, true) => false,
+        _ => apply_config.stage,
+    };
+    if args.commit && !written.is_empty() {
+        match vibetap_git::commit_paths(&paths, &commit_message(&written)) {
+            Ok(Some(id)) => println!(
+                "  {} Committed the test files as {}",
+                "✓".green(),
+                &id[..7.min(id.len())]
+            ),
+            Ok(None) => println!("  {} The test files are already committed", "•".dimmed()),
+            Err(e) => println!(
+                "  {} Could not commit the test files: {}",
+                ui::icon("⚠", "Warning:").yellow(),
+                e
+            ),
+        }
+    } else if stage && !written.is_empty() {
+        match vibetap_git::stage_files(&paths) {
+            Ok(()) => println!("  {} Staged {} test file(s)", "✓".green(), written.len()),
+            Err(e) => println!(
+                "  {} Could not stage the test files: {}",
+                ui::icon("⚠", "Warning:").yellow(),
+                e
+            ),
+        }
+    }
 
     println!(
         "\n{}",
         format!("Applied {} suggestion(s)!", applied_count).green().bold()
     );
+    if rejected_count > 0 {
+        println!(
+            "{}",
+            format!(
+                "Rolled back {} suggestion(s) that failed verification (see {}/)",
+                rejected_count, REJECTED_DIR
+            )
+            .yellow()
+        );
+    }
     println!("\nRun {} to execute the generated tests.", "vibetap run".cyan());
     println!(
         "Run {} to undo if needed.",
@@ -198,6 +811,331 @@ pub async fn execute(args: ApplyArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Lines of the overwrite diff shown before it is cut short
+const CLOBBER_DIFF_LINES: usize = 40;
+
+/// Show what overwriting `existing` with `code` would throw away
+fn print_clobbered(file_path: &str, existing: &str, code: &str) {
+    let Ok(diff) = vibetap_git::diff_contents(file_path, existing, code) else {
+        return;
+    };
+    // Skip the file header; the path is already on screen
+    let lines: Vec<&str> = diff
+        .lines()
+        .skip_while(|line| !line.starts_with("@@"))
+        .collect();
+    for line in lines.iter().take(CLOBBER_DIFF_LINES) {
+        if ui::is_accessible() {
+            println!("    {}", line);
+        } else if line.starts_with('-') {
+            println!("    {}", line.red());
+        } else if line.starts_with('+') {
+            println!("    {}", line.green());
+        } else if line.starts_with("@@") {
+            println!("    {}", line.dimmed());
+        } else {
+            println!("    {}", line);
+        }
+    }
+    if lines.len() > CLOBBER_DIFF_LINES {
+        println!(
+            "    {}",
+            format!("... {} more line(s)", lines.len() - CLOBBER_DIFF_LINES).dimmed()
+        );
+    }
+}
+
+/// Move suggestions to where the project's `testPathTemplates` put tests
+fn relocate_suggestions(saved: &mut SavedSuggestions, templates: &BTreeMap<String, String>) {
+    for suggestion in &mut saved.response.suggestions {
+        let Some(relocated) = placement::relocate(&suggestion.file_path, templates) else {
+            continue;
+        };
+        suggestion.code =
+            placement::rewrite_imports(&suggestion.code, &suggestion.file_path, &relocated);
+
+        // Nothing was recorded about the new path when generating; treat it
+        // as new so a file already there isn't overwritten unasked
+        saved.test_file_bases.remove(&suggestion.file_path);
+        saved.test_file_hashes.remove(&suggestion.file_path);
+        saved.test_file_hashes.insert(relocated.clone(), String::new());
+        suggestion.file_path = relocated;
+    }
+}
+
+/// Syntax/type check each selected suggestion in a sandbox copy of the project
+///
+/// Returns one outcome per selection; every check is skipped if the copy
+/// can't be made.
+async fn check_selected(
+    suggestions: &[TestSuggestion],
+    selected: &[(usize, String, bool)],
+) -> Vec<CheckOutcome> {
+    if !ui::is_accessible() {
+        println!("{}", "Checking suggestions...".dimmed());
+    }
+    let sandbox = match Sandbox::create() {
+        Ok(sandbox) => sandbox,
+        Err(e) => {
+            let reason = format!("could not copy the project: {}", e);
+            return vec![CheckOutcome::Skipped(reason); selected.len()];
+        }
+    };
+
+    let mut outcomes = Vec::with_capacity(selected.len());
+    for (idx, code, _) in selected {
+        let file_path = &suggestions[*idx].file_path;
+        let outcome = match sandbox.write_file(file_path, code) {
+            Ok(()) => sandbox.check_syntax(file_path).await,
+            Err(e) => CheckOutcome::Skipped(e.to_string()),
+        };
+        let _ = sandbox.restore_file(file_path);
+        outcomes.push(outcome);
+    }
+    outcomes
+}
+
+/// Put a test file back the way it was before applying, keeping the
+/// rejected version under [`REJECTED_DIR`]
+///
+/// The copy gets a `.rejected` suffix so test runners don't pick it up.
+fn reject(file_path: &Path, original: Option<&str>) -> io::Result<PathBuf> {
+    let relative = file_path.strip_prefix(".").unwrap_or(file_path);
+    let mut kept = Path::new(REJECTED_DIR).join(relative).into_os_string();
+    kept.push(".rejected");
+    let kept = PathBuf::from(kept);
+    if let Some(parent) = kept.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::copy(file_path, &kept)?;
+    restore(file_path, original)?;
+    Ok(kept)
+}
+
+/// Put back a file's content from before applying, or remove it if it was new
+fn restore(file_path: &Path, original: Option<&str>) -> io::Result<()> {
+    match original {
+        Some(content) => std::fs::write(file_path, content),
+        None => std::fs::remove_file(file_path),
+    }
+}
+
+/// Write every pending file or none of them
+///
+/// Each file is written to a temporary file beside it first, and they are
+/// renamed into place only once all were written. If a rename fails, the
+/// files already replaced are put back and directories created for the
+/// batch are removed again. The error names the file that failed.
+fn write_all(files: &[Pending]) -> Result<(), (PathBuf, io::Error)> {
+    let mut created_dirs: Vec<PathBuf> = Vec::new();
+    let mut temps: Vec<PathBuf> = Vec::new();
+    let cleanup = |temps: &[PathBuf], created_dirs: &mut Vec<PathBuf>| {
+        for temp in temps {
+            let _ = std::fs::remove_file(temp);
+        }
+        // Deepest first, and only while empty
+        created_dirs.sort_by_key(|d| std::cmp::Reverse(d.components().count()));
+        for dir in created_dirs.iter() {
+            let _ = std::fs::remove_dir(dir);
+        }
+    };
+
+    for (i, file) in files.iter().enumerate() {
+        let path = Path::new(&file.suggestion.file_path);
+        let written = (|| {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                created_dirs.extend(
+                    parent
+                        .ancestors()
+                        .filter(|a| !a.as_os_str().is_empty() && !a.exists())
+                        .map(Path::to_path_buf),
+                );
+                std::fs::create_dir_all(parent)?;
+            }
+            let temp = temp_path(path, i);
+            std::fs::write(&temp, &file.content)?;
+            temps.push(temp);
+            Ok(())
+        })();
+        if let Err(e) = written {
+            cleanup(&temps, &mut created_dirs);
+            return Err((path.to_path_buf(), e));
+        }
+    }
+
+    for (i, (file, temp)) in files.iter().zip(&temps).enumerate() {
+        let path = Path::new(&file.suggestion.file_path);
+        if let Err(e) = std::fs::rename(temp, path) {
+            // Later files over the same path come first, so the earliest
+            // original is what's left
+            for done in files[..i].iter().rev() {
+                let _ = restore(
+                    Path::new(&done.suggestion.file_path),
+                    done.original.as_deref(),
+                );
+            }
+            cleanup(&temps[i..], &mut created_dirs);
+            return Err((path.to_path_buf(), e));
+        }
+    }
+    Ok(())
+}
+
+/// Temporary file beside `path` for the `index`th file of a batch, hidden so
+/// test runners skip it
+///
+/// Several suggestions can target the same file, so each gets its own.
+fn temp_path(path: &Path, index: usize) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.vibetap-tmp", name, index))
+}
+
+/// Run the project's formatter over the written test files
+///
+/// `configured` is used for every file; without it each file type gets the
+/// formatter the project appears to use, if any.
+fn format_files(configured: Option<&str>, paths: &[&Path]) {
+    // One run per formatter, in the order the files were written
+    let mut runs: Vec<(String, Vec<&Path>)> = Vec::new();
+    for path in paths {
+        let command = match configured {
+            Some(command) => command.trim().to_string(),
+            None => match detect_formatter(path) {
+                Some(command) => command.to_string(),
+                None => continue,
+            },
+        };
+        if command.is_empty() {
+            continue;
+        }
+        match runs.iter_mut().find(|(c, _)| *c == command) {
+            Some((_, files)) => files.push(path),
+            None => runs.push((command, vec![path])),
+        }
+    }
+
+    for (command, files) in runs {
+        let mut parts = command.split_whitespace();
+        let program = parts.next().unwrap_or_default();
+        let output = Command::new(program).args(parts).args(&files).output();
+        match output {
+            Ok(output) if output.status.success() => println!(
+                "  {} Formatted {} test file(s) with {}",
+                "✓".green(),
+                files.len(),
+                command
+            ),
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let reason = match stderr.lines().find(|l| !l.trim().is_empty()) {
+                    Some(line) => format!(": {}", line.trim()),
+                    None => String::new(),
+                };
+                println!(
+                    "  {} '{}' failed ({}){}",
+                    ui::icon("⚠", "Warning:").yellow(),
+                    command,
+                    output.status,
+                    reason
+                );
+            }
+            Err(e) => println!(
+                "  {} Could not run '{}': {}",
+                ui::icon("⚠", "Warning:").yellow(),
+                command,
+                e
+            ),
+        }
+    }
+}
+
+/// Formatter for a file, when the project looks like it uses one
+fn detect_formatter(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_str()? {
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" => {
+            Path::new("node_modules/.bin/prettier")
+                .exists()
+                .then_some("node_modules/.bin/prettier --write")
+        }
+        "py" => {
+            let pyproject = std::fs::read_to_string("pyproject.toml").unwrap_or_default();
+            if pyproject.contains("[tool.ruff") {
+                Some("ruff format")
+            } else if pyproject.contains("[tool.black") {
+                Some("black")
+            } else {
+                None
+            }
+        }
+        "rs" => Some("rustfmt --edition 2021"),
+        "go" => Some("gofmt -w"),
+        _ => None,
+    }
+}
+
+/// Commit message for the applied suggestions: a subject naming the test
+/// files, what each test covers, and a trailer per suggestion ID
+fn commit_message(applied: &[&TestSuggestion]) -> String {
+    let mut files: Vec<&str> = applied.iter().map(|s| s.file_path.as_str()).collect();
+    files.sort_unstable();
+    files.dedup();
+    let subject = match files.as_slice() {
+        [file] => format!("Add tests in {}", file),
+        _ => format!("Add tests in {} files", files.len()),
+    };
+
+    let mut message = format!("{}\n\n", subject);
+    for suggestion in applied {
+        message.push_str(&format!(
+            "- {}: {}\n",
+            suggestion.file_path, suggestion.description
+        ));
+    }
+    message.push('\n');
+    for suggestion in applied {
+        message.push_str(&format!("Vibetap-Suggestion: {}\n", suggestion.id));
+    }
+    message
+}
+
+/// Let the user edit `code` in their editor and return what they saved
+///
+/// The temp file keeps the test file's extension so the editor highlights it.
+fn edit_in_editor(code: &str, file_path: &str) -> anyhow::Result<String> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+
+    let extension = Path::new(file_path)
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let file = tempfile::Builder::new()
+        .prefix("vibetap-")
+        .suffix(&extension)
+        .tempfile()?;
+    std::fs::write(file.path(), code)?;
+
+    // EDITOR may carry arguments, e.g. "code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let status = Command::new(program)
+        .args(parts)
+        .arg(file.path())
+        .status()
+        .map_err(|e| anyhow::anyhow!("Could not start editor '{}': {}", editor, e))?;
+    if !status.success() {
+        anyhow::bail!("Editor '{}' exited with {}; nothing was applied.", editor, status);
+    }
+
+    Ok(std::fs::read_to_string(file.path())?)
+}
+
 /// Check which source files have changed since suggestions were generated
 fn check_file_changes(saved: &SavedSuggestions) -> Vec<String> {
     let mut changed = Vec::new();
@@ -220,6 +1158,32 @@ fn check_file_changes(saved: &SavedSuggestions) -> Vec<String> {
     changed
 }
 
+/// Describe how HEAD moved since the suggestions were generated, if it did
+fn check_head_moved(saved: &SavedSuggestions) -> Option<String> {
+    let generated_on = saved.head_commit.as_deref()?;
+    let current = vibetap_git::head_commit().ok().flatten()?;
+    if current == generated_on {
+        return None;
+    }
+
+    let short = |sha: &str| sha.chars().take(7).collect::<String>();
+    let mut message = format!(
+        "HEAD moved from {} to {} since suggestions were generated",
+        short(generated_on),
+        short(&current)
+    );
+    // A stash that is gone was popped or dropped, likely onto another commit
+    if let Some(ref stash) = saved.stash_commit {
+        let still_stashed = vibetap_git::stash_entries()
+            .map(|entries| entries.contains(stash))
+            .unwrap_or(true);
+        if !still_stashed {
+            message.push_str(", and the stash they were generated from has been popped");
+        }
+    }
+    Some(message)
+}
+
 fn parse_selections(inputs: &[String], max: usize) -> anyhow::Result<Vec<usize>> {
     let mut result = Vec::new();
 
@@ -276,6 +1240,11 @@ fn parse_selections(inputs: &[String], max: usize) -> anyhow::Result<Vec<usize>>
 }
 
 fn print_code_block(code: &str, file_path: &str) {
+    if ui::is_accessible() {
+        ui::print_plain_code(code);
+        return;
+    }
+
     let ps = SyntaxSet::load_defaults_newlines();
     let ts = ThemeSet::load_defaults();
     let theme = &ts.themes["base16-ocean.dark"];
@@ -298,7 +1267,7 @@ fn print_code_block(code: &str, file_path: &str) {
     println!("   {}\x1b[0m", "└─".dimmed());
 }
 
-fn load_history() -> anyhow::Result<ApplyHistory> {
+pub fn load_history() -> anyhow::Result<ApplyHistory> {
     let path = Path::new(".vibetap/history.json");
     if !path.exists() {
         return Ok(ApplyHistory::default());
@@ -308,7 +1277,7 @@ fn load_history() -> anyhow::Result<ApplyHistory> {
     Ok(serde_json::from_str(&content)?)
 }
 
-fn save_history(history: &ApplyHistory) -> anyhow::Result<()> {
+pub fn save_history(history: &ApplyHistory) -> anyhow::Result<()> {
     let vibetap_dir = Path::new(".vibetap");
     if !vibetap_dir.exists() {
         std::fs::create_dir_all(vibetap_dir)?;
@@ -320,3 +1289,50 @@ fn save_history(history: &ApplyHistory) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggestion(file_path: &Path) -> TestSuggestion {
+        TestSuggestion {
+            id: "sug_1".to_string(),
+            file_path: file_path.to_string_lossy().into_owned(),
+            test_runner: "vitest".to_string(),
+            code: String::new(),
+            description: String::new(),
+            category: "unit".to_string(),
+            confidence: 0.9,
+            runtime_estimate: "fast".to_string(),
+            risks_addressed: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_write_all_same_file_twice() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tests/cart.test.ts");
+        let suggestion = suggestion(&path);
+        let pending = |content: &str| Pending {
+            suggestion: &suggestion,
+            content: content.to_string(),
+            original: None,
+            original_object: None,
+            modified: false,
+            outcome: String::new(),
+        };
+
+        write_all(&[
+            pending("test('a', () => {});\n"),
+            pending("test('b', () => {});\n"),
+        ])
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "test('b', () => {});\n"
+        );
+        // No temporary files are left behind
+        let entries = std::fs::read_dir(dir.path().join("tests")).unwrap().count();
+        assert_eq!(entries, 1);
+    }
+}
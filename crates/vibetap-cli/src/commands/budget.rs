@@ -0,0 +1,340 @@
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use std::path::Path;
+
+use crate::ui;
+use vibetap_core::{
+    api::GenerateResponse,
+    budget::{
+        budget_statuses, generations_since, month_usage, record_usage, BudgetConfig, BudgetStatus,
+        UsageEntry,
+    },
+    Config,
+};
+use vibetap_git::repo_root;
+
+const HOUR: i64 = 60 * 60;
+
+/// Exit status of `vibetap generate` when a budget stops it, so scripts can
+/// tell that from a run without suggestions
+pub const BUDGET_EXHAUSTED_EXIT: i32 = 3;
+
+#[derive(Args)]
+pub struct BudgetArgs {
+    #[command(subcommand)]
+    command: Option<BudgetCommand>,
+}
+
+#[derive(Subcommand)]
+enum BudgetCommand {
+    /// Show budgets and this month's usage (default)
+    Show,
+
+    /// Set a monthly budget for this repository (or all repositories with --global)
+    Set(SetArgs),
+
+    /// Remove a budget
+    Clear {
+        /// Clear the global budget instead of this repository's
+        #[arg(long)]
+        global: bool,
+    },
+}
+
+#[derive(Args)]
+struct SetArgs {
+    /// Apply to all repositories instead of just this one
+    #[arg(long)]
+    global: bool,
+
+    /// Maximum tokens per month
+    #[arg(long)]
+    tokens: Option<u64>,
+
+    /// Maximum generations per month
+    #[arg(long)]
+    generations: Option<u64>,
+
+    /// Percentages at which to warn (e.g., 50,80,90)
+    #[arg(long, value_delimiter = ',')]
+    warn_at: Option<Vec<u8>>,
+}
+
+pub async fn execute(args: BudgetArgs) -> anyhow::Result<()> {
+    match args.command.unwrap_or(BudgetCommand::Show) {
+        BudgetCommand::Show => show(),
+        BudgetCommand::Set(set_args) => set(set_args),
+        BudgetCommand::Clear { global } => clear(global),
+    }
+}
+
+fn show() -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let repo = current_repo();
+    let statuses = budget_statuses(&config, &repo)?;
+
+    if statuses.is_empty() {
+        let used = month_usage(Some(&repo))?;
+        println!("{}", "No budget configured.".yellow());
+        println!(
+            "This repository used {} tokens in {} generation(s) this month.",
+            used.tokens, used.generations
+        );
+        println!(
+            "Set one with: {}",
+            "vibetap budget set --tokens 500000 [--global]".cyan()
+        );
+        return Ok(());
+    }
+
+    for status in &statuses {
+        let percent = status.fraction() * 100.0;
+        let label = format!("{} budget", capitalize(status.scope.as_str()));
+        println!("{}", label.bold());
+        println!("  {}", describe(status));
+        println!(
+            "  Warnings at: {}",
+            status
+                .budget
+                .warn_at
+                .iter()
+                .map(|t| format!("{}%", t))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        let summary = format!("{:.0}% used", percent);
+        if status.is_exhausted() {
+            println!("  {}", format!("{} - exhausted", summary).red().bold());
+        } else if status.budget.warn_at.iter().any(|&t| percent >= t as f64) {
+            println!("  {}", summary.yellow());
+        } else {
+            println!("  {}", summary.green());
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+fn set(args: SetArgs) -> anyhow::Result<()> {
+    if args.tokens.is_none() && args.generations.is_none() && args.warn_at.is_none() {
+        anyhow::bail!("Nothing to set. Use --tokens, --generations, or --warn-at.");
+    }
+
+    let mut config = Config::load()?;
+
+    let existing = if args.global {
+        config.global.budget.take()
+    } else {
+        match config.project {
+            Some(ref mut project) => project.budget.take(),
+            None => anyhow::bail!("This repository is not initialized. Run 'vibetap init' first."),
+        }
+    };
+
+    let mut budget = existing.unwrap_or_default();
+    if args.tokens.is_some() {
+        budget.monthly_tokens = args.tokens;
+    }
+    if args.generations.is_some() {
+        budget.monthly_generations = args.generations;
+    }
+    if let Some(mut warn_at) = args.warn_at {
+        warn_at.sort_unstable();
+        warn_at.dedup();
+        budget.warn_at = warn_at;
+    }
+
+    let scope = if args.global { "Global" } else { "Repository" };
+    let summary = describe_limits(&budget);
+
+    if args.global {
+        config.global.budget = Some(budget);
+        Config::save_global(&config.global)?;
+    } else if let Some(mut project) = config.project {
+        project.budget = Some(budget);
+        Config::save_project(&project)?;
+    }
+
+    println!("{} {} budget set: {}", ui::icon("✓", "OK:").green(), scope, summary);
+    Ok(())
+}
+
+fn clear(global: bool) -> anyhow::Result<()> {
+    let mut config = Config::load()?;
+
+    let cleared = if global {
+        let cleared = config.global.budget.take().is_some();
+        Config::save_global(&config.global)?;
+        cleared
+    } else {
+        match config.project {
+            Some(mut project) => {
+                let cleared = project.budget.take().is_some();
+                Config::save_project(&project)?;
+                cleared
+            }
+            None => false,
+        }
+    };
+
+    if cleared {
+        println!("{} Budget removed.", ui::icon("✓", "OK:").green());
+    } else {
+        println!("{}", "No budget was set.".dimmed());
+    }
+    Ok(())
+}
+
+/// Check budgets before generating. Returns false if generation should stop.
+pub fn check_budget(config: &Config, ignore_budget: bool, quiet: bool) -> anyhow::Result<bool> {
+    let statuses = budget_statuses(config, &current_repo())?;
+
+    let Some(exhausted) = statuses.iter().find(|s| s.is_exhausted()) else {
+        return Ok(true);
+    };
+
+    if ignore_budget {
+        if !quiet {
+            println!(
+                "{} {}",
+                ui::icon("⚠", "Warning:").yellow(),
+                format!(
+                    "Over the {} budget ({}); continuing because of --ignore-budget.",
+                    exhausted.scope.as_str(),
+                    describe(exhausted)
+                )
+                .yellow()
+            );
+        }
+        return Ok(true);
+    }
+
+    if quiet {
+        println!(
+            "VibeTap: monthly {} budget exhausted. Run 'vibetap budget' for details.",
+            exhausted.scope.as_str()
+        );
+    } else {
+        println!(
+            "{}",
+            format!("Monthly {} budget exhausted.", exhausted.scope.as_str())
+                .red()
+                .bold()
+        );
+        println!("  {}", describe(exhausted));
+        println!(
+            "Use {} to generate anyway, or raise the limit with {}.",
+            "--ignore-budget".cyan(),
+            "vibetap budget set".cyan()
+        );
+    }
+
+    Ok(false)
+}
+
+/// When another generation fits in `limit` per hour for this repository,
+/// or `None` if one does now
+pub fn hourly_limit_resumes_at(limit: u32) -> anyhow::Result<Option<i64>> {
+    let limit = limit.max(1) as usize;
+    let now = unix_now();
+    let times = generations_since(Some(&current_repo()), now - HOUR)?;
+    if times.len() < limit {
+        return Ok(None);
+    }
+    // Enough of the oldest generations have to leave the hour first
+    Ok(Some(times[times.len() - limit] + HOUR))
+}
+
+/// Log a generation's usage and warn when it crosses a budget threshold
+pub fn track_usage(config: &Config, response: &GenerateResponse, quiet: bool) {
+    let repo = current_repo();
+    let before = budget_statuses(config, &repo).unwrap_or_default();
+
+    let entry = UsageEntry {
+        timestamp: unix_now(),
+        repo: repo.clone(),
+        tokens: response.tokens_used as u64,
+        model: response.model_used.clone(),
+    };
+    if let Err(e) = record_usage(&entry) {
+        if !quiet {
+            eprintln!("{} {}", "Warning: Could not record usage:".yellow(), e);
+        }
+        return;
+    }
+
+    if quiet {
+        return;
+    }
+
+    let after = budget_statuses(config, &repo).unwrap_or_default();
+    for (before, after) in before.iter().zip(&after) {
+        if let Some(threshold) = after.crossed_threshold(before) {
+            println!(
+                "{} {}",
+                ui::icon("⚠", "Warning:").yellow(),
+                format!(
+                    "{}% of the monthly {} budget used ({}).",
+                    threshold,
+                    after.scope.as_str(),
+                    describe(after)
+                )
+                .yellow()
+            );
+        }
+    }
+}
+
+/// Seconds since the Unix epoch
+pub fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Identifier used for this repository in the usage log
+fn current_repo() -> String {
+    repo_root(Path::new("."))
+        .ok()
+        .or_else(|| std::env::current_dir().ok())
+        .and_then(|p| p.canonicalize().ok())
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|| ".".to_string())
+}
+
+/// Usage against each configured limit, e.g. "1200/5000 tokens, 3/20 generations"
+fn describe(status: &BudgetStatus) -> String {
+    let mut parts = Vec::new();
+    if let Some(limit) = status.budget.monthly_tokens {
+        parts.push(format!("{}/{} tokens", status.used.tokens, limit));
+    }
+    if let Some(limit) = status.budget.monthly_generations {
+        parts.push(format!("{}/{} generations", status.used.generations, limit));
+    }
+    parts.join(", ")
+}
+
+fn describe_limits(budget: &BudgetConfig) -> String {
+    let mut parts = Vec::new();
+    if let Some(limit) = budget.monthly_tokens {
+        parts.push(format!("{} tokens", limit));
+    }
+    if let Some(limit) = budget.monthly_generations {
+        parts.push(format!("{} generations", limit));
+    }
+    if parts.is_empty() {
+        parts.push("no limits".to_string());
+    }
+    format!("{} per month", parts.join(", "))
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,163 @@
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use std::io::{self, IsTerminal, Write};
+
+use crate::ui;
+use vibetap_core::provider::{Provider, PROVIDERS};
+use vibetap_core::{ByokConfig, Config};
+
+#[derive(Args)]
+pub struct ByokArgs {
+    #[command(subcommand)]
+    command: ByokCommand,
+}
+
+#[derive(Subcommand)]
+enum ByokCommand {
+    /// Store a provider key for 'vibetap generate --direct'
+    Set {
+        /// Model provider
+        #[arg(value_parser = clap::builder::PossibleValuesParser::new(PROVIDERS))]
+        provider: String,
+
+        /// API key (defaults to OPENAI_API_KEY / ANTHROPIC_API_KEY, or asks)
+        #[arg(long)]
+        key: Option<String>,
+
+        /// Model to use, e.g. gpt-4o or claude-sonnet-4-0 (defaults by model tier)
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Send requests to this endpoint instead of the provider's, e.g. a proxy
+        #[arg(long, value_name = "URL")]
+        base_url: Option<String>,
+
+        /// Always generate directly, never through the VibeTap API
+        #[arg(long)]
+        always: bool,
+    },
+
+    /// Show the configured provider
+    Status,
+
+    /// Remove the stored provider key
+    Remove,
+}
+
+pub async fn execute(args: ByokArgs) -> anyhow::Result<()> {
+    match args.command {
+        ByokCommand::Set {
+            provider,
+            key,
+            model,
+            base_url,
+            always,
+        } => set(provider, key, model, base_url, always),
+        ByokCommand::Status => status(),
+        ByokCommand::Remove => remove(),
+    }
+}
+
+fn set(
+    provider: String,
+    key: Option<String>,
+    model: Option<String>,
+    base_url: Option<String>,
+    always: bool,
+) -> anyhow::Result<()> {
+    let Some(parsed) = Provider::parse(&provider) else {
+        anyhow::bail!("Unknown provider '{}'.", provider);
+    };
+
+    let api_key = match key.or_else(|| std::env::var(parsed.key_env()).ok()) {
+        Some(key) => key,
+        None => {
+            if !io::stdin().is_terminal() {
+                anyhow::bail!("No key given. Pass --key or set {}.", parsed.key_env());
+            }
+            print!("Paste your {} API key: ", provider);
+            io::stdout().flush()?;
+            let mut input = String::new();
+            io::stdin().read_line(&mut input)?;
+            input
+        }
+    };
+    let api_key = api_key.trim().to_string();
+    if api_key.is_empty() {
+        anyhow::bail!("The API key is empty.");
+    }
+
+    let mut config = Config::load()?;
+    config.global.byok = Some(ByokConfig {
+        provider,
+        api_key,
+        model,
+        base_url,
+        always,
+    });
+    Config::save_global(&config.global)?;
+
+    println!("{} Provider key saved.", ui::icon("✓", "OK:").green());
+    if always {
+        println!("Every generation now goes straight to {}.", parsed.as_str());
+    } else {
+        println!(
+            "Run {} to generate without sending your diff to VibeTap.",
+            "vibetap generate --direct".cyan()
+        );
+    }
+    Ok(())
+}
+
+fn status() -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let Some(byok) = config.global.byok else {
+        println!("{}", "No provider key configured.".yellow());
+        println!("Add one with: {}", "vibetap byok set openai".cyan());
+        return Ok(());
+    };
+
+    let model = match (&byok.model, Provider::parse(&byok.provider)) {
+        (Some(model), _) => model.clone(),
+        (None, Some(provider)) => format!("{} (default)", provider.default_model("default")),
+        (None, None) => "unknown".to_string(),
+    };
+    println!("{}", "Direct generation:".bold());
+    println!("  Provider: {}", byok.provider.cyan());
+    println!("  Key:      {}", mask(&byok.api_key));
+    println!("  Model:    {}", model);
+    if let Some(ref url) = byok.base_url {
+        println!("  Endpoint: {}", url);
+    }
+    let mode = if byok.always {
+        "always (the VibeTap API is never used for generation)"
+    } else {
+        "with --direct"
+    };
+    println!("  Used:     {}", mode);
+    Ok(())
+}
+
+fn remove() -> anyhow::Result<()> {
+    let mut config = Config::load()?;
+    if config.global.byok.take().is_none() {
+        println!("{}", "No provider key configured.".dimmed());
+        return Ok(());
+    }
+    Config::save_global(&config.global)?;
+    println!("{} Provider key removed.", ui::icon("✓", "OK:").green());
+    Ok(())
+}
+
+/// The key with all but its last four characters hidden
+fn mask(key: &str) -> String {
+    let visible: String = key
+        .chars()
+        .rev()
+        .take(4)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    format!("****{}", visible)
+}
@@ -0,0 +1,299 @@
+use clap::{Args, ValueEnum};
+use colored::Colorize;
+
+use super::generate::{load_session, load_suggestions, parse_session_name, HunkRange};
+use vibetap_core::api::GenerateResponse;
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Output format
+    #[arg(long, value_enum, default_value = "md")]
+    format: ExportFormat,
+
+    /// Export a named session instead of the latest suggestion set
+    #[arg(long, value_name = "NAME", value_parser = parse_session_name)]
+    session: Option<String>,
+
+    /// Write to a file instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<std::path::PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    /// Markdown report with a code block per suggestion
+    Md,
+    /// SARIF 2.1.0 log, for GitHub code scanning and other tools
+    Sarif,
+}
+
+pub async fn execute(args: ExportArgs) -> anyhow::Result<()> {
+    let saved = match args.session {
+        Some(ref name) => load_session(name)?,
+        None => load_suggestions()?,
+    };
+
+    let report = render(&saved.response, &saved.hunks, args.format);
+    match args.output {
+        Some(ref path) => {
+            std::fs::write(path, report)?;
+            eprintln!(
+                "{} {} suggestion(s) written to {}",
+                "Exported".green(),
+                saved.response.suggestions.len(),
+                path.display()
+            );
+        }
+        None => print!("{}", report),
+    }
+
+    Ok(())
+}
+
+/// Render a suggestion set in `format`
+pub fn render(response: &GenerateResponse, hunks: &[HunkRange], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Md => render_markdown(response),
+        ExportFormat::Sarif => render_sarif(response, hunks),
+    }
+}
+
+/// Render a suggestion set as a Markdown report
+pub fn render_markdown(response: &GenerateResponse) -> String {
+    let mut out = String::from("# VibeTap Test Suggestions\n\n");
+
+    if !response.summary.is_empty() {
+        out.push_str(&response.summary);
+        out.push_str("\n\n");
+    }
+
+    for (i, suggestion) in response.suggestions.iter().enumerate() {
+        out.push_str(&format!("## {}. `{}`\n\n", i + 1, suggestion.file_path));
+        out.push_str(&format!(
+            "**{}** · confidence {:.0}% · {}\n\n",
+            suggestion.category,
+            suggestion.confidence * 100.0,
+            suggestion.test_runner
+        ));
+        out.push_str(&suggestion.description);
+        out.push_str("\n\n");
+        if !suggestion.risks_addressed.is_empty() {
+            out.push_str(&format!(
+                "Risks addressed: {}\n\n",
+                suggestion.risks_addressed.join(", ")
+            ));
+        }
+
+        // A fence longer than any backtick run in the code keeps it intact
+        let longest_run = suggestion
+            .code
+            .split(|c| c != '`')
+            .map(str::len)
+            .max()
+            .unwrap_or(0);
+        let fence = "`".repeat(longest_run.max(2) + 1);
+        out.push_str(&format!("{}{}\n", fence, fence_language(&suggestion.file_path)));
+        out.push_str(&suggestion.code);
+        if !suggestion.code.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str(&fence);
+        out.push_str("\n\n");
+    }
+
+    out
+}
+
+/// Render a suggestion set as a SARIF 2.1.0 log
+///
+/// Each suggestion becomes a result located at the changed lines it covers,
+/// with one rule per suggestion category.
+pub fn render_sarif(response: &GenerateResponse, hunks: &[HunkRange]) -> String {
+    let mut categories: Vec<&str> = response
+        .suggestions
+        .iter()
+        .map(|s| s.category.as_str())
+        .collect();
+    categories.sort_unstable();
+    categories.dedup();
+
+    let rules: Vec<serde_json::Value> = categories
+        .iter()
+        .map(|category| {
+            serde_json::json!({
+                "id": rule_id(category),
+                "name": format!("Untested{}Change", capitalize(category)),
+                "shortDescription": {
+                    "text": format!("Change without a {} test", category)
+                },
+                "defaultConfiguration": { "level": level(category) },
+            })
+        })
+        .collect();
+
+    let results: Vec<serde_json::Value> = response
+        .suggestions
+        .iter()
+        .map(|suggestion| {
+            let mut text = suggestion.description.trim_end().to_string();
+            if !text.ends_with('.') {
+                text.push('.');
+            }
+            if !suggestion.risks_addressed.is_empty() {
+                text.push_str(&format!(
+                    " Risks addressed: {}.",
+                    suggestion.risks_addressed.join(", ")
+                ));
+            }
+            text.push_str(&format!(" Suggested test: {}", suggestion.file_path));
+
+            let locations: Vec<serde_json::Value> = targets(&suggestion.file_path, hunks)
+                .into_iter()
+                .map(|hunk| {
+                    serde_json::json!({
+                        "physicalLocation": {
+                            "artifactLocation": {
+                                "uri": hunk.file_path,
+                                "uriBaseId": "%SRCROOT%",
+                            },
+                            "region": {
+                                "startLine": hunk.start_line,
+                                "endLine": hunk.start_line + hunk.line_count - 1,
+                            },
+                        }
+                    })
+                })
+                .collect();
+
+            serde_json::json!({
+                "ruleId": rule_id(&suggestion.category),
+                "level": level(&suggestion.category),
+                "message": { "text": text },
+                "locations": locations,
+                "partialFingerprints": { "vibetapSuggestion/v1": suggestion.id },
+                "properties": {
+                    "confidence": suggestion.confidence,
+                    "risksAddressed": suggestion.risks_addressed,
+                    "suggestedTestPath": suggestion.file_path,
+                    "testRunner": suggestion.test_runner,
+                },
+            })
+        })
+        .collect();
+
+    let log = serde_json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "VibeTap",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }]
+    });
+
+    let mut out = serde_json::to_string_pretty(&log).unwrap_or_default();
+    out.push('\n');
+    out
+}
+
+fn rule_id(category: &str) -> String {
+    format!("vibetap/{}", category)
+}
+
+fn level(category: &str) -> &'static str {
+    if category == "security" {
+        "warning"
+    } else {
+        "note"
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Whether a test file is named for a source file: `a.test.ts`,
+/// `test_a.py`, and `a_test.go` all cover `a.*`
+pub fn covers(test_path: &str, source_path: &str) -> bool {
+    let test_stem = stem(test_path);
+    let test_stem = test_stem
+        .strip_prefix("test_")
+        .or_else(|| test_stem.strip_suffix("_test"))
+        .unwrap_or(test_stem);
+    stem(source_path) == test_stem
+}
+
+/// Hunks a suggested test covers, or every hunk when no source file matches
+fn targets<'a>(test_path: &str, hunks: &'a [HunkRange]) -> Vec<&'a HunkRange> {
+    let matching: Vec<&HunkRange> = hunks
+        .iter()
+        .filter(|hunk| covers(test_path, &hunk.file_path))
+        .collect();
+    if matching.is_empty() {
+        hunks.iter().collect()
+    } else {
+        matching
+    }
+}
+
+/// File name up to its first dot
+fn stem(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.split('.').next().unwrap_or(name)
+}
+
+/// Info string for a code fence, from the file extension
+fn fence_language(path: &str) -> &'static str {
+    match path.rsplit('.').next().unwrap_or("") {
+        "ts" | "tsx" => "typescript",
+        "js" | "jsx" | "mjs" | "cjs" => "javascript",
+        "py" => "python",
+        "rs" => "rust",
+        "go" => "go",
+        "java" => "java",
+        "rb" => "ruby",
+        "php" => "php",
+        "cs" => "csharp",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(path: &str, start_line: u32) -> HunkRange {
+        HunkRange {
+            file_path: path.to_string(),
+            start_line,
+            line_count: 3,
+        }
+    }
+
+    #[test]
+    fn test_targets_match_test_names_to_sources() {
+        let hunks = [range("src/auth.ts", 10), range("src/db.py", 4), range("pkg/api.go", 1)];
+
+        let paths = |test: &str| -> Vec<String> {
+            targets(test, &hunks)
+                .into_iter()
+                .map(|h| h.file_path.clone())
+                .collect()
+        };
+        assert_eq!(paths("tests/auth.test.ts"), vec!["src/auth.ts"]);
+        assert_eq!(paths("tests/test_db.py"), vec!["src/db.py"]);
+        assert_eq!(paths("pkg/api_test.go"), vec!["pkg/api.go"]);
+        // No obvious source: the result points at every change
+        assert_eq!(paths("tests/integration.test.ts").len(), 3);
+    }
+}
@@ -1,24 +1,52 @@
 use clap::Args;
 use colored::Colorize;
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use indicatif::{ProgressBar, ProgressStyle};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::io::{self, Write};
-use std::path::Path;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 use syntect::easy::HighlightLines;
 use syntect::highlighting::{Style, ThemeSet};
 use syntect::parsing::SyntaxSet;
 use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 
 use vibetap_core::{
     api::{
-        DiffHunk, DiffPayload, FileContext, GenerateOptions, GenerateRequest, GenerateResponse,
-        StreamEvent,
+        ApiError, DiffHunk, DiffPayload, FileContext, GenerateOptions, GenerateRequest,
+        GenerateResponse, StreamEvent, TestSuggestion, MODEL_TIERS,
     },
-    ApiClient, Config,
+    context::{allocate, Allocation, Candidate, ContextBudget},
+    deps::ImportGraph,
+    backend::BACKENDS,
+    dump::debug_dir_from_env,
+    ollama::OllamaClient,
+    provider::ProviderClient,
+    ApiClient, Config, GenerateBackend,
 };
-use vibetap_git::{get_staged_diff, get_uncommitted_diff, GitError};
+use super::budget::{check_budget, track_usage, BUDGET_EXHAUSTED_EXIT};
+use super::export::{self, ExportFormat};
+use crate::cache;
+use crate::retry;
+use crate::lint::{self, LintIssue};
+use crate::ui;
+use vibetap_git::{
+    conflict_state, get_commit_diff, get_diff_against, get_range_diff, get_staged_diff,
+    get_uncommitted_diff, parse_unified_diff, stash_diff, stash_entries, DiffSettings, GitError,
+    IgnoreRules,
+};
+
+mod batch;
+mod refine;
+mod validate;
+
+pub use batch::sub_diff;
+use refine::Refine;
+use validate::{print_validation, Validation};
 
 /// Saved suggestions with source file state for change detection
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,13 +55,84 @@ pub struct SavedSuggestions {
     pub response: GenerateResponse,
     pub source_files: HashMap<String, String>, // path -> content hash
     pub generated_at: i64,
+    /// Optional session name the set was saved under
+    #[serde(default)]
+    pub session: Option<String>,
+    /// HEAD when the suggestions were generated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head_commit: Option<String>,
+    /// Stash commit the suggestions were generated from (`--stash`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stash_commit: Option<String>,
+    /// Branch checked out when the suggestions were generated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// Hash of the diff and options the suggestions answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff_hash: Option<String>,
+    /// Changed line ranges the suggestions were generated for
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hunks: Vec<HunkRange>,
+    /// Test files that already existed, as the suggestions were generated
+    /// against them; apply merges the suggestion into later edits from here
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub test_file_bases: HashMap<String, String>,
+    /// Content hash of every target test file at generation time, empty
+    /// for files that didn't exist; apply won't clobber later changes
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub test_file_hashes: HashMap<String, String>,
+}
+
+/// Lines a hunk covers in the new version of a file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkRange {
+    pub file_path: String,
+    pub start_line: u32,
+    pub line_count: u32,
+}
+
+impl HunkRange {
+    /// Ranges of the hunks that add or change lines; pure deletions have none
+    pub fn from_diff(diff: &vibetap_git::StagedDiff) -> Vec<HunkRange> {
+        diff.hunks
+            .iter()
+            .filter(|h| h.new_lines > 0)
+            .map(|h| HunkRange {
+                file_path: h.file_path.clone(),
+                start_line: h.new_start,
+                line_count: h.new_lines,
+            })
+            .collect()
+    }
+}
+
+impl SavedSuggestions {
+    /// Identifier for the generation session (name or timestamp)
+    pub fn session_id(&self) -> String {
+        self.session
+            .clone()
+            .unwrap_or_else(|| self.generated_at.to_string())
+    }
 }
 
 #[derive(Args)]
 pub struct GenerateArgs {
-    /// Specific file to generate tests for (optional, defaults to all staged changes)
-    #[arg(value_name = "FILE")]
-    file: Option<String>,
+    /// Files or globs to generate tests for, e.g. 'packages/api/**' (defaults to all changes)
+    #[arg(value_name = "PATHSPEC")]
+    paths: Vec<String>,
+
+    /// Only include changed files and context matching this glob (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    only: Vec<String>,
+
+    /// Leave out changed files and context matching this glob (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Only include files in these languages, by extension or name, e.g. ts,py
+    #[arg(long, value_name = "LANGS", value_delimiter = ',')]
+    lang: Vec<String>,
 
     /// Generate tests for staged changes only (default)
     #[arg(long, default_value = "true")]
@@ -43,6 +142,26 @@ pub struct GenerateArgs {
     #[arg(long)]
     uncommitted: bool,
 
+    /// Generate tests for everything since branching from a ref, e.g. origin/main (like a PR diff)
+    #[arg(long, value_name = "REF", conflicts_with = "uncommitted")]
+    base_branch: Option<String>,
+
+    /// Generate tests for an already-committed change (e.g., HEAD~1)
+    #[arg(long, value_name = "REV", conflicts_with_all = ["uncommitted", "base_branch"])]
+    commit: Option<String>,
+
+    /// Generate tests for a range of commits (e.g., main..feature)
+    #[arg(long, value_name = "FROM..TO", conflicts_with_all = ["uncommitted", "base_branch", "commit"])]
+    range: Option<String>,
+
+    /// Generate tests for stashed work, e.g. 0 for stash@{0}, without popping it
+    #[arg(long, value_name = "N", conflicts_with_all = ["uncommitted", "base_branch", "commit", "range"])]
+    stash: Option<usize>,
+
+    /// Read a unified diff from stdin instead of asking git, e.g. a patch file in CI
+    #[arg(long, conflicts_with_all = ["paths", "uncommitted", "base_branch", "commit", "range", "stash"])]
+    stdin: bool,
+
     /// Prioritize security guardrail tests
     #[arg(long)]
     security: bool,
@@ -51,82 +170,265 @@ pub struct GenerateArgs {
     #[arg(long, default_value = "3")]
     max_suggestions: u32,
 
+    /// Ask for tests of error handling and invalid input (default unless generation.includeNegativePaths is false)
+    #[arg(long, overrides_with = "no_negative_paths")]
+    negative_paths: bool,
+
+    /// Only ask for happy-path tests
+    #[arg(long, overrides_with = "negative_paths")]
+    no_negative_paths: bool,
+
     /// Test runner to use (vitest, jest, pytest, etc.)
     #[arg(long)]
     test_runner: Option<String>,
 
+    /// Hide suggestions below this confidence, e.g. 0.7 (also generation.minConfidence)
+    #[arg(long, value_name = "0-1", value_parser = parse_confidence)]
+    min_confidence: Option<f64>,
+
+    /// Put the suggested tests in this file instead of the default location, e.g. tests/unit/auth.test.ts
+    #[arg(long, value_name = "PATH")]
+    test_file: Option<String>,
+
+    /// Model tier to request: cheap (fast, for hooks), default, or enhanced (security-critical diffs).
+    /// With --backend byok or ollama, also a model name such as qwen2.5-coder
+    #[arg(long, value_name = "TIER|MODEL")]
+    model: Option<String>,
+
+    /// Apply a guardrail policy pack (see 'vibetap policy list')
+    #[arg(long, value_name = "ID")]
+    policy: Option<String>,
+
     /// Quiet mode - show condensed output (useful for git hooks)
     #[arg(short, long)]
     quiet: bool,
+
+    /// With --quiet, print one line of JSON like {"count":3,"security":1,"ids":[...]} for scripts
+    #[arg(long, requires = "quiet")]
+    porcelain: bool,
+
+    /// Print the suggestions as a report on stdout instead of the interactive view
+    #[arg(long, value_enum, value_name = "FORMAT", conflicts_with = "dry_run")]
+    export: Option<ExportFormat>,
+
+    /// Also save the suggestion set under a session name (see 'vibetap share')
+    #[arg(long, value_name = "NAME", value_parser = parse_session_name)]
+    session: Option<String>,
+
+    /// Parse/typecheck each suggestion in a temp copy of the project before showing it
+    #[arg(long)]
+    validate: bool,
+
+    /// Also run each suggested test on the sandbox copy, in a Docker container (implies --validate)
+    #[arg(long)]
+    run_sandbox: bool,
+
+    /// Generate even if a local monthly budget is exhausted
+    #[arg(long)]
+    ignore_budget: bool,
+
+    /// Skip linting suggestions before showing them
+    #[arg(long)]
+    no_lint: bool,
+
+    /// Also send files that import the changed files as context
+    #[arg(long)]
+    dependents: bool,
+
+    /// Don't add local modules imported by the changed files as context
+    #[arg(long)]
+    no_imports: bool,
+
+    /// Build the request and summarize what would be sent, without calling the API
+    #[arg(long)]
+    dry_run: bool,
+
+    /// With --dry-run, also print the full JSON payload
+    #[arg(long, requires = "dry_run")]
+    json: bool,
+
+    /// Ask the API again even if this exact diff was already answered
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Regenerate saved suggestion N following an instruction, e.g. --refine 2 "use msw instead of fetch mocks"
+    #[arg(long, num_args = 2, value_names = ["N", "INSTRUCTION"], conflicts_with_all = ["stash", "stdin"])]
+    refine: Vec<String>,
+
+    /// Fail right away when rate limited instead of waiting and retrying
+    #[arg(long)]
+    no_retry: bool,
+
+    /// Generate with your own provider key (see 'vibetap byok') without sending the diff to VibeTap
+    #[arg(long, conflicts_with = "backend")]
+    direct: bool,
+
+    /// Where suggestions come from: vibetap (the API), byok (your provider key, same as --direct),
+    /// or ollama (a local model, fully offline)
+    #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(BACKENDS))]
+    backend: Option<String>,
+
+    /// Write the request, raw response, and parsed suggestions here, secrets redacted (also VIBETAP_DEBUG_DIR)
+    #[arg(long, value_name = "DIR")]
+    debug_dump: Option<PathBuf>,
+
+    #[command(flatten)]
+    diff_flags: DiffFlags,
+}
+
+impl GenerateArgs {
+    /// Condensed output, also used with --export so progress doesn't end up in the report
+    fn quiet(&self) -> bool {
+        self.quiet || self.export.is_some()
+    }
+}
+
+/// Most dependent files added as context with --dependents
+const MAX_DEPENDENT_CONTEXT: usize = 5;
+
+/// Bytes of imported local modules added as context
+const IMPORT_CONTEXT_BYTES: u64 = 64 * 1024;
+
+/// Largest untracked file turned into a hunk with --untracked
+const UNTRACKED_CONTENT_LIMIT: u64 = 100 * 1024;
+
+/// Commit messages sent when `generation.includeCommitMessages` is on
+const RECENT_COMMIT_MESSAGES: usize = 5;
+
+/// Files read for project conventions, first one found wins
+pub const INSTRUCTION_FILES: [&str; 3] = [".vibetap/instructions.md", "AGENTS.md", "CLAUDE.md"];
+
+/// Largest part of the instructions file sent with a request
+const MAX_INSTRUCTIONS_BYTES: usize = 16 * 1024;
+
+/// Diff options shared by commands that diff the working tree
+#[derive(Args)]
+pub struct DiffFlags {
+    /// Ignore whitespace when comparing lines
+    #[arg(short = 'w', long)]
+    ignore_whitespace: bool,
+
+    /// Ignore changes in the amount of whitespace
+    #[arg(long)]
+    ignore_whitespace_change: bool,
+
+    /// Ignore added or removed blank lines
+    #[arg(long)]
+    ignore_blank_lines: bool,
+
+    /// Lines of unchanged context around each change (default from config, else 3)
+    #[arg(short = 'U', long, value_name = "N")]
+    context_lines: Option<u32>,
+
+    /// Merge changes at most N unchanged lines apart into one hunk (default from config, else 0)
+    #[arg(long, value_name = "N")]
+    interhunk_lines: Option<u32>,
+
+    /// Include the contents of new, untracked files (with --uncommitted or --base-branch)
+    #[arg(long)]
+    untracked: bool,
+}
+
+impl DiffFlags {
+    /// Combine the flags with the project's diff config
+    pub fn settings(&self, config: &Config, pathspecs: Vec<String>) -> DiffSettings {
+        let project = config
+            .project
+            .as_ref()
+            .and_then(|p| p.diff.clone())
+            .unwrap_or_default();
+        let context_lines = self.context_lines.or_else(|| {
+            config
+                .project
+                .as_ref()
+                .and_then(|p| p.generation.diff_context_lines)
+        });
+        let interhunk_lines = self.interhunk_lines.or_else(|| {
+            config
+                .project
+                .as_ref()
+                .and_then(|p| p.generation.diff_interhunk_lines)
+        });
+        DiffSettings {
+            pathspecs,
+            ignore_whitespace: self.ignore_whitespace || project.ignore_whitespace,
+            ignore_whitespace_change: self.ignore_whitespace_change
+                || project.ignore_whitespace_change,
+            ignore_blank_lines: self.ignore_blank_lines || project.ignore_blank_lines,
+            context_lines,
+            interhunk_lines,
+            untracked_content_limit: self.untracked.then_some(UNTRACKED_CONTENT_LIMIT),
+            ..Default::default()
+        }
+    }
+}
+
+/// The `--quiet --porcelain` line; fields are only ever added, never renamed
+#[derive(Serialize)]
+struct PorcelainSummary<'a> {
+    count: usize,
+    security: usize,
+    ids: Vec<&'a str>,
 }
 
 pub async fn execute(args: GenerateArgs) -> anyhow::Result<()> {
     // Load configuration
     let mut config = Config::load()?;
-    let access_token = config.get_valid_access_token().await?;
-    let api_url = config.api_url().to_string();
 
-    let quiet = args.quiet;
+    let quiet = args.quiet();
+    let refine = Refine::from_args(&args)?;
+    // Report a missing provider key or a stray model name before any work is done
+    let backend = local_backend(&args, &config)?;
 
-    // Get the diff based on scope
-    let diff = if args.uncommitted {
-        if !quiet {
-            println!("{}", "Analyzing uncommitted changes...".cyan());
+    if let Some(ref path) = args.test_file {
+        let path = Path::new(path);
+        if path.is_absolute() || path.components().any(|c| c == std::path::Component::ParentDir) {
+            anyhow::bail!("--test-file must be a relative path inside the repository.");
         }
-        get_uncommitted_diff()
-    } else {
-        if !quiet {
-            println!("{}", "Analyzing staged changes...".cyan());
-        }
-        get_staged_diff()
+    }
+
+    // A dry run never reaches the API, so it needs neither a login nor budget
+    if !args.dry_run && !check_budget(&config, args.ignore_budget, quiet)? {
+        std::process::exit(BUDGET_EXHAUSTED_EXIT);
+    }
+
+    if mid_merge(quiet) {
+        return Ok(());
+    }
+
+    let Some(diff) = load_diff(&args, &config)? else {
+        return Ok(());
     };
 
-    let mut diff = match diff {
-        Ok(d) => d,
-        Err(GitError::NoStagedChanges) => {
-            if !quiet {
-                println!(
-                    "\n{}",
-                    "No changes found. Stage some changes first with 'git add'.".yellow()
-                );
-            }
-            return Ok(());
-        }
-        Err(GitError::NotARepo) => {
-            if !quiet {
-                println!(
-                    "\n{}",
-                    "Not a git repository. Run this command from within a git repo.".red()
-                );
-            }
-            return Ok(());
-        }
-        Err(e) => {
-            return Err(e.into());
-        }
+    let filter = PathFilter::new(&args);
+    let diff = filter.apply(diff);
+    let diff = match refine {
+        Some(ref refine) => refine.narrow(diff),
+        None => diff,
     };
+    if diff.files_changed.is_empty() && !filter.is_empty() {
+        if !quiet {
+            println!(
+                "\n{}",
+                "No changes match the --only, --exclude, or --lang filters.".yellow()
+            );
+        }
+        return Ok(());
+    }
 
-    // Filter by specific file if provided
-    if let Some(ref file_filter) = args.file {
-        let normalized_filter = file_filter.trim_start_matches("./");
-        diff.hunks.retain(|h| {
-            let normalized_path = h.file_path.trim_start_matches("./");
-            normalized_path == normalized_filter || normalized_path.ends_with(normalized_filter)
-        });
-        diff.files_changed.retain(|f| {
-            let normalized_path = f.trim_start_matches("./");
-            normalized_path == normalized_filter || normalized_path.ends_with(normalized_filter)
-        });
+    if !quiet {
+        print_skipped_changes(&diff);
+    }
 
-        if diff.hunks.is_empty() {
-            if !quiet {
-                println!(
-                    "\n{}",
-                    format!("No changes found for file: {}", file_filter).yellow()
-                );
-            }
-            return Ok(());
+    if diff.hunks.is_empty() {
+        if !quiet {
+            println!(
+                "\n{}",
+                "Only binary files, submodules, or file modes changed; there is nothing to generate tests for."
+                    .yellow()
+            );
         }
+        return Ok(());
     }
 
     if !quiet {
@@ -137,198 +439,460 @@ pub async fn execute(args: GenerateArgs) -> anyhow::Result<()> {
         );
     }
 
+    let context_paths = context_paths(&diff, &filter, &args, &config);
+
+    // Load files once; the content feeds the request and the hashes feed apply's change detection
+    let loaded_files = load_context_files(&context_paths, !quiet).await;
+    let file_hashes: HashMap<String, String> = loaded_files
+        .iter()
+        .filter(|f| diff.files_changed.contains(&f.path))
+        .map(|f| (f.path.clone(), f.hash.clone()))
+        .collect();
+
     // Build the API request
-    let request = build_request(&diff, &args, &config);
+    let allocation = fit_context(
+        &config,
+        &diff,
+        loaded_files.iter().map(|f| (f.path.as_str(), f.content.as_str())),
+    );
+    if !quiet && !args.dry_run {
+        print_context_trimming(&allocation);
+    }
+    let mut request = build_request(&diff, &allocation, &args, &config);
+    if let Some(ref refine) = refine {
+        refine.apply_to(&mut request);
+    }
 
     // Calculate payload size for progress display
     let payload_size = serde_json::to_string(&request)
         .map(|s| s.len())
         .unwrap_or(0);
 
-    // Show upload progress bar (only in non-quiet mode)
-    if !quiet {
-        print_upload_progress(payload_size);
+    if args.dry_run {
+        let batches = batch::plan(&request, payload_size).len();
+        if batches > 1 {
+            println!(
+                "  {} Too large for one request; would be sent in {} batches",
+                ui::icon("ℹ", "Note:").dimmed(),
+                batches
+            );
+        }
+        return print_dry_run(&request, payload_size, &diff, &allocation, args.json);
     }
 
-    // Call the streaming API
-    let client = ApiClient::new(api_url, access_token);
-
-    // Create progress bar for generation phase
-    let progress_bar = if !quiet {
-        let pb = ProgressBar::new_spinner();
-        pb.set_style(
-            ProgressStyle::default_spinner()
-                .template("{spinner:.cyan} {msg}")
-                .unwrap(),
-        );
-        pb.enable_steady_tick(Duration::from_millis(100));
-        Some(pb)
-    } else {
+    // The same diff with the same options gets the earlier answer back, so
+    // re-running in a hook doesn't spend quota. Refinements always go out.
+    let cache_key = cache::key(&request, backend_label(backend.as_deref(), &request).as_deref());
+    let cached = if args.no_cache || refine.is_some() {
         None
+    } else {
+        cache::load(&cache_key)
     };
 
-    // Track suggestions as they stream in
-    let mut streamed_suggestions: Vec<vibetap_core::api::TestSuggestion> = Vec::new();
-
-    let response = match client
-        .generate_streaming(request, |event| {
-            match event {
-                StreamEvent::Progress { phase, message, .. } => {
-                    if let Some(ref pb) = progress_bar {
-                        let phase_icon = match phase.as_str() {
-                            "authenticating" => "🔐",
-                            "analyzing" => "🔍",
-                            "context" => "📚",
-                            "generating" => "⚡",
-                            _ => "•",
-                        };
-                        pb.set_message(format!("{} {}", phase_icon, message));
-                    }
-                }
-                StreamEvent::Suggestion {
-                    index,
-                    total,
-                    suggestion,
-                } => {
-                    if let Some(ref pb) = progress_bar {
-                        pb.set_message(format!(
-                            "📝 Generated suggestion {}/{}: {}",
-                            index,
-                            total,
-                            suggestion.file_path.cyan()
-                        ));
-                    }
-                    streamed_suggestions.push(suggestion);
+    // Suggestions printed while they streamed in aren't printed again
+    let mut shown = 0;
+    let response = match cached {
+        Some(response) => {
+            if !quiet {
+                println!(
+                    "{} Diff unchanged since the last run; using cached suggestions (--no-cache to regenerate)",
+                    ui::icon("ℹ", "Note:").dimmed()
+                );
+            }
+            response
+        }
+        None => {
+            let generated = match backend {
+                Some(ref backend) => generate_local(backend.as_ref(), request, &args, &config)
+                    .await
+                    .map(|response| (response, 0)),
+                None => {
+                    request_suggestions(
+                        request,
+                        &diff,
+                        &loaded_files,
+                        &args,
+                        &mut config,
+                        payload_size,
+                    )
+                    .await?
                 }
-                StreamEvent::Complete { .. } => {
-                    if let Some(ref pb) = progress_bar {
-                        pb.finish_and_clear();
-                    }
+            };
+            let Some((response, streamed)) = generated else {
+                if args.export.is_some() {
+                    anyhow::bail!("Generation failed; run without --export to see why.");
                 }
-                StreamEvent::Error { code, message } => {
-                    if let Some(ref pb) = progress_bar {
-                        pb.finish_and_clear();
-                    }
-                    if !quiet {
-                        eprintln!("\n{} {} - {}", "Error:".red(), code, message);
-                    }
+                return Ok(());
+            };
+            shown = streamed;
+            track_usage(&config, &response, quiet);
+            if refine.is_none() {
+                if let Err(e) = cache::store(&cache_key, &response) {
+                    tracing::debug!("Could not cache suggestions: {}", e);
                 }
             }
-        })
-        .await
-    {
-        Ok(r) => r,
-        Err(e) => {
-            if let Some(pb) = progress_bar {
-                pb.finish_and_clear();
-            }
-            if !quiet {
-                println!("\n{} {}", "Error:".red(), e);
-            }
-            return Ok(());
+            response
         }
     };
 
+    if let Some(refine) = refine {
+        return refine::finish(refine, response, file_hashes, &args).await;
+    }
+
+    // The cache keeps everything, so a different threshold applies to the next run right away
+    let mut response = response;
+    let threshold = min_confidence(&config, args.min_confidence);
+    let hidden = drop_low_confidence(&mut response, threshold);
+    if hidden > 0 && !quiet {
+        println!(
+            "{} {} suggestion(s) below {:.0}% confidence hidden (--min-confidence)",
+            ui::icon("ℹ", "Note:").dimmed(),
+            hidden,
+            threshold * 100.0
+        );
+    }
+
     // Save suggestions for later use by apply command (with source file hashes)
-    if let Err(e) = save_suggestions(&response, &diff.files_changed) {
+    let stash_commit = args
+        .stash
+        .and_then(|index| stash_entries().ok()?.get(index).cloned());
+    if let Err(e) = save_suggestions(
+        &response,
+        file_hashes,
+        args.session.as_deref(),
+        stash_commit,
+        Some(cache_key),
+        HunkRange::from_diff(&diff),
+    ) {
         if !quiet {
             eprintln!("{} {}", "Warning: Could not save suggestions:".yellow(), e);
         }
     }
 
+    if let Some(format) = args.export {
+        print!("{}", export::render(&response, &HunkRange::from_diff(&diff), format));
+        return Ok(());
+    }
+
     // Quiet mode: show condensed output
+    if args.porcelain {
+        return print_porcelain(&response.suggestions);
+    }
     if quiet {
-        let count = response.suggestions.len();
-        if count > 0 {
-            let security_count = response
-                .suggestions
-                .iter()
-                .filter(|s| s.category == "security")
-                .count();
+        print_quiet_summary(&response.suggestions);
+        return Ok(());
+    }
 
-            if security_count > 0 {
-                println!(
-                    "VibeTap: {} test suggestion(s) available ({} security). Run 'vibetap generate' for details.",
-                    count, security_count
-                );
-            } else {
-                println!(
-                    "VibeTap: {} test suggestion(s) available. Run 'vibetap generate' for details or 'vibetap apply' to add.",
-                    count
-                );
-            }
+    print_suggestions(&response, shown, &args).await;
+    Ok(())
+}
+
+/// Whether a merge, rebase, or similar is underway that generation should wait for
+///
+/// Half-resolved conflicts make for misleading hunks. Hooks fire while a merge
+/// or rebase is concluded; in quiet mode those changes are not the user's own.
+fn mid_merge(quiet: bool) -> bool {
+    let Ok(state) = conflict_state() else {
+        return false;
+    };
+    if state.has_conflicts() {
+        if !quiet {
+            println!(
+                "{}",
+                format!(
+                    "A {} is in progress with {} conflicted file(s). Resolve them before generating.",
+                    state.operation.map_or("merge", |o| o.as_str()),
+                    state.conflicted.len()
+                )
+                .yellow()
+            );
         }
-        return Ok(());
+        return true;
     }
+    quiet && state.operation.is_some()
+}
 
-    // Full output mode
-    println!("\n{}", "=== Test Suggestions ===".bold());
-    println!();
+/// The diff for the requested scope, limited to the requested paths
+///
+/// Returns `None` when there is nothing to generate for; why has already been
+/// shown.
+fn load_diff(
+    args: &GenerateArgs,
+    config: &Config,
+) -> anyhow::Result<Option<vibetap_git::StagedDiff>> {
+    let quiet = args.quiet();
+    let settings = args.diff_flags.settings(config, to_pathspecs(&args.paths));
+    let diff = if args.stdin {
+        if io::stdin().is_terminal() {
+            anyhow::bail!("--stdin expects a piped diff, e.g. 'git diff main | vibetap generate --stdin'.");
+        }
+        if !quiet {
+            println!("{}", "Analyzing diff from stdin...".cyan());
+        }
+        let mut patch = String::new();
+        io::stdin().read_to_string(&mut patch)?;
+        parse_unified_diff(&patch, &settings)
+    } else if let Some(index) = args.stash {
+        if !quiet {
+            println!("{}", format!("Analyzing stash@{{{}}}...", index).cyan());
+        }
+        stash_diff(index, &settings)
+    } else if let Some(ref rev) = args.commit {
+        if !quiet {
+            println!("{}", format!("Analyzing commit {}...", rev).cyan());
+        }
+        get_commit_diff(rev, &settings)
+    } else if let Some(ref range) = args.range {
+        let Some((from, to)) = range.split_once("..") else {
+            anyhow::bail!("Invalid range '{}'. Use FROM..TO, e.g. main..feature.", range);
+        };
+        if to.starts_with('.') {
+            anyhow::bail!("Three-dot ranges are not supported. Use FROM..TO, e.g. main..feature.");
+        }
+        if !quiet {
+            println!("{}", format!("Analyzing commit range {}", range).cyan());
+        }
+        get_range_diff(from, if to.is_empty() { "HEAD" } else { to }, &settings)
+    } else if let Some(ref base) = args.base_branch {
+        if !quiet {
+            println!("{}", format!("Analyzing changes since branching from {}...", base).cyan());
+        }
+        get_diff_against(base, &settings)
+    } else if args.uncommitted {
+        if !quiet {
+            println!("{}", "Analyzing uncommitted changes...".cyan());
+        }
+        get_uncommitted_diff(&settings)
+    } else {
+        if !quiet {
+            println!("{}", "Analyzing staged changes...".cyan());
+        }
+        get_staged_diff(&settings)
+    };
 
-    if response.used_byok {
+    let message = match diff {
+        Ok(d) => return Ok(Some(d)),
+        Err(GitError::StashNotFound(index)) => {
+            format!("There is no stash@{{{}}}. See 'git stash list'.", index).yellow()
+        }
+        Err(GitError::NoStagedChanges) if args.stdin => {
+            "The diff on stdin contains no changes.".yellow()
+        }
+        Err(GitError::NoStagedChanges) if !args.paths.is_empty() => {
+            format!("No changes found for: {}", args.paths.join(" ")).yellow()
+        }
+        Err(GitError::NoStagedChanges) => {
+            "No changes found. Stage some changes first with 'git add'.".yellow()
+        }
+        Err(GitError::NotARepo) => {
+            "Not a git repository. Run this command from within a git repo.".red()
+        }
+        Err(
+            e @ (GitError::EmptyRepository
+            | GitError::ShallowClone
+            | GitError::Bare
+            | GitError::DetachedHead),
+        ) => format!("{}.", e).yellow(),
+        Err(e) => return Err(e.into()),
+    };
+    if !quiet {
+        println!("\n{}", message);
+    }
+    Ok(None)
+}
+
+/// Note the binary files, truncation, submodules, and mode changes left out of the request
+fn print_skipped_changes(diff: &vibetap_git::StagedDiff) {
+    if !diff.binary_files.is_empty() {
         println!(
-            "{}",
-            "ℹ Using your own API key (BYOK mode)".dimmed()
+            "  {} {} binary file(s) skipped: {}",
+            ui::icon("⚠", "Warning:").yellow(),
+            diff.binary_files.len(),
+            diff.binary_files.join(", ").dimmed()
         );
-        println!();
     }
 
-    if let Some(ref warning) = response.warning {
-        println!("{} {}", "⚠".yellow(), warning.yellow());
-        println!();
+    if diff.truncation.is_truncated() {
+        let mut message = String::from("Diff truncated to stay within size limits");
+        if !diff.truncation.files.is_empty() {
+            message.push_str(&format!("; cut short: {}", diff.truncation.files.join(", ")));
+        }
+        if diff.truncation.total_limit_reached {
+            message.push_str("; later files were left out");
+        }
+        println!("  {} {}", ui::icon("⚠", "Warning:").yellow(), message.yellow());
     }
 
-    if response.suggestions.is_empty() {
-        println!("{}", "No test suggestions generated.".yellow());
-        return Ok(());
+    if !diff.submodules_changed.is_empty() {
+        println!(
+            "  {} {} submodule update(s) skipped: {}",
+            ui::icon("ℹ", "Note:").dimmed(),
+            diff.submodules_changed.len(),
+            diff.submodules_changed.join(", ").dimmed()
+        );
     }
 
-    for (i, suggestion) in response.suggestions.iter().enumerate() {
+    if !diff.mode_changes.is_empty() {
+        let changes: Vec<String> = diff
+            .mode_changes
+            .iter()
+            .map(|m| format!("{} ({})", m.file_path, m.kind.as_str()))
+            .collect();
         println!(
-            "{} {}",
-            format!("{}.", i + 1).bold(),
-            suggestion.file_path.cyan()
+            "  {} {} mode/symlink change(s) skipped: {}",
+            ui::icon("ℹ", "Note:").dimmed(),
+            changes.len(),
+            changes.join(", ").dimmed()
+        );
+    }
+}
+
+/// The files to send as context: the changed files, then what they import, then their dependents
+///
+/// Local modules the changed files import come next, so helpers the change
+/// calls are visible; direct importers go last, within the context limit.
+fn context_paths(
+    diff: &vibetap_git::StagedDiff,
+    filter: &PathFilter,
+    args: &GenerateArgs,
+    config: &Config,
+) -> Vec<String> {
+    let quiet = args.quiet();
+    let mut context_paths = diff.files_changed.clone();
+    // Only finding what imports the changed files needs the whole project read
+    let graph = if args.dependents {
+        Some(ImportGraph::build(Path::new(".")))
+    } else if !args.no_imports {
+        Some(ImportGraph::of_files(Path::new("."), &diff.files_changed))
+    } else {
+        None
+    };
+    if let Some(graph) = graph.as_ref().filter(|_| !args.no_imports) {
+        let imports = within_byte_budget(graph.direct_imports(&diff.files_changed), IMPORT_CONTEXT_BYTES);
+        if !quiet && !imports.is_empty() {
+            println!("  Including {} imported module(s) as context", imports.len());
+        }
+        context_paths.extend(imports);
+    }
+    if let Some(graph) = graph.as_ref().filter(|_| args.dependents) {
+        let mut dependents = graph.reverse_dependencies(&diff.files_changed);
+        dependents.retain(|path| !context_paths.contains(path));
+        if !quiet && !dependents.is_empty() {
+            println!(
+                "  Including {} of {} dependent file(s) as context",
+                dependents.len().min(MAX_DEPENDENT_CONTEXT),
+                dependents.len()
+            );
+        }
+        context_paths.extend(dependents.into_iter().take(MAX_DEPENDENT_CONTEXT));
+    }
+
+    let mut context_paths = with_context_globs(config, context_paths);
+    context_paths.retain(|path| filter.matches(path));
+    context_paths
+}
+
+/// The one-line `--quiet` summary, for hooks
+fn print_quiet_summary(suggestions: &[TestSuggestion]) {
+    let count = suggestions.len();
+    if count == 0 {
+        return;
+    }
+    let security_count = suggestions
+        .iter()
+        .filter(|s| s.category == "security")
+        .count();
+
+    if security_count > 0 {
+        println!(
+            "VibeTap: {} test suggestion(s) available ({} security). Run 'vibetap generate' for details.",
+            count, security_count
         );
+    } else {
         println!(
-            "   {} {} | {} {:.0}%",
-            "Type:".dimmed(),
-            format_category(&suggestion.category),
-            "Confidence:".dimmed(),
-            suggestion.confidence * 100.0
+            "VibeTap: {} test suggestion(s) available. Run 'vibetap generate' for details or 'vibetap apply' to add.",
+            count
         );
-        println!("   {}", suggestion.description.dimmed());
+    }
+}
+
+/// Validate, lint, and print the suggestions, skipping the `shown` already streamed
+async fn print_suggestions(response: &GenerateResponse, shown: usize, args: &GenerateArgs) {
+    // Check suggestions in a sandbox before anything is displayed
+    let validations = validate::when_requested(&response.suggestions, args).await;
+
+    let lint_results = if args.no_lint {
+        Vec::new()
+    } else {
+        lint_suggestions(&response.suggestions).await
+    };
+
+    let shown_progressively = shown > 0 && shown == response.suggestions.len();
+    if !shown_progressively {
+        print_suggestions_header();
+    }
+
+    if response.used_byok {
+        println!(
+            "{} {}",
+            ui::icon("ℹ", "Note:").dimmed(),
+            "Using your own API key (BYOK mode)".dimmed()
+        );
+        println!();
+    }
+
+    if let Some(ref warning) = response.warning {
+        println!("{} {}", ui::icon("⚠", "Warning:").yellow(), warning.yellow());
         println!();
+    }
 
-        // Display the test code with a border
-        print_code_block(&suggestion.code, &suggestion.file_path);
+    if response.suggestions.is_empty() {
+        println!("{}", "No test suggestions generated.".yellow());
+        return;
+    }
 
-        if !suggestion.risks_addressed.is_empty() {
+    if shown_progressively {
+        // Lint results only exist once every suggestion is in
+        for (i, issues) in lint_results.iter().enumerate() {
+            if issues.is_empty() {
+                continue;
+            }
             println!(
-                "   {} {}",
-                "Risks:".dimmed(),
-                suggestion.risks_addressed.join(", ").dimmed()
+                "{} {}",
+                format!("{}.", i + 1).bold(),
+                response.suggestions[i].file_path.cyan()
+            );
+            lint::print_issues(issues);
+            println!();
+        }
+    } else {
+        for (i, suggestion) in response.suggestions.iter().enumerate() {
+            print_suggestion(
+                i + 1,
+                suggestion,
+                validations.as_ref().and_then(|v| v.get(i)),
+                lint_results.get(i).map(Vec::as_slice),
             );
         }
-        println!();
     }
 
     println!("{}", response.summary.dimmed());
     println!();
     println!(
-        "Run {} to apply a suggestion.",
-        "vibetap apply <number>".cyan()
+        "Run {} or {} to apply a suggestion.",
+        "vibetap apply <number>".cyan(),
+        "vibetap apply --id <id>".cyan()
     );
     println!(
         "Tokens used: {} | Model: {}",
         response.tokens_used.to_string().dimmed(),
         response.model_used.dimmed()
     );
-
-    Ok(())
 }
 
 fn build_request(
     diff: &vibetap_git::StagedDiff,
+    allocation: &Allocation,
     args: &GenerateArgs,
     config: &Config,
 ) -> GenerateRequest {
@@ -342,49 +906,354 @@ fn build_request(
             new_start: h.new_start,
             new_lines: h.new_lines,
             content: h.content.clone(),
+            context_header: h.context_header.clone(),
         })
         .collect();
 
-    // Load context files (the changed files themselves)
-    let context: Vec<FileContext> = diff
-        .files_changed
-        .iter()
-        .filter_map(|path| {
-            std::fs::read_to_string(path).ok().map(|content| FileContext {
-                path: path.clone(),
-                content: content.chars().take(50000).collect(), // Limit to 50KB
-                language: Some(detect_language(path)),
-            })
-        })
-        .take(10) // Limit context files
-        .collect();
+    let context: Vec<FileContext> = allocation
+        .files
+        .iter()
+        .map(|file| FileContext {
+            path: file.path.clone(),
+            content: file.content.clone(),
+            language: Some(detect_language(&file.path)),
+            start_line: file.start_line,
+            truncated: file.truncated,
+        })
+        .collect();
+
+    // Determine test runner
+    let test_runner = args.test_runner.clone().unwrap_or_else(|| {
+        config
+            .project
+            .as_ref()
+            .map(|p| p.test_runner.clone())
+            .unwrap_or_else(|| "vitest".to_string())
+    });
+
+    GenerateRequest {
+        diff: DiffPayload {
+            hunks,
+            base_branch: args
+                .base_branch
+                .clone()
+                .or_else(|| vibetap_git::default_branch().ok().flatten()),
+            head_commit: target_commit(args),
+        },
+        context,
+        options: GenerateOptions {
+            test_runner,
+            max_suggestions: args.max_suggestions,
+            include_security: args.security,
+            include_negative_paths: include_negative_paths(
+                config,
+                args.negative_paths,
+                args.no_negative_paths,
+            ),
+            model_tier: model_tier(
+                config,
+                args.model.as_deref().filter(|m| MODEL_TIERS.contains(m)),
+            ),
+            test_file: args
+                .test_file
+                .as_ref()
+                .map(|path| path.trim_start_matches("./").to_string()),
+        },
+        policy_pack_id: policy_pack(config, args.policy.as_deref()),
+        repo_identifier: vibetap_git::remote_identifier().ok().flatten(),
+        commit_messages: recent_commit_messages(config),
+        project_instructions: project_instructions(),
+        refine: None,
+    }
+}
+
+/// Commit the suggestions are for: the one given with --commit, the end of
+/// --range, or HEAD
+fn target_commit(args: &GenerateArgs) -> Option<String> {
+    let rev = match (&args.commit, &args.range) {
+        (Some(rev), _) => rev.as_str(),
+        (None, Some(range)) => match range.split_once("..") {
+            Some((_, to)) if !to.is_empty() => to,
+            _ => "HEAD",
+        },
+        (None, None) => return vibetap_git::head_commit().ok().flatten(),
+    };
+    vibetap_git::resolve_commit(rev).ok()
+}
+
+/// The leading paths whose combined size on disk fits in `budget`
+///
+/// A file too large for what is left is skipped so smaller ones after it can still fit.
+fn within_byte_budget(paths: Vec<String>, budget: u64) -> Vec<String> {
+    let mut remaining = budget;
+    paths
+        .into_iter()
+        .filter(|path| {
+            let size = std::fs::metadata(path).map_or(u64::MAX, |m| m.len());
+            if size > remaining {
+                return false;
+            }
+            remaining -= size;
+            true
+        })
+        .collect()
+}
+
+/// Apply the project's `context.include`/`context.exclude` globs to the context paths
+///
+/// Included files are added after `paths`; excluded ones are dropped from both.
+pub fn with_context_globs(config: &Config, mut paths: Vec<String>) -> Vec<String> {
+    let Some(context) = config.project.as_ref().and_then(|p| p.context.as_ref()) else {
+        return paths;
+    };
+    let exclude = glob_set(&context.exclude);
+    let include = glob_set(&context.include);
+
+    if !context.include.is_empty() {
+        let root = Path::new(".");
+        for file in IgnoreRules::load(root).files(root) {
+            let path = file.strip_prefix(".").unwrap_or(&file);
+            let path = path.to_string_lossy().replace('\\', "/");
+            if include.is_match(&path) && !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+
+    paths.retain(|path| !exclude.is_match(path));
+    paths
+}
+
+/// Compile globs, skipping invalid ones the way `.gitignore` does
+/// The --only, --exclude, and --lang filters
+struct PathFilter {
+    only: Option<GlobSet>,
+    exclude: GlobSet,
+    langs: Vec<String>,
+}
+
+impl PathFilter {
+    fn new(args: &GenerateArgs) -> Self {
+        Self {
+            only: (!args.only.is_empty()).then(|| glob_set(&args.only)),
+            exclude: glob_set(&args.exclude),
+            // An extension stands for its language, so `ts` also covers .tsx
+            langs: args
+                .lang
+                .iter()
+                .map(|lang| lang.trim().to_lowercase())
+                .filter(|lang| !lang.is_empty())
+                .map(|lang| match detect_language(&format!("x.{}", lang)).as_str() {
+                    "text" => lang,
+                    language => language.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.only.is_none() && self.exclude.is_empty() && self.langs.is_empty()
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        self.only.as_ref().is_none_or(|only| only.is_match(path))
+            && !self.exclude.is_match(path)
+            && (self.langs.is_empty() || self.langs.contains(&detect_language(path)))
+    }
+
+    /// Drop everything in `diff` outside the filters
+    fn apply(&self, mut diff: vibetap_git::StagedDiff) -> vibetap_git::StagedDiff {
+        if self.is_empty() {
+            return diff;
+        }
+        diff.hunks.retain(|h| self.matches(&h.file_path));
+        diff.files_changed.retain(|f| self.matches(f));
+        diff.binary_files.retain(|f| self.matches(f));
+        diff.submodules_changed.retain(|f| self.matches(f));
+        diff.mode_changes.retain(|m| self.matches(&m.file_path));
+        diff.truncation.files.retain(|f| self.matches(f));
+        diff
+    }
+}
+
+fn glob_set(patterns: &[String]) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        if let Ok(glob) = Glob::new(pattern) {
+            builder.add(glob);
+        }
+    }
+    builder.build().unwrap_or_else(|_| GlobSet::empty())
+}
+
+/// Fit `(path, content)` context files into the project's context budget
+///
+/// Files in the diff are served first, and cut around their first change.
+pub fn fit_context<'a>(
+    config: &Config,
+    diff: &vibetap_git::StagedDiff,
+    files: impl Iterator<Item = (&'a str, &'a str)>,
+) -> Allocation {
+    let settings = config.project.as_ref().and_then(|p| p.context.as_ref());
+    let defaults = ContextBudget::default();
+    let budget = ContextBudget {
+        max_files: settings.and_then(|c| c.max_files).unwrap_or(defaults.max_files),
+        max_bytes: settings.and_then(|c| c.max_bytes).unwrap_or(defaults.max_bytes),
+    };
+
+    let candidates: Vec<Candidate> = files
+        .map(|(path, content)| Candidate {
+            path,
+            content,
+            changed: diff.files_changed.iter().any(|f| f == path),
+            first_changed_line: diff
+                .hunks
+                .iter()
+                .filter(|h| h.file_path == path)
+                .map(|h| h.new_start)
+                .min(),
+        })
+        .collect();
+    allocate(&candidates, &budget)
+}
+
+/// Say which context files were cut or left out to fit the budget
+pub fn print_context_trimming(allocation: &Allocation) {
+    if !allocation.truncated.is_empty() {
+        println!(
+            "  {} Context cut to fit the budget: {}",
+            ui::icon("ℹ", "Note:").dimmed(),
+            allocation.truncated.join(", ").dimmed()
+        );
+    }
+    if !allocation.dropped.is_empty() {
+        println!(
+            "  {} Context left out (over budget): {}",
+            ui::icon("ℹ", "Note:").dimmed(),
+            allocation.dropped.join(", ").dimmed()
+        );
+    }
+}
+
+/// Model tier for a request: the flag, then `generation.modelTier`, then "default"
+pub fn model_tier(config: &Config, flag: Option<&str>) -> String {
+    if let Some(tier) = flag {
+        return tier.to_string();
+    }
+    match config
+        .project
+        .as_ref()
+        .and_then(|p| p.generation.model_tier.as_deref())
+    {
+        Some(tier) if MODEL_TIERS.contains(&tier) => tier.to_string(),
+        Some(tier) => {
+            eprintln!(
+                "{} Unknown generation.modelTier '{}' (expected one of: {}); using default",
+                ui::icon("⚠", "Warning:").yellow(),
+                tier,
+                MODEL_TIERS.join(", ")
+            );
+            "default".to_string()
+        }
+        None => "default".to_string(),
+    }
+}
+
+/// Parse a confidence threshold between 0 and 1
+pub fn parse_confidence(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(v) if (0.0..=1.0).contains(&v) => Ok(v),
+        _ => Err("expected a number from 0 to 1, e.g. 0.7".to_string()),
+    }
+}
+
+/// A `--session` name: it becomes a file name under `.vibetap/sessions`,
+/// so only letters, digits, `.`, `_` and `-` are allowed, and not `..`
+pub fn parse_session_name(value: &str) -> Result<String, String> {
+    let allowed = value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if value.is_empty() || !allowed || value.starts_with('.') || value.contains("..") {
+        return Err("use letters, digits, '.', '_' and '-', e.g. auth-refactor".to_string());
+    }
+    Ok(value.to_string())
+}
+
+/// Lowest confidence kept: the flag, then `generation.minConfidence`, then 0 (keep everything)
+pub fn min_confidence(config: &Config, flag: Option<f64>) -> f64 {
+    flag.or_else(|| {
+        config
+            .project
+            .as_ref()
+            .and_then(|p| p.generation.min_confidence)
+    })
+    .unwrap_or(0.0)
+}
+
+/// Drop suggestions below `threshold`, returning how many were dropped
+pub fn drop_low_confidence(response: &mut GenerateResponse, threshold: f64) -> usize {
+    let before = response.suggestions.len();
+    response.suggestions.retain(|s| s.confidence >= threshold);
+    before - response.suggestions.len()
+}
+
+/// Whether to ask for negative-path tests: the flags, then `generation.includeNegativePaths`
+pub fn include_negative_paths(config: &Config, on: bool, off: bool) -> bool {
+    if on || off {
+        return on;
+    }
+    config
+        .project
+        .as_ref()
+        .is_none_or(|p| p.generation.include_negative_paths)
+}
 
-    // Determine test runner
-    let test_runner = args.test_runner.clone().unwrap_or_else(|| {
+/// Policy pack for a request: the flag, then `generation.policyPack`
+pub fn policy_pack(config: &Config, flag: Option<&str>) -> Option<String> {
+    flag.map(String::from).or_else(|| {
         config
             .project
             .as_ref()
-            .map(|p| p.test_runner.clone())
-            .unwrap_or_else(|| "vitest".to_string())
-    });
+            .and_then(|p| p.generation.policy_pack.clone())
+    })
+}
 
-    GenerateRequest {
-        diff: DiffPayload {
-            hunks,
-            base_branch: None,
-            head_commit: None,
-        },
-        context,
-        options: GenerateOptions {
-            test_runner,
-            max_suggestions: args.max_suggestions,
-            include_security: args.security,
-            include_negative_paths: true,
-            model_tier: "default".to_string(),
-        },
-        policy_pack_id: None,
-        repo_identifier: None,
+/// The first of `INSTRUCTION_FILES` that exists and isn't empty
+pub fn instructions_file() -> Option<&'static str> {
+    INSTRUCTION_FILES.into_iter().find(|path| {
+        std::fs::read_to_string(path).is_ok_and(|content| !content.trim().is_empty())
+    })
+}
+
+/// Project conventions sent with every request, cut at a line boundary if very long
+pub fn project_instructions() -> Option<String> {
+    let content = std::fs::read_to_string(instructions_file()?).ok()?;
+    if content.len() <= MAX_INSTRUCTIONS_BYTES {
+        return Some(content);
+    }
+    let mut kept = String::new();
+    for line in content.split_inclusive('\n') {
+        if kept.len() + line.len() > MAX_INSTRUCTIONS_BYTES {
+            break;
+        }
+        kept.push_str(line);
+    }
+    Some(kept)
+}
+
+/// Commit messages sent as context when the project opts in
+pub fn recent_commit_messages(config: &Config) -> Option<Vec<String>> {
+    let enabled = config
+        .project
+        .as_ref()
+        .and_then(|p| p.generation.include_commit_messages)
+        .unwrap_or(false);
+    if !enabled {
+        return None;
     }
+    vibetap_git::last_commit_messages(RECENT_COMMIT_MESSAGES)
+        .ok()
+        .filter(|messages| !messages.is_empty())
 }
 
 fn detect_language(path: &str) -> String {
@@ -414,6 +1283,84 @@ fn detect_language(path: &str) -> String {
     }
 }
 
+/// Turn user paths into git pathspecs; bare names also match in subdirectories
+fn to_pathspecs(paths: &[String]) -> Vec<String> {
+    let mut specs = Vec::new();
+    for path in paths {
+        let path = path.trim_start_matches("./");
+        specs.push(path.to_string());
+        if !path.starts_with('/') && !path.starts_with("**/") {
+            specs.push(format!("**/{}", path));
+        }
+    }
+    specs
+}
+
+/// Lint every suggestion concurrently, returning issues in suggestion order
+async fn lint_suggestions(suggestions: &[TestSuggestion]) -> Vec<Vec<LintIssue>> {
+    let mut tasks = JoinSet::new();
+    for (i, suggestion) in suggestions.iter().enumerate() {
+        let code = suggestion.code.clone();
+        let file_path = suggestion.file_path.clone();
+        tasks.spawn(async move { (i, lint::lint(&code, &file_path).await) });
+    }
+
+    let mut results = vec![Vec::new(); suggestions.len()];
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok((i, issues)) = joined {
+            results[i] = issues;
+        }
+    }
+    results
+}
+
+fn print_suggestions_header() {
+    println!("\n{}", "=== Test Suggestions ===".bold());
+    println!();
+}
+
+/// Print one numbered suggestion with its code
+fn print_suggestion(
+    number: usize,
+    suggestion: &TestSuggestion,
+    validation: Option<&Validation>,
+    issues: Option<&[LintIssue]>,
+) {
+    println!(
+        "{} {} {}",
+        format!("{}.", number).bold(),
+        suggestion.file_path.cyan(),
+        format!("({})", suggestion.id).dimmed()
+    );
+    println!(
+        "   {} {} | {} {:.0}%",
+        "Type:".dimmed(),
+        format_category(&suggestion.category),
+        "Confidence:".dimmed(),
+        suggestion.confidence * 100.0
+    );
+    if let Some(validation) = validation {
+        print_validation(validation);
+    }
+    if let Some(issues) = issues {
+        lint::print_issues(issues);
+    }
+    println!("   {}", suggestion.description.dimmed());
+    println!();
+
+    // Display the test code with a border
+    print_code_block(&suggestion.code, &suggestion.file_path);
+
+    if !suggestion.risks_addressed.is_empty() {
+        println!(
+            "   {} {}",
+            "Risks:".dimmed(),
+            suggestion.risks_addressed.join(", ").dimmed()
+        );
+    }
+    println!();
+}
+
 fn format_category(category: &str) -> String {
     match category {
         "unit" => "Unit test".to_string(),
@@ -426,6 +1373,11 @@ fn format_category(category: &str) -> String {
 }
 
 fn print_code_block(code: &str, file_path: &str) {
+    if ui::is_accessible() {
+        ui::print_plain_code(code);
+        return;
+    }
+
     let ps = SyntaxSet::load_defaults_newlines();
     let ts = ThemeSet::load_defaults();
     let theme = &ts.themes["base16-ocean.dark"];
@@ -464,21 +1416,419 @@ fn print_code_block(code: &str, file_path: &str) {
     println!("   {}", "└─".dimmed());
 }
 
-/// Save suggestions to .vibetap/last-suggestions.json for apply command
-fn save_suggestions(response: &GenerateResponse, source_files: &[String]) -> anyhow::Result<()> {
-    let vibetap_dir = Path::new(".vibetap");
-    if !vibetap_dir.exists() {
-        std::fs::create_dir_all(vibetap_dir)?;
+/// A changed file read from disk, shared between request building and change detection
+struct LoadedFile {
+    path: String,
+    content: String,
+    hash: String,
+}
+
+/// Maximum number of files read concurrently while loading context
+const CONTEXT_LOAD_CONCURRENCY: usize = 8;
+
+/// Show per-file load progress when a diff touches more files than this
+const CONTEXT_PROGRESS_THRESHOLD: usize = 10;
+
+/// Read and hash changed files concurrently, preserving their original order.
+/// Unreadable files (deleted, binary, permission errors) are skipped.
+async fn load_context_files(paths: &[String], show_progress: bool) -> Vec<LoadedFile> {
+    let semaphore = Arc::new(Semaphore::new(CONTEXT_LOAD_CONCURRENCY));
+    let mut tasks = JoinSet::new();
+
+    for (index, path) in paths.iter().cloned().enumerate() {
+        let semaphore = semaphore.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await.ok()?;
+            let content = tokio::fs::read_to_string(&path).await.ok()?;
+            let hash = compute_hash(&content);
+            Some((index, LoadedFile { path, content, hash }))
+        });
+    }
+
+    let progress_bar = if show_progress
+        && !ui::is_accessible()
+        && paths.len() > CONTEXT_PROGRESS_THRESHOLD
+    {
+        let pb = ProgressBar::new(paths.len() as u64);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("  {msg} [{bar:30.cyan/dim}] {pos}/{len}")
+                .unwrap()
+                .progress_chars("█░ "),
+        );
+        pb.set_message("Loading context");
+        Some(pb)
+    } else {
+        None
+    };
+
+    let mut slots: Vec<Option<LoadedFile>> = (0..paths.len()).map(|_| None).collect();
+    while let Some(result) = tasks.join_next().await {
+        if let Ok(Some((index, file))) = result {
+            slots[index] = Some(file);
+        }
+        if let Some(ref pb) = progress_bar {
+            pb.inc(1);
+        }
+    }
+
+    if let Some(pb) = progress_bar {
+        pb.finish_and_clear();
+    }
+
+    slots.into_iter().flatten().collect()
+}
+
+/// Send the request, split into batches when it is too large, and wait for the suggestions
+///
+/// Unless output is quiet or suggestions are validated first, streamed
+/// suggestions are printed as they arrive; the count printed is returned with
+/// the response. Returns `None` when generation failed; the error has already
+/// been shown.
+async fn request_suggestions(
+    request: GenerateRequest,
+    diff: &vibetap_git::StagedDiff,
+    loaded_files: &[LoadedFile],
+    args: &GenerateArgs,
+    config: &mut Config,
+    payload_size: usize,
+) -> anyhow::Result<Option<(GenerateResponse, usize)>> {
+    let quiet = args.quiet();
+    // Validation has to finish before anything is shown, and a refinement
+    // is printed under its own number
+    let progressive = !quiet && !args.validate && !args.run_sandbox && args.refine.is_empty();
+    // Huge diffs go out as several smaller requests
+    let batches = batch::plan(&request, payload_size);
+
+    let access_token = config.get_valid_access_token().await?;
+    let api_url = config.api_url().to_string();
+
+    // Show upload progress bar (only in non-quiet mode)
+    if !quiet {
+        print_upload_progress(payload_size);
+    }
+
+    // Call the streaming API
+    let mut client = ApiClient::new(api_url, access_token);
+    if let Some(dir) = args.debug_dump.clone().or_else(debug_dir_from_env) {
+        if !quiet {
+            println!(
+                "  {} Writing a debug dump (secrets redacted) to {}",
+                ui::icon("ℹ", "Note:").dimmed(),
+                dir.display()
+            );
+        }
+        client = client.with_debug_dir(dir);
+    }
+
+    // Create progress bar for generation phase (plain status lines in accessible mode)
+    let accessible = ui::is_accessible();
+    let mut last_phase = String::new();
+    let progress_bar = if !quiet && !accessible {
+        let pb = ProgressBar::new_spinner();
+        pb.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner:.cyan} {msg}")
+                .unwrap(),
+        );
+        pb.enable_steady_tick(Duration::from_millis(100));
+        Some(pb)
+    } else {
+        None
+    };
+
+    // Track suggestions as they stream in
+    let mut streamed_suggestions: Vec<TestSuggestion> = Vec::new();
+    let threshold = min_confidence(config, args.min_confidence);
+
+    let response = if batches.len() > 1 {
+        if let Some(pb) = progress_bar {
+            pb.finish_and_clear();
+        }
+        let Some(response) = batch::generate(
+            Arc::new(client),
+            &batches,
+            diff,
+            loaded_files,
+            args,
+            config,
+        )
+        .await
+        else {
+            return Ok(None);
+        };
+        response
+    } else {
+        let max_retries = retry::max_retries(config, args.no_retry);
+        let mut attempt = 0;
+        let result = loop {
+            let result = client
+                .generate_streaming(request.clone(), |event| {
+                    match event {
+                        StreamEvent::Progress { phase, message, .. } => {
+                            if accessible && !quiet && phase != last_phase {
+                                println!("  {}", message);
+                                last_phase = phase.clone();
+                            }
+                            if let Some(ref pb) = progress_bar {
+                                let phase_icon = match phase.as_str() {
+                                    "authenticating" => "🔐",
+                                    "analyzing" => "🔍",
+                                    "context" => "📚",
+                                    "generating" => "⚡",
+                                    _ => "•",
+                                };
+                                pb.set_message(format!("{} {}", phase_icon, message));
+                            }
+                        }
+                        StreamEvent::Suggestion {
+                            index,
+                            total,
+                            suggestion,
+                        } => {
+                            if let Some(ref pb) = progress_bar {
+                                pb.set_message(format!(
+                                    "📝 Generated suggestion {}/{}: {}",
+                                    index,
+                                    total,
+                                    suggestion.file_path.cyan()
+                                ));
+                            } else if accessible && !quiet && !progressive {
+                                println!(
+                                    "  Generated suggestion {} of {}: {}",
+                                    index, total, suggestion.file_path
+                                );
+                            }
+                            // Suggestions under --min-confidence are dropped once the response is in
+                            if suggestion.confidence < threshold {
+                                return;
+                            }
+                            if progressive {
+                                let number = streamed_suggestions.len() + 1;
+                                let print = || {
+                                    if number == 1 {
+                                        print_suggestions_header();
+                                    }
+                                    print_suggestion(number, &suggestion, None, None);
+                                };
+                                match progress_bar {
+                                    Some(ref pb) => pb.suspend(print),
+                                    None => print(),
+                                }
+                            }
+                            streamed_suggestions.push(suggestion);
+                        }
+                        StreamEvent::Complete { .. } => {
+                            if let Some(ref pb) = progress_bar {
+                                pb.finish_and_clear();
+                            }
+                        }
+                        StreamEvent::Error { code, message } => {
+                            if let Some(ref pb) = progress_bar {
+                                pb.finish_and_clear();
+                            }
+                            if !quiet {
+                                eprintln!("\n{} {} - {}", "Error:".red(), code, message);
+                            }
+                        }
+                    }
+                })
+                .await;
+            match result {
+                Err(ApiError::RateLimited { retry_after }) if attempt < max_retries => {
+                    let Some(secs) = retry::delay(retry_after, attempt) else {
+                        break result;
+                    };
+                    attempt += 1;
+                    retry::countdown(
+                        "Rate limited",
+                        secs,
+                        attempt,
+                        max_retries,
+                        progress_bar.as_ref(),
+                        quiet,
+                    )
+                    .await;
+                }
+                _ => break result,
+            }
+        };
+
+        match result {
+            Ok(r) => r,
+            Err(e) => {
+                if let Some(pb) = progress_bar {
+                    pb.finish_and_clear();
+                }
+                if !quiet {
+                    println!("\n{} {}", "Error:".red(), e);
+                }
+                return Ok(None);
+            }
+        }
+    };
+
+    let shown = if progressive && batches.len() <= 1 {
+        streamed_suggestions.len()
+    } else {
+        0
+    };
+    Ok(Some((response, shown)))
+}
+
+/// The backend to generate with instead of the VibeTap API, if any
+///
+/// `--backend` and `--direct` win; otherwise a backend configured with
+/// `always` is used. `--model` may name a model only for these backends.
+fn local_backend(
+    args: &GenerateArgs,
+    config: &Config,
+) -> anyhow::Result<Option<Box<dyn GenerateBackend>>> {
+    let global = &config.global;
+    let backend = match (args.backend.as_deref(), args.direct) {
+        (Some(backend), _) => backend,
+        (None, true) => "byok",
+        _ if global.ollama.as_ref().is_some_and(|o| o.always) => "ollama",
+        _ if global.byok.as_ref().is_some_and(|b| b.always) => "byok",
+        _ => "vibetap",
+    };
+    let model = args
+        .model
+        .clone()
+        .filter(|m| !MODEL_TIERS.contains(&m.as_str()));
+
+    match backend {
+        "ollama" => Ok(Some(Box::new(OllamaClient::from_config(
+            global.ollama.as_ref(),
+            model,
+        )))),
+        "byok" => match global.byok {
+            Some(ref byok) => Ok(Some(Box::new(
+                ProviderClient::from_config(byok)?.with_model(model),
+            ))),
+            None => anyhow::bail!(
+                "No provider key configured. Add one with 'vibetap byok set openai' first."
+            ),
+        },
+        _ => match model {
+            Some(model) => anyhow::bail!(
+                "Unknown model tier '{}' (expected one of: {}). Model names need --backend byok or ollama.",
+                model,
+                MODEL_TIERS.join(", ")
+            ),
+            None => Ok(None),
+        },
+    }
+}
+
+/// Identifies a local backend's answers in the cache, so they are not
+/// mixed up with the API's or another model's
+fn backend_label(backend: Option<&dyn GenerateBackend>, request: &GenerateRequest) -> Option<String> {
+    backend.map(|b| format!("{}:{}", b.name(), b.model_for(request)))
+}
+
+/// Generate with a local backend; the prompt is built on this machine
+///
+/// Returns `None` when generation failed; the error has already been shown.
+async fn generate_local(
+    backend: &dyn GenerateBackend,
+    request: GenerateRequest,
+    args: &GenerateArgs,
+    config: &Config,
+) -> Option<GenerateResponse> {
+    let quiet = args.quiet();
+    let model = backend.model_for(&request);
+    if !quiet {
+        println!(
+            "  Generating with {} ({}); the diff is not sent to VibeTap",
+            backend.name(),
+            model
+        );
+        if request.policy_pack_id.is_some() {
+            println!(
+                "  {} Policy packs are applied by the VibeTap API and are ignored here",
+                ui::icon("ℹ", "Note:").dimmed()
+            );
+        }
+    }
+
+    let spinner = (!quiet && !ui::is_accessible()).then(|| {
+        let pb = ProgressBar::new_spinner();
+        pb.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner:.cyan} {msg}")
+                .unwrap(),
+        );
+        pb.set_message("⚡ Generating tests");
+        pb.enable_steady_tick(Duration::from_millis(100));
+        pb
+    });
+
+    let max_retries = retry::max_retries(config, args.no_retry);
+    let mut attempt = 0;
+    let result = loop {
+        let result = backend.generate(&request).await;
+        match result {
+            Err(ApiError::RateLimited { retry_after }) if attempt < max_retries => {
+                let Some(secs) = retry::delay(retry_after, attempt) else {
+                    break result;
+                };
+                attempt += 1;
+                retry::countdown(
+                    "Rate limited",
+                    secs,
+                    attempt,
+                    max_retries,
+                    spinner.as_ref(),
+                    quiet,
+                )
+                .await;
+            }
+            _ => break result,
+        }
+    };
+    if let Some(pb) = spinner {
+        pb.finish_and_clear();
     }
 
-    // Compute hashes of source files
-    let mut file_hashes = HashMap::new();
-    for path in source_files {
-        if let Ok(content) = std::fs::read_to_string(path) {
-            file_hashes.insert(path.clone(), compute_hash(&content));
+    match result {
+        Ok(response) => Some(response),
+        Err(e) => {
+            if !quiet {
+                println!("\n{} {}", "Error:".red(), e);
+            }
+            None
         }
     }
+}
+
+/// Print a machine-readable summary of the suggestions as one line of JSON
+fn print_porcelain(suggestions: &[TestSuggestion]) -> anyhow::Result<()> {
+    let summary = PorcelainSummary {
+        count: suggestions.len(),
+        security: suggestions
+            .iter()
+            .filter(|s| s.category == "security")
+            .count(),
+        ids: suggestions.iter().map(|s| s.id.as_str()).collect(),
+    };
+    println!("{}", serde_json::to_string(&summary)?);
+    Ok(())
+}
 
+/// Save suggestions for the apply command
+///
+/// Each branch keeps its own set under `.vibetap/suggestions/`, so switching
+/// branches doesn't clobber them. `.vibetap/last-suggestions.json` holds the
+/// latest set from any branch.
+pub fn save_suggestions(
+    response: &GenerateResponse,
+    file_hashes: HashMap<String, String>,
+    session: Option<&str>,
+    stash_commit: Option<String>,
+    diff_hash: Option<String>,
+    hunks: Vec<HunkRange>,
+) -> anyhow::Result<()> {
     let saved = SavedSuggestions {
         response: response.clone(),
         source_files: file_hashes,
@@ -486,11 +1836,65 @@ fn save_suggestions(response: &GenerateResponse, source_files: &[String]) -> any
             .duration_since(std::time::UNIX_EPOCH)
             .map(|d| d.as_secs() as i64)
             .unwrap_or(0),
+        session: session.map(String::from),
+        head_commit: vibetap_git::head_commit().ok().flatten(),
+        stash_commit,
+        branch: vibetap_git::current_branch().ok().flatten(),
+        diff_hash,
+        hunks,
+        test_file_bases: test_file_bases(&response.suggestions),
+        test_file_hashes: test_file_hashes(&response.suggestions),
     };
+    write_suggestions(&saved)
+}
+
+/// Current contents of the test files the suggestions target that exist
+fn test_file_bases(suggestions: &[TestSuggestion]) -> HashMap<String, String> {
+    suggestions
+        .iter()
+        .filter_map(|s| {
+            let content = std::fs::read_to_string(&s.file_path).ok()?;
+            Some((s.file_path.clone(), content))
+        })
+        .collect()
+}
+
+/// Hash of each target test file's current contents, or "" if it doesn't exist
+fn test_file_hashes(suggestions: &[TestSuggestion]) -> HashMap<String, String> {
+    suggestions
+        .iter()
+        .map(|s| {
+            let hash = std::fs::read_to_string(&s.file_path)
+                .map(|content| compute_hash(&content))
+                .unwrap_or_default();
+            (s.file_path.clone(), hash)
+        })
+        .collect()
+}
+
+/// Write a suggestion set to the latest, per-branch, and session files
+fn write_suggestions(saved: &SavedSuggestions) -> anyhow::Result<()> {
+    let vibetap_dir = Path::new(".vibetap");
+    std::fs::create_dir_all(vibetap_dir)?;
 
     let suggestions_path = vibetap_dir.join("last-suggestions.json");
-    let json = serde_json::to_string_pretty(&saved)?;
-    std::fs::write(suggestions_path, json)?;
+    let json = serde_json::to_string_pretty(saved)?;
+    std::fs::write(suggestions_path, &json)?;
+
+    if let Some(ref branch) = saved.branch {
+        let path = branch_suggestions_path(branch);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, &json)?;
+    }
+
+    // Keep a named copy so the set can be shared or revisited later
+    if let Some(ref name) = saved.session {
+        let sessions_dir = vibetap_dir.join("sessions");
+        std::fs::create_dir_all(&sessions_dir)?;
+        std::fs::write(sessions_dir.join(format!("{}.json", name)), json)?;
+    }
 
     Ok(())
 }
@@ -505,32 +1909,213 @@ pub fn compute_hash(content: &str) -> String {
     format!("{:016x}", hasher.finish())
 }
 
-/// Load the last saved suggestions
+/// Where the suggestion set for `branch` is kept
+fn branch_suggestions_path(branch: &str) -> PathBuf {
+    // Branch names may contain '/', which would otherwise nest directories
+    Path::new(".vibetap/suggestions").join(format!("{}.json", branch.replace('/', "__")))
+}
+
+/// Stop offering the latest set when it was made on a branch other than
+/// `branch`; it stays saved for its own branch
+pub fn forget_other_branch_suggestions(branch: Option<&str>) -> anyhow::Result<()> {
+    let suggestions_path = Path::new(".vibetap/last-suggestions.json");
+    if !suggestions_path.exists() {
+        return Ok(());
+    }
+    let saved = parse_saved_suggestions(&std::fs::read_to_string(suggestions_path)?)?;
+    match saved.branch {
+        Some(ref made_on) if Some(made_on.as_str()) != branch => {
+            std::fs::remove_file(suggestions_path)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Load the suggestions for the current branch, or the latest set from any branch
 pub fn load_suggestions() -> anyhow::Result<SavedSuggestions> {
+    if let Some(branch) = vibetap_git::current_branch().ok().flatten() {
+        let path = branch_suggestions_path(&branch);
+        if path.exists() {
+            return parse_saved_suggestions(&std::fs::read_to_string(path)?);
+        }
+    }
+
     let suggestions_path = Path::new(".vibetap/last-suggestions.json");
     if !suggestions_path.exists() {
         anyhow::bail!("No suggestions found. Run 'vibetap generate' first.");
     }
 
-    let content = std::fs::read_to_string(suggestions_path)?;
+    parse_saved_suggestions(&std::fs::read_to_string(suggestions_path)?)
+}
+
+/// Load the suggestion set saved for `branch`
+pub fn load_branch_suggestions(branch: &str) -> anyhow::Result<SavedSuggestions> {
+    let path = branch_suggestions_path(branch);
+    if !path.exists() {
+        anyhow::bail!(
+            "No suggestions saved for branch '{}'. Check it out and run 'vibetap generate' first.",
+            branch
+        );
+    }
+
+    parse_saved_suggestions(&std::fs::read_to_string(path)?)
+}
+
+/// Load a suggestion set from a file, e.g. one exported from CI or another machine
+pub fn load_suggestions_file(path: &Path) -> anyhow::Result<SavedSuggestions> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Could not read {}: {}", path.display(), e))?;
+    let saved = parse_saved_suggestions(&content)
+        .map_err(|e| anyhow::anyhow!("{} is not a suggestion set: {}", path.display(), e))?;
+
+    // A set made elsewhere must not write outside the project
+    for suggestion in &saved.response.suggestions {
+        let target = Path::new(&suggestion.file_path);
+        if suggestion.file_path.is_empty()
+            || target.is_absolute()
+            || target
+                .components()
+                .any(|c| matches!(c, std::path::Component::ParentDir))
+        {
+            anyhow::bail!(
+                "{}: suggestion {} targets '{}', which is outside the project.",
+                path.display(),
+                suggestion.id,
+                suggestion.file_path
+            );
+        }
+    }
+    Ok(saved)
+}
+
+/// Load a suggestion set saved under a session name
+pub fn load_session(name: &str) -> anyhow::Result<SavedSuggestions> {
+    let session_path = Path::new(".vibetap/sessions").join(format!("{}.json", name));
+    if !session_path.exists() {
+        anyhow::bail!(
+            "No session named '{}'. Run 'vibetap generate --session {}' first.",
+            name,
+            name
+        );
+    }
+
+    parse_saved_suggestions(&std::fs::read_to_string(session_path)?)
+}
 
+fn parse_saved_suggestions(content: &str) -> anyhow::Result<SavedSuggestions> {
     // Try to load new format first, fall back to old format for backwards compatibility
-    if let Ok(saved) = serde_json::from_str::<SavedSuggestions>(&content) {
+    if let Ok(saved) = serde_json::from_str::<SavedSuggestions>(content) {
         return Ok(saved);
     }
 
     // Fall back to old format (just GenerateResponse)
-    let response: GenerateResponse = serde_json::from_str(&content)?;
+    let response: GenerateResponse = serde_json::from_str(content)?;
     Ok(SavedSuggestions {
         response,
         source_files: HashMap::new(), // No hashes in old format
         generated_at: 0,
+        session: None,
+        head_commit: None,
+        stash_commit: None,
+        branch: None,
+        diff_hash: None,
+        hunks: Vec::new(),
+        test_file_bases: HashMap::new(),
+        test_file_hashes: HashMap::new(),
     })
 }
 
+/// Summarize the request that would be sent, and everything left out of it
+fn print_dry_run(
+    request: &GenerateRequest,
+    payload_size: usize,
+    diff: &vibetap_git::StagedDiff,
+    allocation: &Allocation,
+    show_json: bool,
+) -> anyhow::Result<()> {
+    println!();
+    println!("{}", "Dry run: nothing was sent.".cyan().bold());
+    println!("  Files:         {}", diff.files_changed.len());
+    for file in &diff.files_changed {
+        println!("    {}", file.dimmed());
+    }
+    println!("  Hunks:         {}", request.diff.hunks.len());
+    println!("  Context files: {}", request.context.len());
+    for file in &request.context {
+        println!("    {} ({} bytes)", file.path.dimmed(), file.content.len());
+    }
+    if let Some(ref messages) = request.commit_messages {
+        println!("  Commit messages: {}", messages.len());
+    }
+    if let Some(ref policy) = request.policy_pack_id {
+        println!("  Policy pack:   {}", policy);
+    }
+    if let Some(ref instructions) = request.project_instructions {
+        let path = instructions_file().unwrap_or(INSTRUCTION_FILES[0]);
+        println!("  Instructions:  {} ({} bytes)", path, instructions.len());
+    }
+    println!("  Payload size:  {:.1} KB", payload_size as f64 / 1024.0);
+
+    // Anything dropped or shortened before sending
+    let mut left_out: Vec<String> = Vec::new();
+    left_out.extend(diff.binary_files.iter().map(|f| format!("{} (binary)", f)));
+    left_out.extend(diff.submodules_changed.iter().map(|f| format!("{} (submodule)", f)));
+    left_out.extend(
+        diff.mode_changes
+            .iter()
+            .map(|m| format!("{} ({})", m.file_path, m.kind.as_str())),
+    );
+    left_out.extend(
+        diff.truncation
+            .files
+            .iter()
+            .map(|f| format!("{} (diff cut at size limit)", f)),
+    );
+    if diff.truncation.total_limit_reached {
+        left_out.push("later files (total diff size limit)".to_string());
+    }
+    left_out.extend(
+        allocation
+            .truncated
+            .iter()
+            .map(|f| format!("{} (context cut to fit the budget)", f)),
+    );
+    left_out.extend(
+        allocation
+            .dropped
+            .iter()
+            .map(|f| format!("{} (context over budget)", f)),
+    );
+    if left_out.is_empty() {
+        println!("  Left out:      nothing");
+    } else {
+        println!("  Left out:");
+        for item in &left_out {
+            println!("    {}", item.dimmed());
+        }
+    }
+
+    if show_json {
+        println!();
+        println!("{}", serde_json::to_string_pretty(request)?);
+    } else {
+        println!();
+        println!("Run with {} to print the full payload.", "--dry-run --json".cyan());
+    }
+
+    Ok(())
+}
+
 /// Print a nice ASCII art upload progress bar
 fn print_upload_progress(payload_size: usize) {
     let size_kb = payload_size as f64 / 1024.0;
+
+    if ui::is_accessible() {
+        println!("  Uploading {:.1} KB... done", size_kb);
+        return;
+    }
+
     let bar_width = 30;
     let filled = bar_width; // Instant upload visualization
 
@@ -571,3 +2156,19 @@ fn print_upload_progress(payload_size: usize) {
         "✓".green()
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_session_name() {
+        assert_eq!(
+            parse_session_name("auth-refactor_2.1").unwrap(),
+            "auth-refactor_2.1"
+        );
+        for name in ["", "../../x", "a/b", "..", ".hidden", "a b", "a\\b"] {
+            assert!(parse_session_name(name).is_err(), "{:?} was accepted", name);
+        }
+    }
+}
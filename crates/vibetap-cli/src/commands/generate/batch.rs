@@ -0,0 +1,223 @@
+//! Sending a diff too large for one request as several smaller ones
+
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::Arc;
+use std::time::Duration;
+
+use vibetap_core::{
+    api::{ApiError, GenerateRequest, GenerateResponse, StreamEvent},
+    orchestrator::{self, ChunkEvent, Orchestrator},
+    ApiClient, Config,
+};
+use super::{build_request, fit_context, GenerateArgs, LoadedFile};
+use crate::retry;
+use crate::ui;
+
+/// The files of each batch, or none when the request can go out whole
+///
+/// A refinement asks for one suggestion, so it is never split.
+pub fn plan(request: &GenerateRequest, payload_size: usize) -> Vec<Vec<String>> {
+    if payload_size > orchestrator::CHUNK_THRESHOLD_BYTES && request.refine.is_none() {
+        orchestrator::plan(&request.diff.hunks, orchestrator::CHUNK_DIFF_BYTES)
+    } else {
+        Vec::new()
+    }
+}
+
+/// The part of `diff` touching `files`
+pub fn sub_diff(diff: &vibetap_git::StagedDiff, files: &[String]) -> vibetap_git::StagedDiff {
+    vibetap_git::StagedDiff {
+        hunks: diff
+            .hunks
+            .iter()
+            .filter(|h| files.contains(&h.file_path))
+            .cloned()
+            .collect(),
+        files_changed: files.to_vec(),
+        binary_files: Vec::new(),
+        submodules_changed: Vec::new(),
+        mode_changes: Vec::new(),
+        truncation: vibetap_git::Truncation::default(),
+    }
+}
+
+/// Stream each batch of files as its own request, several at once, and merge the suggestions
+///
+/// Progress from every batch feeds one progress bar. Rate-limited batches
+/// are sent again like a single request would be; batches that still fail
+/// are reported and skipped. Returns None if every batch failed. Each batch
+/// may suggest up to `--max-suggestions`, so only the most confident of
+/// them are kept.
+pub async fn generate(
+    client: Arc<ApiClient>,
+    batches: &[Vec<String>],
+    diff: &vibetap_git::StagedDiff,
+    loaded_files: &[LoadedFile],
+    args: &GenerateArgs,
+    config: &Config,
+) -> Option<GenerateResponse> {
+    let quiet = args.quiet();
+    let requests: Vec<GenerateRequest> = batches
+        .iter()
+        .map(|files| {
+            let part = sub_diff(diff, files);
+            let allocation = fit_context(
+                config,
+                &part,
+                loaded_files
+                    .iter()
+                    .filter(|f| files.contains(&f.path))
+                    .map(|f| (f.path.as_str(), f.content.as_str())),
+            );
+            build_request(&part, &allocation, args, config)
+        })
+        .collect();
+
+    let concurrency = config
+        .project
+        .as_ref()
+        .and_then(|p| p.generation.concurrency)
+        .unwrap_or(orchestrator::DEFAULT_CONCURRENCY);
+    if !quiet {
+        println!(
+            "  Diff is too large for one request; sending it in {} batches, {} at a time",
+            batches.len(),
+            concurrency.min(batches.len())
+        );
+    }
+    let progress_bar = (!quiet && !ui::is_accessible()).then(|| {
+        let pb = ProgressBar::new(batches.len() as u64);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("  [{bar:30.cyan/dim}] {pos}/{len} {msg}")
+                .unwrap()
+                .progress_chars("█░ "),
+        );
+        pb.enable_steady_tick(Duration::from_millis(100));
+        pb
+    });
+
+    let mut suggestions = 0;
+    let mut on_event = |event| match event {
+        ChunkEvent::Stream { chunk, event } => match *event {
+            StreamEvent::Progress { message, .. } => {
+                if let Some(ref pb) = progress_bar {
+                    pb.set_message(format!(
+                        "{} suggestion(s) | batch {}: {}",
+                        suggestions,
+                        chunk + 1,
+                        message
+                    ));
+                }
+            }
+            StreamEvent::Suggestion { suggestion, .. } => {
+                suggestions += 1;
+                if let Some(ref pb) = progress_bar {
+                    pb.set_message(format!(
+                        "{} suggestion(s) | batch {}: {}",
+                        suggestions,
+                        chunk + 1,
+                        suggestion.file_path
+                    ));
+                }
+            }
+            _ => {}
+        },
+        ChunkEvent::Finished { chunk, ok } => {
+            if let Some(ref pb) = progress_bar {
+                pb.inc(1);
+            } else if !quiet {
+                let status = if ok { "done" } else { "failed" };
+                println!("  Batch {} of {} {}", chunk + 1, batches.len(), status);
+            }
+        }
+    };
+    let orchestrator = Orchestrator::new(client, concurrency);
+    let mut results = orchestrator.run(requests.clone(), &mut on_event).await;
+
+    // Rate-limited batches go again together, after the longest wait any was given
+    let max_retries = retry::max_retries(config, args.no_retry);
+    let mut attempt = 0;
+    while attempt < max_retries {
+        let limited: Vec<(usize, u64)> = results
+            .iter()
+            .enumerate()
+            .filter_map(|(chunk, result)| match result {
+                Err(ApiError::RateLimited { retry_after }) => Some((chunk, *retry_after)),
+                _ => None,
+            })
+            .collect();
+        let retry_after = limited.iter().map(|(_, secs)| *secs).max();
+        let Some(secs) = retry_after.and_then(|secs| retry::delay(secs, attempt)) else {
+            break;
+        };
+        attempt += 1;
+        retry::countdown(
+            "Rate limited",
+            secs,
+            attempt,
+            max_retries,
+            progress_bar.as_ref(),
+            quiet,
+        )
+        .await;
+        if let Some(ref pb) = progress_bar {
+            pb.inc_length(limited.len() as u64);
+        }
+
+        let chunks: Vec<usize> = limited.into_iter().map(|(chunk, _)| chunk).collect();
+        let retried = orchestrator
+            .run(
+                chunks
+                    .iter()
+                    .map(|&chunk| requests[chunk].clone())
+                    .collect(),
+                |event| {
+                    // Numbered as in the first round
+                    on_event(match event {
+                        ChunkEvent::Stream { chunk, event } => ChunkEvent::Stream {
+                            chunk: chunks[chunk],
+                            event,
+                        },
+                        ChunkEvent::Finished { chunk, ok } => ChunkEvent::Finished {
+                            chunk: chunks[chunk],
+                            ok,
+                        },
+                    })
+                },
+            )
+            .await;
+        for (&chunk, result) in chunks.iter().zip(retried) {
+            results[chunk] = result;
+        }
+    }
+    if let Some(pb) = progress_bar {
+        pb.finish_and_clear();
+    }
+
+    let mut responses = Vec::new();
+    for (index, result) in results.into_iter().enumerate() {
+        match result {
+            Ok(response) => responses.push(response),
+            // Suggestions for these files are missing, even with --quiet
+            Err(e) => eprintln!(
+                "  {} Batch {} ({}) failed: {}",
+                ui::icon("⚠", "Warning:").yellow(),
+                index + 1,
+                batches[index].join(", "),
+                e
+            ),
+        }
+    }
+
+    if responses.is_empty() {
+        if !quiet {
+            println!("\n{} Every batch failed.", "Error:".red());
+        }
+        return None;
+    }
+    let mut merged = orchestrator::merge(responses);
+    orchestrator::keep_best(&mut merged, args.max_suggestions as usize);
+    Some(merged)
+}
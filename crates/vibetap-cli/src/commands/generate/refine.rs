@@ -0,0 +1,170 @@
+//! Regenerating one saved suggestion, for `vibetap generate --refine N "..."`
+
+use colored::Colorize;
+use std::collections::HashMap;
+
+use vibetap_core::api::{GenerateRequest, GenerateResponse, RefineRequest, TestSuggestion};
+use super::batch::sub_diff;
+use super::export;
+use super::validate;
+use super::{
+    lint_suggestions, load_suggestions, print_porcelain, print_suggestion, test_file_bases,
+    test_file_hashes, write_suggestions, GenerateArgs, SavedSuggestions,
+};
+use crate::ui;
+
+/// A saved suggestion being regenerated with --refine
+pub struct Refine {
+    /// 1-based slot in the saved set
+    number: usize,
+    instruction: String,
+    saved: SavedSuggestions,
+}
+
+impl Refine {
+    /// Parse --refine against the saved suggestion set
+    pub fn from_args(args: &GenerateArgs) -> anyhow::Result<Option<Self>> {
+        let [number, instruction] = args.refine.as_slice() else {
+            return Ok(None);
+        };
+        let saved = load_suggestions()?;
+        let count = saved.response.suggestions.len();
+        let number = number
+            .parse::<usize>()
+            .ok()
+            .filter(|n| (1..=count).contains(n))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Invalid suggestion number '{}'. Choose 1-{} from the last 'vibetap generate'.",
+                    number,
+                    count
+                )
+            })?;
+        if instruction.trim().is_empty() {
+            anyhow::bail!("--refine needs an instruction, e.g. --refine {} \"use fixtures\".", number);
+        }
+        Ok(Some(Self {
+            number,
+            instruction: instruction.clone(),
+            saved,
+        }))
+    }
+
+    fn previous(&self) -> &TestSuggestion {
+        &self.saved.response.suggestions[self.number - 1]
+    }
+
+    /// Only the changes the suggestion tests, or the whole diff when its name matches no file
+    pub fn narrow(&self, diff: vibetap_git::StagedDiff) -> vibetap_git::StagedDiff {
+        let files: Vec<String> = diff
+            .files_changed
+            .iter()
+            .filter(|path| export::covers(&self.previous().file_path, path))
+            .cloned()
+            .collect();
+        if files.is_empty() {
+            diff
+        } else {
+            sub_diff(&diff, &files)
+        }
+    }
+
+    /// Ask for one replacement, written to the same file as before
+    pub fn apply_to(&self, request: &mut GenerateRequest) {
+        request.options.max_suggestions = 1;
+        request.options.test_file = Some(self.previous().file_path.clone());
+        request.refine = Some(RefineRequest {
+            previous: self.previous().clone(),
+            instruction: self.instruction.clone(),
+        });
+    }
+}
+
+/// Put the regenerated suggestion in its slot of the saved set and show it
+pub async fn finish(
+    mut refine: Refine,
+    response: GenerateResponse,
+    file_hashes: HashMap<String, String>,
+    args: &GenerateArgs,
+) -> anyhow::Result<()> {
+    let quiet = args.quiet();
+    let Some(suggestion) = response.suggestions.into_iter().next() else {
+        if args.export.is_some() {
+            anyhow::bail!("No replacement was generated; run without --export to see why.");
+        }
+        if !quiet {
+            println!(
+                "{}",
+                format!(
+                    "No replacement was generated; suggestion {} is unchanged.",
+                    refine.number
+                )
+                .yellow()
+            );
+        }
+        return Ok(());
+    };
+
+    let saved = &mut refine.saved;
+    saved.response.suggestions[refine.number - 1] = suggestion.clone();
+    saved.source_files.extend(file_hashes);
+    saved
+        .test_file_bases
+        .extend(test_file_bases(std::slice::from_ref(&suggestion)));
+    saved
+        .test_file_hashes
+        .extend(test_file_hashes(std::slice::from_ref(&suggestion)));
+    saved.response.tokens_used += response.tokens_used;
+    if let Err(e) = write_suggestions(saved) {
+        if !quiet {
+            eprintln!("{} {}", "Warning: Could not save suggestions:".yellow(), e);
+        }
+    }
+
+    if let Some(format) = args.export {
+        print!("{}", export::render(&saved.response, &saved.hunks, format));
+        return Ok(());
+    }
+    if args.porcelain {
+        return print_porcelain(std::slice::from_ref(&suggestion));
+    }
+    if quiet {
+        println!(
+            "VibeTap: suggestion {} regenerated. Run 'vibetap apply {}' to add it.",
+            refine.number, refine.number
+        );
+        return Ok(());
+    }
+
+    let suggestions = std::slice::from_ref(&suggestion);
+    let validations = validate::when_requested(suggestions, args).await;
+    let lint_results = if args.no_lint {
+        Vec::new()
+    } else {
+        lint_suggestions(suggestions).await
+    };
+
+    println!("\n{}", "=== Refined Suggestion ===".bold());
+    println!();
+    if let Some(ref warning) = response.warning {
+        println!("{} {}", ui::icon("⚠", "Warning:").yellow(), warning.yellow());
+        println!();
+    }
+    print_suggestion(
+        refine.number,
+        &suggestion,
+        validations.as_ref().and_then(|v| v.first()),
+        lint_results.first().map(Vec::as_slice),
+    );
+    println!(
+        "Run {} to apply it.",
+        format!("vibetap apply {}", refine.number).cyan()
+    );
+    println!(
+        "Tokens used: {} | Model: {}",
+        response.tokens_used.to_string().dimmed(),
+        response.model_used.dimmed()
+    );
+
+    Ok(())
+}
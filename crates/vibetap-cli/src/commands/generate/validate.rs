@@ -0,0 +1,154 @@
+//! Checking suggestions in a sandbox copy, for `--validate` and `--run-sandbox`
+
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::time::Duration;
+
+use vibetap_core::api::TestSuggestion;
+use crate::commands::run::detect_test_runner;
+use super::GenerateArgs;
+use crate::sandbox::{CheckOutcome, Sandbox};
+use crate::ui;
+
+/// Sandbox results for one suggestion
+pub struct Validation {
+    compiles: CheckOutcome,
+    runs: Option<CheckOutcome>,
+}
+
+/// Sandbox results for each suggestion, if --validate or --run-sandbox asked for them
+///
+/// A sandbox that can't be created is a warning; the suggestions are shown unchecked.
+pub async fn when_requested(
+    suggestions: &[TestSuggestion],
+    args: &GenerateArgs,
+) -> Option<Vec<Validation>> {
+    if !(args.validate || args.run_sandbox) || suggestions.is_empty() {
+        return None;
+    }
+    match validate_suggestions(suggestions, args.run_sandbox).await {
+        Ok(v) => Some(v),
+        Err(e) => {
+            eprintln!("{} {}", "Warning: Could not create sandbox:".yellow(), e);
+            None
+        }
+    }
+}
+
+/// Check each suggestion in a shared sandbox copy of the project
+pub async fn validate_suggestions(
+    suggestions: &[TestSuggestion],
+    run_tests: bool,
+) -> anyhow::Result<Vec<Validation>> {
+    let accessible = ui::is_accessible();
+    let spinner = if accessible {
+        println!("Copying project into a sandbox...");
+        None
+    } else {
+        let pb = ProgressBar::new_spinner();
+        pb.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner:.cyan} {msg}")
+                .unwrap(),
+        );
+        pb.enable_steady_tick(Duration::from_millis(100));
+        pb.set_message("Copying project into a sandbox...");
+        Some(pb)
+    };
+
+    let sandbox = match Sandbox::create() {
+        Ok(s) => s,
+        Err(e) => {
+            if let Some(pb) = spinner {
+                pb.finish_and_clear();
+            }
+            return Err(e);
+        }
+    };
+    let fallback_runner = detect_test_runner().ok();
+
+    let mut results = Vec::with_capacity(suggestions.len());
+    for (i, suggestion) in suggestions.iter().enumerate() {
+        let message = format!(
+            "Validating suggestion {}/{}: {}",
+            i + 1,
+            suggestions.len(),
+            suggestion.file_path
+        );
+        match spinner {
+            Some(ref pb) => pb.set_message(message),
+            None => println!("{}", message),
+        }
+
+        if let Err(e) = sandbox.write_file(&suggestion.file_path, &suggestion.code) {
+            results.push(Validation {
+                compiles: CheckOutcome::Skipped(e.to_string()),
+                runs: None,
+            });
+            continue;
+        }
+
+        let compiles = sandbox.check_syntax(&suggestion.file_path).await;
+        let runs = if run_tests && !matches!(compiles, CheckOutcome::Failed(_)) {
+            let runner = if suggestion.test_runner.is_empty() {
+                fallback_runner.clone()
+            } else {
+                Some(suggestion.test_runner.clone())
+            };
+            Some(match runner {
+                Some(runner) => sandbox.run_test(&suggestion.file_path, &runner).await,
+                None => CheckOutcome::Skipped("no test runner detected".to_string()),
+            })
+        } else {
+            None
+        };
+
+        let _ = sandbox.restore_file(&suggestion.file_path);
+        results.push(Validation { compiles, runs });
+    }
+
+    if let Some(pb) = spinner {
+        pb.finish_and_clear();
+    }
+    if accessible {
+        println!();
+    }
+
+    Ok(results)
+}
+
+/// Print the sandbox annotation line for a suggestion
+pub fn print_validation(validation: &Validation) {
+    let mut parts = vec![format_outcome("compiles", "does not compile", &validation.compiles)];
+    if let Some(ref runs) = validation.runs {
+        parts.push(format_outcome("runs", "fails", runs));
+    }
+    println!("   {} {}", "Sandbox:".dimmed(), parts.join(" | "));
+
+    let failure = [Some(&validation.compiles), validation.runs.as_ref()]
+        .into_iter()
+        .flatten()
+        .find_map(|outcome| match outcome {
+            CheckOutcome::Failed(reason) if !reason.is_empty() => Some(reason),
+            _ => None,
+        });
+    if let Some(reason) = failure {
+        println!("   {}", reason.red().dimmed());
+    }
+}
+
+fn format_outcome(passed: &str, failed: &str, outcome: &CheckOutcome) -> String {
+    if ui::is_accessible() {
+        return match outcome {
+            CheckOutcome::Passed => format!("{}: yes", passed),
+            CheckOutcome::Failed(_) => format!("{}: no", passed),
+            CheckOutcome::Skipped(reason) => format!("{}: not checked ({})", passed, reason),
+        };
+    }
+
+    match outcome {
+        CheckOutcome::Passed => format!("{} ✓", passed).green().to_string(),
+        CheckOutcome::Failed(_) => format!("{} ✗", failed).red().to_string(),
+        CheckOutcome::Skipped(reason) => format!("{} ? ({})", passed, reason).dimmed().to_string(),
+    }
+}
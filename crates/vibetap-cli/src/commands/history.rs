@@ -0,0 +1,240 @@
+use chrono::{Local, TimeZone};
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::apply::{load_history, save_history, AppliedRecord, ApplyHistory};
+use crate::ui;
+use vibetap_core::Config;
+
+/// Where file contents saved for `vibetap revert` are kept, one file per hash
+const OBJECTS_DIR: &str = ".vibetap/objects";
+
+/// Applied records kept unless `apply.historyLimit` says otherwise
+pub const DEFAULT_HISTORY_LIMIT: usize = 1000;
+
+#[derive(Args)]
+pub struct HistoryArgs {
+    #[command(subcommand)]
+    command: HistoryCommand,
+}
+
+#[derive(Subcommand)]
+enum HistoryCommand {
+    /// Show applied batches, newest first
+    List(ListArgs),
+
+    /// Drop old applied records and the file contents only they needed
+    Prune(PruneArgs),
+}
+
+#[derive(Args)]
+struct ListArgs {
+    /// Show at most this many batches
+    #[arg(short = 'n', long, default_value_t = 20)]
+    limit: usize,
+}
+
+#[derive(Args)]
+struct PruneArgs {
+    /// Keep only the newest N records (defaults to apply.historyLimit)
+    #[arg(long, value_name = "N")]
+    keep: Option<usize>,
+
+    /// Also drop records applied more than this many days ago
+    #[arg(long, value_name = "DAYS")]
+    older_than: Option<u64>,
+
+    /// Show what would be removed without removing it
+    #[arg(long)]
+    dry_run: bool,
+}
+
+pub async fn execute(args: HistoryArgs) -> anyhow::Result<()> {
+    match args.command {
+        HistoryCommand::List(list_args) => list(list_args),
+        HistoryCommand::Prune(prune_args) => prune(prune_args),
+    }
+}
+
+fn list(args: ListArgs) -> anyhow::Result<()> {
+    let history = load_history()?;
+    if history.records.is_empty() {
+        println!("{}", "No applied suggestions yet.".dimmed());
+        return Ok(());
+    }
+
+    // Batches in the order they were applied, each with its records
+    let mut batches: Vec<(String, Vec<&AppliedRecord>)> = Vec::new();
+    for record in &history.records {
+        let id = record.batch_id();
+        match batches.iter_mut().find(|(b, _)| *b == id) {
+            Some((_, records)) => records.push(record),
+            None => batches.push((id, vec![record])),
+        }
+    }
+
+    println!("{}", "Applied batches:".bold());
+    for (id, records) in batches.iter().rev().take(args.limit) {
+        let applied_at = Local
+            .timestamp_opt(records[0].applied_at, 0)
+            .single()
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        let mut files: Vec<&str> = records.iter().map(|r| r.file_path.as_str()).collect();
+        files.dedup();
+        println!(
+            "  {}  {}  {} file(s): {}",
+            format!("{:<11}", id).cyan(),
+            applied_at.dimmed(),
+            records.len(),
+            files.join(", ")
+        );
+    }
+    if batches.len() > args.limit {
+        println!(
+            "{}",
+            format!("... and {} older batch(es)", batches.len() - args.limit).dimmed()
+        );
+    }
+    println!(
+        "\nRun {} to undo one.",
+        "vibetap revert --batch <id>".cyan()
+    );
+    Ok(())
+}
+
+fn prune(args: PruneArgs) -> anyhow::Result<()> {
+    let mut history = load_history()?;
+    let before = history.records.len();
+
+    let keep = args.keep.unwrap_or_else(history_limit);
+    if let Some(days) = args.older_than {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        let cutoff = now - (days * 24 * 60 * 60) as i64;
+        history.records.retain(|r| r.applied_at >= cutoff);
+    }
+    truncate(&mut history, keep);
+    let dropped = before - history.records.len();
+
+    if args.dry_run {
+        let inline = history
+            .records
+            .iter()
+            .filter(|r| r.original_content.is_some())
+            .count();
+        println!("Would remove {} of {} record(s).", dropped, before);
+        if inline > 0 {
+            println!(
+                "Would move {} saved file content(s) into {}.",
+                inline, OBJECTS_DIR
+            );
+        }
+        return Ok(());
+    }
+
+    // Contents from before objects existed are stored inline; move them out
+    let mut compacted = 0;
+    for record in &mut history.records {
+        if let Some(content) = record.original_content.take() {
+            record.original_object = Some(store_object(&content)?);
+            compacted += 1;
+        }
+    }
+    save_history(&history)?;
+    let (objects, bytes) = collect_garbage(&history)?;
+
+    println!(
+        "{} Removed {} of {} record(s) and {} unused file content(s) ({} KB)",
+        ui::icon("✓", "OK:").green(),
+        dropped,
+        before,
+        objects,
+        bytes / 1024
+    );
+    if compacted > 0 {
+        println!(
+            "{}",
+            format!(
+                "Moved {} saved file content(s) into {}",
+                compacted, OBJECTS_DIR
+            )
+            .dimmed()
+        );
+    }
+    Ok(())
+}
+
+/// Most applied records to keep, from `apply.historyLimit`
+pub fn history_limit() -> usize {
+    Config::load()
+        .ok()
+        .and_then(|c| c.project)
+        .and_then(|p| p.apply)
+        .and_then(|a| a.history_limit)
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+}
+
+/// A fresh ID for the records of one apply run
+pub fn new_batch_id() -> String {
+    format!("{:08x}", rand::rng().random::<u32>())
+}
+
+/// Drop the oldest records beyond `keep`
+pub fn truncate(history: &mut ApplyHistory, keep: usize) {
+    let excess = history.records.len().saturating_sub(keep);
+    history.records.drain(..excess);
+}
+
+/// Save file content under its hash, once however often it is saved
+pub fn store_object(content: &str) -> io::Result<String> {
+    let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+    let path = object_path(&hash);
+    if !path.exists() {
+        std::fs::create_dir_all(OBJECTS_DIR)?;
+        std::fs::write(path, content)?;
+    }
+    Ok(hash)
+}
+
+/// Content saved with [`store_object`]
+pub fn read_object(hash: &str) -> io::Result<String> {
+    std::fs::read_to_string(object_path(hash))
+}
+
+/// Remove objects no record refers to; returns how many and their total size
+pub fn collect_garbage(history: &ApplyHistory) -> io::Result<(usize, u64)> {
+    let dir = Path::new(OBJECTS_DIR);
+    if !dir.exists() {
+        return Ok((0, 0));
+    }
+
+    let used: HashSet<&str> = history
+        .records
+        .iter()
+        .filter_map(|r| r.original_object.as_deref())
+        .collect();
+    let (mut count, mut bytes) = (0, 0);
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if used.contains(name.to_string_lossy().as_ref()) {
+            continue;
+        }
+        bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+        std::fs::remove_file(entry.path())?;
+        count += 1;
+    }
+    Ok((count, bytes))
+}
+
+fn object_path(hash: &str) -> PathBuf {
+    Path::new(OBJECTS_DIR).join(hash)
+}
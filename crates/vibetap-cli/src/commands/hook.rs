@@ -2,8 +2,10 @@ use clap::{Args, Subcommand};
 use colored::Colorize;
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
 
 const PRE_COMMIT_HOOK_MARKER: &str = "# VibeTap pre-commit hook";
+const POST_COMMIT_HOOK_MARKER: &str = "# VibeTap post-commit hook";
 
 #[derive(Args)]
 pub struct HookArgs {
@@ -32,6 +34,10 @@ struct InstallArgs {
     /// Only show warnings for security-related suggestions
     #[arg(long)]
     security_only: bool,
+
+    /// Record applied suggestions as git notes after each commit
+    #[arg(long)]
+    notes: bool,
 }
 
 pub async fn execute(args: HookArgs) -> anyhow::Result<()> {
@@ -77,6 +83,9 @@ fn install(args: InstallArgs) -> anyhow::Result<()> {
     // Check if VibeTap hook is already installed
     if let Some(ref content) = existing_hook {
         if content.contains(PRE_COMMIT_HOOK_MARKER) {
+            if args.notes {
+                return install_notes_hook(&hooks_dir);
+            }
             println!("{}", "VibeTap hook is already installed.".yellow());
             println!(
                 "Run {} to reinstall with different options.",
@@ -99,26 +108,7 @@ fn install(args: InstallArgs) -> anyhow::Result<()> {
         generate_non_blocking_hook(&vibetap_cmd)
     };
 
-    // If there's an existing hook, append to it
-    let final_script = if let Some(existing) = existing_hook {
-        if existing.starts_with("#!/") {
-            // Append our hook to the existing one
-            format!("{}\n\n{}", existing.trim_end(), hook_script)
-        } else {
-            // Existing hook doesn't have a shebang, prepend one
-            format!("#!/bin/sh\n{}\n\n{}", existing.trim_end(), hook_script)
-        }
-    } else {
-        format!("#!/bin/sh\n{}", hook_script)
-    };
-
-    // Write the hook
-    fs::write(&pre_commit_path, final_script)?;
-
-    // Make it executable
-    let mut perms = fs::metadata(&pre_commit_path)?.permissions();
-    perms.set_mode(0o755);
-    fs::set_permissions(&pre_commit_path, perms)?;
+    write_hook_section(&pre_commit_path, existing_hook, &hook_script)?;
 
     println!("{}", "✓ VibeTap pre-commit hook installed!".green());
     println!();
@@ -158,12 +148,86 @@ fn install(args: InstallArgs) -> anyhow::Result<()> {
         "vibetap hook uninstall".cyan()
     );
 
+    if args.notes {
+        println!();
+        install_notes_hook(&hooks_dir)?;
+    }
+
+    Ok(())
+}
+
+/// Install the post-commit hook that records applied suggestions as git notes
+fn install_notes_hook(hooks_dir: &Path) -> anyhow::Result<()> {
+    let post_commit_path = hooks_dir.join("post-commit");
+
+    let existing_hook = if post_commit_path.exists() {
+        Some(fs::read_to_string(&post_commit_path)?)
+    } else {
+        None
+    };
+
+    if let Some(ref content) = existing_hook {
+        if content.contains(POST_COMMIT_HOOK_MARKER) {
+            println!("{}", "VibeTap notes hook is already installed.".yellow());
+            return Ok(());
+        }
+    }
+
+    write_hook_section(&post_commit_path, existing_hook, &generate_notes_hook())?;
+
+    println!("{}", "✓ VibeTap post-commit notes hook installed!".green());
+    println!(
+        "{}",
+        "Applied suggestions will be recorded as git notes on the commits that include them."
+            .dimmed()
+    );
+
+    Ok(())
+}
+
+/// Append a VibeTap section to a hook script, creating it if needed
+fn write_hook_section(
+    hook_path: &Path,
+    existing_hook: Option<String>,
+    hook_script: &str,
+) -> anyhow::Result<()> {
+    // If there's an existing hook, append to it
+    let final_script = if let Some(existing) = existing_hook {
+        if existing.starts_with("#!/") {
+            // Append our hook to the existing one
+            format!("{}\n\n{}", existing.trim_end(), hook_script)
+        } else {
+            // Existing hook doesn't have a shebang, prepend one
+            format!("#!/bin/sh\n{}\n\n{}", existing.trim_end(), hook_script)
+        }
+    } else {
+        format!("#!/bin/sh\n{}", hook_script)
+    };
+
+    // Write the hook
+    fs::write(hook_path, final_script)?;
+
+    // Make it executable
+    let mut perms = fs::metadata(hook_path)?.permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(hook_path, perms)?;
+
     Ok(())
 }
 
 fn uninstall() -> anyhow::Result<()> {
     let hooks_dir = get_git_hooks_dir()?;
     let pre_commit_path = hooks_dir.join("pre-commit");
+    let post_commit_path = hooks_dir.join("post-commit");
+
+    // The notes hook is optional; remove it quietly if present
+    if post_commit_path.exists() {
+        let content = fs::read_to_string(&post_commit_path)?;
+        if content.contains(POST_COMMIT_HOOK_MARKER) {
+            remove_hook_section(&post_commit_path, &content, POST_COMMIT_HOOK_MARKER)?;
+            println!("{}", "✓ VibeTap post-commit notes hook removed.".green());
+        }
+    }
 
     if !pre_commit_path.exists() {
         println!("{}", "No pre-commit hook found.".yellow());
@@ -177,13 +241,32 @@ fn uninstall() -> anyhow::Result<()> {
         return Ok(());
     }
 
+    if remove_hook_section(&pre_commit_path, &content, PRE_COMMIT_HOOK_MARKER)? {
+        println!("{}", "✓ VibeTap pre-commit hook removed.".green());
+    } else {
+        println!(
+            "{}",
+            "✓ VibeTap section removed from pre-commit hook.".green()
+        );
+        println!(
+            "{}",
+            "Other pre-commit hooks remain installed.".dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+/// Remove a VibeTap section from a hook script.
+/// Returns true if the hook file was deleted because nothing else remained.
+fn remove_hook_section(hook_path: &Path, content: &str, marker: &str) -> anyhow::Result<bool> {
     // Remove VibeTap section from the hook
     let lines: Vec<&str> = content.lines().collect();
     let mut new_lines: Vec<&str> = Vec::new();
     let mut in_vibetap_section = false;
 
     for line in lines {
-        if line.contains(PRE_COMMIT_HOOK_MARKER) {
+        if line.contains(marker) {
             in_vibetap_section = true;
             continue;
         }
@@ -205,21 +288,12 @@ fn uninstall() -> anyhow::Result<()> {
 
     // If only shebang remains (or empty), remove the file entirely
     if remaining.trim().is_empty() || remaining.trim() == "#!/bin/sh" {
-        fs::remove_file(&pre_commit_path)?;
-        println!("{}", "✓ VibeTap pre-commit hook removed.".green());
+        fs::remove_file(hook_path)?;
+        Ok(true)
     } else {
-        fs::write(&pre_commit_path, format!("{}\n", remaining))?;
-        println!(
-            "{}",
-            "✓ VibeTap section removed from pre-commit hook.".green()
-        );
-        println!(
-            "{}",
-            "Other pre-commit hooks remain installed.".dimmed()
-        );
+        fs::write(hook_path, format!("{}\n", remaining))?;
+        Ok(false)
     }
-
-    Ok(())
 }
 
 fn status() -> anyhow::Result<()> {
@@ -258,6 +332,14 @@ fn status() -> anyhow::Result<()> {
             println!("  Filter: Security-only");
         }
 
+        let post_commit_path = hooks_dir.join("post-commit");
+        let notes_installed = fs::read_to_string(&post_commit_path)
+            .map(|c| c.contains(POST_COMMIT_HOOK_MARKER))
+            .unwrap_or(false);
+        if notes_installed {
+            println!("  Notes: Recording applied suggestions as git notes");
+        }
+
         println!();
         println!(
             "Run {} to remove.",
@@ -293,16 +375,31 @@ fi
     )
 }
 
+fn generate_notes_hook() -> String {
+    format!(
+        r#"
+{marker}
+# Records applied VibeTap suggestions as git notes on the new commit
+if command -v vibetap >/dev/null 2>&1; then
+    vibetap notes record --quiet || true
+fi
+# End VibeTap hook
+"#,
+        marker = POST_COMMIT_HOOK_MARKER
+    )
+}
+
 fn generate_blocking_hook(vibetap_cmd: &str) -> String {
     format!(
         r#"
 {marker}
 # Shows test suggestions and blocks commit if suggestions are available
 if command -v vibetap >/dev/null 2>&1; then
-    output=$({cmd} 2>&1)
-    result=$?
-    if [ -n "$output" ]; then
-        echo "$output"
+    output=$({cmd} --porcelain 2>/dev/null)
+    count=$(printf '%s' "$output" | sed -n 's/.*"count":\([0-9]*\).*/\1/p')
+    security=$(printf '%s' "$output" | sed -n 's/.*"security":\([0-9]*\).*/\1/p')
+    if [ -n "$count" ] && [ "$count" -gt 0 ]; then
+        echo "VibeTap: $count test suggestion(s) available ($security security)."
         echo ""
         echo "Commit blocked: Test suggestions available."
         echo "Run 'vibetap apply' to add tests, or commit with --no-verify to skip."
@@ -16,16 +16,43 @@ pub struct HushArgs {
     /// Clear hush state (resume suggestions)
     #[arg(long)]
     clear: bool,
+
+    /// Categories that still come through, e.g. security (comma-separated)
+    #[arg(long, value_name = "CATEGORIES", value_delimiter = ',')]
+    except: Vec<String>,
 }
 
 /// Persisted hush state
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct HushState {
     /// Unix timestamp when hush expires (None = forever, Some(0) = not hushed)
     pub hush_until: Option<i64>,
+    /// Suggestion categories shown even while hushed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub except: Vec<String>,
+}
+
+impl Default for HushState {
+    /// Not hushed
+    fn default() -> Self {
+        Self {
+            hush_until: Some(0),
+            except: Vec::new(),
+        }
+    }
 }
 
 impl HushState {
+    /// Whether suggestions of `category` are shown right now
+    pub fn shows(&self, category: &str) -> bool {
+        !self.is_hushed() || self.except.iter().any(|c| c.eq_ignore_ascii_case(category))
+    }
+
+    /// Whether no suggestion gets through, so there is no point generating
+    pub fn hides_everything(&self) -> bool {
+        self.is_hushed() && self.except.is_empty()
+    }
+
     /// Check if currently hushed
     pub fn is_hushed(&self) -> bool {
         match self.hush_until {
@@ -76,25 +103,25 @@ pub async fn execute(args: HushArgs) -> anyhow::Result<()> {
     }
 
     // Parse duration and set hush
-    let hush_until = if args.duration.to_lowercase() == "forever" {
-        None // None = forever
+    let duration = if args.duration.to_lowercase() == "forever" {
+        None
     } else {
-        let duration = parse_duration(&args.duration)?;
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map(|d| d.as_secs() as i64)
-            .unwrap_or(0);
-        Some(now + duration.as_secs() as i64)
+        Some(parse_duration(&args.duration)?)
     };
-
-    let state = HushState { hush_until };
-    save_state(&state)?;
+    let except: Vec<String> = args
+        .except
+        .iter()
+        .map(|c| c.trim().to_lowercase())
+        .filter(|c| !c.is_empty())
+        .collect();
+    let state = hush_for(duration, except)?;
 
     if args.duration.to_lowercase() == "forever" {
         println!(
             "{}",
             "Suggestions silenced indefinitely.".cyan()
         );
+        print_exceptions(&state);
         println!(
             "Run {} to resume.",
             "vibetap hush --clear".cyan()
@@ -104,6 +131,7 @@ pub async fn execute(args: HushArgs) -> anyhow::Result<()> {
             "{}",
             format!("Suggestions silenced for {}.", args.duration).cyan()
         );
+        print_exceptions(&state);
         if let Some(remaining) = state.remaining() {
             println!("Will resume in {}.", remaining.green());
         }
@@ -119,6 +147,7 @@ fn show_status() -> anyhow::Result<()> {
         match state.remaining() {
             Some(remaining) => {
                 println!("{} ({})", "Hushed".yellow(), remaining);
+                print_exceptions(&state);
             }
             None => {
                 println!("{}", "Not hushed".green());
@@ -131,18 +160,22 @@ fn show_status() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Say which categories still come through, if any
+fn print_exceptions(state: &HushState) {
+    if !state.except.is_empty() {
+        println!("Still showing: {}", state.except.join(", ").green());
+    }
+}
+
 fn clear_hush() -> anyhow::Result<()> {
-    let state = HushState {
-        hush_until: Some(0), // 0 = not hushed
-    };
-    save_state(&state)?;
+    save_state(&HushState::default())?;
 
     println!("{}", "Hush cleared. Suggestions resumed.".green());
 
     Ok(())
 }
 
-fn parse_duration(s: &str) -> anyhow::Result<std::time::Duration> {
+pub fn parse_duration(s: &str) -> anyhow::Result<std::time::Duration> {
     let s = s.trim().to_lowercase();
 
     // Handle combined format like "1h30m"
@@ -182,6 +215,24 @@ fn parse_duration(s: &str) -> anyhow::Result<std::time::Duration> {
     Ok(std::time::Duration::from_secs(total_secs))
 }
 
+/// Silence suggestions for `duration`, or until cleared when `None`, apart
+/// from the `except` categories
+pub fn hush_for(
+    duration: Option<std::time::Duration>,
+    except: Vec<String>,
+) -> anyhow::Result<HushState> {
+    let hush_until = duration.map(|duration| {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        now + duration.as_secs() as i64
+    });
+    let state = HushState { hush_until, except };
+    save_state(&state)?;
+    Ok(state)
+}
+
 pub fn load_state() -> anyhow::Result<HushState> {
     let path = Path::new(".vibetap/state.json");
     if !path.exists() {
@@ -209,6 +260,20 @@ fn save_state(state: &HushState) -> anyhow::Result<()> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_hush_state_exceptions() {
+        let missing = HushState::default();
+        assert!(!missing.is_hushed());
+
+        let state = HushState {
+            hush_until: None,
+            except: vec!["security".to_string()],
+        };
+        assert!(state.shows("Security"));
+        assert!(!state.shows("unit"));
+        assert!(!state.hides_everything());
+    }
+
     #[test]
     fn test_parse_duration() {
         assert_eq!(parse_duration("30m").unwrap().as_secs(), 30 * 60);
@@ -1,6 +1,8 @@
 use clap::Args;
 use colored::Colorize;
 
+use super::generate::{instructions_file, INSTRUCTION_FILES};
+
 #[derive(Args)]
 pub struct InitArgs {
     /// Force re-initialization even if already configured
@@ -12,8 +14,6 @@ pub async fn execute(args: InitArgs) -> anyhow::Result<()> {
     println!("{}", "Initializing VibeTap...".cyan());
 
     // TODO: Detect framework and test runner
-    // TODO: Read AGENTS.md / CLAUDE.md for project guidance
-    // TODO: Create .vibetap/config.json
 
     if args.force {
         println!("Force mode: overwriting existing configuration");
@@ -59,6 +59,13 @@ pub async fn execute(args: InitArgs) -> anyhow::Result<()> {
 
     println!("{}", "VibeTap initialized successfully!".green());
     println!("Configuration saved to .vibetap/config.json");
+    match instructions_file() {
+        Some(path) => println!("Project conventions will be read from {}", path.cyan()),
+        None => println!(
+            "Describe your test conventions (mocking style, fixtures, naming) in {} to guide suggestions",
+            INSTRUCTION_FILES[0].cyan()
+        ),
+    }
     println!("\nNext steps:");
     println!("  1. Add your API key: vibetap auth login");
     println!("  2. Start watching: vibetap watch");
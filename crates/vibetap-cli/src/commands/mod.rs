@@ -1,11 +1,20 @@
 pub mod apply;
 pub mod auth;
+pub mod budget;
+pub mod byok;
+pub mod export;
 pub mod generate;
+pub mod history;
 pub mod hook;
 pub mod hush;
 pub mod init;
+pub mod notes;
+pub mod policy;
 pub mod revert;
 pub mod run;
 pub mod scan;
+pub mod share;
+pub mod stage;
 pub mod stats;
 pub mod watch;
+pub mod workspace;
@@ -0,0 +1,175 @@
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use super::apply::{load_history, save_history};
+use vibetap_git::{add_note, commit_files, read_note, NOTES_REF};
+
+#[derive(Args)]
+pub struct NotesArgs {
+    #[command(subcommand)]
+    command: NotesCommand,
+}
+
+#[derive(Subcommand)]
+enum NotesCommand {
+    /// Attach metadata for applied suggestions to a commit
+    Record(RecordArgs),
+
+    /// Show the VibeTap note on a commit
+    Show(ShowArgs),
+}
+
+#[derive(Args)]
+struct RecordArgs {
+    /// Commit to annotate
+    #[arg(long, default_value = "HEAD")]
+    commit: String,
+
+    /// Only print errors (useful for git hooks)
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+#[derive(Args)]
+struct ShowArgs {
+    /// Commit to show
+    #[arg(default_value = "HEAD")]
+    commit: String,
+}
+
+/// Metadata stored as a git note on commits containing applied suggestions
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuggestionNote {
+    pub tool: String,
+    pub suggestions: Vec<NoteEntry>,
+}
+
+impl Default for SuggestionNote {
+    fn default() -> Self {
+        Self {
+            tool: "vibetap".to_string(),
+            suggestions: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteEntry {
+    pub id: String,
+    pub file_path: String,
+    pub model: Option<String>,
+    pub confidence: Option<f64>,
+    pub session: Option<String>,
+    pub applied_at: i64,
+    /// Edited before it was applied
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub modified: bool,
+}
+
+pub async fn execute(args: NotesArgs) -> anyhow::Result<()> {
+    match args.command {
+        NotesCommand::Record(record_args) => record(record_args),
+        NotesCommand::Show(show_args) => show(show_args),
+    }
+}
+
+fn record(args: RecordArgs) -> anyhow::Result<()> {
+    let (commit_id, files) = commit_files(&args.commit)?;
+    let mut history = load_history()?;
+
+    // Applied suggestions whose test files landed in this commit
+    let entries: Vec<NoteEntry> = history
+        .records
+        .iter()
+        .filter(|r| r.noted_commit.is_none() && files.contains(&r.file_path))
+        .map(|r| NoteEntry {
+            id: r.suggestion_id.clone(),
+            file_path: r.file_path.clone(),
+            model: r.model.clone(),
+            confidence: r.confidence,
+            session: r.session.clone(),
+            applied_at: r.applied_at,
+            modified: r.modified,
+        })
+        .collect();
+
+    if entries.is_empty() {
+        if !args.quiet {
+            println!(
+                "{}",
+                "No applied suggestions found in this commit.".dimmed()
+            );
+        }
+        return Ok(());
+    }
+
+    // Merge with an existing note so repeated runs don't drop entries
+    let mut note: SuggestionNote = read_note(&commit_id)?
+        .and_then(|existing| serde_json::from_str(&existing).ok())
+        .unwrap_or_default();
+    let count = entries.len();
+    note.suggestions.extend(entries);
+
+    add_note(&commit_id, &serde_json::to_string_pretty(&note)?)?;
+
+    for r in history.records.iter_mut() {
+        if r.noted_commit.is_none() && files.contains(&r.file_path) {
+            r.noted_commit = Some(commit_id.clone());
+        }
+    }
+    save_history(&history)?;
+
+    if !args.quiet {
+        println!(
+            "{} Recorded {} suggestion(s) on {}",
+            "✓".green(),
+            count,
+            commit_id[..7.min(commit_id.len())].cyan()
+        );
+        println!(
+            "{}",
+            format!("Share notes with: git push origin {}", NOTES_REF).dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+fn show(args: ShowArgs) -> anyhow::Result<()> {
+    let note = match read_note(&args.commit)? {
+        Some(n) => n,
+        None => {
+            println!("{}", "No VibeTap note on this commit.".yellow());
+            return Ok(());
+        }
+    };
+
+    let parsed: SuggestionNote = match serde_json::from_str(&note) {
+        Ok(p) => p,
+        Err(_) => {
+            // Not written by us - print verbatim
+            println!("{}", note);
+            return Ok(());
+        }
+    };
+
+    println!("{}", "VibeTap suggestions in this commit:".bold());
+    for entry in &parsed.suggestions {
+        println!("  {} {} ({})", "•".dimmed(), entry.file_path.cyan(), entry.id.dimmed());
+        if let Some(ref model) = entry.model {
+            let confidence = entry
+                .confidence
+                .map(|c| format!("{:.0}%", c * 100.0))
+                .unwrap_or_else(|| "-".to_string());
+            println!("    Model: {} | Confidence: {}", model, confidence);
+        }
+        if entry.modified {
+            println!("    {}", "Edited before it was applied".dimmed());
+        }
+    }
+
+    Ok(())
+}
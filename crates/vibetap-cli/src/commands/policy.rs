@@ -0,0 +1,91 @@
+use clap::{Args, Subcommand};
+use colored::Colorize;
+
+use super::generate::policy_pack;
+use crate::ui;
+use vibetap_core::{ApiClient, Config};
+
+#[derive(Args)]
+pub struct PolicyArgs {
+    #[command(subcommand)]
+    command: PolicyCommand,
+}
+
+#[derive(Subcommand)]
+enum PolicyCommand {
+    /// List the policy packs available to your organization
+    List {
+        /// Show raw JSON output
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+pub async fn execute(args: PolicyArgs) -> anyhow::Result<()> {
+    match args.command {
+        PolicyCommand::List { json } => list(json).await,
+    }
+}
+
+async fn list(json: bool) -> anyhow::Result<()> {
+    let mut config = Config::load()?;
+    let access_token = config.get_valid_access_token().await?;
+    let api_url = config.api_url().to_string();
+
+    let client = ApiClient::new(api_url, access_token);
+    let packs = match client.list_policy_packs().await {
+        Ok(p) => p,
+        Err(e) => {
+            println!("{} {}", "Error:".red(), e);
+            return Ok(());
+        }
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&packs)?);
+        return Ok(());
+    }
+
+    if packs.is_empty() {
+        println!("{}", "No policy packs are available to your account.".yellow());
+        return Ok(());
+    }
+
+    let selected = policy_pack(&config, None);
+    println!("{}", "Policy packs:".bold());
+    for pack in &packs {
+        let marker = if selected.as_deref() == Some(pack.id.as_str()) {
+            " (configured)".green().to_string()
+        } else {
+            String::new()
+        };
+        let owner = if pack.organization { "organization" } else { "built-in" };
+        println!(
+            "  {} {} {}{}",
+            pack.id.cyan(),
+            pack.name,
+            format!("[{}]", owner).dimmed(),
+            marker
+        );
+        if let Some(ref description) = pack.description {
+            println!("      {}", description.dimmed());
+        }
+    }
+
+    if let Some(id) = selected.filter(|id| !packs.iter().any(|p| &p.id == id)) {
+        println!();
+        println!(
+            "{} generation.policyPack is set to '{}', which is not in this list.",
+            ui::icon("⚠", "Warning:").yellow(),
+            id
+        );
+    }
+    println!();
+    println!(
+        "Use one with {} or set {} in .vibetap/config.json.",
+        "vibetap generate --policy <id>".cyan(),
+        "generation.policyPack".cyan()
+    );
+
+    Ok(())
+}
@@ -3,7 +3,10 @@ use colored::Colorize;
 use std::io::{self, Write};
 use std::path::Path;
 
-use super::apply::{ApplyHistory, AppliedRecord};
+use super::apply::{load_history, save_history, AppliedRecord};
+use super::history;
+use crate::feedback;
+use vibetap_core::api::FeedbackAction;
 
 #[derive(Args)]
 pub struct RevertArgs {
@@ -18,6 +21,10 @@ pub struct RevertArgs {
     /// Number of applied files to revert (default: last batch)
     #[arg(short, long)]
     count: Option<usize>,
+
+    /// Revert the batch with this ID instead of the last one (see 'vibetap history list')
+    #[arg(long, value_name = "ID", conflicts_with_all = ["all", "count"])]
+    batch: Option<String>,
 }
 
 pub async fn execute(args: RevertArgs) -> anyhow::Result<()> {
@@ -38,18 +45,27 @@ pub async fn execute(args: RevertArgs) -> anyhow::Result<()> {
             .drain(history.records.len() - count..)
             .collect()
     } else {
-        // Revert the last batch (same applied_at timestamp)
-        if let Some(last) = history.records.last() {
-            let last_timestamp = last.applied_at;
-            let drain_start = history
+        // The batch asked for, or the one applied last
+        let batch = match args.batch {
+            Some(ref batch) => batch.clone(),
+            None => history
                 .records
-                .iter()
-                .position(|r| r.applied_at == last_timestamp)
-                .unwrap_or(history.records.len() - 1);
-            history.records.drain(drain_start..).collect()
-        } else {
-            Vec::new()
+                .last()
+                .map(|r| r.batch_id())
+                .unwrap_or_default(),
+        };
+        let (to_revert, kept) = history
+            .records
+            .drain(..)
+            .partition(|r| r.batch_id() == batch);
+        history.records = kept;
+        if to_revert.is_empty() {
+            anyhow::bail!(
+                "No applied batch '{}'. Run 'vibetap history list' to see them.",
+                batch
+            );
         }
+        to_revert
     };
 
     if to_revert.is_empty() {
@@ -79,10 +95,7 @@ pub async fn execute(args: RevertArgs) -> anyhow::Result<()> {
         io::stdin().read_line(&mut confirm)?;
 
         if !confirm.trim().eq_ignore_ascii_case("y") {
-            // Put the records back
-            let mut new_history = load_history()?;
-            new_history.records.extend(to_revert);
-            save_history(&new_history)?;
+            // Nothing has been saved yet, so the history is untouched
             println!("{}", "Cancelled.".dimmed());
             return Ok(());
         }
@@ -90,9 +103,11 @@ pub async fn execute(args: RevertArgs) -> anyhow::Result<()> {
 
     // Perform the revert
     let mut reverted_count = 0;
+    let mut feedback_events = Vec::new();
     let mut errors = Vec::new();
 
-    for record in &to_revert {
+    // Newest first, so a file applied to twice ends up as it was before both
+    for record in to_revert.iter().rev() {
         let file_path = Path::new(&record.file_path);
 
         let result = if record.created_file {
@@ -104,9 +119,16 @@ pub async fn execute(args: RevertArgs) -> anyhow::Result<()> {
             }
         } else {
             // Restore original content
-            match &record.original_content {
-                Some(content) => std::fs::write(file_path, content),
-                None => {
+            match record.original() {
+                Ok(Some(content)) => std::fs::write(file_path, content),
+                Err(e) => {
+                    errors.push(format!(
+                        "{}: could not read the original content: {}",
+                        record.file_path, e
+                    ));
+                    continue;
+                }
+                Ok(None) => {
                     // No original content recorded - can't restore
                     errors.push(format!(
                         "{}: no original content recorded",
@@ -126,6 +148,10 @@ pub async fn execute(args: RevertArgs) -> anyhow::Result<()> {
                 };
                 println!("  {} {} ({})", "✓".green(), record.file_path, action);
                 reverted_count += 1;
+                feedback_events.push(feedback::event(
+                    &record.suggestion_id,
+                    FeedbackAction::Reverted,
+                ));
             }
             Err(e) => {
                 errors.push(format!("{}: {}", record.file_path, e));
@@ -135,6 +161,8 @@ pub async fn execute(args: RevertArgs) -> anyhow::Result<()> {
 
     // Save updated history
     save_history(&history)?;
+    history::collect_garbage(&history)?;
+    feedback::report(feedback_events).await;
 
     if !errors.is_empty() {
         println!("\n{}", "Errors:".red().bold());
@@ -157,26 +185,3 @@ pub async fn execute(args: RevertArgs) -> anyhow::Result<()> {
 
     Ok(())
 }
-
-fn load_history() -> anyhow::Result<ApplyHistory> {
-    let path = Path::new(".vibetap/history.json");
-    if !path.exists() {
-        return Ok(ApplyHistory::default());
-    }
-
-    let content = std::fs::read_to_string(path)?;
-    Ok(serde_json::from_str(&content)?)
-}
-
-fn save_history(history: &ApplyHistory) -> anyhow::Result<()> {
-    let vibetap_dir = Path::new(".vibetap");
-    if !vibetap_dir.exists() {
-        std::fs::create_dir_all(vibetap_dir)?;
-    }
-
-    let path = vibetap_dir.join("history.json");
-    let json = serde_json::to_string_pretty(history)?;
-    std::fs::write(path, json)?;
-
-    Ok(())
-}
@@ -1,30 +1,92 @@
 use clap::Args;
 use colored::Colorize;
-use std::path::Path;
-use std::process::Command;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::Instant;
+use tempfile::NamedTempFile;
 
-use super::apply::ApplyHistory;
+use super::apply::{load_history, save_history, Verification};
+use super::hush::parse_duration;
+use crate::container::{self, Container};
+use crate::feedback;
+use crate::last_run::{LastRun, TestId};
+use crate::test_results::{self, GoEvents, Status, TestCase, TestResults};
+use crate::test_targets;
+use crate::watchdog::{self, Reason, Stopped, Timeouts, Watchdog};
 use vibetap_core::Config;
 
+/// Lines of failure output kept with an applied test's record
+const FAILURE_LINES: usize = 12;
+
 #[derive(Args)]
 pub struct RunArgs {
     /// Run all tests, not just generated ones
     #[arg(long)]
     all: bool,
 
+    /// Run only the tests that failed in the last run
+    #[arg(long, conflicts_with = "all")]
+    failed: bool,
+
+    /// Run tests that fail again up to N times; ones that then pass count as flaky
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    retries: u32,
+
     /// Test runner to use (auto-detected if not specified)
     #[arg(long)]
     runner: Option<String>,
 
+    /// Run the tests in a container: docker, or docker:<image>
+    #[arg(long, value_name = "docker[:IMAGE]")]
+    sandbox: Option<String>,
+
+    /// Let tests in the sandbox reach the network
+    #[arg(long, requires = "sandbox")]
+    sandbox_network: bool,
+
+    /// Stop the tests if a run takes longer than this, e.g. 10m
+    #[arg(long, value_name = "DURATION")]
+    timeout: Option<String>,
+
+    /// Fail a test that takes longer than this, e.g. 30s
+    #[arg(long, value_name = "DURATION")]
+    test_timeout: Option<String>,
+
+    /// Write each test's status and duration to this file as JUnit XML
+    #[arg(long, value_name = "FILE")]
+    junit: Option<PathBuf>,
+
+    /// Write each test's status and duration to this file as JSON
+    #[arg(long, value_name = "FILE")]
+    json_report: Option<PathBuf>,
+
     /// Pass additional arguments to the test runner
     #[arg(last = true)]
     args: Vec<String>,
 }
 
 pub async fn execute(args: RunArgs) -> anyhow::Result<()> {
+    let last_run = if args.failed {
+        match LastRun::load()? {
+            Some(last_run) => Some(last_run),
+            None => {
+                println!(
+                    "{}",
+                    "No previous run recorded. Run `vibetap run` first.".yellow()
+                );
+                return Ok(());
+            }
+        }
+    } else {
+        None
+    };
+
     // Determine test runner
     let runner = if let Some(r) = args.runner {
         r
+    } else if let Some(last_run) = &last_run {
+        last_run.runner.clone()
     } else {
         detect_test_runner()?
     };
@@ -34,45 +96,200 @@ pub async fn execute(args: RunArgs) -> anyhow::Result<()> {
         "Using test runner:".cyan(),
         runner.bold()
     );
+    let container = args
+        .sandbox
+        .as_deref()
+        .map(|spec| Container::parse(spec, &runner, args.sandbox_network))
+        .transpose()?;
+    if let Some(container) = &container {
+        println!(
+            "{} {} {}",
+            "Sandbox:".cyan(),
+            container.image.bold(),
+            if container.network {
+                "(read-only project, network allowed)"
+            } else {
+                "(read-only project, no network)"
+            }
+            .dimmed()
+        );
+    }
+    let timeouts = timeouts(args.timeout.as_deref(), args.test_timeout.as_deref())?;
+    if timeouts.is_set() {
+        let limits: Vec<String> = [(timeouts.run, "per run"), (timeouts.test, "per test")]
+            .into_iter()
+            .filter_map(|(limit, of)| limit.map(|l| format!("{} {}", watchdog::describe(l), of)))
+            .collect();
+        println!("{} {}", "Timeout:".cyan(), limits.join(", "));
+    }
 
-    // Get files to test
-    let test_files: Vec<String> = if args.all {
-        Vec::new() // Empty = run all tests
+    // Build command based on runner
+    let ((cmd, cmd_args), test_files) = if let Some(last_run) = &last_run {
+        if last_run.failed.is_empty() {
+            let message = if last_run.success {
+                "No tests failed in the last run.".to_string()
+            } else {
+                format!(
+                    "The last run failed, but {} didn't report which tests. Run them all again with `vibetap run`.",
+                    last_run.runner
+                )
+            };
+            println!("{}", message.yellow());
+            return Ok(());
+        }
+        let Some(command) = build_rerun_command(&runner, &last_run.failed, &args.args) else {
+            anyhow::bail!("Can't run only the failed tests with {}", runner);
+        };
+        println!(
+            "{}",
+            format!(
+                "Running {} test(s) that failed last time",
+                last_run.failed.len()
+            )
+            .dimmed()
+        );
+        (command, Vec::new())
     } else {
-        // Get only applied test files
-        let history = load_history()?;
-        history
-            .records
-            .iter()
-            .map(|r| r.file_path.clone())
-            .filter(|p| Path::new(p).exists())
-            .collect()
+        // Get files to test
+        let test_files: Vec<String> = if args.all {
+            Vec::new() // Empty = run all tests
+        } else {
+            // Get only applied test files
+            let history = load_history()?;
+            history
+                .records
+                .iter()
+                .map(|r| r.file_path.clone())
+                .filter(|p| Path::new(p).exists())
+                .collect()
+        };
+
+        if !args.all && test_files.is_empty() {
+            println!(
+                "{}",
+                "No applied test files found. Use --all to run all tests.".yellow()
+            );
+            return Ok(());
+        }
+
+        (build_command(&runner, &test_files, &args.args)?, test_files)
     };
 
-    if !args.all && test_files.is_empty() {
+    // Execute the test runner
+    let started = Instant::now();
+    let (mut status, mut tests, mut interrupted) =
+        run_tests(&runner, &cmd, cmd_args, container.as_ref(), timeouts)?;
+
+    for attempt in 1..=args.retries {
+        if status.success() || interrupted {
+            break;
+        }
+        let failed: Vec<TestId> = tests
+            .iter()
+            .filter(|t| t.status == Status::Failed)
+            .map(|t| TestId {
+                suite: t.suite.clone(),
+                name: t.name.clone(),
+            })
+            .collect();
+        let rerun = if failed.is_empty() {
+            None
+        } else {
+            build_rerun_command(&runner, &failed, &args.args)
+        };
+        let Some((cmd, cmd_args)) = rerun else {
+            println!(
+                "\n{}",
+                format!("Not retrying: {} didn't report which tests failed.", runner).yellow()
+            );
+            break;
+        };
+
         println!(
-            "{}",
-            "No applied test files found. Use --all to run all tests.".yellow()
+            "\n{}",
+            format!(
+                "Retrying {} failed test(s) (attempt {} of {})",
+                failed.len(),
+                attempt,
+                args.retries
+            )
+            .cyan()
         );
-        return Ok(());
+        let (retry_status, retried, retry_interrupted) =
+            run_tests(&runner, &cmd, cmd_args, container.as_ref(), timeouts)?;
+        interrupted = retry_interrupted;
+        for test in tests.iter_mut().filter(|t| t.status == Status::Failed) {
+            // A passing retry run means every test it ran passed, even when
+            // the runner names them differently the second time
+            let passed = retry_status.success()
+                || retried.iter().any(|r| {
+                    r.status == Status::Passed && r.suite == test.suite && r.name == test.name
+                });
+            if passed {
+                test.status = Status::Passed;
+                test.flaky = true;
+                test.message = None;
+            }
+        }
+        status = retry_status;
     }
 
-    // Build command based on runner
-    let (cmd, cmd_args) = build_command(&runner, &test_files, &args.args)?;
+    let flaky: Vec<&TestCase> = tests.iter().filter(|t| t.flaky).collect();
+    if !flaky.is_empty() {
+        println!(
+            "\n{}",
+            "Flaky (failed, then passed on retry):".yellow().bold()
+        );
+        for test in &flaky {
+            if test.suite.is_empty() {
+                println!("  {}", test.name);
+            } else {
+                println!("  {} {}", test.name, format!("({})", test.suite).dimmed());
+            }
+        }
+    }
 
-    println!(
-        "{} {} {}",
-        "Running:".dimmed(),
-        cmd,
-        cmd_args.join(" ")
-    );
-    println!();
+    if let Err(e) = LastRun::new(&runner, status.success(), &tests).save() {
+        eprintln!("Warning: Failed to save last run: {}", e);
+    }
+    // An interrupted run says nothing about whether the applied tests hold up
+    if !interrupted {
+        if let Err(e) = verify_applied(&tests, status.success(), &test_files).await {
+            eprintln!("Warning: Failed to record test results in history: {}", e);
+        }
+    }
 
-    // Execute the test runner
-    let status = Command::new(&cmd)
-        .args(&cmd_args)
-        .status()?;
+    if args.junit.is_some() || args.json_report.is_some() {
+        if tests.is_empty() {
+            println!(
+                "\n{}",
+                format!(
+                    "No per-test results from {}; the report only has the overall result.",
+                    runner
+                )
+                .dimmed()
+            );
+        }
+        let results = TestResults {
+            runner: runner.clone(),
+            success: status.success(),
+            exit_code: status.code().unwrap_or(1),
+            duration_secs: started.elapsed().as_secs_f64(),
+            tests,
+        };
+        if let Some(path) = &args.junit {
+            std::fs::write(path, results.to_junit())?;
+            println!("{} {}", "JUnit report:".dimmed(), path.display());
+        }
+        if let Some(path) = &args.json_report {
+            std::fs::write(path, serde_json::to_string_pretty(&results.to_json())?)?;
+            println!("{} {}", "JSON report:".dimmed(), path.display());
+        }
+    }
 
+    if interrupted {
+        std::process::exit(130);
+    }
     if status.success() {
         println!("\n{}", "All tests passed!".green().bold());
     } else {
@@ -82,13 +299,403 @@ pub async fn execute(args: RunArgs) -> anyhow::Result<()> {
             "Tests failed!".red().bold(),
             code
         );
+        println!(
+            "{}",
+            "Run `vibetap run --failed` to run just the failing tests again.".dimmed()
+        );
         std::process::exit(code);
     }
 
     Ok(())
 }
 
-fn detect_test_runner() -> anyhow::Result<String> {
+/// Record in history whether each applied test passed, and report the
+/// ones whose outcome changed
+///
+/// Tests are matched to applied files through the per-test results. When
+/// the runner gave none, a passing run of `test_files` (the applied files
+/// it was limited to) still counts for all of them, and a failing one for
+/// a single file.
+async fn verify_applied(
+    tests: &[TestCase],
+    success: bool,
+    test_files: &[String],
+) -> anyhow::Result<()> {
+    let mut history = load_history()?;
+    if history.records.is_empty() {
+        return Ok(());
+    }
+    let ran_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    let mut verdicts: Vec<(String, Option<Verification>)> = Vec::new();
+    let mut events = Vec::new();
+    for record in history.records.iter_mut() {
+        let verdict = match verdicts.iter().find(|(file, _)| *file == record.file_path) {
+            Some((_, verdict)) => verdict.clone(),
+            None => {
+                let verdict = verdict_for(&record.file_path, tests, success, test_files, ran_at);
+                verdicts.push((record.file_path.clone(), verdict.clone()));
+                verdict
+            }
+        };
+        let Some(verdict) = verdict else {
+            continue;
+        };
+        if record.verification.as_ref().map(|v| v.passed) != Some(verdict.passed) {
+            events.push(feedback::verified(
+                &record.suggestion_id,
+                (!verdict.passed).then(|| verdict.failure.as_deref().unwrap_or("")),
+            ));
+        }
+        record.verification = Some(verdict);
+    }
+
+    let passing = verdicts
+        .iter()
+        .filter(|(_, v)| v.as_ref().is_some_and(|v| v.passed))
+        .count();
+    let failing = verdicts
+        .iter()
+        .filter(|(_, v)| v.as_ref().is_some_and(|v| !v.passed))
+        .count();
+    if passing + failing == 0 {
+        return Ok(());
+    }
+    save_history(&history)?;
+    println!(
+        "\n{} {} passing, {} failing",
+        "Applied tests:".dimmed(),
+        passing.to_string().green(),
+        if failing > 0 {
+            failing.to_string().red()
+        } else {
+            failing.to_string().normal()
+        }
+    );
+    feedback::report(events).await;
+    Ok(())
+}
+
+/// How the applied test file `file` did in the run, if the run tells
+fn verdict_for(
+    file: &str,
+    tests: &[TestCase],
+    success: bool,
+    test_files: &[String],
+    ran_at: i64,
+) -> Option<Verification> {
+    let outcome = |passed| Verification {
+        passed,
+        ran_at,
+        failure: None,
+    };
+    if tests.is_empty() {
+        let ran = test_files.iter().any(|f| f == file);
+        let single = test_files.iter().all(|f| f == file);
+        return match (ran, success) {
+            (true, true) => Some(outcome(true)),
+            (true, false) if single => Some(outcome(false)),
+            _ => None,
+        };
+    }
+
+    let own = test_targets::tests_in(Path::new("."), file, tests);
+    if own.iter().all(|t| t.status == Status::Skipped) {
+        return None;
+    }
+    let Some(failed) = own.iter().find(|t| t.status == Status::Failed) else {
+        return Some(outcome(true));
+    };
+    let mut excerpt = if failed.suite.is_empty() {
+        failed.name.clone()
+    } else {
+        format!("{} ({})", failed.name, failed.suite)
+    };
+    let message = failed.message.as_deref().unwrap_or("");
+    for line in message.lines().take(FAILURE_LINES) {
+        excerpt.push('\n');
+        excerpt.push_str(line);
+    }
+    Some(Verification {
+        passed: false,
+        ran_at,
+        failure: Some(excerpt),
+    })
+}
+
+/// The timeouts given as flags, or else in the project config
+fn timeouts(run: Option<&str>, test: Option<&str>) -> anyhow::Result<Timeouts> {
+    let configured = Config::load()
+        .ok()
+        .and_then(|config| config.project)
+        .and_then(|project| project.run)
+        .unwrap_or_default();
+    let parse = |flag: Option<&str>, configured: Option<String>| {
+        flag.map(str::to_string)
+            .or(configured)
+            .map(|duration| parse_duration(&duration))
+            .transpose()
+    };
+    Ok(Timeouts {
+        run: parse(run, configured.timeout)?,
+        test: parse(test, configured.test_timeout)?,
+    })
+}
+
+/// Run one test command, reading back which tests passed and failed, and
+/// whether it was stopped with Ctrl+C
+fn run_tests(
+    runner: &str,
+    cmd: &str,
+    mut cmd_args: Vec<String>,
+    container: Option<&Container>,
+    timeouts: Timeouts,
+) -> anyhow::Result<(ExitStatus, Vec<TestCase>, bool)> {
+    if let Some(limit) = timeouts.test {
+        test_timeout_args(runner, limit, &mut cmd_args);
+    }
+    // Reports are written here, which is all a sandbox may write to
+    let reports = tempfile::tempdir()?;
+    let report = NativeReport::request(runner, &mut cmd_args, reports.path())?;
+    println!("{} {} {}", "Running:".dimmed(), cmd, cmd_args.join(" "));
+    println!();
+
+    let (program, program_args) = match container {
+        Some(container) => container.command(
+            &container::new_name(),
+            &std::env::current_dir()?,
+            None,
+            reports.path(),
+            cmd,
+            &cmd_args,
+        ),
+        None => (cmd.to_string(), cmd_args),
+    };
+    let (status, tests, stopped) =
+        match report.run(Command::new(&program).args(&program_args), timeouts) {
+            Err(e)
+                if e.downcast_ref::<std::io::Error>()
+                    .is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound) =>
+            {
+                if container.is_some() {
+                    anyhow::bail!("`docker` was not found. Is Docker installed?")
+                }
+                anyhow::bail!("`{}` was not found. Is {} installed?", cmd, runner)
+            }
+            result => result?,
+        };
+    if let Some(stopped) = &stopped {
+        print_stopped(stopped);
+    }
+    let interrupted = stopped.is_some_and(|s| s.reason == Reason::Interrupted);
+    Ok((status, tests, interrupted))
+}
+
+/// Say why the tests were stopped and which ones hung
+fn print_stopped(stopped: &Stopped) {
+    let message = match stopped.reason {
+        Reason::Interrupted => {
+            println!("\n{}", "Interrupted.".yellow());
+            return;
+        }
+        Reason::RunTimeout(limit) => {
+            format!(
+                "Timed out after {}; stopped the tests.",
+                watchdog::describe(limit)
+            )
+        }
+        Reason::TestTimeout(limit) => format!(
+            "A test ran longer than {}; stopped the tests.",
+            watchdog::describe(limit)
+        ),
+    };
+    println!("\n{}", message.red().bold());
+    if stopped.hung.is_empty() {
+        println!(
+            "{}",
+            "The runner didn't say which test was running.".dimmed()
+        );
+        return;
+    }
+    println!("{}", "Hung:".red().bold());
+    for test in &stopped.hung {
+        if test.suite.is_empty() {
+            println!("  {}", test.name);
+        } else {
+            println!("  {} {}", test.name, format!("({})", test.suite).dimmed());
+        }
+    }
+}
+
+/// Give runners with a per-test timeout of their own the limit; go and
+/// cargo tests are watched instead, and other runners only stop with the
+/// whole run
+fn test_timeout_args(runner: &str, limit: std::time::Duration, cmd_args: &mut Vec<String>) {
+    let ms = limit.as_millis();
+    match runner {
+        "vitest" | "jest" => cmd_args.push(format!("--testTimeout={}", ms)),
+        "mocha" => cmd_args.push(format!("--timeout={}", ms)),
+        // After "test", ahead of the files
+        "bun-test" => cmd_args.insert(1, format!("--timeout={}", ms)),
+        // Fails the hung test and carries on with the rest
+        "dotnet-test" => cmd_args.extend(["--blame-hang-timeout".to_string(), format!("{}ms", ms)]),
+        // Doesn't stop the test, but prints where it is stuck
+        "pytest" => cmd_args.extend([
+            "-o".to_string(),
+            format!("faulthandler_timeout={}", limit.as_secs().max(1)),
+        ]),
+        _ => {}
+    }
+}
+
+/// How per-test results are got from a runner
+enum NativeReport {
+    /// The runner writes JUnit XML to this file (vitest, pytest, bun, deno, phpunit)
+    Junit(NamedTempFile),
+    /// Jest writes its JSON report to this file
+    JestJson(NamedTempFile),
+    /// `go test -json` events on stdout
+    GoJson,
+    /// Cargo's plain "test ... ok" lines on stdout
+    CargoOutput,
+    /// Nothing to read; only the exit status is known
+    Unsupported,
+}
+
+impl NativeReport {
+    /// Add the arguments that make `runner` report each test
+    fn request(runner: &str, cmd_args: &mut Vec<String>, dir: &Path) -> anyhow::Result<Self> {
+        let report = match runner {
+            "vitest" => {
+                let file = tempfile::Builder::new().suffix(".xml").tempfile_in(dir)?;
+                cmd_args.extend([
+                    "--reporter=default".to_string(),
+                    "--reporter=junit".to_string(),
+                    format!("--outputFile.junit={}", file.path().display()),
+                ]);
+                Self::Junit(file)
+            }
+            "pytest" => {
+                let file = tempfile::Builder::new().suffix(".xml").tempfile_in(dir)?;
+                cmd_args.push(format!("--junitxml={}", file.path().display()));
+                Self::Junit(file)
+            }
+            "jest" => {
+                let file = tempfile::Builder::new().suffix(".json").tempfile_in(dir)?;
+                cmd_args.extend([
+                    "--json".to_string(),
+                    format!("--outputFile={}", file.path().display()),
+                ]);
+                Self::JestJson(file)
+            }
+            "go-test" => {
+                // Right after "test", ahead of packages and pass-through flags
+                cmd_args.insert(1, "-json".to_string());
+                Self::GoJson
+            }
+            "bun-test" | "deno-test" => {
+                let file = tempfile::Builder::new().suffix(".xml").tempfile_in(dir)?;
+                let flags = if runner == "bun-test" {
+                    vec![
+                        "--reporter=junit".to_string(),
+                        format!("--reporter-outfile={}", file.path().display()),
+                    ]
+                } else {
+                    vec![format!("--junit-path={}", file.path().display())]
+                };
+                // After "test", ahead of the files
+                cmd_args.splice(1..1, flags);
+                Self::Junit(file)
+            }
+            "phpunit" => {
+                let file = tempfile::Builder::new().suffix(".xml").tempfile_in(dir)?;
+                cmd_args.push(format!("--log-junit={}", file.path().display()));
+                Self::Junit(file)
+            }
+            "cargo-test" => Self::CargoOutput,
+            _ => Self::Unsupported,
+        };
+        Ok(report)
+    }
+
+    /// Run the tests, still showing their output, and collect the results;
+    /// with a timeout, the tests are stopped once they take too long
+    fn run(
+        self,
+        command: &mut Command,
+        timeouts: Timeouts,
+    ) -> anyhow::Result<(ExitStatus, Vec<TestCase>, Option<Stopped>)> {
+        let streamed = matches!(self, Self::GoJson | Self::CargoOutput);
+        if streamed {
+            command.stdout(Stdio::piped());
+        }
+        if timeouts.is_set() {
+            Watchdog::isolate(command);
+        }
+        let mut child = command.spawn()?;
+        let watchdog = timeouts.is_set().then(|| Watchdog::start(&child, timeouts));
+
+        let mut go = GoEvents::default();
+        let mut output = String::new();
+        if let Some(stdout) = child.stdout.take() {
+            for line in BufReader::new(stdout).lines() {
+                let line = line?;
+                if matches!(self, Self::GoJson) {
+                    if let Some(watchdog) = &watchdog {
+                        watchdog.read_go(&line);
+                    }
+                    if let Some(text) = go.read(&line) {
+                        print!("{}", text);
+                    }
+                } else {
+                    if let Some(watchdog) = &watchdog {
+                        watchdog.read_cargo(&line);
+                    }
+                    println!("{}", line);
+                    output.push_str(&line);
+                    output.push('\n');
+                }
+            }
+        }
+        let status = child.wait()?;
+        let stopped = watchdog.and_then(Watchdog::finish);
+
+        let mut tests = match self {
+            Self::Junit(file) => std::fs::read_to_string(file.path())
+                .ok()
+                .filter(|xml| !xml.trim().is_empty())
+                .map(|xml| test_results::from_junit(&xml))
+                .transpose()?
+                .unwrap_or_default(),
+            Self::JestJson(file) => std::fs::read_to_string(file.path())
+                .ok()
+                .filter(|json| !json.trim().is_empty())
+                .map(|json| test_results::from_jest_json(&json))
+                .transpose()?
+                .unwrap_or_default(),
+            Self::GoJson => go.finish(),
+            Self::CargoOutput => test_results::from_cargo_output(&output),
+            Self::Unsupported => Vec::new(),
+        };
+        // Hung tests never got a result of their own
+        if let Some(stopped) = &stopped {
+            for hung in &stopped.hung {
+                if !tests
+                    .iter()
+                    .any(|t| t.suite == hung.suite && t.name == hung.name)
+                {
+                    tests.push(hung.clone());
+                }
+            }
+        }
+        Ok((status, tests, stopped))
+    }
+}
+
+pub fn detect_test_runner() -> anyhow::Result<String> {
     // Try to load from config first
     if let Ok(config) = Config::load() {
         if let Some(project) = config.project {
@@ -96,53 +703,135 @@ fn detect_test_runner() -> anyhow::Result<String> {
         }
     }
 
-    // Auto-detect from project files
-    if Path::new("vitest.config.ts").exists()
-        || Path::new("vitest.config.js").exists()
-        || Path::new("vitest.config.mts").exists()
-    {
-        return Ok("vitest".to_string());
+    if let Some(runner) = detect_test_runner_in(Path::new(".")) {
+        return Ok(runner);
     }
 
-    if Path::new("jest.config.ts").exists()
-        || Path::new("jest.config.js").exists()
-        || Path::new("jest.config.json").exists()
-    {
-        return Ok("jest".to_string());
+    anyhow::bail!(
+        "Could not detect test runner. Use --runner to specify one.\n\
+         Supported: vitest, jest, mocha, bun-test, deno-test, pytest, cargo-test, go-test,\n\
+         rspec, minitest, phpunit, dotnet-test, gradle, swift-test"
+    )
+}
+
+/// Auto-detect the test runner from the project files in `dir`
+pub fn detect_test_runner_in(dir: &Path) -> Option<String> {
+    let exists = |file: &str| dir.join(file).exists();
+
+    if exists("vitest.config.ts") || exists("vitest.config.js") || exists("vitest.config.mts") {
+        return Some("vitest".to_string());
     }
 
-    if Path::new("pytest.ini").exists()
-        || Path::new("pyproject.toml").exists()
-        || Path::new("setup.py").exists()
+    if exists("jest.config.ts") || exists("jest.config.js") || exists("jest.config.json") {
+        return Some("jest".to_string());
+    }
+
+    if exists(".mocharc.js")
+        || exists(".mocharc.cjs")
+        || exists(".mocharc.json")
+        || exists(".mocharc.yml")
+        || exists(".mocharc.yaml")
     {
+        return Some("mocha".to_string());
+    }
+
+    if exists("deno.json") || exists("deno.jsonc") {
+        return Some("deno-test".to_string());
+    }
+
+    if exists("pytest.ini") || exists("pyproject.toml") || exists("setup.py") {
         // Check if pytest is in pyproject.toml
-        if let Ok(content) = std::fs::read_to_string("pyproject.toml") {
+        if let Ok(content) = std::fs::read_to_string(dir.join("pyproject.toml")) {
             if content.contains("pytest") {
-                return Ok("pytest".to_string());
+                return Some("pytest".to_string());
             }
         }
     }
 
-    if Path::new("Cargo.toml").exists() {
-        return Ok("cargo-test".to_string());
+    if exists("Cargo.toml") {
+        return Some("cargo-test".to_string());
     }
 
-    if Path::new("go.mod").exists() {
-        return Ok("go-test".to_string());
+    if exists("go.mod") {
+        return Some("go-test".to_string());
     }
 
-    // Default to vitest for JS/TS projects
-    if Path::new("package.json").exists() {
-        return Ok("vitest".to_string());
+    if exists(".rspec") || exists("spec/spec_helper.rb") {
+        return Some("rspec".to_string());
+    }
+    if let Ok(gemfile) = std::fs::read_to_string(dir.join("Gemfile")) {
+        if gemfile.contains("rspec") {
+            return Some("rspec".to_string());
+        }
+        if gemfile.contains("minitest") || exists("test/test_helper.rb") {
+            return Some("minitest".to_string());
+        }
     }
 
-    anyhow::bail!(
-        "Could not detect test runner. Use --runner to specify one.\n\
-         Supported: vitest, jest, pytest, cargo-test, go-test"
-    )
+    if exists("phpunit.xml") || exists("phpunit.xml.dist") {
+        return Some("phpunit".to_string());
+    }
+    if let Ok(content) = std::fs::read_to_string(dir.join("composer.json")) {
+        if content.contains("phpunit") {
+            return Some("phpunit".to_string());
+        }
+    }
+
+    // Solution or project files are named after the project
+    let dotnet = std::fs::read_dir(dir).ok().is_some_and(|entries| {
+        entries.filter_map(Result::ok).any(|e| {
+            let name = e.file_name();
+            let name = name.to_string_lossy();
+            [".sln", ".csproj", ".fsproj", ".vbproj"]
+                .iter()
+                .any(|ext| name.ends_with(ext))
+        })
+    });
+    if dotnet {
+        return Some("dotnet-test".to_string());
+    }
+
+    if exists("build.gradle")
+        || exists("build.gradle.kts")
+        || exists("settings.gradle")
+        || exists("settings.gradle.kts")
+    {
+        return Some("gradle".to_string());
+    }
+
+    if exists("Package.swift") {
+        return Some("swift-test".to_string());
+    }
+
+    if exists("bunfig.toml") || exists("bun.lockb") || exists("bun.lock") {
+        return Some("bun-test".to_string());
+    }
+
+    // Default to vitest for JS/TS projects, unless package.json only knows jest
+    if let Ok(content) = std::fs::read_to_string(dir.join("package.json")) {
+        if content.contains("jest") && !content.contains("vitest") {
+            return Some("jest".to_string());
+        }
+        if content.contains("\"mocha\"") && !content.contains("vitest") {
+            return Some("mocha".to_string());
+        }
+        return Some("vitest".to_string());
+    }
+
+    None
 }
 
-fn build_command(
+pub fn build_command(
+    runner: &str,
+    test_files: &[String],
+    extra_args: &[String],
+) -> anyhow::Result<(String, Vec<String>)> {
+    build_command_in(Path::new("."), runner, test_files, extra_args)
+}
+
+/// The command running `test_files` (relative to `dir`, where it runs)
+pub fn build_command_in(
+    dir: &Path,
     runner: &str,
     test_files: &[String],
     extra_args: &[String],
@@ -173,28 +862,135 @@ fn build_command(
             Ok(("pytest".to_string(), args))
         }
         "cargo-test" => {
-            let mut args = vec!["test".to_string()];
-            // Cargo test doesn't take file paths directly, use --test for specific tests
-            if !test_files.is_empty() {
-                println!(
-                    "{}",
-                    "Note: Cargo test runs all tests. Use 'cargo test <name>' for specific tests."
-                        .dimmed()
-                );
-            }
-            args.extend(extra_args.iter().cloned());
-            Ok(("cargo".to_string(), args))
+            // Cargo test doesn't take file paths, so filter by the tests in them
+            let targets = if test_files.is_empty() {
+                None
+            } else {
+                test_targets::cargo(dir, test_files)
+            };
+            let Some(targets) = targets else {
+                if !test_files.is_empty() {
+                    println!(
+                        "{}",
+                        "Note: No #[test] functions found in the applied files; running all tests."
+                            .dimmed()
+                    );
+                }
+                let mut args = vec!["test".to_string()];
+                args.extend(extra_args.iter().cloned());
+                return Ok(("cargo".to_string(), args));
+            };
+            Ok((
+                "cargo".to_string(),
+                cargo_test_args(targets.cargo_args, extra_args, targets.filters),
+            ))
         }
         "go-test" => {
             let mut args = vec!["test".to_string()];
             if test_files.is_empty() {
                 args.push("./...".to_string());
             } else {
-                args.extend(test_files.iter().cloned());
+                // Go tests run by package; narrow them to the files' tests
+                let (packages, pattern) = test_targets::go(dir, test_files);
+                if let Some(pattern) = pattern {
+                    args.extend(["-run".to_string(), pattern]);
+                }
+                args.extend(packages);
             }
             args.extend(extra_args.iter().cloned());
             Ok(("go".to_string(), args))
         }
+        "mocha" => {
+            let mut args = vec!["mocha".to_string()];
+            args.extend(test_files.iter().cloned());
+            args.extend(extra_args.iter().cloned());
+            Ok(("npx".to_string(), args))
+        }
+        "bun-test" => {
+            let mut args = vec!["test".to_string()];
+            args.extend(test_files.iter().cloned());
+            args.extend(extra_args.iter().cloned());
+            Ok(("bun".to_string(), args))
+        }
+        "deno-test" => {
+            let mut args = vec!["test".to_string()];
+            args.extend(test_files.iter().cloned());
+            args.extend(extra_args.iter().cloned());
+            Ok(("deno".to_string(), args))
+        }
+        "rspec" => {
+            let (cmd, mut args) = bundled(dir, "rspec");
+            args.extend(test_files.iter().cloned());
+            args.extend(extra_args.iter().cloned());
+            Ok((cmd, args))
+        }
+        "minitest" => {
+            if test_files.is_empty() {
+                let (cmd, mut args) = bundled(dir, "rake");
+                args.push("test".to_string());
+                args.extend(extra_args.iter().cloned());
+                return Ok((cmd, args));
+            }
+            // Minitest has no runner of its own; load the files and let
+            // `minitest/autorun` run them on exit
+            let (cmd, mut args) = bundled(dir, "ruby");
+            args.extend([
+                "-Itest".to_string(),
+                "-Ilib".to_string(),
+                "-e".to_string(),
+                "ARGV.each { |f| require File.expand_path(f) }".to_string(),
+            ]);
+            args.extend(test_files.iter().cloned());
+            args.extend(extra_args.iter().cloned());
+            Ok((cmd, args))
+        }
+        "phpunit" => {
+            let cmd = if dir.join("vendor/bin/phpunit").exists() {
+                "vendor/bin/phpunit"
+            } else {
+                "phpunit"
+            };
+            let mut args = test_files.to_vec();
+            args.extend(extra_args.iter().cloned());
+            Ok((cmd.to_string(), args))
+        }
+        "dotnet-test" => {
+            // Test classes are usually named after their file
+            let mut args = vec!["test".to_string()];
+            if !test_files.is_empty() {
+                let filter: Vec<String> = file_stems(test_files)
+                    .iter()
+                    .map(|stem| format!("FullyQualifiedName~{}", stem))
+                    .collect();
+                args.extend(["--filter".to_string(), filter.join("|")]);
+            }
+            args.extend(extra_args.iter().cloned());
+            Ok(("dotnet".to_string(), args))
+        }
+        "gradle" => {
+            let cmd = if dir.join("gradlew").exists() {
+                "./gradlew"
+            } else {
+                "gradle"
+            };
+            let mut args = vec!["test".to_string()];
+            for stem in file_stems(test_files) {
+                args.extend(["--tests".to_string(), format!("*{}", stem)]);
+            }
+            args.extend(extra_args.iter().cloned());
+            Ok((cmd.to_string(), args))
+        }
+        "swift-test" => {
+            let mut args = vec!["test".to_string()];
+            if !test_files.is_empty() {
+                args.extend([
+                    "--filter".to_string(),
+                    format!("({})", file_stems(test_files).join("|")),
+                ]);
+            }
+            args.extend(extra_args.iter().cloned());
+            Ok(("swift".to_string(), args))
+        }
         _ => {
             // Custom runner - just run it directly
             let mut args = test_files.to_vec();
@@ -204,12 +1000,185 @@ fn build_command(
     }
 }
 
-fn load_history() -> anyhow::Result<ApplyHistory> {
-    let path = Path::new(".vibetap/history.json");
-    if !path.exists() {
-        return Ok(ApplyHistory::default());
+/// The command that runs only `failed` again, in each runner's own terms:
+/// vitest and jest rerun the files with failures, pytest uses its `--lf`
+/// cache, cargo gets exact test names and go a `-run` pattern. `None` for
+/// runners with no way to pick tests.
+pub fn build_rerun_command(
+    runner: &str,
+    failed: &[TestId],
+    extra_args: &[String],
+) -> Option<(String, Vec<String>)> {
+    let mut suites: Vec<String> = Vec::new();
+    for test in failed {
+        if !test.suite.is_empty() && !suites.contains(&test.suite) {
+            suites.push(test.suite.clone());
+        }
     }
 
-    let content = std::fs::read_to_string(path)?;
-    Ok(serde_json::from_str(&content)?)
+    match runner {
+        "vitest" | "jest" if !suites.is_empty() => build_command(runner, &suites, extra_args).ok(),
+        "pytest" => {
+            let mut args = vec!["--lf".to_string()];
+            args.extend(extra_args.iter().cloned());
+            Some(("pytest".to_string(), args))
+        }
+        "cargo-test" => {
+            let names = failed.iter().map(|t| {
+                if t.suite.is_empty() {
+                    t.name.clone()
+                } else {
+                    format!("{}::{}", t.suite, t.name)
+                }
+            });
+            let mut test_args = vec!["--exact".to_string()];
+            test_args.extend(names);
+            Some((
+                "cargo".to_string(),
+                cargo_test_args(Vec::new(), extra_args, test_args),
+            ))
+        }
+        "go-test" if !suites.is_empty() => {
+            // Subtests rerun with their parent
+            let mut names: Vec<String> = Vec::new();
+            for test in failed {
+                let name = test.name.split('/').next().unwrap_or_default();
+                let name = regex::escape(name);
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            let mut args = vec![
+                "test".to_string(),
+                "-run".to_string(),
+                format!("^({})$", names.join("|")),
+            ];
+            args.extend(suites);
+            args.extend(extra_args.iter().cloned());
+            Some(("go".to_string(), args))
+        }
+        _ => None,
+    }
+}
+
+/// `bundle exec <program>` when the project has a Gemfile
+fn bundled(dir: &Path, program: &str) -> (String, Vec<String>) {
+    if dir.join("Gemfile").exists() {
+        (
+            "bundle".to_string(),
+            vec!["exec".to_string(), program.to_string()],
+        )
+    } else {
+        (program.to_string(), Vec::new())
+    }
+}
+
+/// `cargo test` arguments: cargo's own and the user's, then `--` and the
+/// arguments for the test binaries ahead of any the user passed them
+fn cargo_test_args(
+    cargo_args: Vec<String>,
+    extra_args: &[String],
+    test_args: Vec<String>,
+) -> Vec<String> {
+    let split = extra_args
+        .iter()
+        .position(|a| a == "--")
+        .unwrap_or(extra_args.len());
+    let mut args = vec!["test".to_string()];
+    args.extend(cargo_args);
+    args.extend(extra_args[..split].iter().cloned());
+    args.push("--".to_string());
+    args.extend(test_args);
+    args.extend(extra_args.iter().skip(split + 1).cloned());
+    args
+}
+
+/// File names without directory or extension, for runners that select
+/// tests by class name rather than path
+fn file_stems(files: &[String]) -> Vec<String> {
+    files
+        .iter()
+        .filter_map(|f| Path::new(f).file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_rerun_command() {
+        let id = |suite: &str, name: &str| TestId {
+            suite: suite.to_string(),
+            name: name.to_string(),
+        };
+        let extra = vec![
+            "--offline".to_string(),
+            "--".to_string(),
+            "--nocapture".to_string(),
+        ];
+        let (_, args) = build_rerun_command("cargo-test", &[id("tests", "adds")], &extra).unwrap();
+        assert_eq!(
+            args,
+            vec![
+                "test",
+                "--offline",
+                "--",
+                "--exact",
+                "tests::adds",
+                "--nocapture"
+            ]
+        );
+
+        let failed = [id("pkg/a", "TestAdd/negative"), id("pkg/a", "TestAdd")];
+        let (_, args) = build_rerun_command("go-test", &failed, &[]).unwrap();
+        assert_eq!(args, vec!["test", "-run", "^(TestAdd)$", "pkg/a"]);
+
+        let (_, args) = build_rerun_command("pytest", &failed, &[]).unwrap();
+        assert_eq!(args, vec!["--lf"]);
+        assert!(build_rerun_command("make", &failed, &[]).is_none());
+    }
+
+    #[test]
+    fn test_detects_more_runners() {
+        let dir = tempfile::tempdir().unwrap();
+        let detect = |files: &[(&str, &str)]| {
+            for entry in std::fs::read_dir(dir.path()).unwrap() {
+                std::fs::remove_file(entry.unwrap().path()).unwrap();
+            }
+            for (name, content) in files {
+                std::fs::write(dir.path().join(name), content).unwrap();
+            }
+            detect_test_runner_in(dir.path())
+        };
+        assert_eq!(
+            detect(&[("Gemfile", "gem 'rspec'")]).as_deref(),
+            Some("rspec")
+        );
+        assert_eq!(
+            detect(&[("Gemfile", "gem 'minitest'")]).as_deref(),
+            Some("minitest")
+        );
+        assert_eq!(
+            detect(&[("App.csproj", "")]).as_deref(),
+            Some("dotnet-test")
+        );
+        assert_eq!(
+            detect(&[("Package.swift", "")]).as_deref(),
+            Some("swift-test")
+        );
+        assert_eq!(
+            detect(&[("package.json", "{}"), ("bun.lock", "")]).as_deref(),
+            Some("bun-test")
+        );
+        assert_eq!(
+            detect(&[("package.json", r#"{"devDependencies":{"mocha":"^10"}}"#)]).as_deref(),
+            Some("mocha")
+        );
+
+        let files = vec!["Tests/CartTests.swift".to_string()];
+        let (_, args) = build_command("swift-test", &files, &[]).unwrap();
+        assert_eq!(args, vec!["test", "--filter", "(CartTests)"]);
+    }
 }
@@ -3,7 +3,13 @@ use std::path::{Path, PathBuf};
 
 use clap::Args;
 use colored::Colorize;
-use walkdir::WalkDir;
+
+use super::workspace::run_each;
+use vibetap_core::deps::ImportGraph;
+use vibetap_git::IgnoreRules;
+
+/// Importer count at which an untested file's risk is raised one level
+const HEAVILY_IMPORTED: usize = 5;
 
 #[derive(Args)]
 pub struct ScanArgs {
@@ -22,6 +28,10 @@ pub struct ScanArgs {
     /// Output as JSON
     #[arg(long)]
     json: bool,
+
+    /// Scan every repository in the workspace
+    #[arg(long)]
+    workspace: bool,
 }
 
 #[derive(Debug)]
@@ -32,6 +42,8 @@ struct ScanResult {
     has_tests: bool,
     test_file: Option<String>,
     reason: String,
+    /// Number of project files that import this one
+    imported_by: usize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -52,6 +64,10 @@ impl RiskLevel {
 }
 
 pub async fn execute(args: ScanArgs) -> anyhow::Result<()> {
+    if args.workspace {
+        return run_each(args.json).await;
+    }
+
     let scan_path = Path::new(&args.path);
 
     if !scan_path.exists() {
@@ -66,8 +82,9 @@ pub async fn execute(args: ScanArgs) -> anyhow::Result<()> {
     let source_files = find_source_files(scan_path);
     let test_files = find_test_files(scan_path);
 
-    // Analyze coverage
-    let results = analyze_coverage(&source_files, &test_files);
+    // Analyze coverage, weighting modules many files depend on
+    let graph = ImportGraph::build(scan_path);
+    let results = analyze_coverage(scan_path, &source_files, &test_files, &graph);
 
     if args.json {
         let json_results: Vec<_> = results
@@ -80,6 +97,7 @@ pub async fn execute(args: ScanArgs) -> anyhow::Result<()> {
                     "hasTests": r.has_tests,
                     "testFile": r.test_file,
                     "reason": r.reason,
+                    "importedBy": r.imported_by,
                 })
             })
             .collect();
@@ -92,7 +110,7 @@ pub async fn execute(args: ScanArgs) -> anyhow::Result<()> {
         .into_iter()
         .filter(|r| !r.has_tests)
         .collect();
-    results.sort_by(|a, b| a.risk_level.cmp(&b.risk_level));
+    results.sort_by_key(|r| r.risk_level);
 
     let total_files = source_files.len();
     let files_without_tests = results.len();
@@ -171,28 +189,11 @@ pub async fn execute(args: ScanArgs) -> anyhow::Result<()> {
 
 fn find_source_files(base_path: &Path) -> Vec<PathBuf> {
     let source_extensions = ["ts", "tsx", "js", "jsx", "py", "rs", "go", "rb", "java"];
-    let ignore_patterns = [
-        "node_modules",
-        "target",
-        "dist",
-        "build",
-        ".git",
-        "__pycache__",
-        ".next",
-        "coverage",
-        ".turbo",
-    ];
-
-    WalkDir::new(base_path)
+
+    IgnoreRules::load(base_path)
+        .files(base_path)
         .into_iter()
-        .filter_entry(|e| {
-            let name = e.file_name().to_string_lossy();
-            !ignore_patterns.iter().any(|p| name.contains(p))
-        })
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .filter(|e| {
-            let path = e.path();
+        .filter(|path| {
             let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
             let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
 
@@ -207,51 +208,41 @@ fn find_source_files(base_path: &Path) -> Vec<PathBuf> {
                 // Exclude type definition files
                 && !name.ends_with(".d.ts")
         })
-        .map(|e| e.path().to_path_buf())
         .collect()
 }
 
 fn find_test_files(base_path: &Path) -> HashMap<String, PathBuf> {
-    let ignore_patterns = [
-        "node_modules",
-        "target",
-        "dist",
-        "build",
-        ".git",
-        "__pycache__",
-    ];
-
-    WalkDir::new(base_path)
+    IgnoreRules::load(base_path)
+        .files(base_path)
         .into_iter()
-        .filter_entry(|e| {
-            let name = e.file_name().to_string_lossy();
-            !ignore_patterns.iter().any(|p| name.contains(p))
-        })
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .filter(|e| {
-            let name = e.file_name().to_string_lossy();
-            name.contains(".test.")
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().to_string();
+            let is_test = name.contains(".test.")
                 || name.contains(".spec.")
                 || name.contains("_test.")
                 || name.ends_with("_test.go")
-                || name.ends_with("_test.py")
-        })
-        .map(|e| {
+                || name.ends_with("_test.py");
+            if !is_test {
+                return None;
+            }
             // Extract the base name that's being tested
-            let name = e.file_name().to_string_lossy().to_string();
             let base = name
                 .replace(".test.", ".")
                 .replace(".spec.", ".")
                 .replace("_test.", ".")
                 .replace("_test.go", ".go")
                 .replace("_test.py", ".py");
-            (base, e.path().to_path_buf())
+            Some((base, path))
         })
         .collect()
 }
 
-fn analyze_coverage(source_files: &[PathBuf], test_files: &HashMap<String, PathBuf>) -> Vec<ScanResult> {
+fn analyze_coverage(
+    base_path: &Path,
+    source_files: &[PathBuf],
+    test_files: &HashMap<String, PathBuf>,
+    graph: &ImportGraph,
+) -> Vec<ScanResult> {
     source_files
         .iter()
         .map(|source| {
@@ -271,7 +262,18 @@ fn analyze_coverage(source_files: &[PathBuf], test_files: &HashMap<String, PathB
 
             // Determine risk level based on file path and name
             let path_str = source.to_string_lossy().to_lowercase();
-            let (risk_level, reason) = determine_risk(&path_str, &file_name);
+            let (mut risk_level, mut reason) = determine_risk(&path_str, &file_name);
+
+            // A widely imported module breaks many callers when it regresses
+            let relative = source.strip_prefix(base_path).unwrap_or(source);
+            let imported_by = graph.dependents(&relative.to_string_lossy()).len();
+            if imported_by >= HEAVILY_IMPORTED {
+                risk_level = match risk_level {
+                    RiskLevel::Low => RiskLevel::Medium,
+                    _ => RiskLevel::High,
+                };
+                reason = format!("{} (imported by {} files)", reason, imported_by);
+            }
 
             ScanResult {
                 path: source.to_string_lossy().to_string(),
@@ -280,6 +282,7 @@ fn analyze_coverage(source_files: &[PathBuf], test_files: &HashMap<String, PathB
                 has_tests,
                 test_file: test_file.map(|p| p.to_string_lossy().to_string()),
                 reason,
+                imported_by,
             }
         })
         .collect()
@@ -0,0 +1,122 @@
+use clap::Args;
+use colored::Colorize;
+
+use super::export::render_markdown;
+use super::generate::{load_session, load_suggestions, parse_session_name, SavedSuggestions};
+use vibetap_core::{api::ShareRequest, ApiClient, Config};
+
+#[derive(Args)]
+pub struct ShareArgs {
+    /// Share a named session instead of the latest suggestion set
+    #[arg(long, value_name = "NAME", value_parser = parse_session_name)]
+    session: Option<String>,
+
+    /// Publish as a GitHub gist instead of a VibeTap link (uses GITHUB_TOKEN or GH_TOKEN)
+    #[arg(long)]
+    gist: bool,
+
+    /// Make the gist public (gists are secret by default)
+    #[arg(long, requires = "gist")]
+    public: bool,
+}
+
+pub async fn execute(args: ShareArgs) -> anyhow::Result<()> {
+    let saved = match args.session {
+        Some(ref name) => load_session(name)?,
+        None => load_suggestions()?,
+    };
+
+    if saved.response.suggestions.is_empty() {
+        println!("{}", "No suggestions to share.".yellow());
+        return Ok(());
+    }
+
+    let url = if args.gist {
+        println!("{}", "Creating GitHub gist...".cyan());
+        create_gist(&saved, args.public).await?
+    } else {
+        println!("{}", "Uploading suggestion set...".cyan());
+        let mut config = Config::load()?;
+        let access_token = config.get_valid_access_token().await?;
+        let client = ApiClient::new(config.api_url(), access_token);
+
+        let shared = client
+            .share(ShareRequest {
+                suggestions: saved.response.clone(),
+                session: saved.session.clone(),
+                repo_identifier: vibetap_git::remote_identifier().ok().flatten(),
+            })
+            .await?;
+
+        if let Some(ref expires) = shared.expires_at {
+            println!("{}", format!("Link expires {}", expires).dimmed());
+        }
+        shared.url
+    };
+
+    println!(
+        "\n{} {}",
+        "Shared:".green().bold(),
+        url.cyan().underline()
+    );
+    println!(
+        "{}",
+        format!("{} suggestion(s) published.", saved.response.suggestions.len()).dimmed()
+    );
+
+    Ok(())
+}
+
+/// Publish the suggestion set as a GitHub gist and return its URL
+async fn create_gist(saved: &SavedSuggestions, public: bool) -> anyhow::Result<String> {
+    let token = std::env::var("GITHUB_TOKEN")
+        .or_else(|_| std::env::var("GH_TOKEN"))
+        .map_err(|_| {
+            anyhow::anyhow!("Set GITHUB_TOKEN or GH_TOKEN with the 'gist' scope to publish gists.")
+        })?;
+
+    // One markdown overview plus each suggestion as its own file for syntax highlighting
+    let mut files = serde_json::Map::new();
+    files.insert(
+        "vibetap-suggestions.md".to_string(),
+        serde_json::json!({ "content": render_markdown(&saved.response) }),
+    );
+    for (i, suggestion) in saved.response.suggestions.iter().enumerate() {
+        let base_name = suggestion
+            .file_path
+            .rsplit('/')
+            .next()
+            .unwrap_or(&suggestion.file_path);
+        files.insert(
+            format!("{}-{}", i + 1, base_name),
+            serde_json::json!({ "content": suggestion.code }),
+        );
+    }
+
+    let body = serde_json::json!({
+        "description": format!("VibeTap test suggestions ({})", saved.session_id()),
+        "public": public,
+        "files": files,
+    });
+
+    let response = reqwest::Client::new()
+        .post("https://api.github.com/gists")
+        .header("Authorization", format!("Bearer {}", token))
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "vibetap-cli")
+        .json(&body)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        anyhow::bail!("GitHub returned {}: {}", status, text);
+    }
+
+    let gist: serde_json::Value = response.json().await?;
+    gist["html_url"]
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| anyhow::anyhow!("GitHub response did not include a gist URL"))
+}
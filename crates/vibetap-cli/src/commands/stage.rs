@@ -0,0 +1,189 @@
+use clap::Args;
+use colored::Colorize;
+use std::io::{self, Write};
+
+use crate::ui;
+use vibetap_git::{
+    get_staged_diff, get_unstaged_diff, stage_hunk, unstage_hunk, DiffHunk, DiffSettings, GitError,
+};
+
+#[derive(Args)]
+pub struct StageArgs {
+    /// Only offer hunks in these files
+    #[arg(value_name = "FILE")]
+    files: Vec<String>,
+
+    /// Pick staged hunks to unstage instead
+    #[arg(long)]
+    unstage: bool,
+}
+
+pub async fn execute(args: StageArgs) -> anyhow::Result<()> {
+    // Hunks must be listed with the same settings stage_hunk uses to find them again
+    let settings = DiffSettings::default();
+    let diff = if args.unstage {
+        get_staged_diff(&settings)
+    } else {
+        get_unstaged_diff(&settings)
+    };
+
+    let diff = match diff {
+        Ok(d) => d,
+        Err(GitError::NoStagedChanges) => {
+            let message = if args.unstage {
+                "Nothing is staged."
+            } else {
+                "No unstaged changes."
+            };
+            println!("{}", message.yellow());
+            return Ok(());
+        }
+        Err(GitError::NotARepo) => {
+            println!(
+                "{}",
+                "Not a git repository. Run this command from within a git repo.".red()
+            );
+            return Ok(());
+        }
+        Err(e @ (GitError::EmptyRepository | GitError::Bare)) => {
+            println!("{}", format!("{}.", e).yellow());
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let hunks: Vec<&DiffHunk> = diff
+        .hunks
+        .iter()
+        .filter(|h| {
+            args.files.is_empty()
+                || args
+                    .files
+                    .iter()
+                    .any(|f| h.file_path == f.trim_start_matches("./"))
+        })
+        .collect();
+
+    if hunks.is_empty() {
+        println!("{}", "No hunks to choose from.".yellow());
+        return Ok(());
+    }
+
+    let verb = if args.unstage { "Unstage" } else { "Stage" };
+    let mut chosen = 0;
+
+    // Later hunks in a file shift once an earlier one moves, so hunks are picked
+    // first and applied bottom-up afterwards
+    let mut selected: Vec<&DiffHunk> = Vec::new();
+    // Answer from `a`/`d`, applied to the rest of that file without asking
+    let mut file_decision: Option<(&str, bool)> = None;
+
+    'hunks: for (i, hunk) in hunks.iter().enumerate() {
+        if let Some((file, take)) = file_decision {
+            if file == hunk.file_path {
+                if take {
+                    selected.push(hunk);
+                }
+                continue;
+            }
+            file_decision = None;
+        }
+
+        print_hunk(hunk, i + 1, hunks.len());
+
+        loop {
+            print!("{} ", format!("{} this hunk? [y,n,a,d,q,?]", verb).yellow());
+            io::stdout().flush()?;
+
+            let mut input = String::new();
+            if io::stdin().read_line(&mut input)? == 0 {
+                break 'hunks;
+            }
+            match input.trim() {
+                "y" => selected.push(hunk),
+                "n" | "" => {}
+                "a" => {
+                    selected.push(hunk);
+                    file_decision = Some((&hunk.file_path, true));
+                }
+                "d" => file_decision = Some((&hunk.file_path, false)),
+                "q" => break 'hunks,
+                _ => {
+                    let verb = verb.to_lowercase();
+                    println!("y - {} this hunk", verb);
+                    println!("n - skip this hunk");
+                    println!("a - {} this and the remaining hunks in the file", verb);
+                    println!("d - skip the remaining hunks in the file");
+                    println!("q - quit; hunks chosen so far are still {}d", verb);
+                    continue;
+                }
+            }
+            break;
+        }
+    }
+
+    selected.sort_by_key(|h| std::cmp::Reverse((h.file_path.as_str(), h.old_start)));
+
+    for hunk in selected {
+        let result = if args.unstage {
+            unstage_hunk(hunk)
+        } else {
+            stage_hunk(hunk)
+        };
+        match result {
+            Ok(()) => chosen += 1,
+            Err(e) => println!("{} {}", ui::icon("✗", "Error:").red(), e),
+        }
+    }
+
+    println!();
+    if chosen == 0 {
+        println!("{}", "No hunks changed.".dimmed());
+    } else {
+        let done = if args.unstage { "unstaged" } else { "staged" };
+        println!(
+            "{} {} hunk(s) {}.",
+            ui::icon("✓", "OK:").green(),
+            chosen,
+            done
+        );
+        if !args.unstage {
+            println!("Run {} to get suggestions for them.", "vibetap generate".cyan());
+        }
+    }
+
+    Ok(())
+}
+
+fn print_hunk(hunk: &DiffHunk, number: usize, total: usize) {
+    let location = match hunk.context_header {
+        Some(ref header) => format!("{} ({})", hunk.file_path, header),
+        None => hunk.file_path.clone(),
+    };
+    println!();
+    println!(
+        "{} {}",
+        format!("[{}/{}]", number, total).dimmed(),
+        location.cyan().bold()
+    );
+    println!(
+        "{}",
+        format!(
+            "@@ -{},{} +{},{} @@",
+            hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines
+        )
+        .dimmed()
+    );
+
+    for line in hunk.content.lines() {
+        if ui::is_accessible() {
+            println!("{}", line);
+        } else if line.starts_with('+') {
+            println!("{}", line.green());
+        } else if line.starts_with('-') {
+            println!("{}", line.red());
+        } else {
+            println!("{}", line);
+        }
+    }
+}
@@ -1,6 +1,11 @@
 use clap::Args;
 use colored::Colorize;
+use std::collections::HashMap;
 
+use super::apply::load_history;
+use super::notes::SuggestionNote;
+use super::workspace::run_each;
+use crate::ui;
 use vibetap_core::{ApiClient, Config};
 
 #[derive(Args)]
@@ -8,9 +13,25 @@ pub struct StatsArgs {
     /// Show raw JSON output
     #[arg(long)]
     json: bool,
+
+    /// Show stats from git notes in this repository instead of the API
+    #[arg(long)]
+    local: bool,
+
+    /// Combine local stats from every repository in the workspace
+    #[arg(long, requires = "local")]
+    workspace: bool,
 }
 
 pub async fn execute(args: StatsArgs) -> anyhow::Result<()> {
+    if args.workspace {
+        return run_each(args.json).await;
+    }
+
+    if args.local {
+        return local_stats(args.json);
+    }
+
     // Load configuration
     let mut config = Config::load()?;
     let access_token = config.get_valid_access_token().await?;
@@ -36,12 +57,16 @@ pub async fn execute(args: StatsArgs) -> anyhow::Result<()> {
                     "limit": stats.this_month.limit,
                     "securityIssuesCaught": stats.this_month.security_issues_caught,
                     "testsApplied": stats.this_month.tests_applied,
+                    "testsPassing": stats.this_month.tests_passing,
+                    "testsFailing": stats.this_month.tests_failing,
                     "acceptanceRate": stats.this_month.acceptance_rate,
                 },
                 "allTime": {
                     "totalGenerations": stats.all_time.total_generations,
                     "totalSecurityIssues": stats.all_time.total_security_issues,
                     "totalTestsApplied": stats.all_time.total_tests_applied,
+                    "totalTestsPassing": stats.all_time.total_tests_passing,
+                    "totalTestsFailing": stats.all_time.total_tests_failing,
                     "topFramework": stats.all_time.top_framework,
                 },
                 "plan": {
@@ -60,7 +85,11 @@ pub async fn execute(args: StatsArgs) -> anyhow::Result<()> {
 
     // Display formatted stats
     println!();
-    println!("{}", "═══ VibeTap Stats ═══".bold().cyan());
+    if ui::is_accessible() {
+        println!("VibeTap Stats");
+    } else {
+        println!("{}", "═══ VibeTap Stats ═══".bold().cyan());
+    }
     println!();
 
     // This month section
@@ -71,22 +100,30 @@ pub async fn execute(args: StatsArgs) -> anyhow::Result<()> {
         0
     };
 
-    // Progress bar
-    let bar_width = 30;
-    let filled = (bar_width * usage_pct / 100).min(bar_width);
-    let empty = bar_width - filled;
-    let bar = format!(
-        "[{}{}]",
-        "█".repeat(filled as usize).green(),
-        "░".repeat(empty as usize).dimmed()
-    );
+    if ui::is_accessible() {
+        println!(
+            "  {} generations used, {}% of limit",
+            stats.this_month.generations,
+            usage_pct
+        );
+    } else {
+        // Progress bar
+        let bar_width = 30;
+        let filled = (bar_width * usage_pct / 100).min(bar_width);
+        let empty = bar_width - filled;
+        let bar = format!(
+            "[{}{}]",
+            "█".repeat(filled as usize).green(),
+            "░".repeat(empty as usize).dimmed()
+        );
 
-    println!(
-        "  {} generations used {} {}%",
-        stats.this_month.generations.to_string().green(),
-        bar,
-        usage_pct
-    );
+        println!(
+            "  {} generations used {} {}%",
+            stats.this_month.generations.to_string().green(),
+            bar,
+            usage_pct
+        );
+    }
     println!(
         "  {} remaining of {} limit",
         stats.this_month.remaining.to_string().yellow(),
@@ -99,6 +136,10 @@ pub async fn execute(args: StatsArgs) -> anyhow::Result<()> {
             stats.this_month.tests_applied.to_string().green(),
             (stats.this_month.acceptance_rate * 100.0) as u32
         );
+        print_verified(
+            stats.this_month.tests_passing,
+            stats.this_month.tests_failing,
+        );
     }
 
     if stats.this_month.security_issues_caught > 0 {
@@ -121,6 +162,10 @@ pub async fn execute(args: StatsArgs) -> anyhow::Result<()> {
             "  Total tests applied: {}",
             stats.all_time.total_tests_applied.to_string().green()
         );
+        print_verified(
+            stats.all_time.total_tests_passing,
+            stats.all_time.total_tests_failing,
+        );
         if stats.all_time.total_security_issues > 0 {
             println!(
                 "  Security issues caught: {}",
@@ -166,3 +211,142 @@ pub async fn execute(args: StatsArgs) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// How many applied tests passed and failed when last run, when known
+fn print_verified(passing: Option<u32>, failing: Option<u32>) {
+    let (passing, failing) = (passing.unwrap_or(0), failing.unwrap_or(0));
+    if passing + failing == 0 {
+        return;
+    }
+    let failing = if failing > 0 {
+        failing.to_string().red()
+    } else {
+        failing.to_string().normal()
+    };
+    println!(
+        "    {} passing, {} failing when last run",
+        passing.to_string().green(),
+        failing
+    );
+}
+
+/// Attribute applied suggestions to commits using VibeTap git notes
+fn local_stats(json: bool) -> anyhow::Result<()> {
+    let notes = vibetap_git::list_notes()?;
+
+    let mut commits: Vec<(String, SuggestionNote)> = notes
+        .into_iter()
+        .filter_map(|(commit, message)| {
+            serde_json::from_str::<SuggestionNote>(&message)
+                .ok()
+                .map(|note| (commit, note))
+        })
+        .collect();
+    commits.sort_by_key(|(_, note)| {
+        std::cmp::Reverse(note.suggestions.iter().map(|s| s.applied_at).max().unwrap_or(0))
+    });
+
+    let total_suggestions: usize = commits.iter().map(|(_, n)| n.suggestions.len()).sum();
+
+    // Whether each noted suggestion's test passed when `vibetap run` last ran it
+    let history = load_history().unwrap_or_default();
+    let verified = |id: &str| {
+        history
+            .records
+            .iter()
+            .rev()
+            .find(|r| r.suggestion_id == id)
+            .and_then(|r| r.verification.as_ref())
+            .map(|v| v.passed)
+    };
+    let outcomes: Vec<Option<bool>> = commits
+        .iter()
+        .flat_map(|(_, n)| n.suggestions.iter().map(|s| verified(&s.id)))
+        .collect();
+    let passing = outcomes.iter().filter(|o| **o == Some(true)).count();
+    let failing = outcomes.iter().filter(|o| **o == Some(false)).count();
+    let mut by_model: HashMap<String, usize> = HashMap::new();
+    for (_, note) in &commits {
+        for entry in &note.suggestions {
+            let model = entry.model.clone().unwrap_or_else(|| "unknown".to_string());
+            *by_model.entry(model).or_default() += 1;
+        }
+    }
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "commits": commits.len(),
+                "testsApplied": total_suggestions,
+                "testsPassing": passing,
+                "testsFailing": failing,
+                "byModel": by_model,
+                "perCommit": commits
+                    .iter()
+                    .map(|(commit, note)| serde_json::json!({
+                        "commit": commit,
+                        "suggestions": note.suggestions,
+                    }))
+                    .collect::<Vec<_>>(),
+            }))?
+        );
+        return Ok(());
+    }
+
+    println!();
+    if ui::is_accessible() {
+        println!("VibeTap Local Stats");
+    } else {
+        println!("{}", "═══ VibeTap Local Stats ═══".bold().cyan());
+    }
+    println!();
+
+    if commits.is_empty() {
+        println!("{}", "No VibeTap notes found in this repository.".yellow());
+        println!(
+            "Run {} to record applied suggestions on commits.",
+            "vibetap hook install --notes".cyan()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "  {} test(s) applied across {} commit(s)",
+        total_suggestions.to_string().green(),
+        commits.len().to_string().cyan()
+    );
+    print_verified(Some(passing as u32), Some(failing as u32));
+
+    let mut models: Vec<_> = by_model.into_iter().collect();
+    models.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    for (model, count) in &models {
+        println!("  {} {}", format!("{}:", model).dimmed(), count);
+    }
+
+    println!();
+    println!("{}", "Recent commits".bold());
+    for (commit, note) in commits.iter().take(10) {
+        println!(
+            "  {} {} suggestion(s)",
+            commit[..7.min(commit.len())].cyan(),
+            note.suggestions.len()
+        );
+        for entry in &note.suggestions {
+            let outcome = match verified(&entry.id) {
+                Some(true) => " passing".green(),
+                Some(false) => " failing".red(),
+                None => "".normal(),
+            };
+            println!(
+                "    {} {}{}",
+                ui::icon("•", "-"),
+                entry.file_path.dimmed(),
+                outcome
+            );
+        }
+    }
+    println!();
+
+    Ok(())
+}
@@ -1,23 +1,84 @@
+use chrono::{Local, TimeZone};
 use clap::Args;
 use colored::Colorize;
 use notify::RecursiveMode;
-use notify_debouncer_mini::{new_debouncer, DebouncedEventKind};
-use std::path::Path;
-use std::sync::mpsc::channel;
-use std::time::Duration;
+use notify_debouncer_mini::{
+    new_debouncer, DebounceEventResult, DebouncedEvent, DebouncedEventKind,
+};
+use serde_json::json;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant, SystemTime};
 
-use super::hush::load_state;
+use super::budget::{check_budget, hourly_limit_resumes_at, track_usage, unix_now};
+use super::generate::{
+    compute_hash, drop_low_confidence, fit_context, forget_other_branch_suggestions,
+    include_negative_paths, min_confidence, model_tier, parse_confidence, policy_pack,
+    print_context_trimming, project_instructions, recent_commit_messages, save_suggestions,
+    sub_diff, with_context_globs, DiffFlags, HunkRange,
+};
+use super::hush::{hush_for, load_state};
+use super::run::detect_test_runner_in;
+use super::workspace::run_all_labeled;
+use crate::cache;
+use crate::desktop;
+use crate::keys::KeyListener;
+use crate::retry;
+use crate::suggestion_command;
+use crate::ui;
+use crate::watch_log::WatchLog;
+use crate::watch_state::WatchState;
 use vibetap_core::{
-    api::{DiffHunk, DiffPayload, FileContext, GenerateOptions, GenerateRequest},
+    api::{
+        ApiError, DiffHunk, DiffPayload, FileContext, GenerateOptions, GenerateRequest,
+        TestSuggestion, MODEL_TIERS,
+    },
+    config::ConfigError,
+    orchestrator,
+    packages::{self, Package},
     ApiClient, Config,
 };
-use vibetap_git::{get_staged_diff, GitError};
+use vibetap_git::{
+    get_staged_diff, repo_root, ConflictState, DiffSettings, GitError, IgnoreRules, Operation,
+};
+
+/// Changed paths written per trigger in the log
+const LOGGED_PATHS: usize = 20;
+
+/// First wait before checking whether an unreachable API is back
+const NETWORK_RETRY_SECS: i64 = 15;
+
+/// Longest wait between checks while offline
+const MAX_NETWORK_RETRY_SECS: i64 = 5 * 60;
+
+/// How long `h` hushes suggestions for
+const KEY_HUSH: Duration = Duration::from_secs(30 * 60);
+
+/// Debounce used when neither `--debounce` nor watchMode.debounceMs is set
+const DEFAULT_DEBOUNCE_MS: u64 = 2000;
+
+/// Debounce periods a burst of saves may go on before it is generated for
+const BURST_LIMIT: u32 = 10;
+
+/// What the watch loop waits on
+enum WatchEvent {
+    Files(DebounceEventResult),
+    /// A key pressed in the terminal, lowercased
+    Key(char),
+}
 
 #[derive(Args)]
 pub struct WatchArgs {
-    /// Debounce time in milliseconds
-    #[arg(short, long, default_value = "2000")]
-    debounce: u64,
+    /// Directories to watch, e.g. packages/api (defaults to watchMode.paths, or the whole repository)
+    #[arg(value_name = "PATH")]
+    paths: Vec<String>,
+
+    /// Debounce time in milliseconds (defaults to watchMode.debounceMs, or 2000)
+    #[arg(short, long)]
+    debounce: Option<u64>,
 
     /// Watch for all uncommitted changes, not just staged
     #[arg(long)]
@@ -30,12 +91,63 @@ pub struct WatchArgs {
     /// Prioritize security tests
     #[arg(long)]
     security: bool,
+
+    /// Ask for tests of error handling and invalid input (default unless generation.includeNegativePaths is false)
+    #[arg(long, overrides_with = "no_negative_paths")]
+    negative_paths: bool,
+
+    /// Only ask for happy-path tests
+    #[arg(long, overrides_with = "negative_paths")]
+    no_negative_paths: bool,
+
+    /// Model tier to request: cheap, default, or enhanced
+    #[arg(long, value_name = "TIER", value_parser = clap::builder::PossibleValuesParser::new(MODEL_TIERS))]
+    model: Option<String>,
+
+    /// Apply a guardrail policy pack (see 'vibetap policy list')
+    #[arg(long, value_name = "ID")]
+    policy: Option<String>,
+
+    /// Watch every repository in the workspace, labeling output per repository
+    #[arg(long)]
+    workspace: bool,
+
+    /// Generations allowed per hour, counting all of this repository's (also watchMode.maxGenerationsPerHour)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    budget: Option<u32>,
+
+    /// Keep generating even if a local budget is exhausted
+    #[arg(long)]
+    ignore_budget: bool,
+
+    /// Show a desktop notification when suggestions arrive (also watchMode.notify)
+    #[arg(long)]
+    notify: bool,
+
+    /// Report rate limiting right away instead of waiting and retrying
+    #[arg(long)]
+    no_retry: bool,
+
+    /// Apply suggestions at or above this confidence as soon as they arrive, e.g. 0.9
+    #[arg(long, value_name = "0-1", value_parser = parse_confidence)]
+    auto_apply_threshold: Option<f64>,
+
+    /// Log triggers, requests, responses and errors as JSON lines (default path: .vibetap/watch.log)
+    #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = ".vibetap/watch.log")]
+    log_file: Option<PathBuf>,
+
+    #[command(flatten)]
+    diff_flags: DiffFlags,
 }
 
 pub async fn execute(args: WatchArgs) -> anyhow::Result<()> {
+    if args.workspace {
+        return run_all_labeled().await;
+    }
+
     // Check hush state
     let hush_state = load_state()?;
-    if hush_state.is_hushed() {
+    if hush_state.hides_everything() {
         if let Some(remaining) = hush_state.remaining() {
             println!(
                 "{}",
@@ -50,203 +162,1217 @@ pub async fn execute(args: WatchArgs) -> anyhow::Result<()> {
         }
         return Ok(());
     }
+    if hush_state.is_hushed() {
+        println!(
+            "{}",
+            format!("Hushed; only {} suggestions will be shown.", hush_state.except.join(", "))
+                .yellow()
+        );
+    }
 
     // Load config
     let mut config = Config::load()?;
-    let access_token = config.get_valid_access_token().await?;
+    // Fail early when logged out; each generation gets a fresh token
+    config.get_valid_access_token().await?;
     let api_url = config.api_url().to_string();
+    // Only the watched directories trigger generation or end up in its diff
+    let paths = watched_paths(&args, &config)?;
+    let pathspecs = if paths == ["."] {
+        Vec::new()
+    } else {
+        paths.clone()
+    };
+    let settings = args.diff_flags.settings(&config, pathspecs);
+    let notify = args.notify
+        || config
+            .project
+            .as_ref()
+            .is_some_and(|p| p.watch_mode.notify);
+    let on_suggestions = config
+        .project
+        .as_ref()
+        .and_then(|p| p.watch_mode.on_suggestions.clone())
+        .filter(|command| !command.trim().is_empty());
+
+    let trigger_extensions = trigger_extensions(&config);
+    let debounce_ms = args
+        .debounce
+        .or(config.project.as_ref().map(|p| p.watch_mode.debounce_ms))
+        .unwrap_or(DEFAULT_DEBOUNCE_MS);
+    // Monorepo packages, each with the test runner its own config points to
+    let packages = packages::detect(Path::new("."));
+    let runners: HashMap<String, String> = packages
+        .iter()
+        .filter_map(|p| Some((p.root.clone(), detect_test_runner_in(Path::new(&p.root))?)))
+        .collect();
 
     println!("{}", "Starting VibeTap watch mode...".cyan().bold());
-    println!("  Debounce: {}ms", args.debounce);
+    println!("  Debounce: {}ms", debounce_ms);
     println!("  Mode: {}", if args.uncommitted { "all uncommitted" } else { "staged only" });
+    if paths != ["."] {
+        println!("  Paths: {}", paths.join(", "));
+    }
+    if notify {
+        println!("  Notifications: on");
+    }
+    if let Some(command) = &on_suggestions {
+        println!("  On suggestions: {}", command);
+    }
+    if trigger_extensions.is_empty() {
+        println!("  Triggers: any file");
+    }
+    if !packages.is_empty() {
+        let mut workspaces: Vec<&str> = packages.iter().map(|p| p.workspace).collect();
+        workspaces.dedup();
+        println!(
+            "  Packages: {} ({} workspace)",
+            packages.len(),
+            workspaces.join(", ")
+        );
+    }
     println!();
     println!("{}", "Watching for changes. Press Ctrl+C to stop.".dimmed());
     println!();
 
-    // Get initial diff hash
-    let mut last_diff_hash = get_diff_hash(args.uncommitted);
+    let mut log = WatchLog::open(args.log_file.as_deref())?;
+    log.record(
+        "start",
+        json!({
+            "paths": paths,
+            "uncommitted": args.uncommitted,
+            "debounceMs": debounce_ms,
+            "version": env!("CARGO_PKG_VERSION"),
+        }),
+    );
+
+    // Get initial diff hash; a diff already generated for in an earlier
+    // session isn't generated for again
+    let mut session = WatchState::load();
+    let mut diff_hashes = DiffHashes::new(args.uncommitted, &settings);
+    let mut last_diff_hash = session
+        .last_diff_hash
+        .clone()
+        .unwrap_or_else(|| diff_hashes.hash());
+    let mut last_head = head_name();
 
     // Set up file watcher
     let (tx, rx) = channel();
-    let debounce_duration = Duration::from_millis(args.debounce);
+    let debounce_duration = Duration::from_millis(debounce_ms);
 
-    let mut debouncer = new_debouncer(debounce_duration, tx)?;
+    let files_tx = tx.clone();
+    let mut debouncer = new_debouncer(debounce_duration, move |result| {
+        let _ = files_tx.send(WatchEvent::Files(result));
+    })?;
 
-    // Watch current directory recursively
-    debouncer
-        .watcher()
-        .watch(Path::new("."), RecursiveMode::Recursive)?;
+    for path in &paths {
+        debouncer
+            .watcher()
+            .watch(Path::new(path), RecursiveMode::Recursive)?;
+    }
+    // Catch checkouts that leave every watched file alone
+    let git_dir = vibetap_git::git_dir(Path::new("."))
+        .ok()
+        .and_then(|dir| dir.canonicalize().ok());
+    if let Some(git_dir) = git_dir {
+        let covered = paths.iter().any(|p| {
+            Path::new(p)
+                .canonicalize()
+                .is_ok_and(|p| git_dir.starts_with(p))
+        });
+        if !covered {
+            let _ = debouncer
+                .watcher()
+                .watch(&git_dir, RecursiveMode::NonRecursive);
+        }
+    }
+
+    let ignore_rules = IgnoreRules::load(Path::new("."));
+    // Set while paused for a git operation, with the status line shown
+    let mut paused: Option<(Option<Operation>, String)> = None;
+    let hourly_limit = args.budget.or(config
+        .project
+        .as_ref()
+        .and_then(|p| p.watch_mode.max_generations_per_hour));
+    // Set while the hourly limit is used up, to when it allows another
+    // generation; an earlier session may have been waiting already
+    let mut budget_resumes_at = session
+        .budget_resumes_at
+        .filter(|at| *at > unix_now() && hourly_limit.is_some() && !args.ignore_budget);
+    // Set while the API can't be reached, to when to try again
+    let mut network_retry_at: Option<i64> = None;
+    let mut network_failures = 0;
+    // Suggestions in the list printed last, which number keys apply
+    let mut listed = 0;
+
+    // Hands back events that arrive while a burst of saves is coalesced
+    let requeue = tx.clone();
+    let keys = KeyListener::start(tx, WatchEvent::Key);
+    if keys.is_some() {
+        println!(
+            "{}",
+            "Keys: g generate now, 1-9 apply that suggestion, a apply, h hush for 30m, q quit"
+                .dimmed()
+        );
+        println!();
+    }
+    if let Some(at) = session.last_suggestions_at {
+        println!(
+            "{}",
+            format!("Last suggestions arrived {}.", day_time(at)).dimmed()
+        );
+    }
+    if let (Some(at), Some(limit)) = (budget_resumes_at, hourly_limit) {
+        println!(
+            "{}",
+            format!(
+                "Budget exhausted ({} generation(s) per hour), resuming at {}.",
+                limit,
+                clock_time(at)
+            )
+            .yellow()
+        );
+    }
 
     // Main watch loop
     loop {
-        match rx.recv() {
-            Ok(Ok(events)) => {
-                // Check hush state each iteration
-                let hush_state = load_state()?;
-                if hush_state.is_hushed() {
+        let event = match budget_resumes_at.into_iter().chain(network_retry_at).min() {
+            Some(at) => rx.recv_timeout(until(at)),
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        // The hourly limit allows another generation, or it's time to see if
+        // the network is back; changes held back go ahead
+        let budget_due = budget_resumes_at.is_some_and(|at| unix_now() >= at);
+        let retry_due = network_retry_at.is_some_and(|at| unix_now() >= at);
+        // A generation asked for with `g` goes ahead even without new changes
+        let forced = match event {
+            Err(RecvTimeoutError::Timeout) => {
+                if load_state()?.hides_everything() {
+                    budget_resumes_at = None;
+                    session.budget_resumes_at = None;
+                    remember(&session);
+                    network_retry_at = None;
                     continue;
                 }
+                let source = if retry_due { "network" } else { "budget" };
+                log.record("trigger", json!({ "source": source }));
+                false
+            }
+            Ok(WatchEvent::Files(Ok(events))) => {
+                // Filter out irrelevant events: anything git ignores,
+                // .vibetapignore, .git and .vibetap, and files that aren't
+                // source code (docs, fixtures, lockfiles)
+                let relevant = |event: &DebouncedEvent| {
+                    event.kind == DebouncedEventKind::Any
+                        && !ignore_rules.is_ignored(&event.path)
+                        && triggers(&event.path, &trigger_extensions)
+                };
+                let mut changed: Vec<PathBuf> = events
+                    .iter()
+                    .filter(|event| relevant(event))
+                    .map(|event| event.path.clone())
+                    .collect();
+                // A find-and-replace or formatter run saves file after file;
+                // wait for the burst to end so it makes one generation
+                if !changed.is_empty() {
+                    for path in rest_of_burst(&rx, &requeue, debounce_duration, &relevant) {
+                        if !changed.contains(&path) {
+                            changed.push(path);
+                        }
+                    }
+                }
+                let changed: Vec<&Path> = changed.iter().map(PathBuf::as_path).collect();
+                // Noted even while hushed, so the next hash covers them
+                diff_hashes.touched(&changed);
 
-                // Filter out irrelevant events
-                let relevant = events.iter().any(|event| {
-                    if event.kind == DebouncedEventKind::Any {
-                        let path = &event.path;
-                        // Ignore .git, .vibetap, node_modules, target, etc.
-                        !is_ignored_path(path)
-                    } else {
-                        false
+                // A checkout moves the baseline; the switch itself is not a change
+                let head = head_name();
+                // Rebases and bisects move HEAD as they go; that's not a checkout
+                let mid_operation =
+                    vibetap_git::conflict_state().is_ok_and(|state| state.operation.is_some());
+                if head != last_head && !mid_operation {
+                    print_switch(head.as_deref());
+                    log.record("checkout", json!({ "head": head }));
+                    if let Err(e) = forget_other_branch_suggestions(
+                        vibetap_git::current_branch().ok().flatten().as_deref(),
+                    ) {
+                        eprintln!("{} {}", "Warning:".yellow(), e);
                     }
-                });
+                    last_head = head;
+                    last_diff_hash = diff_hashes.hash();
+                    session.last_diff_hash = Some(last_diff_hash.clone());
+                    remember(&session);
+                    continue;
+                }
 
-                if !relevant {
+                // Check hush state each iteration
+                let hush_state = load_state()?;
+                if hush_state.hides_everything() {
+                    if !changed.is_empty() {
+                        log.record(
+                            "skipped",
+                            json!({ "reason": "hushed", "remaining": hush_state.remaining() }),
+                        );
+                    }
                     continue;
                 }
 
-                // Check if diff has changed
-                let new_hash = get_diff_hash(args.uncommitted);
-                if new_hash == last_diff_hash {
+                if changed.is_empty() && !budget_due && !retry_due {
                     continue;
                 }
-                last_diff_hash = new_hash;
+                log.record(
+                    "trigger",
+                    json!({
+                        "source": "files",
+                        "paths": changed
+                            .iter()
+                            .take(LOGGED_PATHS)
+                            .map(|path| display_path(path))
+                            .collect::<Vec<_>>(),
+                        "changed": changed.len(),
+                    }),
+                );
+                false
+            }
+            Ok(WatchEvent::Files(Err(e))) => {
+                println!("{} {}", "Watch error:".red(), e);
+                log.record(
+                    "error",
+                    json!({ "kind": "watch", "message": e.to_string() }),
+                );
+                // Continue watching despite the error
+                continue;
+            }
+            Ok(WatchEvent::Key('g')) => {
+                println!("\n{}", "Generating now...".cyan());
+                log.record("trigger", json!({ "source": "key" }));
+                true
+            }
+            Ok(WatchEvent::Key('a')) => {
+                if let Some(keys) = &keys {
+                    keys.suspend();
+                    run_apply(&[]);
+                    keys.resume();
+                }
+                println!();
+                println!("{}", "Watching for changes...".dimmed());
+                continue;
+            }
+            Ok(WatchEvent::Key(key @ '1'..='9')) => {
+                let number = key as usize - '0' as usize;
+                if number > listed {
+                    println!(
+                        "\n{}",
+                        format!("There is no suggestion {} in the last list.", number).dimmed()
+                    );
+                    continue;
+                }
+                println!("\n{}", format!("Applying suggestion {}...", number).cyan());
+                let number = number.to_string();
+                if let Some(keys) = &keys {
+                    keys.suspend();
+                    let applied = run_apply(&[&number, "--yes"]);
+                    keys.resume();
+                    log.record(
+                        "apply",
+                        json!({ "source": "key", "selection": number, "ok": applied }),
+                    );
+                }
+                println!();
+                println!("{}", "Watching for changes...".dimmed());
+                continue;
+            }
+            Ok(WatchEvent::Key('h')) => {
+                hush_for(Some(KEY_HUSH), Vec::new())?;
+                println!(
+                    "\n{}",
+                    "Suggestions silenced for 30m. Press g to generate anyway.".cyan()
+                );
+                continue;
+            }
+            Ok(WatchEvent::Key('q')) => break,
+            Ok(WatchEvent::Key(_)) => continue,
+            Err(e) => {
+                println!("{} {}", "Channel error:".red(), e);
+                log.record(
+                    "error",
+                    json!({ "kind": "channel", "message": e.to_string() }),
+                );
+                break;
+            }
+        };
+        // Pause while a merge, rebase, cherry-pick, revert or bisect is under
+        // way, or conflicts are unresolved
+        let state = vibetap_git::conflict_state().unwrap_or_default();
+        if let Some(reason) = pause_reason(&state) {
+            if forced || paused.as_ref().map(|(_, shown)| shown) != Some(&reason) {
+                println!("\n{}", reason.yellow());
+                log.record("skipped", json!({ "reason": "paused", "message": reason }));
+                paused = Some((state.operation, reason));
+            }
+            continue;
+        }
+        if let Some((operation, _)) = paused.take() {
+            let resumed = match operation {
+                Some(operation) => format!("The {} finished, resuming.", operation.as_str()),
+                None => "Conflicts resolved, resuming.".to_string(),
+            };
+            println!("\n{}", resumed.green());
+        }
 
-                // Get the current diff
-                let diff = if args.uncommitted {
-                    vibetap_git::get_uncommitted_diff()
-                } else {
-                    get_staged_diff()
-                };
+        // Check if diff has changed
+        let new_hash = diff_hashes.hash();
+        if new_hash == last_diff_hash && !forced {
+            log.record("skipped", json!({ "reason": "unchanged" }));
+            continue;
+        }
+        last_diff_hash = new_hash;
 
-                let diff = match diff {
-                    Ok(d) => d,
-                    Err(GitError::NoStagedChanges) => {
-                        println!("{}", "No staged changes.".dimmed());
-                        continue;
-                    }
-                    Err(GitError::NotARepo) => {
-                        println!("{}", "Not a git repository.".red());
-                        break;
-                    }
-                    Err(e) => {
-                        println!("{} {}", "Git error:".red(), e);
-                        continue;
-                    }
-                };
+        // Get the current diff
+        let diff = if args.uncommitted {
+            vibetap_git::get_uncommitted_diff(&settings)
+        } else {
+            get_staged_diff(&settings)
+        };
 
-                if diff.hunks.is_empty() {
+        let diff = match diff {
+            Ok(d) => d,
+            Err(GitError::NoStagedChanges) => {
+                println!("{}", "No staged changes.".dimmed());
+                log.record("skipped", json!({ "reason": "no_changes" }));
+                continue;
+            }
+            Err(GitError::NotARepo) => {
+                println!("{}", "Not a git repository.".red());
+                log.record(
+                    "error",
+                    json!({ "kind": "git", "message": "not a git repository" }),
+                );
+                break;
+            }
+            Err(e @ GitError::Bare) => {
+                println!("{}", format!("{}.", e).red());
+                log.record("error", json!({ "kind": "git", "message": e.to_string() }));
+                break;
+            }
+            Err(e @ GitError::EmptyRepository) => {
+                println!("{}", format!("{}.", e).dimmed());
+                log.record("skipped", json!({ "reason": "empty_repository" }));
+                continue;
+            }
+            Err(e) => {
+                println!("{} {}", "Git error:".red(), e);
+                log.record("error", json!({ "kind": "git", "message": e.to_string() }));
+                continue;
+            }
+        };
+
+        if diff.hunks.is_empty() {
+            log.record("skipped", json!({ "reason": "no_changes" }));
+            continue;
+        }
+
+        if let Some(limit) = hourly_limit.filter(|_| !args.ignore_budget) {
+            match hourly_limit_resumes_at(limit)? {
+                Some(at) => {
+                    if budget_resumes_at.is_none() || forced {
+                        println!(
+                            "\n{}",
+                            format!(
+                                "Budget exhausted ({} generation(s) per hour), resuming at {}.",
+                                limit,
+                                clock_time(at)
+                            )
+                            .yellow()
+                        );
+                    }
+                    budget_resumes_at = Some(at);
+                    session.budget_resumes_at = Some(at);
+                    remember(&session);
+                    log.record(
+                        "skipped",
+                        json!({ "reason": "hourly_budget", "limit": limit, "resumesAt": at }),
+                    );
+                    // Generate for these changes once the limit allows
+                    last_diff_hash.clear();
                     continue;
                 }
+                None => {
+                    if budget_resumes_at.take().is_some() {
+                        println!("\n{}", "Budget available again, resuming.".green());
+                        session.budget_resumes_at = None;
+                        remember(&session);
+                    }
+                }
+            }
+        }
 
-                println!(
-                    "\n{} {} in {} file(s)",
-                    "Changes detected:".cyan(),
-                    format!("{} hunk(s)", diff.hunks.len()).green(),
-                    diff.files_changed.len()
+        // Retries while offline stay quiet until one gets through
+        let offline = network_retry_at.is_some();
+        if !offline {
+            println!(
+                "\n{} {} in {} file(s)",
+                "Changes detected:".cyan(),
+                format!("{} hunk(s)", diff.hunks.len()).green(),
+                diff.files_changed.len()
+            );
+        }
+
+        let within_budget = match check_budget(&config, args.ignore_budget, false) {
+            Ok(within_budget) => within_budget,
+            Err(e) => {
+                println!("{} {}", "Could not check the budget:".red(), e);
+                log.record(
+                    "error",
+                    json!({ "kind": "budget", "message": e.to_string() }),
+                );
+                continue;
+            }
+        };
+        if !within_budget {
+            log.record("skipped", json!({ "reason": "monthly_budget" }));
+            println!();
+            println!("{}", "Watching for changes...".dimmed());
+            continue;
+        }
+
+        // Long sessions outlive access tokens, so refresh as needed rather
+        // than reusing the one from startup
+        let access_token = match config.get_valid_access_token().await {
+            Ok(token) => token,
+            Err(ConfigError::Http(e)) if e.is_connect() || e.is_timeout() => {
+                let at = schedule_network_retry(&mut network_failures, offline, &api_url);
+                network_retry_at = Some(at);
+                log.record(
+                    "offline",
+                    json!({ "message": e.to_string(), "retryAt": at }),
                 );
+                last_diff_hash.clear();
+                continue;
+            }
+            Err(e) => {
+                println!("{} {}", "Could not refresh the login:".red(), e);
+                log.record("error", json!({ "kind": "auth", "message": e.to_string() }));
+                continue;
+            }
+        };
 
-                // Build and send request
-                let request = build_request(&diff, &args, &config);
-                let client = ApiClient::new(&api_url, &access_token);
+        // Build and send requests; in a monorepo each package's changes go
+        // out on their own, with that package's test runner
+        let runner_of = |package: Option<&Package>| {
+            package
+                .and_then(|p| runners.get(&p.root))
+                .map(String::as_str)
+        };
+        let groups = package_groups(&diff, &packages);
+        let requests: Vec<(Option<&Package>, GenerateRequest)> = if groups.len() > 1 {
+            groups
+                .iter()
+                .map(|(package, files)| {
+                    let part = sub_diff(&diff, files);
+                    (
+                        *package,
+                        build_request(&part, &args, &config, runner_of(*package)),
+                    )
+                })
+                .collect()
+        } else {
+            let package = groups.first().and_then(|(package, _)| *package);
+            vec![(
+                package,
+                build_request(&diff, &args, &config, runner_of(package)),
+            )]
+        };
+        let diff_hash = match requests.as_slice() {
+            [(_, request)] => cache::key(request, None),
+            requests => compute_hash(
+                &requests
+                    .iter()
+                    .map(|(_, request)| cache::key(request, None))
+                    .collect::<String>(),
+            ),
+        };
+        let mut client = ApiClient::new(&api_url, &access_token);
 
+        if !offline {
+            if requests.len() > 1 {
+                println!("{}", "Generating suggestions package by package...".dimmed());
+                for (package, request) in &requests {
+                    println!(
+                        "  {} ({})",
+                        package.map_or("repository root", |p| p.root.as_str()),
+                        request.options.test_runner
+                    );
+                }
+            } else {
                 println!("{}", "Generating suggestions...".dimmed());
+            }
+        }
 
-                match client.generate(request).await {
-                    Ok(response) => {
-                        // Save for apply command
-                        if let Err(e) = save_suggestions(&response) {
-                            eprintln!("{} {}", "Warning:".yellow(), e);
-                        }
+        log.record(
+            "request",
+            json!({
+                "hunks": diff.hunks.len(),
+                "files": diff.files_changed,
+                "packages": requests
+                    .iter()
+                    .filter_map(|(package, _)| package.map(|p| p.root.as_str()))
+                    .collect::<Vec<_>>(),
+                "diffHash": diff_hash,
+                "modelTier": requests.first().map(|(_, r)| r.options.model_tier.clone()),
+            }),
+        );
+        let started = std::time::Instant::now();
 
-                        // Display summary
-                        println!();
-                        if response.suggestions.is_empty() {
-                            println!("{}", "No test suggestions for these changes.".dimmed());
-                        } else {
-                            println!(
-                                "{} {}",
-                                format!("{} suggestion(s) generated:", response.suggestions.len()).green().bold(),
-                                response.model_used.dimmed()
-                            );
-                            for (i, suggestion) in response.suggestions.iter().enumerate() {
-                                println!(
-                                    "  {} {} - {}",
-                                    format!("{}.", i + 1).bold(),
-                                    suggestion.file_path.cyan(),
-                                    suggestion.description.dimmed()
-                                );
-                            }
-                            println!();
-                            println!(
-                                "Run {} to view and apply.",
-                                "vibetap apply".cyan()
-                            );
+        let max_retries = retry::max_retries(&config, args.no_retry);
+        let mut refreshed = false;
+        let mut responses = Vec::new();
+        let mut failure = None;
+        for (_, request) in &requests {
+            let mut attempt = 0;
+            let result = loop {
+                let result = client.generate(request.clone()).await;
+                match result {
+                    // The token may have been revoked or expired early; get a new one once
+                    Err(ApiError::Unauthorized) if !refreshed => {
+                        refreshed = true;
+                        if config.refresh_access_token().await.is_err() {
+                            break result;
                         }
+                        let Ok(token) = config.get_valid_access_token().await else {
+                            break result;
+                        };
+                        log.record("token_refreshed", json!({}));
+                        client = ApiClient::new(&api_url, &token);
+                    }
+                    Err(ref e) if e.is_transient() && !e.is_offline() && attempt < max_retries => {
+                        let Some(secs) = retry::delay(0, attempt) else {
+                            break result;
+                        };
+                        attempt += 1;
+                        log.record(
+                            "retry",
+                            json!({ "message": e.to_string(), "waitSecs": secs, "attempt": attempt }),
+                        );
+                        retry::countdown("Request failed", secs, attempt, max_retries, None, false)
+                            .await;
                     }
-                    Err(e) => {
-                        println!("{} {}", "API error:".red(), e);
+                    Err(ApiError::RateLimited { retry_after }) if attempt < max_retries => {
+                        let Some(secs) = retry::delay(retry_after, attempt) else {
+                            break result;
+                        };
+                        attempt += 1;
+                        log.record(
+                            "rate_limited",
+                            json!({ "retryAfter": retry_after, "waitSecs": secs, "attempt": attempt }),
+                        );
+                        retry::countdown("Rate limited", secs, attempt, max_retries, None, false)
+                            .await;
                     }
+                    _ => break result,
+                }
+            };
+            match result {
+                Ok(response) => responses.push(response),
+                Err(e) => {
+                    failure = Some(e);
+                    break;
                 }
-
-                println!();
-                println!("{}", "Watching for changes...".dimmed());
             }
-            Ok(Err(e)) => {
-                println!("{} {}", "Watch error:".red(), e);
-                // Continue watching despite the error
+        }
+        let result = match failure {
+            Some(e) => Err(e),
+            None if responses.len() == 1 => Ok(responses.remove(0)),
+            None => Ok(orchestrator::merge(responses)),
+        };
+
+        if !matches!(result, Err(ref e) if e.is_offline()) && network_retry_at.take().is_some() {
+            network_failures = 0;
+            println!("\n{}", "Connected again.".green());
+        }
+
+        match result {
+            Err(e) if e.is_offline() => {
+                let at = schedule_network_retry(&mut network_failures, offline, &api_url);
+                network_retry_at = Some(at);
+                log.record(
+                    "offline",
+                    json!({ "message": e.to_string(), "retryAt": at }),
+                );
+                // Generate for these changes once the network is back
+                last_diff_hash.clear();
                 continue;
             }
+            Ok(mut response) => {
+                track_usage(&config, &response, false);
+                let hidden = drop_low_confidence(&mut response, min_confidence(&config, None));
+                // A hush with exceptions still lets those categories through
+                let hush_state = load_state()?;
+                let before = response.suggestions.len();
+                response.suggestions.retain(|s| hush_state.shows(&s.category));
+                let held_back = before - response.suggestions.len();
+                log.record(
+                    "response",
+                    json!({
+                        "suggestions": response.suggestions.len(),
+                        "hidden": hidden,
+                        "heldBack": held_back,
+                        "tokens": response.tokens_used,
+                        "model": response.model_used,
+                        "elapsedMs": started.elapsed().as_millis() as u64,
+                    }),
+                );
+
+                // Save for apply command
+                if let Err(e) = save_suggestions(
+                    &response,
+                    HashMap::new(),
+                    None,
+                    None,
+                    Some(diff_hash),
+                    HunkRange::from_diff(&diff),
+                ) {
+                    eprintln!("{} {}", "Warning:".yellow(), e);
+                }
+
+                // Display summary
+                println!();
+                if hidden > 0 {
+                    println!(
+                        "{}",
+                        format!("{} low-confidence suggestion(s) hidden (generation.minConfidence)", hidden)
+                            .dimmed()
+                    );
+                }
+                if held_back > 0 {
+                    println!(
+                        "{}",
+                        format!(
+                            "{} suggestion(s) held back while hushed (showing: {})",
+                            held_back,
+                            hush_state.except.join(", ")
+                        )
+                        .dimmed()
+                    );
+                }
+                listed = response.suggestions.len();
+                session.last_diff_hash = Some(last_diff_hash.clone());
+                if listed > 0 {
+                    session.last_suggestions_at = Some(unix_now());
+                }
+                remember(&session);
+                if response.suggestions.is_empty() {
+                    println!("{}", "No test suggestions for these changes.".dimmed());
+                } else {
+                    println!(
+                        "{} {}",
+                        format!("{} suggestion(s) generated:", response.suggestions.len()).green().bold(),
+                        response.model_used.dimmed()
+                    );
+                    for (i, suggestion) in response.suggestions.iter().enumerate() {
+                        println!(
+                            "  {} {} - {}",
+                            format!("{}.", i + 1).bold(),
+                            suggestion.file_path.cyan(),
+                            suggestion.description.dimmed()
+                        );
+                    }
+                    println!();
+                    println!(
+                        "Run {} to view and apply.",
+                        "vibetap apply".cyan()
+                    );
+                    if keys.is_some() {
+                        println!(
+                            "{}",
+                            format!("Or press 1-{} to apply one here.", listed.min(9)).dimmed()
+                        );
+                    }
+                    if let Some(threshold) = args.auto_apply_threshold {
+                        auto_apply(&response.suggestions, threshold, keys.as_ref(), &mut log);
+                    }
+                    if let Some(command) = &on_suggestions {
+                        suggestion_command::run(command, &response.suggestions);
+                    }
+                    if notify {
+                        desktop::suggestions_arrived(&response.suggestions).await;
+                    }
+                }
+            }
             Err(e) => {
-                println!("{} {}", "Channel error:".red(), e);
-                break;
+                println!("{} {}", "API error:".red(), e);
+                log.record(
+                    "error",
+                    json!({
+                        "kind": "api",
+                        "message": e.to_string(),
+                        "elapsedMs": started.elapsed().as_millis() as u64,
+                    }),
+                );
             }
         }
+
+        println!();
+        println!("{}", "Watching for changes...".dimmed());
     }
 
+    drop(keys);
+    log.record("stop", json!({}));
+    println!("{}", "Stopped watching.".dimmed());
+
     Ok(())
 }
 
-fn get_diff_hash(uncommitted: bool) -> String {
-    let diff = if uncommitted {
-        vibetap_git::get_uncommitted_diff()
+/// Wait out the rest of a burst of saves, returning the relevant paths it
+/// touched
+///
+/// Each batch of file events arriving within a debounce period of the last
+/// one extends the wait, up to [`BURST_LIMIT`] periods. Anything else that
+/// arrives meanwhile, such as a key press, ends the wait and goes back on
+/// the queue through `requeue`.
+fn rest_of_burst(
+    rx: &Receiver<WatchEvent>,
+    requeue: &Sender<WatchEvent>,
+    debounce: Duration,
+    relevant: &dyn Fn(&DebouncedEvent) -> bool,
+) -> Vec<PathBuf> {
+    let deadline = Instant::now() + debounce * BURST_LIMIT;
+    let mut paths = Vec::new();
+    loop {
+        let wait = debounce.min(deadline.saturating_duration_since(Instant::now()));
+        if wait.is_zero() {
+            break;
+        }
+        match rx.recv_timeout(wait) {
+            Ok(WatchEvent::Files(Ok(events))) => paths.extend(
+                events
+                    .iter()
+                    .filter(|event| relevant(event))
+                    .map(|event| event.path.clone()),
+            ),
+            Ok(event) => {
+                let _ = requeue.send(event);
+                break;
+            }
+            Err(_) => break,
+        }
+    }
+    paths
+}
+
+/// An event path relative to the working directory, without `./` parts
+fn display_path(path: &Path) -> String {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let path = path.strip_prefix(&cwd).unwrap_or(path);
+    path.components()
+        .filter(|c| !matches!(c, std::path::Component::CurDir))
+        .collect::<PathBuf>()
+        .display()
+        .to_string()
+}
+
+/// Why watch is holding off generating, if it is
+fn pause_reason(state: &ConflictState) -> Option<String> {
+    let conflicts = state.conflicted.len();
+    match state.operation {
+        Some(operation) if conflicts > 0 => Some(format!(
+            "Paused: {} in progress with {} conflicted file(s). Watching resumes once it finishes.",
+            operation.as_str(),
+            conflicts
+        )),
+        Some(operation) => Some(format!(
+            "Paused: {} in progress. Watching resumes once it finishes.",
+            operation.as_str()
+        )),
+        None if conflicts > 0 => Some(format!(
+            "Paused: {} conflicted file(s). Watching resumes once they are resolved.",
+            conflicts
+        )),
+        None => None,
+    }
+}
+
+/// What is checked out, e.g. "branch main" or "detached HEAD at 1a2b3c4"
+fn head_name() -> Option<String> {
+    match vibetap_git::current_branch() {
+        Ok(branch) => branch.map(|b| format!("branch {}", b)),
+        Err(GitError::DetachedHead) => vibetap_git::head_commit()
+            .ok()
+            .flatten()
+            .map(|commit| format!("detached HEAD at {}", &commit[..commit.len().min(7)])),
+        Err(_) => None,
+    }
+}
+
+/// Mark where output for the previous branch ends
+fn print_switch(head: Option<&str>) {
+    let head = head.unwrap_or("a new branch");
+    if ui::is_accessible() {
+        println!("\nSwitched to {}", head);
     } else {
-        get_staged_diff()
+        println!("\n{}", format!("─── switched to {} ───", head).bold());
+    }
+    println!("{}", "Watching for changes...".dimmed());
+}
+
+/// Back off before trying an unreachable API again, saying so the first
+/// time; returns when to retry
+fn schedule_network_retry(failures: &mut u32, already_offline: bool, api_url: &str) -> i64 {
+    let wait = NETWORK_RETRY_SECS
+        .saturating_mul(1 << (*failures).min(8))
+        .min(MAX_NETWORK_RETRY_SECS);
+    *failures += 1;
+    if !already_offline {
+        println!(
+            "\n{}",
+            format!(
+                "Can't reach {}; will retry when the network returns.",
+                api_url
+            )
+            .yellow()
+        );
+    }
+    unix_now() + wait
+}
+
+/// Time left until the unix timestamp `at`, rounded up to whole seconds
+fn until(at: i64) -> Duration {
+    Duration::from_secs((at - unix_now()).max(0) as u64 + 1)
+}
+
+/// A unix timestamp as local HH:MM
+fn clock_time(at: i64) -> String {
+    Local
+        .timestamp_opt(at, 0)
+        .single()
+        .map(|t| t.format("%H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// Run `vibetap apply` on the latest suggestions, interactively
+/// "at HH:MM" for today, "on Mon DD at HH:MM" for an earlier day
+fn day_time(at: i64) -> String {
+    let Some(time) = Local.timestamp_opt(at, 0).single() else {
+        return String::new();
     };
+    if time.date_naive() == Local::now().date_naive() {
+        time.format("at %H:%M").to_string()
+    } else {
+        time.format("on %b %-d at %H:%M").to_string()
+    }
+}
 
-    match diff {
-        Ok(d) => {
-            // Create a simple hash from the diff content
-            let mut hash = 0u64;
-            for hunk in &d.hunks {
-                for byte in hunk.content.bytes() {
-                    hash = hash.wrapping_mul(31).wrapping_add(byte as u64);
-                }
+/// Save what the next session should know, warning if it can't be written
+fn remember(session: &WatchState) {
+    if let Err(e) = session.save() {
+        eprintln!("{} Could not save watch state: {}", "Warning:".yellow(), e);
+    }
+}
+
+/// Run `vibetap apply` with `args`, so its history, conflict checks and
+/// prompts work as usual; whether it succeeded
+fn run_apply(args: &[&str]) -> bool {
+    let status = std::env::current_exe().and_then(|exe| {
+        std::process::Command::new(exe)
+            .arg("apply")
+            .args(args)
+            .status()
+    });
+    match status {
+        Ok(status) => status.success(),
+        Err(e) => {
+            println!("{} {}", "Could not start apply:".red(), e);
+            false
+        }
+    }
+}
+
+/// Apply the suggestions at or above `threshold` confidence (`--auto-apply-threshold`)
+fn auto_apply(
+    suggestions: &[TestSuggestion],
+    threshold: f64,
+    keys: Option<&KeyListener>,
+    log: &mut WatchLog,
+) {
+    let ids: Vec<&str> = suggestions
+        .iter()
+        .filter(|s| s.confidence >= threshold)
+        .map(|s| s.id.as_str())
+        .collect();
+    if ids.is_empty() {
+        return;
+    }
+    println!(
+        "\n{}",
+        format!(
+            "Applying {} suggestion(s) with confidence {} or higher...",
+            ids.len(),
+            threshold
+        )
+        .cyan()
+    );
+    let mut args = vec!["--yes"];
+    for id in &ids {
+        args.extend(["--id", id]);
+    }
+    if let Some(keys) = keys {
+        keys.suspend();
+    }
+    let applied = run_apply(&args);
+    if let Some(keys) = keys {
+        keys.resume();
+    }
+    log.record(
+        "apply",
+        json!({ "source": "auto", "ids": ids, "threshold": threshold, "ok": applied }),
+    );
+}
+
+/// Directories to watch, from the arguments or `watchMode.paths`; `.` when
+/// neither names any
+fn watched_paths(args: &WatchArgs, config: &Config) -> anyhow::Result<Vec<String>> {
+    let configured = config
+        .project
+        .as_ref()
+        .map(|p| p.watch_mode.paths.clone())
+        .unwrap_or_default();
+    let paths = if args.paths.is_empty() {
+        configured
+    } else {
+        args.paths.clone()
+    };
+
+    let mut watched = Vec::new();
+    for path in paths {
+        let trimmed = path.trim_start_matches("./").trim_end_matches('/');
+        let trimmed = if trimmed.is_empty() { "." } else { trimmed };
+        if !Path::new(trimmed).is_dir() {
+            anyhow::bail!("Can't watch '{}': it is not a directory", path);
+        }
+        if !watched.iter().any(|w| w == trimmed) {
+            watched.push(trimmed.to_string());
+        }
+    }
+    if watched.is_empty() || watched.iter().any(|w| w == ".") {
+        watched = vec![".".to_string()];
+    }
+    Ok(watched)
+}
+
+/// Extensions of source files, whose edits trigger a generation unless
+/// `watchMode.triggerExtensions` lists others
+const TRIGGER_EXTENSIONS: &[&str] = &[
+    "rs", "ts", "tsx", "js", "jsx", "mjs", "cjs", "mts", "cts", "vue", "svelte", "py", "go",
+    "java", "kt", "kts", "scala", "swift", "rb", "php", "cs", "fs", "c", "h", "cc", "cpp", "cxx",
+    "hpp", "m", "mm", "ex", "exs", "erl", "dart", "lua", "sh",
+];
+
+/// Lowercased extensions (without the dot) that trigger a generation; empty
+/// when any file does
+fn trigger_extensions(config: &Config) -> Vec<String> {
+    match config
+        .project
+        .as_ref()
+        .and_then(|p| p.watch_mode.trigger_extensions.as_ref())
+    {
+        Some(configured) => configured
+            .iter()
+            .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+            .filter(|e| !e.is_empty())
+            .collect(),
+        None => TRIGGER_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+    }
+}
+
+/// Whether a change to `path` should trigger a generation
+fn triggers(path: &Path, extensions: &[String]) -> bool {
+    extensions.is_empty()
+        || path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| extensions.iter().any(|x| x.eq_ignore_ascii_case(e)))
+}
+
+/// File size and modification time, to tell whether a file changed since
+/// it was last diffed
+type Stamp = (u64, SystemTime);
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.len(), meta.modified().ok()?))
+}
+
+/// Hash of the watched diff, kept per file so an event batch only re-diffs
+/// the files it names
+struct DiffHashes {
+    uncommitted: bool,
+    settings: DiffSettings,
+    root: PathBuf,
+    index: PathBuf,
+    /// Index and HEAD as of the last full diff; when either moves, any
+    /// file's diff may have changed
+    base: (Option<Stamp>, Option<String>),
+    /// Hash of each changed file's hunks, with the file's stamp at the time
+    files: BTreeMap<String, (Option<Stamp>, u64)>,
+    /// Files named by events since the last hash, relative to the root
+    touched: HashSet<String>,
+}
+
+impl DiffHashes {
+    fn new(uncommitted: bool, settings: &DiffSettings) -> Self {
+        let root = repo_root(Path::new("."))
+            .ok()
+            .and_then(|root| root.canonicalize().ok())
+            .unwrap_or_else(|| PathBuf::from("."));
+        let index = vibetap_git::git_dir(Path::new("."))
+            .map(|dir| dir.join("index"))
+            .unwrap_or_default();
+        let mut hashes = Self {
+            uncommitted,
+            settings: settings.clone(),
+            root,
+            index,
+            base: (None, None),
+            files: BTreeMap::new(),
+            touched: HashSet::new(),
+        };
+        hashes.rebuild();
+        hashes
+    }
+
+    /// Note files named in an event batch
+    fn touched(&mut self, paths: &[&Path]) {
+        for path in paths {
+            let absolute = std::env::current_dir().unwrap_or_default().join(path);
+            // A deleted file can't be resolved, but its directory can
+            let Some(resolved) = absolute
+                .parent()
+                .and_then(|dir| dir.canonicalize().ok())
+                .zip(absolute.file_name())
+                .map(|(dir, name)| dir.join(name))
+            else {
+                continue;
+            };
+            if let Ok(relative) = resolved.strip_prefix(&self.root) {
+                self.touched
+                    .insert(relative.to_string_lossy().replace('\\', "/"));
             }
-            format!("{:x}", hash)
         }
-        Err(_) => String::new(),
+    }
+
+    /// Hash of the whole diff, re-diffing only files touched since the last
+    /// call; the staged diff only changes along with the index
+    fn hash(&mut self) -> String {
+        let touched = std::mem::take(&mut self.touched);
+        if self.current_base() != self.base {
+            self.rebuild();
+        } else if self.uncommitted {
+            for path in touched {
+                self.refresh(&path);
+            }
+        }
+        if self.files.is_empty() {
+            return String::new();
+        }
+        let mut hasher = DefaultHasher::new();
+        for (path, (_, hash)) in &self.files {
+            (path, hash).hash(&mut hasher);
+        }
+        format!("{:x}", hasher.finish())
+    }
+
+    fn current_base(&self) -> (Option<Stamp>, Option<String>) {
+        (
+            stamp(&self.index),
+            vibetap_git::head_commit().ok().flatten(),
+        )
+    }
+
+    /// Diff everything again
+    fn rebuild(&mut self) {
+        self.base = self.current_base();
+        self.files.clear();
+        if let Ok(diff) = self.diff(&self.settings) {
+            self.record(&diff);
+        }
+    }
+
+    /// Diff one file again, unless it is unchanged since it was last diffed
+    fn refresh(&mut self, path: &str) {
+        let full = self.root.join(path);
+        if full.is_dir() {
+            return;
+        }
+        let current = stamp(&full);
+        if current.is_some()
+            && self
+                .files
+                .get(path)
+                .is_some_and(|(stamp, _)| *stamp == current)
+        {
+            return;
+        }
+
+        // A deleted directory takes the files under it along
+        let nested = format!("{}/", path);
+        self.files
+            .retain(|file, _| file != path && !file.starts_with(&nested));
+        if !vibetap_git::matches_pathspecs(&self.settings.pathspecs, path) {
+            return;
+        }
+        let settings = DiffSettings {
+            pathspecs: vec![path.to_string()],
+            ..self.settings.clone()
+        };
+        if let Ok(diff) = self.diff(&settings) {
+            self.record(&diff);
+        }
+    }
+
+    fn diff(&self, settings: &DiffSettings) -> Result<vibetap_git::StagedDiff, GitError> {
+        if self.uncommitted {
+            vibetap_git::get_uncommitted_diff(settings)
+        } else {
+            get_staged_diff(settings)
+        }
+    }
+
+    fn record(&mut self, diff: &vibetap_git::StagedDiff) {
+        for hunk in &diff.hunks {
+            let entry = self
+                .files
+                .entry(hunk.file_path.clone())
+                .or_insert_with(|| (stamp(&self.root.join(&hunk.file_path)), 0));
+            let mut hasher = DefaultHasher::new();
+            entry.1.hash(&mut hasher);
+            hunk.content.hash(&mut hasher);
+            entry.1 = hasher.finish();
+        }
     }
 }
 
-fn is_ignored_path(path: &Path) -> bool {
-    let path_str = path.to_string_lossy();
-
-    path_str.contains(".git/")
-        || path_str.contains(".vibetap/")
-        || path_str.contains("node_modules/")
-        || path_str.contains("target/")
-        || path_str.contains("__pycache__/")
-        || path_str.contains(".pytest_cache/")
-        || path_str.contains("dist/")
-        || path_str.contains("build/")
-        || path_str.contains(".next/")
-        || path_str.contains(".turbo/")
-        || path_str.ends_with(".lock")
-        || path_str.ends_with(".log")
+/// Changed files grouped by the package they belong to, in path order;
+/// files outside every package make up a group of their own
+fn package_groups<'a>(
+    diff: &vibetap_git::StagedDiff,
+    packages: &'a [Package],
+) -> Vec<(Option<&'a Package>, Vec<String>)> {
+    let mut groups: BTreeMap<Option<&str>, (Option<&'a Package>, Vec<String>)> = BTreeMap::new();
+    for file in &diff.files_changed {
+        let package = packages::owner(packages, file);
+        groups
+            .entry(package.map(|p| p.root.as_str()))
+            .or_insert((package, Vec::new()))
+            .1
+            .push(file.clone());
+    }
+    groups.into_values().collect()
 }
 
 fn build_request(
     diff: &vibetap_git::StagedDiff,
     args: &WatchArgs,
     config: &Config,
+    test_runner: Option<&str>,
 ) -> GenerateRequest {
     let hunks: Vec<DiffHunk> = diff
         .hunks
@@ -258,44 +1384,66 @@ fn build_request(
             new_start: h.new_start,
             new_lines: h.new_lines,
             content: h.content.clone(),
+            context_header: h.context_header.clone(),
         })
         .collect();
 
-    let context: Vec<FileContext> = diff
-        .files_changed
-        .iter()
-        .filter_map(|path| {
-            std::fs::read_to_string(path).ok().map(|content| FileContext {
-                path: path.clone(),
-                content: content.chars().take(50000).collect(),
-                language: detect_language(path),
-            })
+    let loaded: Vec<(String, String)> = with_context_globs(config, diff.files_changed.clone())
+        .into_iter()
+        .filter_map(|path| std::fs::read_to_string(&path).ok().map(|content| (path, content)))
+        .collect();
+    let allocation = fit_context(
+        config,
+        diff,
+        loaded.iter().map(|(path, content)| (path.as_str(), content.as_str())),
+    );
+    print_context_trimming(&allocation);
+
+    let context: Vec<FileContext> = allocation
+        .files
+        .into_iter()
+        .map(|file| FileContext {
+            language: detect_language(&file.path),
+            path: file.path,
+            content: file.content,
+            start_line: file.start_line,
+            truncated: file.truncated,
         })
-        .take(10)
         .collect();
 
-    let test_runner = config
-        .project
-        .as_ref()
-        .map(|p| p.test_runner.clone())
-        .unwrap_or_else(|| "vitest".to_string());
+    let test_runner = match test_runner {
+        Some(runner) => runner.to_string(),
+        None => config
+            .project
+            .as_ref()
+            .map(|p| p.test_runner.clone())
+            .unwrap_or_else(|| "vitest".to_string()),
+    };
 
     GenerateRequest {
         diff: DiffPayload {
             hunks,
-            base_branch: None,
-            head_commit: None,
+            base_branch: vibetap_git::default_branch().ok().flatten(),
+            head_commit: vibetap_git::head_commit().ok().flatten(),
         },
         context,
         options: GenerateOptions {
             test_runner,
             max_suggestions: args.max_suggestions,
             include_security: args.security,
-            include_negative_paths: true,
-            model_tier: "default".to_string(),
+            include_negative_paths: include_negative_paths(
+                config,
+                args.negative_paths,
+                args.no_negative_paths,
+            ),
+            model_tier: model_tier(config, args.model.as_deref()),
+            test_file: None,
         },
-        policy_pack_id: None,
-        repo_identifier: None,
+        policy_pack_id: policy_pack(config, args.policy.as_deref()),
+        repo_identifier: vibetap_git::remote_identifier().ok().flatten(),
+        commit_messages: recent_commit_messages(config),
+        project_instructions: project_instructions(),
+        refine: None,
     }
 }
 
@@ -317,15 +1465,3 @@ fn detect_language(path: &str) -> Option<String> {
     }
 }
 
-fn save_suggestions(response: &vibetap_core::api::GenerateResponse) -> anyhow::Result<()> {
-    let vibetap_dir = Path::new(".vibetap");
-    if !vibetap_dir.exists() {
-        std::fs::create_dir_all(vibetap_dir)?;
-    }
-
-    let suggestions_path = vibetap_dir.join("last-suggestions.json");
-    let json = serde_json::to_string_pretty(response)?;
-    std::fs::write(suggestions_path, json)?;
-
-    Ok(())
-}
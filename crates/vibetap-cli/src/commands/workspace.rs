@@ -0,0 +1,329 @@
+use clap::{Args, Subcommand};
+use colored::{Color, Colorize};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio::task::JoinSet;
+
+use crate::ui;
+use vibetap_core::Config;
+use vibetap_git::repo_root;
+
+/// Colors cycled through for per-repository output labels
+const LABEL_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Blue,
+    Color::Green,
+    Color::BrightRed,
+];
+
+#[derive(Args)]
+pub struct WorkspaceArgs {
+    #[command(subcommand)]
+    command: WorkspaceCommand,
+}
+
+#[derive(Subcommand)]
+enum WorkspaceCommand {
+    /// Add repositories to the workspace
+    Add {
+        /// Repository paths
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+
+    /// Remove repositories from the workspace
+    Remove {
+        /// Repository paths
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+
+    /// List repositories in the workspace
+    List,
+}
+
+pub async fn execute(args: WorkspaceArgs) -> anyhow::Result<()> {
+    match args.command {
+        WorkspaceCommand::Add { paths } => add(paths),
+        WorkspaceCommand::Remove { paths } => remove(paths),
+        WorkspaceCommand::List => list(),
+    }
+}
+
+fn add(paths: Vec<PathBuf>) -> anyhow::Result<()> {
+    let mut config = Config::load()?;
+
+    for path in paths {
+        let root = match repo_root(&path).ok().and_then(|root| root.canonicalize().ok()) {
+            Some(root) => root,
+            None => {
+                println!(
+                    "{} {} is not a git repository",
+                    ui::icon("⚠", "Warning:").yellow(),
+                    path.display()
+                );
+                continue;
+            }
+        };
+
+        if config.global.workspace.contains(&root) {
+            println!("{}", format!("Already in workspace: {}", root.display()).dimmed());
+            continue;
+        }
+
+        println!("{} Added {}", ui::icon("✓", "OK:").green(), root.display());
+        config.global.workspace.push(root);
+    }
+
+    Config::save_global(&config.global)?;
+    Ok(())
+}
+
+fn remove(paths: Vec<PathBuf>) -> anyhow::Result<()> {
+    let mut config = Config::load()?;
+
+    for path in paths {
+        // Entries may point at repositories that no longer exist, so fall back to the raw path
+        let target = path.canonicalize().unwrap_or(path);
+        let before = config.global.workspace.len();
+        config.global.workspace.retain(|repo| repo != &target);
+
+        if config.global.workspace.len() < before {
+            println!("{} Removed {}", ui::icon("✓", "OK:").green(), target.display());
+        } else {
+            println!("{}", format!("Not in workspace: {}", target.display()).dimmed());
+        }
+    }
+
+    Config::save_global(&config.global)?;
+    Ok(())
+}
+
+fn list() -> anyhow::Result<()> {
+    let config = Config::load()?;
+
+    if config.global.workspace.is_empty() {
+        println!("{}", "No repositories in the workspace.".yellow());
+        println!("Add some with: {}", "vibetap workspace add <path>...".cyan());
+        return Ok(());
+    }
+
+    println!("{}", "Workspace repositories:".bold());
+    for repo in &config.global.workspace {
+        let status = if repo.exists() {
+            String::new()
+        } else {
+            " (missing)".red().to_string()
+        };
+        println!("  {} {}{}", label(repo).bold(), repo.display().to_string().dimmed(), status);
+    }
+
+    Ok(())
+}
+
+/// Load the workspace repositories, failing if none are configured
+fn workspace_repos() -> anyhow::Result<Vec<PathBuf>> {
+    let config = Config::load()?;
+    if config.global.workspace.is_empty() {
+        anyhow::bail!("No repositories in the workspace. Add some with 'vibetap workspace add <path>'.");
+    }
+    Ok(config.global.workspace)
+}
+
+/// Short display name for a repository
+fn label(repo: &Path) -> String {
+    repo.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| repo.display().to_string())
+}
+
+/// Build a child invocation of the current command for one repository.
+///
+/// Re-runs this binary with the same arguments, minus `--workspace` and any
+/// `--repo` of its own, pinned to the repository via `--repo`.
+fn child_command(repo: &Path) -> anyhow::Result<Command> {
+    let mut command = Command::new(std::env::current_exe()?);
+    command.arg("--repo").arg(repo);
+    command.args(forwarded_args(std::env::args().skip(1)));
+    Ok(command)
+}
+
+/// The arguments a child gets: everything but `--workspace`, `--repo <path>`
+/// and `--repo=<path>`, up to a `--` that passes the rest through
+fn forwarded_args(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut forwarded = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--" => {
+                forwarded.push(arg);
+                forwarded.extend(args.by_ref());
+            }
+            "--workspace" => {}
+            "--repo" => {
+                args.next();
+            }
+            _ if arg.starts_with("--repo=") => {}
+            _ => forwarded.push(arg),
+        }
+    }
+    forwarded
+}
+
+/// Run the current command in each workspace repository, one after another.
+///
+/// With `json`, each repository's output is parsed and combined into one
+/// object keyed by repository path.
+pub async fn run_each(json: bool) -> anyhow::Result<()> {
+    let repos = workspace_repos()?;
+    let mut failed = 0;
+
+    if json {
+        let mut combined = serde_json::Map::new();
+        for repo in &repos {
+            let output = child_command(repo)?.stderr(Stdio::inherit()).output().await?;
+            let value = if output.status.success() {
+                serde_json::from_slice(&output.stdout).unwrap_or_else(|_| {
+                    serde_json::Value::String(String::from_utf8_lossy(&output.stdout).to_string())
+                })
+            } else {
+                failed += 1;
+                serde_json::json!({ "error": format!("exited with {}", output.status) })
+            };
+            combined.insert(repo.display().to_string(), value);
+        }
+        println!("{}", serde_json::to_string_pretty(&combined)?);
+    } else {
+        for (i, repo) in repos.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            println!(
+                "{} {} {}",
+                "==>".cyan().bold(),
+                label(repo).bold(),
+                format!("({})", repo.display()).dimmed()
+            );
+
+            let status = child_command(repo)?.status().await?;
+            if !status.success() {
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} repositories failed", failed, repos.len());
+    }
+    Ok(())
+}
+
+/// Run the current command in every workspace repository concurrently,
+/// prefixing each output line with the repository name.
+pub async fn run_all_labeled() -> anyhow::Result<()> {
+    let repos = workspace_repos()?;
+    let width = repos.iter().map(|r| label(r).len()).max().unwrap_or(0);
+
+    // Children write to pipes, so keep colors on if our own output is a terminal
+    let force_color = std::io::stdout().is_terminal() && !ui::is_accessible();
+
+    println!(
+        "{}",
+        format!("Running across {} repositories. Press Ctrl+C to stop.", repos.len()).dimmed()
+    );
+
+    let mut tasks = JoinSet::new();
+    for (i, repo) in repos.iter().enumerate() {
+        let prefix = format!("[{:width$}]", label(repo), width = width)
+            .color(LABEL_COLORS[i % LABEL_COLORS.len()])
+            .to_string();
+
+        let mut command = child_command(repo)?;
+        command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if force_color {
+            command.env("CLICOLOR_FORCE", "1");
+        }
+
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                println!("{} {}", prefix, format!("failed to start: {}", e).red());
+                continue;
+            }
+        };
+
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        tasks.spawn(async move {
+            let out_prefix = prefix.clone();
+            let out = async move {
+                if let Some(stdout) = stdout {
+                    let mut lines = BufReader::new(stdout).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        println!("{} {}", out_prefix, line);
+                    }
+                }
+            };
+            let err_prefix = prefix.clone();
+            let err = async move {
+                if let Some(stderr) = stderr {
+                    let mut lines = BufReader::new(stderr).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        eprintln!("{} {}", err_prefix, line);
+                    }
+                }
+            };
+            tokio::join!(out, err);
+
+            let status = child.wait().await;
+            let succeeded = matches!(status, Ok(ref s) if s.success());
+            if !succeeded {
+                let reason = match status {
+                    Ok(s) => format!("exited with {}", s),
+                    Err(e) => e.to_string(),
+                };
+                println!("{} {}", prefix, reason.red());
+            }
+            succeeded
+        });
+    }
+
+    let total = tasks.len();
+    let mut failed = repos.len() - total;
+    while let Some(result) = tasks.join_next().await {
+        if !matches!(result, Ok(true)) {
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} repositories failed", failed, repos.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forwarded_args() {
+        let args = |line: &str| line.split(' ').map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            forwarded_args(args("--repo ../app stats --workspace --json")),
+            args("stats --json")
+        );
+        assert_eq!(
+            forwarded_args(args("--workspace --repo=../app run -- --repo x")),
+            args("run -- --repo x")
+        );
+    }
+}
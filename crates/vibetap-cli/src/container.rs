@@ -0,0 +1,222 @@
+//! Running tests inside a container, for `vibetap run --sandbox docker[:image]`
+//!
+//! The project is mounted read-only at `/repo` and copied into a tmpfs at
+//! `/work`, where the tests run, so whatever they write is gone with the
+//! container. Dependency directories and `.git` are linked from `/repo`
+//! rather than copied, like the local sandbox does. The container has no
+//! network unless asked for, drops every capability and runs as the owner
+//! of the project. The only writable mount is the directory the runner's
+//! reports go to.
+
+use std::path::Path;
+use std::process::Stdio;
+
+use rand::Rng;
+
+use crate::sandbox::LINKED_DIRS;
+
+/// Where the project is mounted, read-only
+const REPO_MOUNT: &str = "/repo";
+
+/// The writable copy the tests run in
+const WORK_DIR: &str = "/work";
+
+/// Copies the project into the work directory, then runs the test command
+const ENTRYPOINT: &str = r#"set -e
+cd /repo
+for entry in .[!.]* ..?* *; do
+  [ -e "$entry" ] || continue
+  case " $VIBETAP_LINKED " in
+    *" $entry "*) ln -s "/repo/$entry" "/work/$entry" ;;
+    *) cp -R "$entry" /work/ ;;
+  esac
+done
+cd /work
+exec "$@""#;
+
+/// A container to run tests in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Container {
+    pub image: String,
+    /// Let the tests reach the network
+    pub network: bool,
+}
+
+impl Container {
+    /// Read a `--sandbox` value: `docker`, or `docker:<image>`; without an
+    /// image, the runner's usual one is used
+    pub fn parse(spec: &str, runner: &str, network: bool) -> anyhow::Result<Self> {
+        let image = match spec.split_once(':') {
+            Some(("docker", image)) if !image.is_empty() => image.to_string(),
+            None if spec == "docker" => match default_image(runner) {
+                Some(image) => image.to_string(),
+                None => anyhow::bail!(
+                    "No default image for {}. Name one with --sandbox docker:<image>",
+                    runner
+                ),
+            },
+            _ => anyhow::bail!(
+                "Unsupported sandbox '{}'. Use docker or docker:<image>",
+                spec
+            ),
+        };
+        Ok(Self { image, network })
+    }
+
+    /// The `docker run` command that runs `cmd` on a copy of `project`,
+    /// with `reports` mounted writable at the same path
+    ///
+    /// The container is called `name` (see [`new_name`]), so it can be
+    /// [killed](kill) if the command has to be given up on.
+    ///
+    /// When `project` is itself a sandbox copy, `links_to` is the project
+    /// its dependency links point into, mounted read-only at its own path so
+    /// they still resolve.
+    pub fn command(
+        &self,
+        name: &str,
+        project: &Path,
+        links_to: Option<&Path>,
+        reports: &Path,
+        cmd: &str,
+        cmd_args: &[String],
+    ) -> (String, Vec<String>) {
+        let mut args: Vec<String> = [
+            "run",
+            "--rm",
+            "--name",
+            name,
+            "--init",
+            "--cap-drop",
+            "ALL",
+            "--security-opt",
+            "no-new-privileges",
+        ]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+        if !self.network {
+            args.extend(["--network".to_string(), "none".to_string()]);
+        }
+        if let Some(user) = owner(project) {
+            args.extend(["--user".to_string(), user]);
+        }
+        args.extend([
+            "--env".to_string(),
+            "HOME=/tmp".to_string(),
+            "--env".to_string(),
+            format!("VIBETAP_LINKED=.git {}", LINKED_DIRS.join(" ")),
+            "--volume".to_string(),
+            format!("{}:{}:ro", project.display(), REPO_MOUNT),
+        ]);
+        if let Some(original) = links_to {
+            args.extend([
+                "--volume".to_string(),
+                format!("{}:{}:ro", original.display(), original.display()),
+            ]);
+        }
+        args.extend([
+            "--tmpfs".to_string(),
+            format!("{}:rw,exec,mode=1777", WORK_DIR),
+            "--volume".to_string(),
+            format!("{}:{}", reports.display(), reports.display()),
+            "--workdir".to_string(),
+            WORK_DIR.to_string(),
+            self.image.clone(),
+            "sh".to_string(),
+            "-c".to_string(),
+            ENTRYPOINT.to_string(),
+            "vibetap-sandbox".to_string(),
+            cmd.to_string(),
+        ]);
+        args.extend(cmd_args.iter().cloned());
+        ("docker".to_string(), args)
+    }
+}
+
+/// A fresh name for a container, unlikely to clash with any other
+pub fn new_name() -> String {
+    format!("vibetap-{:016x}", rand::rng().random::<u64>())
+}
+
+/// Stop the container called `name`
+///
+/// Killing `docker run` itself leaves its container running; this is what
+/// stops whatever the container was doing. Best effort: it may be gone
+/// already.
+pub async fn kill(name: &str) {
+    let _ = tokio::process::Command::new("docker")
+        .args(["kill", name])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await;
+}
+
+/// The image a runner's tests usually run in
+pub fn default_image(runner: &str) -> Option<&'static str> {
+    let image = match runner {
+        "vitest" | "jest" | "mocha" => "node:22",
+        "bun-test" => "oven/bun:1",
+        "deno-test" => "denoland/deno:latest",
+        "pytest" => "python:3.12",
+        "cargo-test" => "rust:1",
+        "go-test" => "golang:1.23",
+        "rspec" | "minitest" => "ruby:3.3",
+        "phpunit" => "php:8.3-cli",
+        "dotnet-test" => "mcr.microsoft.com/dotnet/sdk:8.0",
+        "gradle" => "gradle:8-jdk21",
+        "swift-test" => "swift:6.0",
+        _ => return None,
+    };
+    Some(image)
+}
+
+/// `uid:gid` of the project directory, so the container can write the
+/// reports the way the user could
+#[cfg(unix)]
+fn owner(project: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = std::fs::metadata(project).ok()?;
+    Some(format!("{}:{}", metadata.uid(), metadata.gid()))
+}
+
+#[cfg(not(unix))]
+fn owner(_project: &Path) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_container_command() {
+        let container = Container::parse("docker", "pytest", false).unwrap();
+        assert_eq!(container.image, "python:3.12");
+        assert_eq!(
+            Container::parse("docker:my/python:3.11", "pytest", true)
+                .unwrap()
+                .image,
+            "my/python:3.11"
+        );
+        assert!(Container::parse("docker", "make", false).is_err());
+        assert!(Container::parse("podman", "pytest", false).is_err());
+
+        let (cmd, args) = container.command(
+            "vibetap-test",
+            Path::new("/home/me/app"),
+            None,
+            Path::new("/tmp/reports"),
+            "pytest",
+            &["tests/test_cart.py".to_string()],
+        );
+        assert_eq!(cmd, "docker");
+        assert!(args.windows(2).any(|w| w == ["--network", "none"]));
+        assert!(args.windows(2).any(|w| w == ["--name", "vibetap-test"]));
+        assert!(args.contains(&"/home/me/app:/repo:ro".to_string()));
+        assert!(args.contains(&"/tmp/reports:/tmp/reports".to_string()));
+        assert_eq!(args[args.len() - 2..], ["pytest", "tests/test_cart.py"]);
+    }
+}
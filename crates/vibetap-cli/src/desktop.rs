@@ -0,0 +1,75 @@
+//! Desktop notifications for `vibetap watch --notify`
+//!
+//! Notifications go through the platform's own service (the freedesktop
+//! notification daemon on Linux, Notification Center on macOS, toasts on
+//! Windows). When none is available the first failure is reported once and
+//! watching carries on.
+
+use colored::Colorize;
+use notify_rust::Notification;
+use std::sync::atomic::{AtomicBool, Ordering};
+use vibetap_core::api::TestSuggestion;
+
+/// Files named in the notification body before the rest are counted
+const LISTED_FILES: usize = 3;
+
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Tell the user suggestions arrived; security suggestions are called out
+/// and, where the platform supports it, shown as urgent
+pub async fn suggestions_arrived(suggestions: &[TestSuggestion]) {
+    if suggestions.is_empty() {
+        return;
+    }
+    let security = suggestions
+        .iter()
+        .filter(|s| s.category.eq_ignore_ascii_case("security"))
+        .count();
+    let repo = std::env::current_dir()
+        .ok()
+        .and_then(|dir| dir.file_name().map(|n| n.to_string_lossy().into_owned()));
+
+    let summary = match (security, repo) {
+        (0, Some(repo)) => format!("{} test suggestion(s) for {}", suggestions.len(), repo),
+        (0, None) => format!("{} test suggestion(s)", suggestions.len()),
+        (n, Some(repo)) => format!("{} security test(s) suggested for {}", n, repo),
+        (n, None) => format!("{} security test(s) suggested", n),
+    };
+    let mut files: Vec<&str> = suggestions.iter().map(|s| s.file_path.as_str()).collect();
+    files.dedup();
+    let mut body = files
+        .iter()
+        .take(LISTED_FILES)
+        .copied()
+        .collect::<Vec<_>>()
+        .join(", ");
+    if files.len() > LISTED_FILES {
+        body.push_str(&format!(" and {} more", files.len() - LISTED_FILES));
+    }
+    body.push_str("\nRun vibetap apply to review them.");
+
+    let mut notification = Notification::new();
+    notification
+        .appname("VibeTap")
+        .summary(&summary)
+        .body(&body);
+    #[cfg(not(target_os = "macos"))]
+    if security > 0 {
+        notification.urgency(notify_rust::Urgency::Critical);
+    }
+
+    // Showing can wait on the notification service, so keep it off the runtime
+    let shown = tokio::task::spawn_blocking(move || notification.show().map(|_| ())).await;
+    let error = match shown {
+        Ok(Ok(())) => return,
+        Ok(Err(e)) => e.to_string(),
+        Err(e) => e.to_string(),
+    };
+    tracing::debug!("Could not show a notification: {}", error);
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        println!(
+            "{}",
+            format!("Desktop notifications are unavailable: {}", error).dimmed()
+        );
+    }
+}
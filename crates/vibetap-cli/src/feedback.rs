@@ -0,0 +1,80 @@
+//! Telling the VibeTap API what became of its suggestions
+//!
+//! Applying, editing, skipping and reverting are reported by suggestion ID,
+//! as is whether an applied test passed when `vibetap run` last ran it,
+//! so acceptance can be measured and generation can learn from rejections.
+//! Reporting is best effort: it never fails or noticeably slows a command.
+//! It is off when `feedback = false` is set in the global config or
+//! `VIBETAP_NO_FEEDBACK` is set, and suggestions generated without the API
+//! are never reported.
+
+use std::time::Duration;
+
+use vibetap_core::api::{FeedbackAction, FeedbackEvent, FeedbackRequest};
+use vibetap_core::prompt::LOCAL_ID_PREFIX;
+use vibetap_core::{ApiClient, Config};
+
+/// Set to anything but "0" to turn reporting off
+pub const OPT_OUT_ENV: &str = "VIBETAP_NO_FEEDBACK";
+
+/// Longest a command waits for the report to go out
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A suggestion was applied, edited first, or reverted
+pub fn event(suggestion_id: &str, action: FeedbackAction) -> FeedbackEvent {
+    FeedbackEvent {
+        suggestion_id: suggestion_id.to_string(),
+        action,
+        reason: None,
+    }
+}
+
+/// A selected suggestion was not applied, and why
+pub fn skipped(suggestion_id: &str, reason: &str) -> FeedbackEvent {
+    FeedbackEvent {
+        suggestion_id: suggestion_id.to_string(),
+        action: FeedbackAction::Skipped,
+        reason: Some(reason.to_string()),
+    }
+}
+
+/// An applied suggestion's test passed, or failed with this output
+pub fn verified(suggestion_id: &str, failure: Option<&str>) -> FeedbackEvent {
+    FeedbackEvent {
+        suggestion_id: suggestion_id.to_string(),
+        action: if failure.is_some() {
+            FeedbackAction::Failed
+        } else {
+            FeedbackAction::Passed
+        },
+        reason: failure.filter(|f| !f.is_empty()).map(str::to_string),
+    }
+}
+
+/// Send the events, unless reporting is off or nothing can be reported
+pub async fn report(events: Vec<FeedbackEvent>) {
+    let events: Vec<FeedbackEvent> = events
+        .into_iter()
+        .filter(|e| !e.suggestion_id.starts_with(LOCAL_ID_PREFIX))
+        .collect();
+    let opted_out = std::env::var(OPT_OUT_ENV).is_ok_and(|v| !v.is_empty() && v != "0");
+    if events.is_empty() || opted_out || vibetap_core::replay::replay_path().is_some() {
+        return;
+    }
+    let Ok(mut config) = Config::load() else {
+        return;
+    };
+    if config.global.feedback == Some(false) || !config.is_authenticated() {
+        return;
+    }
+
+    let send = async {
+        let access_token = config.get_valid_access_token().await.ok()?;
+        ApiClient::new(config.api_url(), access_token)
+            .send_feedback(FeedbackRequest { events })
+            .await
+            .map_err(|e| tracing::debug!("Could not send feedback: {}", e))
+            .ok()
+    };
+    let _ = tokio::time::timeout(TIMEOUT, send).await;
+}
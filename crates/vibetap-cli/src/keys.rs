@@ -0,0 +1,125 @@
+//! Single-key commands while `vibetap watch` runs
+//!
+//! The terminal is switched out of line mode (without echo) so a key press
+//! arrives on its own, while output and Ctrl+C behave as usual. Keys are
+//! only read when stdin is a terminal on a Unix-like system; elsewhere watch
+//! stops with Ctrl+C as before.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+
+/// Reads key presses on a background thread and passes them on
+pub struct KeyListener {
+    paused: Arc<AtomicBool>,
+}
+
+impl KeyListener {
+    /// Start listening, sending each key through `tx` wrapped by `wrap`;
+    /// `None` when keys can't be read here
+    pub fn start<T: Send + 'static>(tx: Sender<T>, wrap: fn(char) -> T) -> Option<Self> {
+        use std::io::IsTerminal;
+
+        if !std::io::stdin().is_terminal() || !term::enter() {
+            return None;
+        }
+        // Put the terminal back however watch ends
+        tokio::spawn(async {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                term::restore();
+                std::process::exit(130);
+            }
+        });
+
+        let paused = Arc::new(AtomicBool::new(false));
+        let reader_paused = paused.clone();
+        std::thread::spawn(move || {
+            use std::io::Read;
+
+            let mut stdin = std::io::stdin();
+            let mut byte = [0u8; 1];
+            loop {
+                if reader_paused.load(Ordering::Relaxed) {
+                    std::thread::sleep(term::READ_TIMEOUT);
+                    continue;
+                }
+                // Reads time out so a pause takes effect quickly
+                match stdin.read(&mut byte) {
+                    Ok(0) => continue,
+                    Ok(_) => {
+                        if tx.send(wrap(byte[0].to_ascii_lowercase() as char)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+        Some(Self { paused })
+    }
+
+    /// Hand the terminal back, e.g. to an interactive command
+    pub fn suspend(&self) {
+        self.paused.store(true, Ordering::Relaxed);
+        // Let a read already waiting run out, so it can't take the next key
+        std::thread::sleep(term::READ_TIMEOUT * 2);
+        term::restore();
+    }
+
+    /// Take keys again after [`suspend`](Self::suspend)
+    pub fn resume(&self) {
+        term::enter();
+        self.paused.store(false, Ordering::Relaxed);
+    }
+}
+
+impl Drop for KeyListener {
+    fn drop(&mut self) {
+        self.paused.store(true, Ordering::Relaxed);
+        term::restore();
+    }
+}
+
+#[cfg(unix)]
+mod term {
+    use std::sync::OnceLock;
+    use std::time::Duration;
+    use termios::{tcsetattr, Termios, ECHO, ICANON, TCSANOW, VMIN, VTIME};
+
+    /// How long a read waits for a key before giving up
+    pub const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+    /// The terminal settings from before watch changed them
+    static ORIGINAL: OnceLock<Termios> = OnceLock::new();
+
+    /// Deliver keys one at a time, unechoed; false if stdin isn't a terminal
+    pub fn enter() -> bool {
+        let Ok(mut settings) = Termios::from_fd(0) else {
+            return false;
+        };
+        ORIGINAL.get_or_init(|| settings);
+        settings.c_lflag &= !(ICANON | ECHO);
+        settings.c_cc[VMIN] = 0;
+        settings.c_cc[VTIME] = (READ_TIMEOUT.as_millis() / 100) as u8;
+        tcsetattr(0, TCSANOW, &settings).is_ok()
+    }
+
+    pub fn restore() {
+        if let Some(original) = ORIGINAL.get() {
+            let _ = tcsetattr(0, TCSANOW, original);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod term {
+    use std::time::Duration;
+
+    pub const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+    pub fn enter() -> bool {
+        false
+    }
+
+    pub fn restore() {}
+}
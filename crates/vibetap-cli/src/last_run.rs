@@ -0,0 +1,79 @@
+//! What the last `vibetap run` left failing
+//!
+//! Kept in `.vibetap/last-run.json`, so `vibetap run --failed` can run just
+//! those tests again. Each run replaces it, including `--failed` runs, so
+//! tests drop off once they pass.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::test_results::{Status, TestCase};
+
+const LAST_RUN_FILE: &str = ".vibetap/last-run.json";
+
+/// A test, as the runner named it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestId {
+    /// File, module or package, as in the run's reports
+    pub suite: String,
+    pub name: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LastRun {
+    pub runner: String,
+    pub success: bool,
+    /// Unix timestamp of when the run finished
+    #[serde(alias = "ran_at")]
+    pub ran_at: i64,
+    /// Tests that failed, in the order the runner reported them
+    #[serde(default)]
+    pub failed: Vec<TestId>,
+    /// Tests that failed at first but passed on a retry
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flaky: Vec<TestId>,
+}
+
+impl LastRun {
+    pub fn new(runner: &str, success: bool, tests: &[TestCase]) -> Self {
+        let ids = |keep: &dyn Fn(&TestCase) -> bool| -> Vec<TestId> {
+            tests
+                .iter()
+                .filter(|t| keep(t))
+                .map(|t| TestId {
+                    suite: t.suite.clone(),
+                    name: t.name.clone(),
+                })
+                .collect()
+        };
+        Self {
+            runner: runner.to_string(),
+            success,
+            ran_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0),
+            failed: ids(&|t| t.status == Status::Failed),
+            flaky: ids(&|t| t.flaky),
+        }
+    }
+
+    /// The last run, if there was one
+    pub fn load() -> anyhow::Result<Option<Self>> {
+        match std::fs::read_to_string(LAST_RUN_FILE) {
+            Ok(content) => Ok(Some(serde_json::from_str(&content)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Path::new(LAST_RUN_FILE);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,390 @@
+//! Static quality checks for suggested test code
+//!
+//! Cheap heuristics (placeholders, missing or empty assertions, unused
+//! imports) run on every suggestion. When a linter for the language is
+//! installed (eslint, ruff/pyflakes, rustfmt, gofmt) it is run on a temp
+//! copy of the code too, so nothing in the working tree is touched.
+
+use colored::Colorize;
+use regex::Regex;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::ui;
+
+/// Time allowed for an external linter
+const LINT_TIMEOUT: Duration = Duration::from_secs(30);
+
+static JS_NAMED_IMPORT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?m)^\s*import\s+(?:type\s+)?(?:(\w+)\s*,?\s*)?(?:\{([^}]*)\}|\*\s+as\s+(\w+))?\s*from\s+['"]"#)
+        .unwrap()
+});
+static PY_IMPORT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*import\s+([\w., ]+)$").unwrap());
+static PY_FROM_IMPORT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*from\s+[\w.]+\s+import\s+\(?([\w, ]+)\)?\s*$").unwrap());
+static BARE_EXPECT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)(?:^|[^.\w\n])expect\((?:[^()]|\([^()]*\))*\)\s*;?\s*$").unwrap());
+static TRIVIAL_ASSERT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)expect\(\s*true\s*\)\.toBe(?:Truthy)?\(\s*(?:true)?\s*\)|^\s*assert\s+True\s*$|assert!\(\s*true\s*\)")
+        .unwrap()
+});
+static LINTER_LINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[^:]+:(\d+):(?:\d+:)?\s*(.+)$").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// One quality problem found in a suggestion
+#[derive(Debug, Clone)]
+pub struct LintIssue {
+    pub severity: Severity,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl LintIssue {
+    fn warning(line: Option<usize>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+/// Lint a suggestion's code with heuristics and any installed linter
+pub async fn lint(code: &str, file_path: &str) -> Vec<LintIssue> {
+    let (tool_issues, checked_imports) = run_linter(code, file_path).await.unwrap_or_default();
+
+    // A real linter reports unused imports more accurately than the heuristic
+    let mut issues = heuristic_lints(code, file_path, !checked_imports);
+    issues.extend(tool_issues);
+    issues.sort_by_key(|i| (i.severity != Severity::Error, i.line));
+    issues
+}
+
+/// Checks that need no external tools
+pub fn heuristic_lints(code: &str, file_path: &str, check_imports: bool) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let extension = extension(file_path);
+
+    for (i, line) in code.lines().enumerate() {
+        let trimmed = line.trim();
+        let is_comment = ["//", "#", "/*", "*"].iter().any(|c| trimmed.starts_with(c));
+        if is_comment && (trimmed.contains("TODO") || trimmed.contains("FIXME")) {
+            issues.push(LintIssue::warning(Some(i + 1), "TODO placeholder left in test"));
+        } else if matches!(trimmed, "..." | "// ..." | "# ..." | "/* ... */") {
+            issues.push(LintIssue::warning(Some(i + 1), "Elided code placeholder"));
+        }
+    }
+
+    if !has_assertion(code, extension) {
+        issues.push(LintIssue::warning(None, "No assertions found"));
+    }
+
+    for m in BARE_EXPECT.find_iter(code) {
+        issues.push(LintIssue::warning(
+            Some(line_of(code, m.start())),
+            "expect() without a matcher asserts nothing",
+        ));
+    }
+
+    for m in TRIVIAL_ASSERT.find_iter(code) {
+        issues.push(LintIssue::warning(
+            Some(line_of(code, m.start())),
+            "Assertion is always true",
+        ));
+    }
+
+    if check_imports {
+        for (name, offset) in imported_names(code, extension) {
+            let uses = Regex::new(&format!(r"\b{}\b", regex::escape(&name)))
+                .map(|re| re.find_iter(code).count())
+                .unwrap_or(2);
+            if uses <= 1 {
+                issues.push(LintIssue::warning(
+                    Some(line_of(code, offset)),
+                    format!("'{}' is imported but never used", name),
+                ));
+            }
+        }
+    }
+
+    issues
+}
+
+/// Print issues under a suggestion in the generate/apply preview
+pub fn print_issues(issues: &[LintIssue]) {
+    if issues.is_empty() {
+        return;
+    }
+
+    println!(
+        "   {} {}",
+        ui::icon("⚠", "Lint:").yellow(),
+        format!("{} lint issue(s)", issues.len()).yellow()
+    );
+    for issue in issues {
+        let location = issue
+            .line
+            .map(|l| format!("line {}: ", l))
+            .unwrap_or_default();
+        let text = format!("{}{}", location, issue.message);
+        match issue.severity {
+            Severity::Error => println!("     {}", text.red()),
+            Severity::Warning => println!("     {}", text.dimmed()),
+        }
+    }
+}
+
+fn extension(file_path: &str) -> &str {
+    Path::new(file_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+}
+
+fn line_of(code: &str, offset: usize) -> usize {
+    code[..offset].matches('\n').count() + 1
+}
+
+fn has_assertion(code: &str, extension: &str) -> bool {
+    let markers: &[&str] = match extension {
+        "py" => &["assert", "pytest.raises", "self.fail"],
+        "rs" => &["assert", "panic!", "should_panic", ".unwrap()", ".expect("],
+        "go" => &["t.Error", "t.Fatal", "assert.", "require.", "t.Fail"],
+        _ => &["expect(", "assert", ".should", "toThrow", "rejects", "resolves"],
+    };
+    markers.iter().any(|m| code.contains(m))
+}
+
+/// Local names introduced by import statements, with their byte offsets
+fn imported_names(code: &str, extension: &str) -> Vec<(String, usize)> {
+    let mut names = Vec::new();
+
+    match extension {
+        "ts" | "tsx" | "js" | "jsx" | "mjs" | "cjs" | "mts" | "cts" => {
+            for caps in JS_NAMED_IMPORT.captures_iter(code) {
+                let offset = caps.get(0).map(|m| m.start()).unwrap_or(0);
+                if let Some(default) = caps.get(1) {
+                    names.push((default.as_str().to_string(), offset));
+                }
+                if let Some(star) = caps.get(3) {
+                    names.push((star.as_str().to_string(), offset));
+                }
+                if let Some(list) = caps.get(2) {
+                    for item in list.as_str().split(',') {
+                        // `a as b` binds b; `type a` binds a
+                        let local = item.split(" as ").last().unwrap_or("").trim();
+                        let local = local.trim_start_matches("type ").trim();
+                        if !local.is_empty() {
+                            names.push((local.to_string(), offset));
+                        }
+                    }
+                }
+            }
+        }
+        "py" => {
+            let imports = PY_IMPORT.captures_iter(code).chain(PY_FROM_IMPORT.captures_iter(code));
+            for caps in imports {
+                let offset = caps.get(0).map(|m| m.start()).unwrap_or(0);
+                for item in caps[1].split(',') {
+                    let local = match item.split_once(" as ") {
+                        Some((_, alias)) => alias.trim(),
+                        // `import os.path` binds `os`
+                        None => item.trim().split('.').next().unwrap_or(""),
+                    };
+                    // pytest is commonly imported only for its fixtures and plugins
+                    if !local.is_empty() && local != "pytest" {
+                        names.push((local.to_string(), offset));
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+
+    names
+}
+
+/// Run the language's linter on a temp copy, returning its issues and whether
+/// it checks imports. Returns None when no linter is available.
+async fn run_linter(code: &str, file_path: &str) -> Option<(Vec<LintIssue>, bool)> {
+    let extension = extension(file_path);
+    let is_js = matches!(extension, "ts" | "tsx" | "js" | "jsx" | "mjs" | "cjs" | "mts" | "cts");
+    if is_js && Path::new("node_modules/.bin/eslint").exists() {
+        return run_eslint(code, file_path).await.map(|issues| (issues, true));
+    }
+
+    let dir = tempfile::tempdir().ok()?;
+    let temp_file = dir.path().join(Path::new(file_path).file_name()?);
+    std::fs::write(&temp_file, code).ok()?;
+
+    let (program, args): (&str, &[&str]) = match extension {
+        "js" | "mjs" | "cjs" => ("node", &["--check"]),
+        "py" => ("ruff", &["check", "--output-format", "concise", "--select", "E9,F"]),
+        "rs" => ("rustfmt", &["--edition", "2021", "--emit", "stdout"]),
+        "go" => ("gofmt", &["-e", "-l"]),
+        _ => return None,
+    };
+    let mut command = Command::new(program);
+    command.args(args).arg(&temp_file);
+
+    let mut output = run_tool(command, None).await;
+    if output.is_none() && extension == "py" {
+        // Fall back to pyflakes when ruff is not installed
+        let mut pyflakes = Command::new("python3");
+        pyflakes.args(["-m", "pyflakes"]).arg(&temp_file);
+        output = run_tool(pyflakes, None)
+            .await
+            .filter(|(_, text)| !text.contains("No module named pyflakes"));
+    }
+    let checks_imports = output.is_some() && extension == "py";
+    if output.is_none() && extension == "py" {
+        // Without either, at least catch syntax errors
+        let mut py_compile = Command::new("python3");
+        py_compile.args(["-m", "py_compile"]).arg(&temp_file);
+        output = run_tool(py_compile, None).await;
+    }
+    let (success, text) = output?;
+
+    if success {
+        return Some((Vec::new(), checks_imports));
+    }
+
+    // ruff, pyflakes and gofmt print `path:line:col: message`
+    let issues: Vec<LintIssue> = if checks_imports || extension == "go" {
+        text.lines()
+            .filter_map(|line| {
+                let caps = LINTER_LINE.captures(line.trim())?;
+                let message = caps[2].trim().to_string();
+                let severity = if message.contains("E9") || message.to_lowercase().contains("syntax") {
+                    Severity::Error
+                } else {
+                    Severity::Warning
+                };
+                Some(LintIssue {
+                    severity,
+                    line: caps[1].parse().ok(),
+                    message,
+                })
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    if !issues.is_empty() {
+        return Some((issues, checks_imports));
+    }
+
+    // Parse-only checkers fail with free-form output; report the error line
+    let lines: Vec<&str> = text.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    let message = lines
+        .iter()
+        .find(|l| l.to_lowercase().contains("error"))
+        .or(lines.first())
+        .copied()
+        .unwrap_or("syntax check failed");
+    let issue = LintIssue {
+        severity: Severity::Error,
+        line: None,
+        message: message.chars().take(160).collect(),
+    };
+    Some((vec![issue], checks_imports))
+}
+
+/// Run the project's eslint on the code as if it were at `file_path`
+async fn run_eslint(code: &str, file_path: &str) -> Option<Vec<LintIssue>> {
+    let mut command = Command::new("node_modules/.bin/eslint");
+    command.args(["--stdin", "--stdin-filename", file_path, "--format", "json"]);
+    let (_, text) = run_tool(command, Some(code)).await?;
+
+    // Output is JSON followed by nothing else; anything unparsable means eslint itself failed
+    let start = text.find('[')?;
+    let results: serde_json::Value = serde_json::from_str(text[start..].trim()).ok()?;
+    let issues = results
+        .as_array()?
+        .iter()
+        .flat_map(|r| r["messages"].as_array().cloned().unwrap_or_default())
+        .map(|m| LintIssue {
+            severity: if m["severity"].as_u64() == Some(2) {
+                Severity::Error
+            } else {
+                Severity::Warning
+            },
+            line: m["line"].as_u64().map(|l| l as usize),
+            message: match m["ruleId"].as_str() {
+                Some(rule) => format!("{} ({})", m["message"].as_str().unwrap_or(""), rule),
+                None => m["message"].as_str().unwrap_or("").to_string(),
+            },
+        })
+        .collect();
+    Some(issues)
+}
+
+/// Run a linter, returning (succeeded, combined output), or None if it could not run
+async fn run_tool(mut command: Command, stdin: Option<&str>) -> Option<(bool, String)> {
+    command
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = command.spawn().ok()?;
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        pipe.write_all(input.as_bytes()).await.ok()?;
+    }
+
+    let output = tokio::time::timeout(LINT_TIMEOUT, child.wait_with_output())
+        .await
+        .ok()?
+        .ok()?;
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Some((output.status.success(), text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(code: &str, path: &str) -> Vec<String> {
+        heuristic_lints(code, path, true)
+            .into_iter()
+            .map(|i| i.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_flags_unused_js_imports() {
+        let code = "import { add, sub as minus } from './math';\nimport { it, expect } from 'vitest';\n\nit('adds', () => {\n  expect(add(1, 2)).toBe(3);\n});\n";
+        assert_eq!(messages(code, "math.test.ts"), vec!["'minus' is imported but never used"]);
+    }
+
+    #[test]
+    fn test_flags_placeholders_and_empty_assertions() {
+        let code = "def test_it():\n    # TODO: check the result\n    run()\n";
+        assert_eq!(
+            messages(code, "test_it.py"),
+            vec!["TODO placeholder left in test", "No assertions found"]
+        );
+
+        let code = "it('works', () => {\n  expect(run());\n  expect(true).toBe(true);\n});\n";
+        assert_eq!(
+            messages(code, "a.test.js"),
+            vec!["expect() without a matcher asserts nothing", "Assertion is always true"]
+        );
+    }
+}
@@ -1,7 +1,25 @@
 use clap::{Parser, Subcommand};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+mod cache;
 mod commands;
+mod container;
+mod desktop;
+mod feedback;
+mod keys;
+mod last_run;
+mod lint;
+mod merge;
+mod placement;
+mod retry;
+mod sandbox;
+mod suggestion_command;
+mod test_results;
+mod test_targets;
+mod ui;
+mod watch_log;
+mod watch_state;
+mod watchdog;
 
 #[derive(Parser)]
 #[command(name = "vibetap")]
@@ -11,6 +29,19 @@ struct Cli {
     #[arg(short, long, global = true)]
     verbose: bool,
 
+    /// Plain-text output without spinners, borders, or emoji (screen readers, CI logs)
+    #[arg(
+        long,
+        global = true,
+        env = "VIBETAP_ACCESSIBLE",
+        value_parser = clap::builder::FalseyValueParser::new()
+    )]
+    accessible: bool,
+
+    /// Run as if started in this repository
+    #[arg(long, global = true, value_name = "PATH")]
+    repo: Option<std::path::PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -28,7 +59,7 @@ enum Commands {
 
     /// Generate tests for current changes
     #[command(visible_alias = "gen")]
-    Generate(commands::generate::GenerateArgs),
+    Generate(Box<commands::generate::GenerateArgs>),
 
     /// Apply a suggestion or the latest suggestion set
     Apply(commands::apply::ApplyArgs),
@@ -36,6 +67,9 @@ enum Commands {
     /// Revert the last applied patch
     Revert(commands::revert::RevertArgs),
 
+    /// Manage the record of applied suggestions
+    History(commands::history::HistoryArgs),
+
     /// Silence suggestions for a period
     Hush(commands::hush::HushArgs),
 
@@ -50,6 +84,30 @@ enum Commands {
 
     /// Scan repository for coverage gaps
     Scan(commands::scan::ScanArgs),
+
+    /// Record and inspect suggestion metadata stored as git notes
+    Notes(commands::notes::NotesArgs),
+
+    /// Export a suggestion set as a report
+    Export(commands::export::ExportArgs),
+
+    /// Share a suggestion set as a link or GitHub gist
+    Share(commands::share::ShareArgs),
+
+    /// Interactively stage or unstage individual hunks
+    Stage(commands::stage::StageArgs),
+
+    /// List the guardrail policy packs generation can apply
+    Policy(commands::policy::PolicyArgs),
+
+    /// Manage local monthly token and generation budgets
+    Budget(commands::budget::BudgetArgs),
+
+    /// Configure your own OpenAI or Anthropic key for direct generation
+    Byok(commands::byok::ByokArgs),
+
+    /// Manage the list of repositories used with --workspace
+    Workspace(commands::workspace::WorkspaceArgs),
 }
 
 #[tokio::main]
@@ -69,18 +127,40 @@ async fn main() -> anyhow::Result<()> {
         tracing::info!("Verbose mode enabled");
     }
 
+    // Everything below works relative to the repository, including project config
+    if let Some(ref repo) = cli.repo {
+        std::env::set_current_dir(repo)
+            .map_err(|e| anyhow::anyhow!("Cannot use repository {}: {}", repo.display(), e))?;
+    }
+
+    // Accessible output can be requested per run or persisted in the global config
+    let accessible_config = vibetap_core::Config::load()
+        .ok()
+        .and_then(|c| c.global.accessible)
+        .unwrap_or(false);
+    ui::set_accessible(cli.accessible || accessible_config);
+
     match cli.command {
         Commands::Auth(args) => commands::auth::execute(args).await,
         Commands::Init(args) => commands::init::execute(args).await,
         Commands::Watch(args) => commands::watch::execute(args).await,
-        Commands::Generate(args) => commands::generate::execute(args).await,
+        Commands::Generate(args) => commands::generate::execute(*args).await,
         Commands::Apply(args) => commands::apply::execute(args).await,
         Commands::Revert(args) => commands::revert::execute(args).await,
+        Commands::History(args) => commands::history::execute(args).await,
         Commands::Hush(args) => commands::hush::execute(args).await,
         Commands::Run(args) => commands::run::execute(args).await,
         Commands::Hook(args) => commands::hook::execute(args).await,
         Commands::Stats(args) => commands::stats::execute(args).await,
         Commands::Scan(args) => commands::scan::execute(args).await,
+        Commands::Notes(args) => commands::notes::execute(args).await,
+        Commands::Export(args) => commands::export::execute(args).await,
+        Commands::Share(args) => commands::share::execute(args).await,
+        Commands::Stage(args) => commands::stage::execute(args).await,
+        Commands::Policy(args) => commands::policy::execute(args).await,
+        Commands::Budget(args) => commands::budget::execute(args).await,
+        Commands::Byok(args) => commands::byok::execute(args).await,
+        Commands::Workspace(args) => commands::workspace::execute(args).await,
     }
 }
 // test comment
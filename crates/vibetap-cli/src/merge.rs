@@ -0,0 +1,1074 @@
+//! Merging a suggested test file into one that already exists
+//!
+//! Both files are parsed (tree-sitter for JavaScript, TypeScript and Python,
+//! syn for Rust) and reduced to their top-level statements: imports, tests,
+//! and everything else. Imports the existing file lacks are added after its
+//! own, helpers after its own setup, and tests it doesn't have yet at the
+//! end. A `describe` block, test class or test module found in both files
+//! gets the new tests added inside it. Nothing already in the file changes.
+//!
+//! The same parse lets a suggestion be trimmed to some of its tests, for
+//! `vibetap apply --tests`.
+
+use std::collections::HashSet;
+use std::path::Path;
+use syn::spanned::Spanned;
+use tree_sitter::{Language, Node, Parser};
+
+/// Result of merging a suggestion into an existing file
+#[derive(Debug)]
+pub struct Merged {
+    pub content: String,
+    /// Tests that were added, by name
+    pub added: Vec<String>,
+    /// Tests left out because the file already has one with the same name
+    pub skipped: Vec<String>,
+}
+
+/// What a statement in a test file is, as far as merging goes
+enum Kind {
+    /// An import; `named` holds (binding, specifier) pairs that can be added
+    /// to another import of the same `source` at `extend_at`
+    Import {
+        source: String,
+        named: Vec<(String, String)>,
+        other: Vec<String>,
+        extend_at: Option<usize>,
+    },
+    /// A test, or a block of tests that can be merged into
+    Test {
+        key: String,
+        name: String,
+        body: Option<Container>,
+    },
+    /// Anything else, identified by the names it declares (or by its text)
+    Other { names: Vec<String> },
+}
+
+struct Entry {
+    kind: Kind,
+    start: usize,
+    end: usize,
+}
+
+/// The statements of a file, or of a block that holds tests
+struct Container {
+    entries: Vec<Entry>,
+    /// Where to insert into the container while it is empty
+    open: usize,
+}
+
+#[derive(Clone, Copy)]
+enum Lang {
+    JavaScript,
+    TypeScript,
+    Tsx,
+    Python,
+    Rust,
+}
+
+impl Lang {
+    fn from_path(path: &str) -> Option<Self> {
+        let ext = Path::new(path).extension()?.to_str()?;
+        match ext {
+            "js" | "jsx" | "mjs" | "cjs" => Some(Self::JavaScript),
+            "ts" | "mts" | "cts" => Some(Self::TypeScript),
+            "tsx" => Some(Self::Tsx),
+            "py" => Some(Self::Python),
+            "rs" => Some(Self::Rust),
+            _ => None,
+        }
+    }
+
+    fn tree_sitter(&self) -> Option<Language> {
+        match self {
+            Self::JavaScript => Some(tree_sitter_javascript::LANGUAGE.into()),
+            Self::TypeScript => Some(tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into()),
+            Self::Tsx => Some(tree_sitter_typescript::LANGUAGE_TSX.into()),
+            Self::Python => Some(tree_sitter_python::LANGUAGE.into()),
+            Self::Rust => None,
+        }
+    }
+
+    /// Separator before an added test or helper; top-level Python definitions
+    /// are two blank lines apart
+    fn separator(&self, top_level: bool) -> &'static str {
+        match self {
+            Self::Python if top_level => "\n\n\n",
+            _ => "\n\n",
+        }
+    }
+
+    fn parse(&self, source: &str) -> Result<Container, String> {
+        match self.tree_sitter() {
+            Some(language) => {
+                let mut parser = Parser::new();
+                parser
+                    .set_language(&language)
+                    .map_err(|e| format!("could not load the parser: {}", e))?;
+                let tree = parser.parse(source, None).ok_or("the parser gave up")?;
+                let root = tree.root_node();
+                if root.has_error() {
+                    return Err("it has syntax errors".to_string());
+                }
+                Ok(ts_container(*self, root, source, 0))
+            }
+            None => {
+                let file =
+                    syn::parse_file(source).map_err(|e| format!("it does not parse: {}", e))?;
+                Ok(rust_container(&file.items, source, 0))
+            }
+        }
+    }
+}
+
+/// Merge `suggested` into the `existing` contents of the test file at `path`
+///
+/// Fails when the language isn't supported or either file doesn't parse.
+pub fn merge(path: &str, existing: &str, suggested: &str) -> Result<Merged, String> {
+    let lang = Lang::from_path(path)
+        .ok_or("merging supports JavaScript, TypeScript, Python and Rust test files")?;
+    let old = lang
+        .parse(existing)
+        .map_err(|e| format!("the existing file can't be merged into: {}", e))?;
+    let new = lang
+        .parse(suggested)
+        .map_err(|e| format!("the suggestion can't be merged: {}", e))?;
+
+    let mut merged = Merged {
+        content: String::new(),
+        added: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let mut inserts = Vec::new();
+    let sources = Sources {
+        existing,
+        suggested,
+    };
+    merge_into(lang, &old, &new, sources, None, &mut inserts, &mut merged);
+
+    // Imports first, then helpers, then tests, where they share a position
+    inserts.sort_by_key(|(position, order, _)| (*position, *order));
+    let mut content = String::with_capacity(existing.len() + suggested.len());
+    let mut copied = 0;
+    for (position, _, text) in inserts {
+        content.push_str(&existing[copied..position]);
+        content.push_str(&text);
+        copied = position;
+    }
+    content.push_str(&existing[copied..]);
+
+    lang.parse(&content)
+        .map_err(|e| format!("the merged file would not be valid: {}", e))?;
+    merged.content = content;
+    Ok(merged)
+}
+
+/// Names of the tests in a suggestion, in the order `--tests` numbers them
+///
+/// Tests inside a `describe` block, test class or test module are listed
+/// individually, prefixed with the block's name.
+pub fn list_tests(path: &str, code: &str) -> Result<Vec<String>, String> {
+    let lang = Lang::from_path(path)
+        .ok_or("picking tests supports JavaScript, TypeScript, Python and Rust test files")?;
+    let container = lang.parse(code)?;
+    let mut names = Vec::new();
+    collect_tests(&container, None, &mut names);
+    Ok(names)
+}
+
+/// The suggestion with only the tests numbered in `keep` (from 1, as
+/// [`list_tests`] lists them); blocks left without tests are dropped too
+pub fn keep_tests(path: &str, code: &str, keep: &[usize]) -> Result<String, String> {
+    let lang = Lang::from_path(path)
+        .ok_or("picking tests supports JavaScript, TypeScript, Python and Rust test files")?;
+    let container = lang.parse(code)?;
+    let mut names = Vec::new();
+    collect_tests(&container, None, &mut names);
+    if let Some(missing) = keep.iter().find(|&&n| n == 0 || n > names.len()) {
+        let listed: Vec<String> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| format!("{}. {}", i + 1, name))
+            .collect();
+        return Err(format!(
+            "there is no test {}; the suggestion has: {}",
+            missing,
+            listed.join(", ")
+        ));
+    }
+
+    let mut removed = Vec::new();
+    let mut number = 0;
+    drop_tests(&container, keep, &mut number, &mut removed);
+    removed.sort_by_key(|range: &std::ops::Range<usize>| range.start);
+
+    let mut content = String::with_capacity(code.len());
+    let mut copied = 0;
+    for range in removed {
+        if range.start > copied {
+            content.push_str(&code[copied..range.start]);
+        }
+        copied = copied.max(range.end);
+    }
+    content.push_str(&code[copied..]);
+
+    lang.parse(&content)
+        .map_err(|e| format!("the trimmed suggestion would not be valid: {}", e))?;
+    Ok(content)
+}
+
+/// A block whose tests are numbered one by one rather than as a whole
+fn test_block(kind: &Kind) -> Option<&Container> {
+    match kind {
+        Kind::Test {
+            body: Some(body), ..
+        } if body
+            .entries
+            .iter()
+            .any(|e| matches!(e.kind, Kind::Test { .. })) =>
+        {
+            Some(body)
+        }
+        _ => None,
+    }
+}
+
+fn collect_tests(container: &Container, parent: Option<&str>, names: &mut Vec<String>) {
+    for entry in &container.entries {
+        let Kind::Test { ref name, .. } = entry.kind else {
+            continue;
+        };
+        let label = match parent {
+            Some(parent) => format!("{} > {}", parent, name),
+            None => name.clone(),
+        };
+        match test_block(&entry.kind) {
+            Some(body) => collect_tests(body, Some(&label), names),
+            None => names.push(label),
+        }
+    }
+}
+
+/// Byte ranges to cut so only the tests in `keep` remain; returns whether
+/// any test in the container is kept
+fn drop_tests(
+    container: &Container,
+    keep: &[usize],
+    number: &mut usize,
+    removed: &mut Vec<std::ops::Range<usize>>,
+) -> bool {
+    let mut kept_any = false;
+    for (i, entry) in container.entries.iter().enumerate() {
+        if !matches!(entry.kind, Kind::Test { .. }) {
+            continue;
+        }
+        let kept = match test_block(&entry.kind) {
+            Some(body) => {
+                let mut inner = Vec::new();
+                let kept = drop_tests(body, keep, number, &mut inner);
+                if kept {
+                    removed.extend(inner);
+                }
+                kept
+            }
+            None => {
+                *number += 1;
+                keep.contains(number)
+            }
+        };
+        if kept {
+            kept_any = true;
+            continue;
+        }
+        // Take the blank lines before the test with it, or after it when
+        // it comes first
+        let range = match (i.checked_sub(1), container.entries.get(i + 1)) {
+            (Some(previous), _) => container.entries[previous].end..entry.end,
+            (None, Some(next)) => entry.start..next.start,
+            (None, None) => entry.start..entry.end,
+        };
+        removed.push(range);
+    }
+    kept_any
+}
+
+/// The two files being merged
+#[derive(Clone, Copy)]
+struct Sources<'a> {
+    existing: &'a str,
+    suggested: &'a str,
+}
+
+fn merge_into(
+    lang: Lang,
+    old: &Container,
+    new: &Container,
+    sources: Sources,
+    parent: Option<&str>,
+    inserts: &mut Vec<(usize, u8, String)>,
+    merged: &mut Merged,
+) {
+    let top_level = parent.is_none();
+    let separator = lang.separator(top_level);
+    let label = |name: &str| match parent {
+        Some(parent) => format!("{} > {}", parent, name),
+        None => name.to_string(),
+    };
+
+    let mut bound: HashSet<String> = HashSet::new();
+    let mut defined: HashSet<String> = HashSet::new();
+    let mut texts: HashSet<String> = HashSet::new();
+    for entry in &old.entries {
+        match entry.kind {
+            Kind::Import {
+                ref named,
+                ref other,
+                ..
+            } => {
+                bound.extend(named.iter().map(|(binding, _)| binding.clone()));
+                bound.extend(other.iter().cloned());
+            }
+            Kind::Other { ref names } if names.is_empty() => {
+                texts.insert(normalize(&sources.existing[entry.start..entry.end]));
+            }
+            Kind::Other { ref names } => defined.extend(names.iter().cloned()),
+            Kind::Test { .. } => {}
+        }
+    }
+
+    let last_import = old
+        .entries
+        .iter()
+        .filter(|e| matches!(e.kind, Kind::Import { .. }))
+        .map(|e| e.end)
+        .next_back();
+    let last_setup = old
+        .entries
+        .iter()
+        .filter(|e| !matches!(e.kind, Kind::Test { .. }))
+        .map(|e| e.end)
+        .next_back();
+    let last = old.entries.last().map(|e| e.end);
+    let first = old.entries.first().map(|e| e.start);
+
+    for entry in &new.entries {
+        let text = &sources.suggested[entry.start..entry.end];
+        match entry.kind {
+            Kind::Import {
+                ref source,
+                ref named,
+                ref other,
+                ..
+            } => {
+                let missing: Vec<&(String, String)> = named
+                    .iter()
+                    .filter(|(binding, _)| !bound.contains(binding))
+                    .collect();
+                let other_missing = other.iter().any(|binding| !bound.contains(binding));
+                if missing.is_empty() && !other_missing {
+                    continue;
+                }
+                let extendable = old.entries.iter().find_map(|e| match e.kind {
+                    Kind::Import {
+                        source: ref s,
+                        extend_at: Some(at),
+                        ..
+                    } if !other_missing && !source.is_empty() && s == source => Some(at),
+                    _ => None,
+                });
+                match (extendable, last_import) {
+                    (Some(at), _) => {
+                        let specifiers: Vec<&str> =
+                            missing.iter().map(|(_, s)| s.as_str()).collect();
+                        inserts.push((at, 0, format!(", {}", specifiers.join(", "))));
+                    }
+                    (None, Some(at)) => inserts.push((at, 0, format!("\n{}", text))),
+                    (None, None) if old.open == 0 => inserts.push((0, 0, format!("{}\n", text))),
+                    (None, None) => inserts.push((old.open, 0, format!("\n{}", text))),
+                }
+                bound.extend(missing.iter().map(|(binding, _)| binding.clone()));
+                bound.extend(other.iter().cloned());
+            }
+            Kind::Test {
+                ref key,
+                ref name,
+                ref body,
+            } => {
+                let existing = old.entries.iter().find_map(|e| match e.kind {
+                    Kind::Test {
+                        key: ref k,
+                        body: ref b,
+                        ..
+                    } if k == key => Some(b),
+                    _ => None,
+                });
+                match (existing, body) {
+                    (Some(Some(old_body)), Some(new_body)) => {
+                        let parent = label(name);
+                        merge_into(
+                            lang,
+                            old_body,
+                            new_body,
+                            sources,
+                            Some(&parent),
+                            inserts,
+                            merged,
+                        );
+                    }
+                    (Some(_), _) => merged.skipped.push(label(name)),
+                    (None, _) => {
+                        let at = last.unwrap_or(old.open);
+                        inserts.push((at, 2, format!("{}{}", separator, text)));
+                        merged.added.push(label(name));
+                    }
+                }
+            }
+            Kind::Other { ref names } => {
+                let present = if names.is_empty() {
+                    !texts.insert(normalize(text))
+                } else {
+                    names
+                        .iter()
+                        .all(|n| defined.contains(n) || bound.contains(n))
+                };
+                if present {
+                    continue;
+                }
+                // One-liners like `vi.mock(...)` stay together; definitions and
+                // the first statement after the imports get room
+                let separator = if text.trim().contains('\n') || last_setup == last_import {
+                    separator
+                } else {
+                    "\n"
+                };
+                // Setup goes before the tests, which may use it while being collected
+                match (last_setup, first) {
+                    (Some(at), _) => inserts.push((at, 1, format!("{}{}", separator, text))),
+                    (None, Some(at)) => inserts.push((
+                        at,
+                        1,
+                        format!("{}{}", text.trim_start_matches('\n'), separator),
+                    )),
+                    (None, None) => inserts.push((old.open, 1, format!("\n{}", text))),
+                }
+                defined.extend(names.iter().cloned());
+            }
+        }
+    }
+}
+
+/// Whitespace-insensitive form of a statement, for spotting duplicates
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect()
+}
+
+/// Start of the line `position` is on, if only indentation precedes it
+fn line_start(source: &str, position: usize) -> usize {
+    let start = source[..position].rfind('\n').map_or(0, |i| i + 1);
+    if source[start..position].trim().is_empty() {
+        start
+    } else {
+        position
+    }
+}
+
+fn text<'a>(node: Node, source: &'a str) -> &'a str {
+    &source[node.byte_range()]
+}
+
+/// Statements directly inside `node`, with leading comments attached
+fn ts_container(lang: Lang, node: Node, source: &str, open: usize) -> Container {
+    let mut entries = Vec::new();
+    let mut comment_start = None;
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        if child.kind() == "comment" {
+            comment_start.get_or_insert(line_start(source, child.start_byte()));
+            continue;
+        }
+        let kind = match lang {
+            Lang::Python => python_kind(child, source),
+            _ => js_kind(lang, child, source),
+        };
+        entries.push(Entry {
+            kind,
+            start: comment_start
+                .take()
+                .unwrap_or_else(|| line_start(source, child.start_byte())),
+            end: child.end_byte(),
+        });
+    }
+    Container { entries, open }
+}
+
+/// Test functions whose blocks are merged by name
+const JS_TEST_FUNCTIONS: [&str; 6] = ["describe", "context", "suite", "test", "it", "bench"];
+
+fn js_kind(lang: Lang, node: Node, source: &str) -> Kind {
+    match node.kind() {
+        "import_statement" => {
+            let mut source_name = node
+                .child_by_field_name("source")
+                .map(|s| {
+                    text(s, source)
+                        .trim_matches(|c| c == '"' || c == '\'')
+                        .to_string()
+                })
+                .unwrap_or_default();
+            // Type-only imports can't take value specifiers
+            if text(node, source).starts_with("import type") {
+                source_name = format!("type:{}", source_name);
+            }
+            let mut named = Vec::new();
+            let mut other = Vec::new();
+            let mut extend_at = None;
+            let mut cursor = node.walk();
+            let clause = node
+                .named_children(&mut cursor)
+                .find(|c| c.kind() == "import_clause");
+            match clause {
+                Some(clause) => {
+                    let mut cursor = clause.walk();
+                    for part in clause.named_children(&mut cursor) {
+                        match part.kind() {
+                            "identifier" => other.push(text(part, source).to_string()),
+                            "namespace_import" => {
+                                if let Some(name) = part.named_child(0) {
+                                    other.push(text(name, source).to_string());
+                                }
+                            }
+                            "named_imports" => {
+                                let mut cursor = part.walk();
+                                for specifier in part.named_children(&mut cursor) {
+                                    if specifier.kind() != "import_specifier" {
+                                        continue;
+                                    }
+                                    let binding = specifier
+                                        .child_by_field_name("alias")
+                                        .or_else(|| specifier.child_by_field_name("name"))
+                                        .map(|n| text(n, source).to_string())
+                                        .unwrap_or_default();
+                                    named.push((binding, text(specifier, source).to_string()));
+                                    extend_at = Some(specifier.end_byte());
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                // A bare `import './setup'` binds nothing but still matters
+                None => other.push(format!("import:{}", source_name)),
+            }
+            Kind::Import {
+                source: source_name,
+                named,
+                other,
+                extend_at,
+            }
+        }
+        "expression_statement" => {
+            js_test(lang, node, source).unwrap_or(Kind::Other { names: Vec::new() })
+        }
+        "lexical_declaration" | "variable_declaration" => {
+            let mut cursor = node.walk();
+            let names = node
+                .named_children(&mut cursor)
+                .filter(|c| c.kind() == "variable_declarator")
+                .filter_map(|c| c.child_by_field_name("name"))
+                .map(|n| text(n, source).to_string())
+                .collect();
+            Kind::Other { names }
+        }
+        "function_declaration"
+        | "generator_function_declaration"
+        | "class_declaration"
+        | "type_alias_declaration"
+        | "interface_declaration"
+        | "enum_declaration" => Kind::Other {
+            names: node
+                .child_by_field_name("name")
+                .map(|n| vec![text(n, source).to_string()])
+                .unwrap_or_default(),
+        },
+        _ => Kind::Other { names: Vec::new() },
+    }
+}
+
+/// A `describe(...)`, `it(...)` or `test.each(...)(...)` call with a literal name
+fn js_test(lang: Lang, node: Node, source: &str) -> Option<Kind> {
+    let call = node
+        .named_child(0)
+        .filter(|c| c.kind() == "call_expression")?;
+
+    // Find the identifier the call chain starts from
+    let mut callee = call.child_by_field_name("function")?;
+    loop {
+        callee = match callee.kind() {
+            "call_expression" => callee.child_by_field_name("function")?,
+            "member_expression" => callee.child_by_field_name("object")?,
+            "identifier" => break,
+            _ => return None,
+        };
+    }
+    let function = text(callee, source);
+    if !JS_TEST_FUNCTIONS.contains(&function) {
+        return None;
+    }
+    let group = if matches!(function, "describe" | "context" | "suite") {
+        "describe"
+    } else {
+        "test"
+    };
+
+    let arguments = call.child_by_field_name("arguments")?;
+    let title = arguments
+        .named_child(0)
+        .filter(|a| matches!(a.kind(), "string" | "template_string"))?;
+    let name = text(title, source)
+        .trim_matches(|c| c == '"' || c == '\'' || c == '`')
+        .to_string();
+
+    let body = if group == "describe" {
+        let count = arguments.named_child_count();
+        arguments
+            .named_child(count.saturating_sub(1))
+            .filter(|f| {
+                matches!(
+                    f.kind(),
+                    "arrow_function" | "function_expression" | "function"
+                )
+            })
+            .and_then(|f| f.child_by_field_name("body"))
+            .filter(|b| b.kind() == "statement_block")
+            .map(|b| ts_container(lang, b, source, b.start_byte() + 1))
+    } else {
+        None
+    };
+
+    Some(Kind::Test {
+        key: format!("{}:{}", group, name),
+        name,
+        body,
+    })
+}
+
+fn python_kind(node: Node, source: &str) -> Kind {
+    match node.kind() {
+        "import_statement" | "import_from_statement" => {
+            let module = node
+                .child_by_field_name("module_name")
+                .map(|m| text(m, source).to_string());
+            let mut named = Vec::new();
+            let mut other = Vec::new();
+            let mut extend_at = None;
+            let mut cursor = node.walk();
+            for name in node.children_by_field_name("name", &mut cursor) {
+                let binding = match name.kind() {
+                    "aliased_import" => name
+                        .child_by_field_name("alias")
+                        .map(|a| text(a, source).to_string())
+                        .unwrap_or_default(),
+                    // `import os.path` binds `os`
+                    _ => text(name, source)
+                        .split('.')
+                        .next()
+                        .unwrap_or("")
+                        .to_string(),
+                };
+                if module.is_some() {
+                    named.push((binding, text(name, source).to_string()));
+                    extend_at = Some(name.end_byte());
+                } else {
+                    other.push(binding);
+                }
+            }
+            let mut cursor = node.walk();
+            if node
+                .named_children(&mut cursor)
+                .any(|c| c.kind() == "wildcard_import")
+            {
+                other.push(format!("*{}", module.clone().unwrap_or_default()));
+            }
+            Kind::Import {
+                source: module.unwrap_or_default(),
+                named,
+                other,
+                extend_at,
+            }
+        }
+        "decorated_definition" => match node.child_by_field_name("definition") {
+            Some(definition) => python_kind(definition, source),
+            None => Kind::Other { names: Vec::new() },
+        },
+        "function_definition" => {
+            let name = node
+                .child_by_field_name("name")
+                .map(|n| text(n, source).to_string())
+                .unwrap_or_default();
+            if name.starts_with("test") {
+                Kind::Test {
+                    key: format!("def:{}", name),
+                    name,
+                    body: None,
+                }
+            } else {
+                Kind::Other { names: vec![name] }
+            }
+        }
+        "class_definition" => {
+            let name = node
+                .child_by_field_name("name")
+                .map(|n| text(n, source).to_string())
+                .unwrap_or_default();
+            if name.starts_with("Test") {
+                let body = node
+                    .child_by_field_name("body")
+                    .map(|b| ts_container(Lang::Python, b, source, b.start_byte()));
+                Kind::Test {
+                    key: format!("class:{}", name),
+                    name,
+                    body,
+                }
+            } else {
+                Kind::Other { names: vec![name] }
+            }
+        }
+        "expression_statement" => {
+            let names = node
+                .named_child(0)
+                .filter(|c| c.kind() == "assignment")
+                .and_then(|a| a.child_by_field_name("left"))
+                .map(|left| vec![text(left, source).to_string()])
+                .unwrap_or_default();
+            Kind::Other { names }
+        }
+        _ => Kind::Other { names: Vec::new() },
+    }
+}
+
+/// Byte offsets of a syn span within the source it was parsed from
+fn span_range(span: proc_macro2::Span) -> std::ops::Range<usize> {
+    span.byte_range()
+}
+
+fn rust_container(items: &[syn::Item], source: &str, open: usize) -> Container {
+    let entries = items
+        .iter()
+        .map(|item| {
+            let range = span_range(item.span());
+            Entry {
+                kind: rust_kind(item, source),
+                start: line_start(source, range.start),
+                end: range.end,
+            }
+        })
+        .collect();
+    Container { entries, open }
+}
+
+fn rust_kind(item: &syn::Item, source: &str) -> Kind {
+    match item {
+        syn::Item::Use(item) => {
+            let mut bindings = Vec::new();
+            use_bindings(&item.tree, String::new(), &mut bindings);
+            Kind::Import {
+                source: String::new(),
+                named: Vec::new(),
+                other: bindings,
+                extend_at: None,
+            }
+        }
+        syn::Item::Fn(item) => {
+            let name = item.sig.ident.to_string();
+            let is_test = item.attrs.iter().any(|attr| {
+                attr.path()
+                    .segments
+                    .last()
+                    .is_some_and(|segment| segment.ident == "test")
+            });
+            if is_test {
+                Kind::Test {
+                    key: format!("fn:{}", name),
+                    name,
+                    body: None,
+                }
+            } else {
+                Kind::Other { names: vec![name] }
+            }
+        }
+        syn::Item::Mod(item) => {
+            let name = item.ident.to_string();
+            match item.content {
+                Some((ref brace, ref items)) => Kind::Test {
+                    key: format!("mod:{}", name),
+                    body: Some(rust_container(
+                        items,
+                        source,
+                        span_range(brace.span.open()).end,
+                    )),
+                    name,
+                },
+                None => Kind::Other { names: vec![name] },
+            }
+        }
+        syn::Item::Struct(item) => Kind::Other {
+            names: vec![item.ident.to_string()],
+        },
+        syn::Item::Enum(item) => Kind::Other {
+            names: vec![item.ident.to_string()],
+        },
+        syn::Item::Const(item) => Kind::Other {
+            names: vec![item.ident.to_string()],
+        },
+        syn::Item::Static(item) => Kind::Other {
+            names: vec![item.ident.to_string()],
+        },
+        syn::Item::Type(item) => Kind::Other {
+            names: vec![item.ident.to_string()],
+        },
+        syn::Item::Trait(item) => Kind::Other {
+            names: vec![item.ident.to_string()],
+        },
+        _ => Kind::Other { names: Vec::new() },
+    }
+}
+
+/// Names a `use` tree brings into scope; globs are keyed by their path
+fn use_bindings(tree: &syn::UseTree, prefix: String, bindings: &mut Vec<String>) {
+    match tree {
+        syn::UseTree::Path(path) => {
+            use_bindings(&path.tree, format!("{}{}::", prefix, path.ident), bindings)
+        }
+        syn::UseTree::Name(name) if name.ident == "self" => bindings.push(
+            prefix
+                .trim_end_matches("::")
+                .rsplit("::")
+                .next()
+                .unwrap_or("")
+                .to_string(),
+        ),
+        syn::UseTree::Name(name) => bindings.push(name.ident.to_string()),
+        syn::UseTree::Rename(rename) => bindings.push(rename.rename.to_string()),
+        syn::UseTree::Glob(_) => bindings.push(format!("{}*", prefix)),
+        syn::UseTree::Group(group) => {
+            for tree in &group.items {
+                use_bindings(tree, prefix.clone(), bindings);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_typescript_adds_missing_imports_and_tests() {
+        let existing = "\
+import { describe, it, expect } from 'vitest';
+import { add } from '../src/math';
+
+describe('add', () => {
+  it('adds two numbers', () => {
+    expect(add(1, 2)).toBe(3);
+  });
+});
+";
+        let suggested = "\
+import { describe, it, expect, vi } from 'vitest';
+import { add, subtract } from '../src/math';
+import { logger } from '../src/log';
+
+const spy = vi.spyOn(logger, 'warn');
+
+describe('add', () => {
+  it('adds two numbers', () => {
+    expect(add(1, 2)).toBe(3);
+  });
+
+  it('adds negative numbers', () => {
+    expect(add(-1, -2)).toBe(-3);
+  });
+});
+
+describe('subtract', () => {
+  it('subtracts', () => {
+    expect(subtract(3, 1)).toBe(2);
+  });
+});
+";
+        let merged = merge("tests/math.test.ts", existing, suggested).unwrap();
+        assert!(merged.content.starts_with(
+            "import { describe, it, expect, vi } from 'vitest';\n\
+             import { add, subtract } from '../src/math';\n\
+             import { logger } from '../src/log';\n\n\
+             const spy = vi.spyOn(logger, 'warn');\n\n\
+             describe('add'"
+        ));
+        assert!(merged.content.contains(
+            "    expect(add(1, 2)).toBe(3);\n  });\n\n  it('adds negative numbers', () => {"
+        ));
+        assert!(merged.content.ends_with("});\n\ndescribe('subtract', () => {\n  it('subtracts', () => {\n    expect(subtract(3, 1)).toBe(2);\n  });\n});\n"));
+        assert_eq!(
+            merged.added,
+            vec!["add > adds negative numbers", "subtract"]
+        );
+        assert_eq!(merged.skipped, vec!["add > adds two numbers"]);
+    }
+
+    #[test]
+    fn test_merge_python_classes_and_rust_modules() {
+        let existing = "\
+import pytest
+from app.math import add
+
+
+class TestAdd:
+    def test_adds(self):
+        assert add(1, 2) == 3
+";
+        let suggested = "\
+import pytest
+from app.math import add, subtract
+
+
+class TestAdd:
+    def test_adds(self):
+        assert add(1, 2) == 3
+
+    def test_negative(self):
+        assert add(-1, -2) == -3
+
+
+def test_subtract():
+    assert subtract(3, 1) == 2
+";
+        let merged = merge("tests/test_math.py", existing, suggested).unwrap();
+        assert_eq!(merged.content, suggested);
+        assert_eq!(
+            merged.added,
+            vec!["TestAdd > test_negative", "test_subtract"]
+        );
+
+        let existing = "\
+use super::*;
+
+#[test]
+fn adds() {
+    assert_eq!(add(1, 2), 3);
+}
+
+#[cfg(test)]
+mod more {
+    use super::*;
+
+    #[test]
+    fn zero() {
+        assert_eq!(add(0, 0), 0);
+    }
+}
+";
+        let suggested = "\
+use super::*;
+use std::collections::HashMap;
+
+/// Negative numbers
+#[test]
+fn negative() {
+    assert_eq!(add(-1, -2), -3);
+}
+
+#[cfg(test)]
+mod more {
+    use super::*;
+
+    #[test]
+    fn zero() {
+        assert_eq!(add(0, 0), 0);
+    }
+
+    #[test]
+    fn large() {
+        assert_eq!(add(1 << 20, 1), (1 << 20) + 1);
+    }
+}
+";
+        let merged = merge("tests/math.rs", existing, suggested).unwrap();
+        assert!(merged
+            .content
+            .starts_with("use super::*;\nuse std::collections::HashMap;\n\n#[test]\nfn adds()"));
+        assert!(merged
+            .content
+            .contains("        assert_eq!(add(0, 0), 0);\n    }\n\n    #[test]\n    fn large() {"));
+        assert!(merged.content.ends_with("}\n\n/// Negative numbers\n#[test]\nfn negative() {\n    assert_eq!(add(-1, -2), -3);\n}\n"));
+
+        assert!(merge("tests/math.rb", "", "").is_err());
+        assert!(merge("tests/math.rs", "fn broken(", suggested).is_err());
+    }
+
+    #[test]
+    fn test_keep_tests() {
+        let code = "\
+import { describe, it, expect } from 'vitest';
+import { add } from '../src/math';
+
+describe('add', () => {
+  it('adds two numbers', () => {
+    expect(add(1, 2)).toBe(3);
+  });
+
+  it('adds negative numbers', () => {
+    expect(add(-1, -2)).toBe(-3);
+  });
+});
+
+it('adds zero', () => {
+  expect(add(0, 0)).toBe(0);
+});
+";
+        assert_eq!(
+            list_tests("tests/math.test.ts", code).unwrap(),
+            vec![
+                "add > adds two numbers",
+                "add > adds negative numbers",
+                "adds zero"
+            ]
+        );
+        assert_eq!(
+            keep_tests("tests/math.test.ts", code, &[2, 3]).unwrap(),
+            "\
+import { describe, it, expect } from 'vitest';
+import { add } from '../src/math';
+
+describe('add', () => {
+  it('adds negative numbers', () => {
+    expect(add(-1, -2)).toBe(-3);
+  });
+});
+
+it('adds zero', () => {
+  expect(add(0, 0)).toBe(0);
+});
+"
+        );
+        assert_eq!(
+            keep_tests("tests/math.test.ts", code, &[3]).unwrap(),
+            "\
+import { describe, it, expect } from 'vitest';
+import { add } from '../src/math';
+
+it('adds zero', () => {
+  expect(add(0, 0)).toBe(0);
+});
+"
+        );
+
+        let code = "import pytest\n\n\ndef test_a():\n    pass\n\n\ndef test_b():\n    pass\n";
+        assert_eq!(
+            keep_tests("tests/test_x.py", code, &[1]).unwrap(),
+            "import pytest\n\n\ndef test_a():\n    pass\n"
+        );
+        assert!(keep_tests("tests/test_x.py", code, &[3]).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+//! Moving suggested test files to where the project keeps its tests
+//!
+//! `apply.testPathTemplates` maps a language (or a file extension) to a path
+//! template such as `src/{dir}/__tests__/{name}.test.ts`. The placeholders
+//! come from the path the suggestion was generated for:
+//!
+//! - `{dir}`: its directory, without a leading `src/`, `lib/`, `test/`,
+//!   `tests/` or `spec/` and without `__tests__` directories
+//! - `{name}`: its file name without test markers (`.test`, `.spec`,
+//!   `_test`, `test_`) or extension
+//! - `{ext}`: its extension
+//!
+//! Relative imports in JavaScript and TypeScript tests are rewritten so
+//! they still point at the same modules from the new location.
+
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::LazyLock;
+
+/// Top-level directories that hold sources or tests rather than naming a module
+const ROOT_DIRS: &[&str] = &["src", "lib", "test", "tests", "spec"];
+
+/// Import specifiers relative to the importing file
+static RELATIVE_IMPORT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(\bfrom\s*|\brequire\(\s*|\bimport\(\s*|\bimport\s+)(['"])(\.\.?/[^'"]*)['"]"#)
+        .unwrap()
+});
+
+/// Where a suggested test file belongs under the configured templates,
+/// if that isn't where it was suggested
+pub fn relocate(file_path: &str, templates: &BTreeMap<String, String>) -> Option<String> {
+    let path = Path::new(file_path);
+    let ext = path.extension()?.to_str()?;
+    let template = templates
+        .get(ext)
+        .or_else(|| language(ext).and_then(|l| templates.get(l)))?;
+
+    let file_name = path.file_name()?.to_str()?;
+    let stem = &file_name[..file_name.len() - ext.len() - 1];
+    let name = [".test", ".spec", "_test", "_spec"]
+        .iter()
+        .find_map(|suffix| stem.strip_suffix(suffix))
+        .or_else(|| stem.strip_prefix("test_"))
+        .unwrap_or(stem);
+
+    let mut dirs: Vec<&str> = path
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .filter_map(|c| match c {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect();
+    if dirs.first().is_some_and(|d| ROOT_DIRS.contains(d)) {
+        dirs.remove(0);
+    }
+    dirs.retain(|d| *d != "__tests__");
+
+    let rendered = template
+        .replace("{dir}", &dirs.join("/"))
+        .replace("{name}", name)
+        .replace("{ext}", ext);
+    // An empty {dir} leaves doubled or leading separators behind
+    let relocated: Vec<&str> = rendered
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect();
+    if relocated.is_empty() || relocated.contains(&"..") {
+        return None;
+    }
+
+    let relocated = relocated.join("/");
+    (relocated != file_path.trim_start_matches("./")).then_some(relocated)
+}
+
+/// The test code with relative imports pointing at the same modules from `to`
+///
+/// Only JavaScript and TypeScript are rewritten; other code is returned as is.
+pub fn rewrite_imports(code: &str, from: &str, to: &str) -> String {
+    let ext = Path::new(to)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("");
+    if !matches!(language(ext), Some("javascript" | "typescript")) {
+        return code.to_string();
+    }
+    let from_dir = Path::new(from).parent().unwrap_or(Path::new(""));
+    let to_dir = Path::new(to).parent().unwrap_or(Path::new(""));
+
+    RELATIVE_IMPORT
+        .replace_all(code, |caps: &regex::Captures| {
+            let target = normalize(&from_dir.join(&caps[3]));
+            format!(
+                "{}{}{}{}",
+                &caps[1],
+                &caps[2],
+                relative_to(to_dir, &target),
+                &caps[2]
+            )
+        })
+        .into_owned()
+}
+
+/// Template key for a file extension
+fn language(ext: &str) -> Option<&'static str> {
+    match ext {
+        "ts" | "tsx" | "mts" | "cts" => Some("typescript"),
+        "js" | "jsx" | "mjs" | "cjs" => Some("javascript"),
+        "py" => Some("python"),
+        "rs" => Some("rust"),
+        "go" => Some("go"),
+        _ => None,
+    }
+}
+
+/// `path` with `.` and `..` resolved, without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut parts: Vec<&str> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                parts.pop();
+            }
+            Component::Normal(part) => parts.extend(part.to_str()),
+            _ => {}
+        }
+    }
+    parts.iter().collect()
+}
+
+/// Import specifier for `target` as seen from a file in `dir`
+fn relative_to(dir: &Path, target: &Path) -> String {
+    let dir = normalize(dir);
+    let dir: Vec<_> = dir.components().collect();
+    let target: Vec<_> = target.components().collect();
+    let common = dir.iter().zip(&target).take_while(|(a, b)| a == b).count();
+
+    let mut parts: Vec<String> = vec!["..".to_string(); dir.len() - common];
+    parts.extend(
+        target[common..]
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned()),
+    );
+    let specifier = parts.join("/");
+    if specifier.starts_with("..") {
+        specifier
+    } else {
+        format!("./{}", specifier)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relocate() {
+        let templates = BTreeMap::from([
+            (
+                "typescript".to_string(),
+                "src/{dir}/__tests__/{name}.test.{ext}".to_string(),
+            ),
+            ("python".to_string(), "tests/test_{name}.py".to_string()),
+        ]);
+
+        assert_eq!(
+            relocate("src/utils/math.spec.ts", &templates).as_deref(),
+            Some("src/utils/__tests__/math.test.ts")
+        );
+        assert_eq!(
+            relocate("math.test.tsx", &templates).as_deref(),
+            Some("src/__tests__/math.test.tsx")
+        );
+        assert_eq!(
+            relocate("src/pkg/calc_test.py", &templates).as_deref(),
+            Some("tests/test_calc.py")
+        );
+        // Already in place, or no template for the language
+        assert_eq!(relocate("tests/test_calc.py", &templates), None);
+        assert_eq!(relocate("calc_test.go", &templates), None);
+    }
+
+    #[test]
+    fn test_rewrite_imports() {
+        let code = "import { add } from './math';\nimport '../setup';\nconst x = require(\"./x\");\nimport { it } from 'vitest';\n";
+        assert_eq!(
+            rewrite_imports(code, "src/utils/math.test.ts", "src/utils/__tests__/math.test.ts"),
+            "import { add } from '../math';\nimport '../../setup';\nconst x = require(\"../x\");\nimport { it } from 'vitest';\n"
+        );
+        assert_eq!(
+            rewrite_imports("from './a'", "tests/a.test.ts", "a.test.ts"),
+            "from './tests/a'"
+        );
+    }
+}
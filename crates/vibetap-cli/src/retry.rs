@@ -0,0 +1,91 @@
+//! Waiting out rate limits and transient failures
+//!
+//! A rate-limited generation is retried after the delay the API asks for,
+//! or with exponential backoff when it doesn't say, up to
+//! `generation.maxRetries` times. Watch also backs off this way when the
+//! server fails. Waits the user would rather not sit through are not
+//! retried at all.
+
+use colored::Colorize;
+use indicatif::ProgressBar;
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crate::ui;
+use vibetap_core::Config;
+
+/// Retries when `generation.maxRetries` isn't set
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// First backoff delay when the API gives no Retry-After
+const BASE_BACKOFF_SECS: u64 = 5;
+
+/// Longest wait before a retry; anything longer is reported instead
+const MAX_WAIT_SECS: u64 = 120;
+
+/// Retries allowed for this run
+pub fn max_retries(config: &Config, no_retry: bool) -> u32 {
+    if no_retry {
+        return 0;
+    }
+    config
+        .project
+        .as_ref()
+        .and_then(|p| p.generation.max_retries)
+        .unwrap_or(DEFAULT_MAX_RETRIES)
+}
+
+/// Seconds to wait before retry number `attempt` (0-based), or `None` if too long
+pub fn delay(retry_after: u64, attempt: u32) -> Option<u64> {
+    let secs = if retry_after > 0 {
+        retry_after
+    } else {
+        BASE_BACKOFF_SECS.saturating_mul(1 << attempt.min(16))
+    };
+    (secs <= MAX_WAIT_SECS).then_some(secs)
+}
+
+/// Count down `secs` seconds, on the spinner if there is one; `reason`
+/// says why, e.g. "Rate limited"
+pub async fn countdown(
+    reason: &str,
+    secs: u64,
+    attempt: u32,
+    max: u32,
+    progress_bar: Option<&ProgressBar>,
+    quiet: bool,
+) {
+    let in_place = progress_bar.is_none() && !quiet && !ui::is_accessible();
+    for remaining in (1..=secs).rev() {
+        let message = format!(
+            "{}; retrying in {}s (attempt {} of {})",
+            reason, remaining, attempt, max
+        );
+        if let Some(pb) = progress_bar {
+            pb.set_message(format!("⏳ {}", message));
+        } else if in_place {
+            print!("\r{} ", message.yellow());
+            let _ = io::stdout().flush();
+        } else if !quiet && remaining == secs {
+            println!("{}", message);
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    if in_place {
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_prefers_retry_after_and_backs_off() {
+        assert_eq!(delay(30, 0), Some(30));
+        assert_eq!(delay(0, 0), Some(5));
+        assert_eq!(delay(0, 2), Some(20));
+        assert_eq!(delay(600, 0), None);
+        assert_eq!(delay(0, 10), None);
+    }
+}
@@ -0,0 +1,312 @@
+//! Throwaway project copies for checking suggestions before they touch the tree
+//!
+//! A sandbox is a temp directory holding every non-ignored file of the
+//! project, with dependency directories (node_modules, virtualenvs) linked
+//! rather than copied. Suggestions are written into the copy and checked
+//! with the language's own tooling. Running a suggested test goes further:
+//! it is generated code, so it runs in a container (see [`Container`]) on
+//! the copy, without network or write access to the project.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::commands::run::build_command_in;
+use crate::container::{self, Container};
+
+/// Dependency directories linked into the sandbox instead of copied
+pub const LINKED_DIRS: &[&str] = &["node_modules", ".venv", "venv", "vendor"];
+
+/// Time allowed for a parse/typecheck
+const CHECK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Time allowed for running a single test file
+const RUN_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Result of one sandbox check
+#[derive(Debug, Clone)]
+pub enum CheckOutcome {
+    Passed,
+    /// The check ran and failed, with the first relevant line of output
+    Failed(String),
+    /// The check could not run (missing tool, unsupported language)
+    Skipped(String),
+}
+
+/// A temporary copy of the current project
+pub struct Sandbox {
+    dir: tempfile::TempDir,
+    project: PathBuf,
+}
+
+impl Sandbox {
+    /// Copy the current project into a fresh temp directory
+    pub fn create() -> anyhow::Result<Self> {
+        let project = std::env::current_dir()?;
+        let dir = tempfile::Builder::new().prefix("vibetap-sandbox-").tempdir()?;
+
+        let walker = ignore::WalkBuilder::new(&project)
+            .hidden(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build();
+
+        for entry in walker {
+            let entry = entry?;
+            let relative = match entry.path().strip_prefix(&project) {
+                Ok(r) if !r.as_os_str().is_empty() => r,
+                _ => continue,
+            };
+            let target = dir.path().join(relative);
+
+            let file_type = entry.file_type();
+            if file_type.is_some_and(|t| t.is_dir()) {
+                std::fs::create_dir_all(&target)?;
+            } else if file_type.is_some_and(|t| t.is_symlink()) {
+                // Links to directories or to nothing can't be copied as files
+                copy_link(entry.path(), &target, &project, dir.path())?;
+            } else {
+                std::fs::copy(entry.path(), &target)?;
+            }
+        }
+
+        for name in LINKED_DIRS {
+            let source = project.join(name);
+            let target = dir.path().join(name);
+            if source.is_dir() && !target.exists() {
+                // Best effort: files without third-party imports still check fine unlinked
+                let _ = link_dir(&source, &target);
+            }
+        }
+
+        Ok(Self { dir, project })
+    }
+
+    /// Root of the sandbox copy
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Write a file into the sandbox, replacing any existing copy
+    pub fn write_file(&self, relative: &str, content: &str) -> io::Result<()> {
+        let path = self.path().join(relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)
+    }
+
+    /// Put a file back to the project's version (or remove it if the project has none)
+    pub fn restore_file(&self, relative: &str) -> io::Result<()> {
+        let original = self.project.join(relative);
+        let path = self.path().join(relative);
+        if original.is_file() {
+            std::fs::copy(original, path).map(|_| ())
+        } else if path.exists() {
+            std::fs::remove_file(path)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Parse or typecheck a single file with the language's own tooling
+    pub async fn check_syntax(&self, relative: &str) -> CheckOutcome {
+        let extension = relative.rsplit('.').next().unwrap_or("");
+
+        let (program, args): (&str, Vec<String>) = match extension {
+            "ts" | "tsx" | "mts" | "cts" => {
+                if !self.path().join("node_modules/.bin/tsc").exists() {
+                    return CheckOutcome::Skipped("typescript not installed".to_string());
+                }
+                if self.path().join("tsconfig.json").exists() {
+                    // Project mode honours path aliases and types; errors are filtered to this file below
+                    let args = ["--noEmit", "--pretty", "false", "-p", "tsconfig.json"];
+                    return self.check_tsc(relative, &args).await;
+                }
+                let args = ["--noEmit", "--pretty", "false", "--skipLibCheck", relative];
+                return self.check_tsc(relative, &args).await;
+            }
+            "js" | "jsx" | "mjs" | "cjs" => ("node", vec!["--check".into(), relative.into()]),
+            "py" => ("python3", vec!["-m".into(), "py_compile".into(), relative.into()]),
+            "rs" => (
+                "rustfmt",
+                vec!["--edition".into(), "2021".into(), "--emit".into(), "stdout".into(), relative.into()],
+            ),
+            "go" => ("gofmt", vec!["-e".into(), "-l".into(), relative.into()]),
+            _ => return CheckOutcome::Skipped(format!("no checker for .{} files", extension)),
+        };
+
+        let mut command = Command::new(program);
+        command.args(&args);
+        self.run(command, CHECK_TIMEOUT, |_| true).await
+    }
+
+    /// Run tsc and only count errors reported against `relative`
+    async fn check_tsc(&self, relative: &str, args: &[&str]) -> CheckOutcome {
+        let mut command = Command::new(self.path().join("node_modules/.bin/tsc"));
+        command.args(args);
+        let prefix = relative.trim_start_matches("./").to_string();
+        let outcome = self
+            .run(command, CHECK_TIMEOUT, |line| line.starts_with(&prefix))
+            .await;
+
+        // Errors elsewhere in the project are not the suggestion's fault
+        match outcome {
+            CheckOutcome::Failed(ref line) if line.is_empty() => CheckOutcome::Passed,
+            other => other,
+        }
+    }
+
+    /// Execute a test file in the sandbox with the project's test runner,
+    /// in a container with the runner's usual image
+    pub async fn run_test(&self, relative: &str, runner: &str) -> CheckOutcome {
+        let container = match Container::parse("docker", runner, false) {
+            Ok(container) => container,
+            Err(e) => return CheckOutcome::Skipped(e.to_string()),
+        };
+        let (program, args) =
+            match build_command_in(self.path(), runner, &[relative.to_string()], &[]) {
+                Ok(c) => c,
+                Err(e) => return CheckOutcome::Skipped(e.to_string()),
+            };
+        // Nothing is read back from here; it's just the one writable mount
+        let reports = match tempfile::tempdir() {
+            Ok(dir) => dir,
+            Err(e) => return CheckOutcome::Skipped(e.to_string()),
+        };
+        let name = container::new_name();
+        let (program, args) = container.command(
+            &name,
+            self.path(),
+            Some(&self.project),
+            reports.path(),
+            &program,
+            &args,
+        );
+
+        let mut command = Command::new(program);
+        command.args(&args);
+        run_command(self.path(), command, Some(&name), RUN_TIMEOUT, |_| true).await
+    }
+
+    async fn run(
+        &self,
+        command: Command,
+        timeout: Duration,
+        relevant: impl Fn(&str) -> bool,
+    ) -> CheckOutcome {
+        run_command(self.path(), command, None, timeout, relevant).await
+    }
+}
+
+/// Run a command in `dir`, reporting the first output line accepted by `relevant`
+///
+/// `container` names the container the command runs in, if any, so it can
+/// be stopped along with the command when it times out.
+async fn run_command(
+    dir: &Path,
+    mut command: Command,
+    container: Option<&str>,
+    timeout: Duration,
+    relevant: impl Fn(&str) -> bool,
+) -> CheckOutcome {
+    command
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let output = match tokio::time::timeout(timeout, command.output()).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) if e.kind() == io::ErrorKind::NotFound => {
+            let program = Path::new(command.as_std().get_program());
+            let name = program.file_name().unwrap_or(program.as_os_str());
+            return CheckOutcome::Skipped(format!("{} not installed", name.to_string_lossy()));
+        }
+        Ok(Err(e)) => return CheckOutcome::Skipped(e.to_string()),
+        Err(_) => {
+            if let Some(name) = container {
+                container::kill(name).await;
+            }
+            return CheckOutcome::Failed(format!("timed out after {}s", timeout.as_secs()));
+        }
+    };
+
+    if output.status.success() {
+        return CheckOutcome::Passed;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<&str> = stderr
+        .lines()
+        .chain(stdout.lines())
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && relevant(line))
+        .collect();
+    // Prefer the actual error message over location or banner lines
+    let reason = lines
+        .iter()
+        .find(|line| line.to_lowercase().contains("error"))
+        .or(lines.first())
+        .copied()
+        .unwrap_or_default();
+    CheckOutcome::Failed(reason.chars().take(160).collect())
+}
+
+/// Recreate the symlink `source` at `target`, pointing where it points
+///
+/// An absolute link into `project` is pointed at the same place under
+/// `root`, the copy, so nothing run in the copy writes through it into the
+/// project.
+#[cfg(unix)]
+fn copy_link(source: &Path, target: &Path, project: &Path, root: &Path) -> io::Result<()> {
+    let link = std::fs::read_link(source)?;
+    let link = match link.strip_prefix(project) {
+        Ok(inside) => root.join(inside),
+        Err(_) => link,
+    };
+    std::os::unix::fs::symlink(link, target)
+}
+
+/// Creating symlinks takes extra privileges on Windows; links are left out
+#[cfg(windows)]
+fn copy_link(_source: &Path, _target: &Path, _project: &Path, _root: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn link_dir(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(source, target)
+}
+
+#[cfg(windows)]
+fn link_dir(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_dir(source, target)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_link_stays_in_copy() {
+        let project = tempfile::tempdir().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(project.path().join("data"), project.path().join("fixtures"))
+            .unwrap();
+        std::os::unix::fs::symlink("/etc/hosts", project.path().join("hosts")).unwrap();
+        std::os::unix::fs::symlink("../shared", project.path().join("shared")).unwrap();
+
+        let (from, to) = (project.path(), root.path());
+        for name in ["fixtures", "hosts", "shared"] {
+            copy_link(&from.join(name), &to.join(name), from, to).unwrap();
+        }
+        let read = |name: &str| std::fs::read_link(root.path().join(name)).unwrap();
+        assert_eq!(read("fixtures"), root.path().join("data"));
+        assert_eq!(read("hosts"), Path::new("/etc/hosts"));
+        assert_eq!(read("shared"), Path::new("../shared"));
+    }
+}
@@ -0,0 +1,87 @@
+//! Run `watchMode.onSuggestions` when watch gets suggestions
+//!
+//! The command gets a summary in environment variables and the full list as
+//! JSON on stdin, so it can post to a webhook, update a status bar, or feed a
+//! dashboard. It runs in the background; watching doesn't wait for it.
+
+use colored::Colorize;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use vibetap_core::api::TestSuggestion;
+
+/// Start `command` for a set of new suggestions
+pub fn run(command: &str, suggestions: &[TestSuggestion]) {
+    // The command may carry arguments, e.g. "./notify.sh --channel tests"
+    let mut parts = command.split_whitespace();
+    let Some(program) = parts.next() else {
+        return;
+    };
+
+    let mut files: Vec<&str> = suggestions.iter().map(|s| s.file_path.as_str()).collect();
+    files.dedup();
+    let security = suggestions
+        .iter()
+        .filter(|s| s.category.eq_ignore_ascii_case("security"))
+        .count();
+    let payload = serde_json::json!({
+        "count": suggestions.len(),
+        "files": files,
+        "suggestions": suggestions
+            .iter()
+            .map(|s| serde_json::json!({
+                "id": s.id,
+                "filePath": s.file_path,
+                "description": s.description,
+                "category": s.category,
+                "confidence": s.confidence,
+            }))
+            .collect::<Vec<_>>(),
+    });
+
+    let child = Command::new(program)
+        .args(parts)
+        .env("VIBETAP_SUGGESTION_COUNT", suggestions.len().to_string())
+        .env("VIBETAP_SECURITY_COUNT", security.to_string())
+        // One path per line
+        .env("VIBETAP_SUGGESTION_FILES", files.join("\n"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            report(command, &e.to_string());
+            return;
+        }
+    };
+
+    let command = command.to_string();
+    tokio::spawn(async move {
+        if let Some(mut stdin) = child.stdin.take() {
+            // A command that ignores stdin may close it early; that's fine
+            let _ = stdin.write_all(payload.to_string().as_bytes()).await;
+        }
+        match child.wait_with_output().await {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let detail = stderr
+                    .lines()
+                    .last()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| output.status.to_string());
+                report(&command, &detail);
+            }
+            Err(e) => report(&command, &e.to_string()),
+        }
+    });
+}
+
+fn report(command: &str, error: &str) {
+    println!(
+        "{}",
+        format!("watchMode.onSuggestions '{}' failed: {}", command, error).dimmed()
+    );
+}
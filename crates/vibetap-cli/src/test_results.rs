@@ -0,0 +1,477 @@
+//! Per-test results of `vibetap run`, for `--junit` and `--json-report`
+//!
+//! Each runner reports in its own way, so results are read from what it
+//! already produces: vitest and pytest write JUnit XML, jest writes its JSON
+//! report, `go test -json` streams events, and cargo's plain output is read
+//! line by line. Whatever the source, the results are written back out as
+//! one JUnit XML file and one JSON file CI systems can pick up.
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// One test and how it went
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestCase {
+    pub name: String,
+    /// File, module or package the test belongs to
+    pub suite: String,
+    /// Source file, when the runner reports it apart from the suite
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<f64>,
+    /// Why the test failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Failed at first, then passed when retried
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub flaky: bool,
+}
+
+/// A whole run
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestResults {
+    pub runner: String,
+    pub success: bool,
+    pub exit_code: i32,
+    pub duration_secs: f64,
+    pub tests: Vec<TestCase>,
+}
+
+impl TestResults {
+    fn count(&self, status: Status) -> usize {
+        self.tests.iter().filter(|t| t.status == status).count()
+    }
+
+    /// The results as a JUnit XML report, one `<testsuite>` per suite
+    pub fn to_junit(&self) -> String {
+        let mut suites: Vec<(&str, Vec<&TestCase>)> = Vec::new();
+        for test in &self.tests {
+            match suites.iter_mut().find(|(name, _)| *name == test.suite) {
+                Some((_, tests)) => tests.push(test),
+                None => suites.push((&test.suite, vec![test])),
+            }
+        }
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites name=\"vibetap run ({})\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+            escape(&self.runner),
+            self.tests.len(),
+            self.count(Status::Failed),
+            self.count(Status::Skipped),
+            self.duration_secs
+        ));
+        for (name, tests) in suites {
+            let failures = tests.iter().filter(|t| t.status == Status::Failed).count();
+            let skipped = tests.iter().filter(|t| t.status == Status::Skipped).count();
+            let time: f64 = tests
+                .iter()
+                .filter_map(|t| t.duration_secs)
+                .fold(0.0, |sum, t| sum + t);
+            xml.push_str(&format!(
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+                escape(name),
+                tests.len(),
+                failures,
+                skipped,
+                time
+            ));
+            for test in tests {
+                xml.push_str(&format!(
+                    "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                    escape(&test.name),
+                    escape(&test.suite),
+                    test.duration_secs.unwrap_or(0.0)
+                ));
+                match (test.status, &test.message) {
+                    (Status::Passed, _) => xml.push_str("/>\n"),
+                    (Status::Skipped, _) => xml.push_str(">\n      <skipped/>\n    </testcase>\n"),
+                    (Status::Failed, message) => {
+                        let message = message.as_deref().unwrap_or("");
+                        let summary = message.lines().next().unwrap_or("");
+                        xml.push_str(&format!(
+                            ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+                            escape(summary),
+                            escape(message)
+                        ));
+                    }
+                }
+            }
+            xml.push_str("  </testsuite>\n");
+        }
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+
+    /// The results as JSON, with a summary of the counts
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        value["summary"] = serde_json::json!({
+            "total": self.tests.len(),
+            "passed": self.count(Status::Passed),
+            "failed": self.count(Status::Failed),
+            "skipped": self.count(Status::Skipped),
+            "flaky": self.tests.iter().filter(|t| t.flaky).count(),
+        });
+        value
+    }
+}
+
+fn escape(text: &str) -> std::borrow::Cow<'_, str> {
+    quick_xml::escape::escape(text)
+}
+
+/// Tests in a JUnit XML report (vitest, pytest)
+pub fn from_junit(xml: &str) -> anyhow::Result<Vec<TestCase>> {
+    let mut reader = Reader::from_str(xml);
+
+    let mut tests = Vec::new();
+    let mut suite = String::new();
+    let mut suite_file: Option<String> = None;
+    let mut current: Option<TestCase> = None;
+    // Inside a <failure> or <error>, whose text is the detail; entity
+    // references arrive separately, so the text is put together here
+    let mut in_failure = false;
+    let mut detail = String::new();
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"testsuite" => {
+                    suite = attribute(&e, "name").unwrap_or_default();
+                    suite_file = attribute(&e, "file");
+                }
+                b"testcase" => current = Some(start_case(&e, &suite, suite_file.as_deref())),
+                b"failure" | b"error" => {
+                    if let Some(test) = current.as_mut() {
+                        test.status = Status::Failed;
+                        test.message = attribute(&e, "message");
+                    }
+                    in_failure = true;
+                }
+                b"skipped" => {
+                    if let Some(test) = current.as_mut() {
+                        test.status = Status::Skipped;
+                    }
+                }
+                _ => {}
+            },
+            Event::Empty(e) => match e.local_name().as_ref() {
+                b"testcase" => tests.push(start_case(&e, &suite, suite_file.as_deref())),
+                b"failure" | b"error" => {
+                    if let Some(test) = current.as_mut() {
+                        test.status = Status::Failed;
+                        test.message = attribute(&e, "message");
+                    }
+                }
+                b"skipped" => {
+                    if let Some(test) = current.as_mut() {
+                        test.status = Status::Skipped;
+                    }
+                }
+                _ => {}
+            },
+            Event::Text(text) if in_failure => detail.push_str(&text.decode()?),
+            Event::CData(text) if in_failure => detail.push_str(&text.decode()?),
+            Event::GeneralRef(entity) if in_failure => {
+                let text = match entity.resolve_char_ref()? {
+                    Some(c) => c.to_string(),
+                    None => quick_xml::escape::unescape(&format!("&{};", entity.decode()?))
+                        .map(|s| s.into_owned())
+                        .unwrap_or_default(),
+                };
+                detail.push_str(&text);
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"testcase" => tests.extend(current.take()),
+                b"failure" | b"error" => {
+                    in_failure = false;
+                    append_detail(current.as_mut(), detail.trim());
+                    detail.clear();
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(tests)
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Option<String> {
+    element
+        .try_get_attribute(name)
+        .ok()
+        .flatten()
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.into_owned())
+}
+
+fn start_case(element: &BytesStart, suite: &str, suite_file: Option<&str>) -> TestCase {
+    TestCase {
+        name: attribute(element, "name").unwrap_or_default(),
+        suite: attribute(element, "classname")
+            .filter(|c| !c.is_empty())
+            .unwrap_or_else(|| suite.to_string()),
+        file: attribute(element, "file").or_else(|| suite_file.map(str::to_string)),
+        status: Status::Passed,
+        duration_secs: attribute(element, "time").and_then(|t| t.parse().ok()),
+        message: None,
+        flaky: false,
+    }
+}
+
+/// Add failure detail after the one-line message, if it says more
+fn append_detail(test: Option<&mut TestCase>, text: &str) {
+    let Some(test) = test else {
+        return;
+    };
+    match &mut test.message {
+        _ if text.is_empty() => {}
+        Some(message) if message.as_str() == text => {}
+        Some(message) if !message.is_empty() => {
+            message.push('\n');
+            message.push_str(text);
+        }
+        message => *message = Some(text.to_string()),
+    }
+}
+
+/// Tests in jest's `--json` report
+pub fn from_jest_json(json: &str) -> anyhow::Result<Vec<TestCase>> {
+    let report: serde_json::Value = serde_json::from_str(json)?;
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let mut tests = Vec::new();
+    for file in report["testResults"].as_array().into_iter().flatten() {
+        let path = file["name"].as_str().unwrap_or_default();
+        let suite = Path::new(path)
+            .strip_prefix(&cwd)
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| path.to_string());
+        for assertion in file["assertionResults"].as_array().into_iter().flatten() {
+            let status = match assertion["status"].as_str() {
+                Some("passed") => Status::Passed,
+                Some("failed") => Status::Failed,
+                _ => Status::Skipped,
+            };
+            let failures: Vec<&str> = assertion["failureMessages"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|m| m.as_str())
+                .collect();
+            tests.push(TestCase {
+                name: assertion["fullName"]
+                    .as_str()
+                    .or_else(|| assertion["title"].as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                suite: suite.clone(),
+                file: None,
+                status,
+                duration_secs: assertion["duration"].as_f64().map(|ms| ms / 1000.0),
+                message: (!failures.is_empty()).then(|| failures.join("\n")),
+                flaky: false,
+            });
+        }
+    }
+    Ok(tests)
+}
+
+/// Collects tests from the events `go test -json` prints, one per line
+#[derive(Default)]
+pub struct GoEvents {
+    tests: Vec<TestCase>,
+    /// Output of tests still running, keyed by package and test
+    output: Vec<((String, String), String)>,
+}
+
+impl GoEvents {
+    /// Read one line, returning the text it carries for the terminal
+    pub fn read(&mut self, line: &str) -> Option<String> {
+        let Ok(event) = serde_json::from_str::<serde_json::Value>(line) else {
+            // Build errors and the like aren't events
+            return Some(format!("{}\n", line));
+        };
+        let package = event["Package"].as_str().unwrap_or_default().to_string();
+        let test = event["Test"].as_str().map(str::to_string);
+        let action = event["Action"].as_str().unwrap_or_default();
+        let text = event["Output"].as_str().map(str::to_string);
+
+        if let (Some(test), Some(text)) = (&test, &text) {
+            let key = (package.clone(), test.clone());
+            match self.output.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, output)) => output.push_str(text),
+                None => self.output.push((key, text.clone())),
+            }
+        }
+        let status = match action {
+            "pass" => Some(Status::Passed),
+            "fail" => Some(Status::Failed),
+            "skip" => Some(Status::Skipped),
+            _ => None,
+        };
+        if let (Some(test), Some(status)) = (test, status) {
+            let key = (package.clone(), test.clone());
+            let output = self
+                .output
+                .iter()
+                .position(|(k, _)| *k == key)
+                .map(|i| self.output.remove(i).1);
+            self.tests.push(TestCase {
+                name: test,
+                suite: package,
+                file: None,
+                status,
+                duration_secs: event["Elapsed"].as_f64(),
+                message: output.filter(|_| status == Status::Failed),
+                flaky: false,
+            });
+        }
+        text
+    }
+
+    pub fn finish(self) -> Vec<TestCase> {
+        self.tests
+    }
+}
+
+/// Tests in cargo test's plain output (`test path::name ... ok`), with the
+/// captured output cargo prints for each failure
+pub fn from_cargo_output(output: &str) -> Vec<TestCase> {
+    let mut tests: Vec<(String, TestCase)> = Vec::new();
+    // The failure section being read: `---- path::name stdout ----`
+    let mut section: Option<(String, String)> = None;
+    for line in output.lines() {
+        if let Some(full) = line
+            .strip_prefix("---- ")
+            .and_then(|rest| rest.strip_suffix(" stdout ----"))
+        {
+            finish_section(&mut tests, section.take());
+            section = Some((full.to_string(), String::new()));
+            continue;
+        }
+        if let Some((_, text)) = section.as_mut() {
+            if line == "failures:" || line.starts_with("test result:") {
+                finish_section(&mut tests, section.take());
+            } else {
+                text.push_str(line);
+                text.push('\n');
+            }
+            continue;
+        }
+
+        let Some((full, result)) = line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.rsplit_once(" ... "))
+        else {
+            continue;
+        };
+        let status = match result.split([' ', ',']).next() {
+            Some("ok") => Status::Passed,
+            Some("FAILED") => Status::Failed,
+            Some("ignored") => Status::Skipped,
+            _ => continue,
+        };
+        let (suite, name) = full.rsplit_once("::").unwrap_or(("", full));
+        tests.push((
+            full.to_string(),
+            TestCase {
+                name: name.to_string(),
+                suite: suite.to_string(),
+                file: None,
+                status,
+                duration_secs: None,
+                message: None,
+                flaky: false,
+            },
+        ));
+    }
+    finish_section(&mut tests, section);
+    tests.into_iter().map(|(_, test)| test).collect()
+}
+
+fn finish_section(tests: &mut [(String, TestCase)], section: Option<(String, String)>) {
+    let Some((full, text)) = section else {
+        return;
+    };
+    if let Some((_, test)) = tests.iter_mut().find(|(name, _)| *name == full) {
+        test.message = Some(text.trim().to_string()).filter(|t| !t.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_junit() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="src/a.test.ts" tests="3">
+    <testcase classname="src/a.test.ts" name="adds" time="0.002"/>
+    <testcase classname="src/a.test.ts" name="rejects &lt;0" time="0.010">
+      <failure message="expected 1 to be 2">AssertionError: expected 1 to be 2 &amp; more</failure>
+    </testcase>
+    <testcase classname="src/a.test.ts" name="later"><skipped/></testcase>
+  </testsuite>
+</testsuites>"#;
+        let tests = from_junit(xml).unwrap();
+        assert_eq!(tests.len(), 3);
+        assert_eq!(tests[0].status, Status::Passed);
+        assert_eq!(tests[1].name, "rejects <0");
+        assert_eq!(tests[1].status, Status::Failed);
+        assert_eq!(
+            tests[1].message.as_deref(),
+            Some("expected 1 to be 2\nAssertionError: expected 1 to be 2 & more")
+        );
+        assert_eq!(tests[2].status, Status::Skipped);
+
+        let results = TestResults {
+            runner: "vitest".to_string(),
+            success: false,
+            exit_code: 1,
+            duration_secs: 0.5,
+            tests,
+        };
+        let written = from_junit(&results.to_junit()).unwrap();
+        assert_eq!(written.len(), 3);
+        assert_eq!(written[1].status, Status::Failed);
+        assert_eq!(results.to_json()["summary"]["failed"], 1);
+    }
+
+    #[test]
+    fn test_reads_cargo_and_go_output() {
+        let cargo = "running 3 tests\ntest tests::adds ... ok\ntest tests::slow ... ignored, needs db\ntest tests::subs ... FAILED\n\nfailures:\n\n---- tests::subs stdout ----\nassertion failed: 1 == 2\n\nfailures:\n    tests::subs\n\ntest result: FAILED.";
+        let tests = from_cargo_output(cargo);
+        assert_eq!(tests.len(), 3);
+        assert_eq!(tests[0].suite, "tests");
+        assert_eq!(tests[1].status, Status::Skipped);
+        assert_eq!(
+            tests[2].message.as_deref(),
+            Some("assertion failed: 1 == 2")
+        );
+
+        let mut go = GoEvents::default();
+        go.read(r#"{"Action":"run","Package":"ex/calc","Test":"TestAdd"}"#);
+        go.read(r#"{"Action":"output","Package":"ex/calc","Test":"TestAdd","Output":"want 2\n"}"#);
+        go.read(r#"{"Action":"fail","Package":"ex/calc","Test":"TestAdd","Elapsed":0.01}"#);
+        go.read(r#"{"Action":"fail","Package":"ex/calc","Elapsed":0.02}"#);
+        let tests = go.finish();
+        assert_eq!(tests.len(), 1);
+        assert_eq!(tests[0].message.as_deref(), Some("want 2\n"));
+    }
+}
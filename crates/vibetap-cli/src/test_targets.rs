@@ -0,0 +1,353 @@
+//! Running just the tests in given files with cargo and go
+//!
+//! Neither runner takes test files as arguments, so the test functions are
+//! read from the files instead: `#[test]` functions (and `#[tokio::test]`
+//! and the like) for Rust, `func TestX` and `func FuzzX` for Go. Cargo gets
+//! their paths as filters, go a `-run` pattern over the files' packages.
+//! The same names tie the tests a run reports back to the files they are in.
+
+use regex::Regex;
+use std::path::{Component, Path, PathBuf};
+use std::sync::LazyLock;
+
+use crate::test_results::TestCase;
+
+/// `func TestX(` and `func FuzzX(` declarations
+static GO_TEST: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^func\s+((?:Test|Fuzz)\w*)\s*\(").unwrap());
+
+/// What `cargo test` needs to run the tests of some files
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CargoTargets {
+    /// Arguments for cargo itself: `-p` for workspace members and `--test`
+    /// when every file is an integration test
+    pub cargo_args: Vec<String>,
+    /// Test paths for the test binaries, matched as substrings
+    pub filters: Vec<String>,
+}
+
+/// The targets and filters for the tests in `files`, relative to `dir`;
+/// `None` when no test functions could be found in them
+pub fn cargo(dir: &Path, files: &[String]) -> Option<CargoTargets> {
+    let mut targets = CargoTargets::default();
+    let mut integration: Vec<String> = Vec::new();
+    let mut all_integration = true;
+
+    for file in files {
+        let (crate_dir, within) = crate_of(dir, Path::new(file));
+        let tests = rust_test_paths(dir, file, &within);
+        if tests.is_empty() {
+            continue;
+        }
+        for test in tests {
+            if !targets.filters.contains(&test) {
+                targets.filters.push(test);
+            }
+        }
+
+        if let Some(name) = crate_dir
+            .filter(|d| !d.as_os_str().is_empty())
+            .and_then(|d| package_name(&dir.join(d)))
+        {
+            if !targets.cargo_args.contains(&name) {
+                targets.cargo_args.extend(["-p".to_string(), name]);
+            }
+        }
+        match integration_target(&within) {
+            Some(target) if !integration.contains(&target) => integration.push(target),
+            Some(_) => {}
+            None => all_integration = false,
+        }
+    }
+
+    if targets.filters.is_empty() {
+        return None;
+    }
+    if all_integration {
+        for target in integration {
+            targets.cargo_args.extend(["--test".to_string(), target]);
+        }
+    }
+    Some(targets)
+}
+
+/// The packages of the Go `files`, relative to `dir`, and a `-run` pattern
+/// for their tests; no pattern when a file has none of its own, so its
+/// whole package runs
+pub fn go(dir: &Path, files: &[String]) -> (Vec<String>, Option<String>) {
+    let mut packages: Vec<String> = Vec::new();
+    let mut names: Vec<String> = Vec::new();
+    let mut whole_package = false;
+
+    for file in files {
+        let package = match Path::new(file).parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => {
+                format!("./{}", parent.to_string_lossy())
+            }
+            _ => ".".to_string(),
+        };
+        if !packages.contains(&package) {
+            packages.push(package);
+        }
+
+        let found = go_test_names(dir, file);
+        if found.is_empty() {
+            whole_package = true;
+        }
+        for name in found {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+
+    let pattern = (!whole_package && !names.is_empty()).then(|| format!("^({})$", names.join("|")));
+    (packages, pattern)
+}
+
+/// The tests of a run that come from `file` (relative to `dir`), however
+/// the runner names them: by file, by the module path of a Python file
+/// (`tests.test_cart`), by Rust test path, or by Go package and test name
+pub fn tests_in<'a>(dir: &Path, file: &str, tests: &'a [TestCase]) -> Vec<&'a TestCase> {
+    let file = file.trim_start_matches("./");
+    let is_file = |path: &str| {
+        let path = path.trim_start_matches("./");
+        path == file || path.ends_with(&format!("/{}", file))
+    };
+    let module = Path::new(file)
+        .with_extension("")
+        .to_string_lossy()
+        .replace('/', ".");
+    let rust = if file.ends_with(".rs") {
+        rust_test_paths(dir, file, &crate_of(dir, Path::new(file)).1)
+    } else {
+        Vec::new()
+    };
+    let go = if file.ends_with("_test.go") {
+        go_test_names(dir, file)
+    } else {
+        Vec::new()
+    };
+    let package = Path::new(file)
+        .parent()
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    tests
+        .iter()
+        .filter(|t| {
+            let full = if t.suite.is_empty() {
+                t.name.clone()
+            } else {
+                format!("{}::{}", t.suite, t.name)
+            };
+            let in_package = package.is_empty()
+                || t.suite == package
+                || t.suite.ends_with(&format!("/{}", package));
+            t.file.as_deref().is_some_and(is_file)
+                || is_file(&t.suite)
+                || t.suite == module
+                || t.suite.starts_with(&format!("{}.", module))
+                || rust.contains(&full)
+                || (in_package && go.iter().any(|n| t.name.split('/').next() == Some(n)))
+        })
+        .collect()
+}
+
+/// Paths of the `#[test]` functions in a Rust file, as cargo names them
+fn rust_test_paths(dir: &Path, file: &str, within: &Path) -> Vec<String> {
+    let Ok(source) = std::fs::read_to_string(dir.join(file)) else {
+        return Vec::new();
+    };
+    let Ok(parsed) = syn::parse_file(&source) else {
+        return Vec::new();
+    };
+    let mut tests = Vec::new();
+    rust_tests(&parsed.items, &module_path(within), &mut tests);
+    tests
+}
+
+/// Top-level test functions of a Go test file
+fn go_test_names(dir: &Path, file: &str) -> Vec<String> {
+    let source = std::fs::read_to_string(dir.join(file)).unwrap_or_default();
+    GO_TEST
+        .captures_iter(&source)
+        .filter_map(|c| c.get(1))
+        .map(|m| m.as_str().to_string())
+        .collect()
+}
+
+/// Paths of the test functions in `items`, below module path `prefix`
+fn rust_tests(items: &[syn::Item], prefix: &[String], found: &mut Vec<String>) {
+    for item in items {
+        match item {
+            syn::Item::Fn(item) => {
+                let is_test = item.attrs.iter().any(|attr| {
+                    attr.path()
+                        .segments
+                        .last()
+                        .is_some_and(|segment| segment.ident == "test")
+                });
+                if is_test {
+                    let mut path = prefix.to_vec();
+                    path.push(item.sig.ident.to_string());
+                    found.push(path.join("::"));
+                }
+            }
+            syn::Item::Mod(item) => {
+                if let Some((_, items)) = &item.content {
+                    let mut path = prefix.to_vec();
+                    path.push(item.ident.to_string());
+                    rust_tests(items, &path, found);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The directory of the crate `file` belongs to (the nearest one with a
+/// `Cargo.toml`, relative to `dir`) and the file's path within it
+fn crate_of(dir: &Path, file: &Path) -> (Option<PathBuf>, PathBuf) {
+    for ancestor in file.ancestors().skip(1) {
+        if dir.join(ancestor).join("Cargo.toml").is_file() {
+            let within = file.strip_prefix(ancestor).unwrap_or(file);
+            return (Some(ancestor.to_path_buf()), within.to_path_buf());
+        }
+    }
+    (None, file.to_path_buf())
+}
+
+/// Module path of a file within its crate: `src/api/client.rs` is
+/// `api::client`; crate roots, binaries and integration tests have none
+fn module_path(within: &Path) -> Vec<String> {
+    let parts: Vec<String> = within
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    let Some(("src", rest)) = parts
+        .split_first()
+        .map(|(first, rest)| (first.as_str(), rest))
+    else {
+        return Vec::new();
+    };
+    if rest.first().is_some_and(|dir| dir == "bin") {
+        return Vec::new();
+    }
+    let mut path: Vec<String> = rest
+        .iter()
+        .map(|part| part.strip_suffix(".rs").unwrap_or(part).to_string())
+        .collect();
+    if path.last().is_some_and(|last| last == "mod") {
+        path.pop();
+    }
+    if path.len() == 1 && (path[0] == "lib" || path[0] == "main") {
+        path.clear();
+    }
+    path
+}
+
+/// The `--test` target of an integration test file (`tests/api.rs` or
+/// `tests/api/main.rs`)
+fn integration_target(within: &Path) -> Option<String> {
+    let mut components = within.components().filter_map(|c| match c {
+        Component::Normal(part) => part.to_str(),
+        _ => None,
+    });
+    if components.next() != Some("tests") {
+        return None;
+    }
+    match (components.next(), components.next(), components.next()) {
+        (Some(file), None, None) => file.strip_suffix(".rs").map(str::to_string),
+        (Some(dir), Some("main.rs"), None) => Some(dir.to_string()),
+        _ => None,
+    }
+}
+
+/// `package.name` of the `Cargo.toml` in `crate_dir`
+fn package_name(crate_dir: &Path) -> Option<String> {
+    let manifest = std::fs::read_to_string(crate_dir.join("Cargo.toml")).ok()?;
+    let manifest: toml::Table = manifest.parse().ok()?;
+    manifest
+        .get("package")?
+        .get("name")?
+        .as_str()
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cargo_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write("Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\n");
+        write("crates/core/Cargo.toml", "[package]\nname = \"app-core\"\n");
+        write(
+            "crates/core/src/api/client.rs",
+            "fn helper() {}\n#[cfg(test)]\nmod tests {\n    #[test]\n    fn retries() {}\n    #[tokio::test]\n    async fn times_out() {}\n}\n",
+        );
+        write("crates/core/tests/flow.rs", "#[test]\nfn end_to_end() {}\n");
+
+        let targets = cargo(dir.path(), &["crates/core/src/api/client.rs".to_string()]).unwrap();
+        assert_eq!(targets.cargo_args, vec!["-p", "app-core"]);
+        assert_eq!(
+            targets.filters,
+            vec![
+                "api::client::tests::retries",
+                "api::client::tests::times_out"
+            ]
+        );
+
+        let targets = cargo(dir.path(), &["crates/core/tests/flow.rs".to_string()]).unwrap();
+        assert_eq!(targets.cargo_args, vec!["-p", "app-core", "--test", "flow"]);
+        assert_eq!(targets.filters, vec!["end_to_end"]);
+    }
+
+    #[test]
+    fn test_tests_in_file() {
+        let case = |suite: &str, name: &str| TestCase {
+            name: name.to_string(),
+            suite: suite.to_string(),
+            file: None,
+            status: crate::test_results::Status::Passed,
+            duration_secs: None,
+            message: None,
+            flaky: false,
+        };
+        let tests = vec![
+            case("src/cart.test.ts", "cart > totals"),
+            case("tests.test_cart.TestCart", "test_total"),
+            case("tests.test_cartesian", "test_product"),
+        ];
+        let dir = Path::new(".");
+        assert_eq!(tests_in(dir, "src/cart.test.ts", &tests).len(), 1);
+        let python = tests_in(dir, "tests/test_cart.py", &tests);
+        assert_eq!(python.len(), 1);
+        assert_eq!(python[0].name, "test_total");
+    }
+
+    #[test]
+    fn test_go_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("pkg/cart")).unwrap();
+        std::fs::write(
+            dir.path().join("pkg/cart/cart_test.go"),
+            "package cart\n\nfunc TestTotal(t *testing.T) {}\n\nfunc FuzzParse(f *testing.F) {}\n\nfunc helper() {}\n",
+        )
+        .unwrap();
+
+        let (packages, pattern) = go(dir.path(), &["pkg/cart/cart_test.go".to_string()]);
+        assert_eq!(packages, vec!["./pkg/cart"]);
+        assert_eq!(pattern.as_deref(), Some("^(TestTotal|FuzzParse)$"));
+    }
+}
@@ -0,0 +1,40 @@
+//! Terminal output helpers shared across commands
+//!
+//! Accessible mode replaces spinners, box-drawing borders, block progress
+//! bars, and emoji with plain sequential text for screen readers and dumb
+//! terminals.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ACCESSIBLE: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable accessible output for the rest of the process
+pub fn set_accessible(enabled: bool) {
+    ACCESSIBLE.store(enabled, Ordering::Relaxed);
+    if enabled {
+        colored::control::set_override(false);
+    }
+}
+
+/// Check whether accessible output is enabled
+pub fn is_accessible() -> bool {
+    ACCESSIBLE.load(Ordering::Relaxed)
+}
+
+/// Pick a decorative symbol, or its plain-text label in accessible mode
+pub fn icon(symbol: &'static str, label: &'static str) -> &'static str {
+    if is_accessible() {
+        label
+    } else {
+        symbol
+    }
+}
+
+/// Print code without borders or highlighting
+pub fn print_plain_code(code: &str) {
+    println!("Code:");
+    for line in code.lines() {
+        println!("    {}", line);
+    }
+    println!("End of code.");
+}
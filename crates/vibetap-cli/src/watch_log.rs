@@ -0,0 +1,85 @@
+//! JSON-lines log of a `vibetap watch` session (`--log-file`)
+//!
+//! Each line is one event: what triggered a check, why a check was skipped,
+//! the request sent, the response or error that came back. Once the file
+//! passes [`MAX_BYTES`] it is moved to `<file>.1` (older copies shift up to
+//! `<file>.3`) and a new one is started.
+
+use serde_json::{Map, Value};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Size at which the log is rotated
+const MAX_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Rotated copies kept next to the log
+const KEPT: usize = 3;
+
+/// Where watch events are written; does nothing when no file was given
+pub struct WatchLog {
+    path: Option<PathBuf>,
+    file: Option<File>,
+}
+
+impl WatchLog {
+    /// Append to `path`, creating it and its directory if needed
+    pub fn open(path: Option<&Path>) -> anyhow::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self {
+                path: None,
+                file: None,
+            });
+        };
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = append(path)
+            .map_err(|e| anyhow::anyhow!("Could not open log file {}: {}", path.display(), e))?;
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            file: Some(file),
+        })
+    }
+
+    /// Write one event; `fields` should be a JSON object
+    pub fn record(&mut self, event: &str, fields: Value) {
+        let (Some(path), Some(file)) = (&self.path, &mut self.file) else {
+            return;
+        };
+
+        let mut line = Map::new();
+        line.insert(
+            "timestamp".to_string(),
+            chrono::Local::now().to_rfc3339().into(),
+        );
+        line.insert("event".to_string(), event.into());
+        if let Value::Object(fields) = fields {
+            line.extend(fields);
+        }
+        // Logging must never stop watch, so write failures are dropped
+        let _ = writeln!(file, "{}", Value::Object(line));
+
+        if file.metadata().is_ok_and(|m| m.len() >= MAX_BYTES) {
+            rotate(path);
+            self.file = append(path).ok();
+        }
+    }
+}
+
+fn append(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Shift `<path>.1` .. `<path>.{KEPT-1}` up by one and move the log to `<path>.1`
+fn rotate(path: &Path) {
+    let numbered = |n: usize| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    };
+    for n in (1..KEPT).rev() {
+        let _ = std::fs::rename(numbered(n), numbered(n + 1));
+    }
+    let _ = std::fs::rename(path, numbered(1));
+}
@@ -0,0 +1,57 @@
+//! What `vibetap watch` remembers between runs
+//!
+//! Kept in `.vibetap/watch-state.json`, so a restarted watch knows which diff
+//! it already generated for and whether it was waiting out the hourly
+//! budget. The hourly count itself comes from the usage log, which outlives
+//! any one session anyway.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const STATE_FILE: &str = ".vibetap/watch-state.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchState {
+    /// Hash of the diff suggestions were last generated for, or of the
+    /// diff after the last checkout
+    #[serde(
+        default,
+        alias = "last_diff_hash",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_diff_hash: Option<String>,
+    /// While the hourly budget is used up, when it allows another generation
+    #[serde(
+        default,
+        alias = "budget_resumes_at",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub budget_resumes_at: Option<i64>,
+    /// Unix timestamp of the last generation that brought suggestions
+    #[serde(
+        default,
+        alias = "last_suggestions_at",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_suggestions_at: Option<i64>,
+}
+
+impl WatchState {
+    /// The saved state; empty when there is none or it can't be read
+    pub fn load() -> Self {
+        std::fs::read_to_string(STATE_FILE)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Path::new(STATE_FILE);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,385 @@
+//! Stopping hung test runs, for `vibetap run --timeout` and `--test-timeout`
+//!
+//! With a timeout set, the runner starts in a process group of its own, so
+//! whatever it spawns (workers, servers the tests start) is stopped with it:
+//! SIGTERM first, which lets `docker run` stop its container, then SIGKILL a
+//! few seconds later. The group no longer gets Ctrl+C from the terminal, so
+//! it is passed on from here for as long as the runner is watched.
+//!
+//! Most runners enforce the per-test limit themselves once given it. Go and
+//! cargo report tests as they go, so those are watched here: a go test that
+//! runs past the limit, or a cargo test binary that finishes no test within
+//! it, stops the run, and the tests still running are reported as hung.
+//! Cargo only names a running test once it has run for a minute, or when
+//! tests run on one thread.
+
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::test_results::{Status, TestCase};
+
+/// How long the runner gets to exit after SIGTERM
+const GRACE: Duration = Duration::from_secs(5);
+
+/// How often the clock is checked
+const TICK: Duration = Duration::from_millis(100);
+
+/// Libtest names a test once it has been running this long
+const CARGO_SLOW_AFTER: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timeouts {
+    /// Longest the whole run may take
+    pub run: Option<Duration>,
+    /// Longest a single test may take
+    pub test: Option<Duration>,
+}
+
+impl Timeouts {
+    pub fn is_set(&self) -> bool {
+        self.run.is_some() || self.test.is_some()
+    }
+}
+
+/// Why a run was stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    RunTimeout(Duration),
+    TestTimeout(Duration),
+    Interrupted,
+}
+
+/// A run that was stopped before it finished
+#[derive(Debug)]
+pub struct Stopped {
+    pub reason: Reason,
+    /// Tests that were still running, as failures
+    pub hung: Vec<TestCase>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Tests that started and haven't finished: suite, name and since when
+    running: Vec<(String, String, Instant)>,
+    /// While cargo runs a test binary, when it last finished a test
+    binary: Option<Instant>,
+    stopped: Option<Reason>,
+    done: bool,
+}
+
+impl State {
+    fn overdue(&self, started: Instant, timeouts: Timeouts) -> Option<Reason> {
+        if let Some(limit) = timeouts.run {
+            if started.elapsed() >= limit {
+                return Some(Reason::RunTimeout(limit));
+            }
+        }
+        let limit = timeouts.test?;
+        let stuck = self
+            .running
+            .iter()
+            .any(|(_, _, since)| since.elapsed() >= limit)
+            || self.binary.is_some_and(|since| since.elapsed() >= limit);
+        stuck.then_some(Reason::TestTimeout(limit))
+    }
+
+    fn finished(&mut self, suite: &str, name: &str) {
+        self.running.retain(|(s, n, _)| !(s == suite && n == name));
+    }
+}
+
+/// Set when Ctrl+C arrives while a runner is watched
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Watches a running test command and stops it once it takes too long
+pub struct Watchdog {
+    state: Arc<Mutex<State>>,
+    interrupts: Option<Interrupts>,
+}
+
+impl Watchdog {
+    /// Start `command` in a process group of its own, so all of it can be
+    /// stopped
+    pub fn isolate(command: &mut Command) {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        #[cfg(not(unix))]
+        let _ = command;
+    }
+
+    /// Watch `child`, started from an [isolated](Self::isolate) command,
+    /// until [`Watchdog::finish`]
+    pub fn start(child: &Child, timeouts: Timeouts) -> Self {
+        let pid = child.id();
+        let started = Instant::now();
+        let state = Arc::new(Mutex::new(State::default()));
+
+        let watched = state.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(TICK);
+            let mut state = watched.lock().unwrap();
+            if state.done {
+                return;
+            }
+            if let Some(reason) = state.overdue(started, timeouts) {
+                state.stopped = Some(reason);
+                drop(state);
+                stop(pid, &watched);
+                return;
+            }
+        });
+
+        let interrupts = Interrupts::forward_to(pid);
+        Self { state, interrupts }
+    }
+
+    /// Follow a line of `go test -json` output
+    pub fn read_go(&self, line: &str) {
+        let Ok(event) = serde_json::from_str::<serde_json::Value>(line) else {
+            return;
+        };
+        let package = event["Package"].as_str().unwrap_or_default();
+        let mut state = self.state.lock().unwrap();
+        let Some(test) = event["Test"].as_str() else {
+            // The package is done, or its binary died
+            if matches!(event["Action"].as_str(), Some("pass" | "fail" | "skip")) {
+                state.running.retain(|(suite, _, _)| suite != package);
+            }
+            return;
+        };
+        match event["Action"].as_str().unwrap_or_default() {
+            // Paused parallel tests wait for the others; the clock starts
+            // again when they continue
+            "run" | "cont" => {
+                state
+                    .running
+                    .push((package.to_string(), test.to_string(), Instant::now()));
+            }
+            "pause" | "pass" | "fail" | "skip" => state.finished(package, test),
+            _ => {}
+        }
+    }
+
+    /// Follow a line of cargo test output
+    pub fn read_cargo(&self, line: &str) {
+        let mut state = self.state.lock().unwrap();
+        if line.starts_with("running ") {
+            state.binary = Some(Instant::now());
+        } else if line.starts_with("test result:") {
+            state.binary = None;
+        } else if let Some(full) = line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.strip_suffix(" has been running for over 60 seconds"))
+        {
+            let (suite, name) = full.rsplit_once("::").unwrap_or(("", full));
+            let since = Instant::now()
+                .checked_sub(CARGO_SLOW_AFTER)
+                .unwrap_or_else(Instant::now);
+            state
+                .running
+                .push((suite.to_string(), name.to_string(), since));
+        } else if let Some((full, result)) = line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.rsplit_once(" ... "))
+        {
+            let (suite, name) = full.rsplit_once("::").unwrap_or(("", full));
+            if result.trim().is_empty() {
+                // Run on one thread, libtest names each test as it starts;
+                // the line only ends here when the test never finished
+                let since = state.binary.unwrap_or_else(Instant::now);
+                state
+                    .running
+                    .push((suite.to_string(), name.to_string(), since));
+            } else {
+                state.finished(suite, name);
+                state.binary = Some(Instant::now());
+            }
+        }
+    }
+
+    /// Stop watching, once the runner has exited; how it was stopped, if
+    /// it was
+    pub fn finish(self) -> Option<Stopped> {
+        // Ctrl+C is the terminal's again from here
+        let interrupted = self.interrupts.is_some() && INTERRUPTED.swap(false, Ordering::SeqCst);
+        drop(self.interrupts);
+        let mut state = self.state.lock().unwrap();
+        state.done = true;
+        if interrupted {
+            state.stopped = Some(Reason::Interrupted);
+        }
+        let reason = state.stopped?;
+
+        let message = match reason {
+            Reason::RunTimeout(limit) => {
+                format!(
+                    "Still running when the run timed out after {}",
+                    describe(limit)
+                )
+            }
+            Reason::TestTimeout(limit) => format!("Timed out after {}", describe(limit)),
+            Reason::Interrupted => "Interrupted".to_string(),
+        };
+        let hung = state
+            .running
+            .iter()
+            // A go test is running while its subtests are; name the subtests
+            .filter(|(suite, name, _)| {
+                !state
+                    .running
+                    .iter()
+                    .any(|(s, n, _)| s == suite && n.starts_with(&format!("{}/", name)))
+            })
+            .map(|(suite, name, since)| TestCase {
+                name: name.clone(),
+                suite: suite.clone(),
+                file: None,
+                status: Status::Failed,
+                duration_secs: Some(since.elapsed().as_secs_f64()),
+                message: Some(message.clone()),
+                flaky: false,
+            })
+            .collect();
+        Some(Stopped { reason, hung })
+    }
+}
+
+/// A limit as it was likely given: `90s`, `10m`
+pub fn describe(limit: Duration) -> String {
+    let secs = limit.as_secs();
+    if secs >= 60 && secs.is_multiple_of(60) {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Stop the runner's process group: politely, then for sure
+#[cfg(unix)]
+fn stop(pid: u32, state: &Mutex<State>) {
+    signal_group(pid, libc::SIGTERM);
+    let deadline = Instant::now() + GRACE;
+    while Instant::now() < deadline && !state.lock().unwrap().done {
+        std::thread::sleep(TICK);
+    }
+    // Whatever the runner left behind goes too
+    signal_group(pid, libc::SIGKILL);
+}
+
+#[cfg(not(unix))]
+fn stop(pid: u32, _state: &Mutex<State>) {
+    let _ = Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &pid.to_string()])
+        .status();
+}
+
+/// Passes Ctrl+C on to the runner's process group until dropped, then puts
+/// back whatever handled it before
+#[cfg(unix)]
+struct Interrupts {
+    previous: libc::sigaction,
+}
+
+/// The runner's process group, while Ctrl+C is passed on to it
+#[cfg(unix)]
+static FORWARD_TO: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+
+#[cfg(unix)]
+impl Interrupts {
+    fn forward_to(pid: u32) -> Option<Self> {
+        FORWARD_TO.store(pid, Ordering::SeqCst);
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        // SAFETY: the handler only touches atomics and calls kill, which is
+        // async-signal-safe
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            // Reading the runner's output carries on
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            let mut previous: libc::sigaction = std::mem::zeroed();
+            (libc::sigaction(libc::SIGINT, &action, &mut previous) == 0)
+                .then_some(Self { previous })
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Interrupts {
+    fn drop(&mut self) {
+        // SAFETY: puts back the action sigaction handed out
+        unsafe {
+            libc::sigaction(libc::SIGINT, &self.previous, std::ptr::null_mut());
+        }
+        FORWARD_TO.store(0, Ordering::SeqCst);
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+    let pid = FORWARD_TO.load(Ordering::SeqCst);
+    if pid != 0 {
+        signal_group(pid, libc::SIGINT);
+    }
+}
+
+/// Windows sends Ctrl+C to every process on the console anyway
+#[cfg(not(unix))]
+struct Interrupts;
+
+#[cfg(not(unix))]
+impl Interrupts {
+    fn forward_to(_pid: u32) -> Option<Self> {
+        None
+    }
+}
+
+/// The group was created with the runner's pid as its id
+#[cfg(unix)]
+fn signal_group(pid: u32, signal: libc::c_int) {
+    // SAFETY: kill has no memory safety requirements
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), signal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hung_tests() {
+        let watchdog = Watchdog {
+            state: Arc::default(),
+            interrupts: None,
+        };
+        watchdog.read_go(r#"{"Action":"run","Package":"ex/cart","Test":"TestTotal"}"#);
+        watchdog.read_go(r#"{"Action":"run","Package":"ex/cart","Test":"TestTotal/empty"}"#);
+        watchdog.read_go(r#"{"Action":"run","Package":"ex/cart","Test":"TestTax"}"#);
+        watchdog.read_go(r#"{"Action":"pass","Package":"ex/cart","Test":"TestTax"}"#);
+        watchdog.read_cargo("test cart::tests::hangs has been running for over 60 seconds");
+        watchdog.state.lock().unwrap().stopped = Some(Reason::TestTimeout(Duration::from_secs(30)));
+
+        let stopped = watchdog.finish().unwrap();
+        assert_eq!(stopped.reason, Reason::TestTimeout(Duration::from_secs(30)));
+        let names: Vec<(&str, &str)> = stopped
+            .hung
+            .iter()
+            .map(|t| (t.suite.as_str(), t.name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![("ex/cart", "TestTotal/empty"), ("cart::tests", "hangs")]
+        );
+        assert_eq!(
+            stopped.hung[0].message.as_deref(),
+            Some("Timed out after 30s")
+        );
+    }
+}
@@ -2,10 +2,14 @@
 //!
 //! Handles communication with the VibeTap SaaS API.
 
-use futures::StreamExt;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
 use thiserror::Error;
 
+use crate::dump::{debug_dir_from_env, Dump};
+use crate::replay::{append_exchange, Exchange, Transport};
+
 #[derive(Error, Debug)]
 pub enum ApiError {
     #[error("HTTP request failed: {0}")]
@@ -22,6 +26,29 @@ pub enum ApiError {
 
     #[error("Quota exceeded")]
     QuotaExceeded,
+
+    #[error("Server error ({status}): {message}")]
+    Server { status: u16, message: String },
+
+    #[error("Replay failed: {0}")]
+    Replay(String),
+}
+
+impl ApiError {
+    /// Whether trying again later may succeed: the server or network failed
+    /// rather than rejecting the request
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ApiError::Request(e) => e.is_connect() || e.is_timeout(),
+            ApiError::Server { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the server couldn't be reached at all
+    pub fn is_offline(&self) -> bool {
+        matches!(self, ApiError::Request(e) if e.is_connect())
+    }
 }
 
 /// API client for VibeTap SaaS
@@ -29,10 +56,96 @@ pub struct ApiClient {
     client: reqwest::Client,
     base_url: String,
     api_key: String,
+    transport: Transport,
+    /// Where generate calls are dumped for debugging, if anywhere
+    debug_dir: Option<PathBuf>,
+}
+
+/// A response read from the network or from a replayed recording
+struct RawResponse {
+    status: reqwest::StatusCode,
+    retry_after: Option<String>,
+    body: RawBody,
+    /// Exchange being captured when recording; written out on drop
+    recording: Option<(PathBuf, Exchange)>,
+}
+
+enum RawBody {
+    Live(reqwest::Response),
+    Replayed(VecDeque<String>),
+}
+
+impl RawResponse {
+    /// Seconds to wait from the Retry-After header
+    fn retry_after_secs(&self) -> u64 {
+        self.retry_after
+            .as_deref()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(60)
+    }
+
+    /// Read the next body chunk, or `None` at the end of the body
+    async fn chunk(&mut self) -> Result<Option<String>, ApiError> {
+        let chunk = match self.body {
+            RawBody::Live(ref mut response) => response
+                .chunk()
+                .await?
+                .map(|bytes| String::from_utf8_lossy(&bytes).to_string()),
+            RawBody::Replayed(ref mut chunks) => chunks.pop_front(),
+        };
+
+        if let (Some(ref chunk), Some((_, ref mut exchange))) = (&chunk, &mut self.recording) {
+            exchange.chunks.push(chunk.clone());
+        }
+
+        Ok(chunk)
+    }
+
+    /// Read the whole body as text
+    async fn text(mut self) -> Result<String, ApiError> {
+        let mut text = String::new();
+        while let Some(chunk) = self.chunk().await? {
+            text.push_str(&chunk);
+        }
+        Ok(text)
+    }
+}
+
+impl Drop for RawResponse {
+    fn drop(&mut self) {
+        if let Some((path, exchange)) = self.recording.take() {
+            append_exchange(&path, &exchange);
+        }
+    }
+}
+
+/// Parse a JSON API envelope, keeping part of the body for diagnostics
+fn parse_envelope<T: DeserializeOwned>(text: &str) -> Result<ApiResponse<T>, ApiError> {
+    serde_json::from_str(text).map_err(|e| ApiError::Api {
+        code: "PARSE_ERROR".to_string(),
+        message: format!(
+            "Failed to parse response: {}. Body: {}",
+            e,
+            text.chars().take(500).collect::<String>()
+        ),
+    })
+}
+
+/// A 5xx response, with the envelope's message when the body has one
+fn server_error(status: reqwest::StatusCode, text: &str) -> ApiError {
+    let message = serde_json::from_str::<ApiResponse<serde_json::Value>>(text)
+        .ok()
+        .and_then(|envelope| envelope.error)
+        .map(|error| error.message)
+        .unwrap_or_else(|| text.chars().take(200).collect());
+    ApiError::Server {
+        status: status.as_u16(),
+        message,
+    }
 }
 
 /// Request to generate tests
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GenerateRequest {
     pub diff: DiffPayload,
@@ -40,9 +153,26 @@ pub struct GenerateRequest {
     pub options: GenerateOptions,
     pub policy_pack_id: Option<String>,
     pub repo_identifier: Option<String>,
+    /// Messages of the most recent commits, newest first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_messages: Option<Vec<String>>,
+    /// Project conventions for generated tests (mocking style, fixture locations, naming)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_instructions: Option<String>,
+    /// Earlier suggestion to rework instead of generating a fresh set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refine: Option<RefineRequest>,
 }
 
-#[derive(Debug, Serialize)]
+/// A suggestion sent back with the user's instruction for changing it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefineRequest {
+    pub previous: TestSuggestion,
+    pub instruction: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DiffPayload {
     pub hunks: Vec<DiffHunk>,
@@ -50,7 +180,7 @@ pub struct DiffPayload {
     pub head_commit: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DiffHunk {
     pub file_path: String,
@@ -59,17 +189,29 @@ pub struct DiffHunk {
     pub new_start: u32,
     pub new_lines: u32,
     pub content: String,
+    /// Enclosing function or class of the hunk, when git can tell
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_header: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FileContext {
     pub path: String,
     pub content: String,
     pub language: Option<String>,
+    /// Line `content` starts at, when the file was cut to fit the context budget
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_line: Option<u32>,
+    /// Whether `content` is only part of the file
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
 }
 
-#[derive(Debug, Serialize)]
+/// Model tiers the API accepts, cheapest first
+pub const MODEL_TIERS: [&str; 3] = ["cheap", "default", "enhanced"];
+
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GenerateOptions {
     pub test_runner: String,
@@ -77,6 +219,9 @@ pub struct GenerateOptions {
     pub include_security: bool,
     pub include_negative_paths: bool,
     pub model_tier: String,
+    /// Path to write the tests to, instead of the server's naming convention
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub test_file: Option<String>,
 }
 
 /// Response from generate endpoint
@@ -166,45 +311,129 @@ impl ApiClient {
             client: reqwest::Client::new(),
             base_url: base_url.into(),
             api_key: api_key.into(),
+            transport: Transport::from_env(),
+            debug_dir: debug_dir_from_env(),
         }
     }
 
+    /// Dump every generate call's request and response under `dir`
+    pub fn with_debug_dir(mut self, dir: PathBuf) -> Self {
+        self.debug_dir = Some(dir);
+        self
+    }
+
+    /// Start a debug dump for a generate call, when dumping is on
+    fn start_dump(&self, request: &GenerateRequest) -> Option<Dump> {
+        let dir = self.debug_dir.as_ref()?;
+        Dump::start(dir, &self.api_key, request)
+    }
+
+    /// Send a request, or serve it from a recording in replay mode
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<Vec<u8>>,
+    ) -> Result<RawResponse, ApiError> {
+        if let Some(replayed) = self.transport.next_replayed(method.as_str(), path) {
+            let exchange = replayed?;
+            return Ok(RawResponse {
+                status: reqwest::StatusCode::from_u16(exchange.status)
+                    .unwrap_or(reqwest::StatusCode::INTERNAL_SERVER_ERROR),
+                retry_after: exchange.retry_after,
+                body: RawBody::Replayed(exchange.chunks.into()),
+                recording: None,
+            });
+        }
+
+        let url = format!("{}{}", self.base_url, path);
+        let mut request = self
+            .client
+            .request(method.clone(), &url)
+            .header("Authorization", format!("Bearer {}", self.api_key));
+        if let Some(body) = body {
+            request = request
+                .header("Content-Type", "application/json")
+                .body(body);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get("Retry-After")
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+
+        let recording = match self.transport {
+            Transport::Live {
+                record_to: Some(ref record_to),
+            } => Some((
+                record_to.clone(),
+                Exchange {
+                    method: method.to_string(),
+                    path: path.to_string(),
+                    status: status.as_u16(),
+                    retry_after: retry_after.clone(),
+                    chunks: Vec::new(),
+                },
+            )),
+            _ => None,
+        };
+
+        Ok(RawResponse {
+            status,
+            retry_after,
+            body: RawBody::Live(response),
+            recording,
+        })
+    }
+
     /// Generate test suggestions from a diff
     pub async fn generate(&self, request: GenerateRequest) -> Result<GenerateResponse, ApiError> {
-        let url = format!("{}/api/v1/generate", self.base_url);
+        let dump = self.start_dump(&request);
+        let mut raw = String::new();
+        let result = self.fetch_generate(request, &mut raw).await;
+        if let Some(dump) = dump {
+            dump.finish(&raw, &result);
+        }
+        result
+    }
+
+    async fn fetch_generate(
+        &self,
+        request: GenerateRequest,
+        raw: &mut String,
+    ) -> Result<GenerateResponse, ApiError> {
+        let body = serde_json::to_vec(&request).map_err(|e| ApiError::Api {
+            code: "SERIALIZE_ERROR".to_string(),
+            message: e.to_string(),
+        })?;
 
         let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
+            .send(reqwest::Method::POST, "/api/v1/generate", Some(body))
             .await?;
 
-        let status = response.status();
+        let status = response.status;
 
         if status == reqwest::StatusCode::UNAUTHORIZED {
             return Err(ApiError::Unauthorized);
         }
 
         if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
-            let retry_after = response
-                .headers()
-                .get("Retry-After")
-                .and_then(|v| v.to_str().ok())
-                .and_then(|v| v.parse().ok())
-                .unwrap_or(60);
-            return Err(ApiError::RateLimited { retry_after });
+            return Err(ApiError::RateLimited {
+                retry_after: response.retry_after_secs(),
+            });
         }
 
         let response_text = response.text().await?;
+        raw.push_str(&response_text);
 
-        let api_response: ApiResponse<GenerateResponse> = serde_json::from_str(&response_text)
-            .map_err(|e| ApiError::Api {
-                code: "PARSE_ERROR".to_string(),
-                message: format!("Failed to parse response: {}. Body: {}", e, &response_text[..response_text.len().min(500)]),
-            })?;
+        if status.is_server_error() {
+            return Err(server_error(status, &response_text));
+        }
+
+        let api_response: ApiResponse<GenerateResponse> = parse_envelope(&response_text)?;
 
         if !api_response.success {
             if let Some(error) = api_response.error {
@@ -229,42 +458,56 @@ impl ApiClient {
     /// Generate test suggestions with streaming SSE response
     /// Calls the callback for each SSE event received
     pub async fn generate_streaming<F>(
+        &self,
+        request: GenerateRequest,
+        on_event: F,
+    ) -> Result<GenerateResponse, ApiError>
+    where
+        F: FnMut(StreamEvent),
+    {
+        let dump = self.start_dump(&request);
+        let mut transcript = String::new();
+        let result = self.stream_generate(request, on_event, &mut transcript).await;
+        if let Some(dump) = dump {
+            dump.finish(&transcript, &result);
+        }
+        result
+    }
+
+    async fn stream_generate<F>(
         &self,
         request: GenerateRequest,
         mut on_event: F,
+        transcript: &mut String,
     ) -> Result<GenerateResponse, ApiError>
     where
         F: FnMut(StreamEvent),
     {
-        let url = format!("{}/api/v1/generate/stream", self.base_url);
         let body = serde_json::to_vec(&request).map_err(|e| ApiError::Api {
             code: "SERIALIZE_ERROR".to_string(),
             message: e.to_string(),
         })?;
 
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .body(body)
-            .send()
+        let mut response = self
+            .send(reqwest::Method::POST, "/api/v1/generate/stream", Some(body))
             .await?;
 
-        let status = response.status();
+        let status = response.status;
 
         if status == reqwest::StatusCode::UNAUTHORIZED {
             return Err(ApiError::Unauthorized);
         }
 
         if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
-            let retry_after = response
-                .headers()
-                .get("Retry-After")
-                .and_then(|v| v.to_str().ok())
-                .and_then(|v| v.parse().ok())
-                .unwrap_or(60);
-            return Err(ApiError::RateLimited { retry_after });
+            return Err(ApiError::RateLimited {
+                retry_after: response.retry_after_secs(),
+            });
+        }
+
+        if status.is_server_error() {
+            let text = response.text().await?;
+            transcript.push_str(&text);
+            return Err(server_error(status, &text));
         }
 
         // Parse SSE stream
@@ -276,11 +519,9 @@ impl ApiClient {
         let mut warning: Option<String> = None;
         let mut buffer = String::new();
 
-        let mut stream = response.bytes_stream();
-
-        while let Some(chunk_result) = stream.next().await {
-            let chunk = chunk_result?;
-            buffer.push_str(&String::from_utf8_lossy(&chunk));
+        while let Some(chunk) = response.chunk().await? {
+            transcript.push_str(&chunk);
+            buffer.push_str(&chunk);
 
             // Process complete SSE events (separated by double newlines)
             while let Some(event_end) = buffer.find("\n\n") {
@@ -377,20 +618,60 @@ impl ApiClient {
 
     /// Query current usage
     pub async fn get_usage(&self) -> Result<UsageResponse, ApiError> {
-        let url = format!("{}/api/v1/usage", self.base_url);
+        let response = self.send(reqwest::Method::GET, "/api/v1/usage", None).await?;
+
+        if response.status == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(ApiError::Unauthorized);
+        }
+
+        let api_response: ApiResponse<UsageResponse> = parse_envelope(&response.text().await?)?;
+
+        api_response
+            .data
+            .ok_or_else(|| ApiError::Api {
+                code: "NO_DATA".to_string(),
+                message: "Response contained no data".to_string(),
+            })
+    }
+
+    /// Get user stats for the stats command
+    pub async fn get_stats(&self) -> Result<StatsResponse, ApiError> {
+        let response = self.send(reqwest::Method::GET, "/api/v1/stats", None).await?;
+
+        if response.status == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(ApiError::Unauthorized);
+        }
+
+        let api_response: ApiResponse<StatsResponse> = parse_envelope(&response.text().await?)?;
 
+        api_response
+            .data
+            .ok_or_else(|| ApiError::Api {
+                code: "NO_DATA".to_string(),
+                message: "Response contained no data".to_string(),
+            })
+    }
+
+    /// List the policy packs available to the user's organization
+    pub async fn list_policy_packs(&self) -> Result<Vec<PolicyPack>, ApiError> {
         let response = self
-            .client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .send()
+            .send(reqwest::Method::GET, "/api/v1/policy-packs", None)
             .await?;
 
-        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        if response.status == reqwest::StatusCode::UNAUTHORIZED {
             return Err(ApiError::Unauthorized);
         }
 
-        let api_response: ApiResponse<UsageResponse> = response.json().await?;
+        let api_response: ApiResponse<Vec<PolicyPack>> = parse_envelope(&response.text().await?)?;
+
+        if !api_response.success {
+            if let Some(error) = api_response.error {
+                return Err(ApiError::Api {
+                    code: error.code,
+                    message: error.message,
+                });
+            }
+        }
 
         api_response
             .data
@@ -400,22 +681,31 @@ impl ApiClient {
             })
     }
 
-    /// Get user stats for the stats command
-    pub async fn get_stats(&self) -> Result<StatsResponse, ApiError> {
-        let url = format!("{}/api/v1/stats", self.base_url);
+    /// Upload a suggestion set and get a shareable link
+    pub async fn share(&self, request: ShareRequest) -> Result<ShareResponse, ApiError> {
+        let body = serde_json::to_vec(&request).map_err(|e| ApiError::Api {
+            code: "SERIALIZE_ERROR".to_string(),
+            message: e.to_string(),
+        })?;
 
         let response = self
-            .client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .send()
+            .send(reqwest::Method::POST, "/api/v1/share", Some(body))
             .await?;
 
-        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        if response.status == reqwest::StatusCode::UNAUTHORIZED {
             return Err(ApiError::Unauthorized);
         }
 
-        let api_response: ApiResponse<StatsResponse> = response.json().await?;
+        let api_response: ApiResponse<ShareResponse> = parse_envelope(&response.text().await?)?;
+
+        if !api_response.success {
+            if let Some(error) = api_response.error {
+                return Err(ApiError::Api {
+                    code: error.code,
+                    message: error.message,
+                });
+            }
+        }
 
         api_response
             .data
@@ -424,6 +714,99 @@ impl ApiClient {
                 message: "Response contained no data".to_string(),
             })
     }
+
+    /// Report what was done with suggestions, so acceptance can be measured
+    pub async fn send_feedback(&self, request: FeedbackRequest) -> Result<(), ApiError> {
+        let body = serde_json::to_vec(&request).map_err(|e| ApiError::Api {
+            code: "SERIALIZE_ERROR".to_string(),
+            message: e.to_string(),
+        })?;
+
+        let response = self
+            .send(reqwest::Method::POST, "/api/v1/feedback", Some(body))
+            .await?;
+
+        if response.status == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(ApiError::Unauthorized);
+        }
+
+        let api_response: ApiResponse<serde_json::Value> =
+            parse_envelope(&response.text().await?)?;
+
+        match api_response.error {
+            Some(error) if !api_response.success => Err(ApiError::Api {
+                code: error.code,
+                message: error.message,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A set of guardrail rules generation can be asked to enforce
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyPack {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Whether the pack belongs to the user's organization rather than being built in
+    #[serde(default)]
+    pub organization: bool,
+}
+
+/// Request to publish a suggestion set for sharing
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareRequest {
+    pub suggestions: GenerateResponse,
+    pub session: Option<String>,
+    pub repo_identifier: Option<String>,
+}
+
+/// What was done with a suggestion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedbackAction {
+    Applied,
+    /// Applied after being changed in an editor
+    Edited,
+    /// Selected but not applied
+    Skipped,
+    Reverted,
+    /// Its test passed when `vibetap run` ran it
+    Passed,
+    /// Its test failed when `vibetap run` ran it
+    Failed,
+}
+
+/// Feedback on one suggestion
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedbackEvent {
+    pub suggestion_id: String,
+    pub action: FeedbackAction,
+    /// Why a suggestion was skipped, e.g. "declined" or "failed_check",
+    /// or the start of its test's failure output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Request to the feedback endpoint
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedbackRequest {
+    pub events: Vec<FeedbackEvent>,
+}
+
+/// Response from the share endpoint
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareResponse {
+    pub id: String,
+    pub url: String,
+    pub expires_at: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -483,6 +866,11 @@ pub struct MonthlyStats {
     pub limit: u32,
     pub security_issues_caught: u32,
     pub tests_applied: u32,
+    /// Applied tests whose last run passed, and failed
+    #[serde(default)]
+    pub tests_passing: Option<u32>,
+    #[serde(default)]
+    pub tests_failing: Option<u32>,
     pub acceptance_rate: f64,
 }
 
@@ -492,6 +880,10 @@ pub struct AllTimeStats {
     pub total_generations: u32,
     pub total_security_issues: u32,
     pub total_tests_applied: u32,
+    #[serde(default)]
+    pub total_tests_passing: Option<u32>,
+    #[serde(default)]
+    pub total_tests_failing: Option<u32>,
     pub top_framework: Option<String>,
 }
 
@@ -0,0 +1,46 @@
+//! Backends that turn a generate request into suggestions
+//!
+//! The VibeTap API is the default. The other backends build the prompt
+//! locally (see [`crate::prompt`]) and send it to a model the user talks to
+//! directly: a provider account with their own key, or a model served by
+//! Ollama on their own machine, which needs no network access at all.
+
+use futures::future::BoxFuture;
+
+use crate::api::{ApiClient, ApiError, GenerateRequest, GenerateResponse};
+
+/// Backends that can be selected with `--backend`
+pub const BACKENDS: [&str; 3] = ["vibetap", "byok", "ollama"];
+
+/// Something that can answer a generate request
+pub trait GenerateBackend: Send + Sync {
+    /// Short name shown to the user, e.g. "openai" or "ollama"
+    fn name(&self) -> &str;
+
+    /// Model that will answer `request`
+    fn model_for(&self, request: &GenerateRequest) -> String;
+
+    /// Generate suggestions for a request
+    fn generate<'a>(
+        &'a self,
+        request: &'a GenerateRequest,
+    ) -> BoxFuture<'a, Result<GenerateResponse, ApiError>>;
+}
+
+impl GenerateBackend for ApiClient {
+    fn name(&self) -> &str {
+        "vibetap"
+    }
+
+    /// The API picks the model for the requested tier
+    fn model_for(&self, request: &GenerateRequest) -> String {
+        request.options.model_tier.clone()
+    }
+
+    fn generate<'a>(
+        &'a self,
+        request: &'a GenerateRequest,
+    ) -> BoxFuture<'a, Result<GenerateResponse, ApiError>> {
+        Box::pin(ApiClient::generate(self, request.clone()))
+    }
+}
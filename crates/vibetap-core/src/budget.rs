@@ -0,0 +1,200 @@
+//! Local token and generation budgets
+//!
+//! Every generation is appended to a usage log in the global config
+//! directory. Budgets are monthly limits checked against that log, either
+//! for one repository (project config) or across all of them (global
+//! config), independent of the server-side plan.
+
+use chrono::{Datelike, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::config::{Config, ConfigError};
+
+/// Monthly limits and warning thresholds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetConfig {
+    /// Maximum tokens per calendar month
+    #[serde(default, alias = "monthly_tokens", skip_serializing_if = "Option::is_none")]
+    pub monthly_tokens: Option<u64>,
+    /// Maximum generations per calendar month
+    #[serde(default, alias = "monthly_generations", skip_serializing_if = "Option::is_none")]
+    pub monthly_generations: Option<u64>,
+    /// Percentages of the budget at which to warn
+    #[serde(default = "default_warn_at", alias = "warn_at")]
+    pub warn_at: Vec<u8>,
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            monthly_tokens: None,
+            monthly_generations: None,
+            warn_at: default_warn_at(),
+        }
+    }
+}
+
+fn default_warn_at() -> Vec<u8> {
+    vec![50, 80, 90]
+}
+
+/// One generation recorded in the usage log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageEntry {
+    pub timestamp: i64,
+    pub repo: String,
+    pub tokens: u64,
+    pub model: String,
+}
+
+/// Consumption over a period
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Usage {
+    pub tokens: u64,
+    pub generations: u64,
+}
+
+/// Which budget a status refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetScope {
+    Repo,
+    Global,
+}
+
+impl BudgetScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetScope::Repo => "repository",
+            BudgetScope::Global => "global",
+        }
+    }
+}
+
+/// A budget compared against this month's usage
+#[derive(Debug, Clone)]
+pub struct BudgetStatus {
+    pub scope: BudgetScope,
+    pub budget: BudgetConfig,
+    pub used: Usage,
+}
+
+impl BudgetStatus {
+    /// Fraction of the tighter of the two limits consumed (0.0 when unlimited)
+    pub fn fraction(&self) -> f64 {
+        let tokens = self
+            .budget
+            .monthly_tokens
+            .map(|limit| self.used.tokens as f64 / limit.max(1) as f64);
+        let generations = self
+            .budget
+            .monthly_generations
+            .map(|limit| self.used.generations as f64 / limit.max(1) as f64);
+        tokens.into_iter().chain(generations).fold(0.0, f64::max)
+    }
+
+    /// Whether either limit has been reached
+    pub fn is_exhausted(&self) -> bool {
+        self.fraction() >= 1.0
+    }
+
+    /// Highest warning threshold crossed when moving from `before` to this status
+    pub fn crossed_threshold(&self, before: &BudgetStatus) -> Option<u8> {
+        let (from, to) = (before.fraction() * 100.0, self.fraction() * 100.0);
+        self.budget
+            .warn_at
+            .iter()
+            .copied()
+            .filter(|&t| from < t as f64 && to >= t as f64)
+            .max()
+    }
+}
+
+/// Path of the usage log
+pub fn usage_log_path() -> PathBuf {
+    Config::global_config_dir().join("usage.jsonl")
+}
+
+/// Append a generation to the usage log
+pub fn record_usage(entry: &UsageEntry) -> Result<(), ConfigError> {
+    std::fs::create_dir_all(Config::global_config_dir())?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(usage_log_path())?;
+    let line = serde_json::to_string(entry).map_err(|e| ConfigError::Parse(e.to_string()))?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+/// Sum this calendar month's usage, for one repository or all of them
+pub fn month_usage(repo: Option<&str>) -> Result<Usage, ConfigError> {
+    let usage = entries_since(repo, month_start())?
+        .into_iter()
+        .fold(Usage::default(), |acc, e| Usage {
+            tokens: acc.tokens + e.tokens,
+            generations: acc.generations + 1,
+        });
+    Ok(usage)
+}
+
+/// Times of the generations logged after `since`, oldest first
+pub fn generations_since(repo: Option<&str>, since: i64) -> Result<Vec<i64>, ConfigError> {
+    let mut times: Vec<i64> = entries_since(repo, since + 1)?
+        .into_iter()
+        .map(|e| e.timestamp)
+        .collect();
+    times.sort_unstable();
+    Ok(times)
+}
+
+/// Usage log entries from `start` on, for one repository or all of them
+fn entries_since(repo: Option<&str>, start: i64) -> Result<Vec<UsageEntry>, ConfigError> {
+    let path = usage_log_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = std::fs::read_to_string(path)?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<UsageEntry>(line).ok())
+        .filter(|e| e.timestamp >= start && repo.is_none_or(|r| e.repo == r))
+        .collect())
+}
+
+/// Statuses for every budget that applies to `repo` (repository first, then global)
+pub fn budget_statuses(config: &Config, repo: &str) -> Result<Vec<BudgetStatus>, ConfigError> {
+    let mut statuses = Vec::new();
+
+    if let Some(budget) = config.project.as_ref().and_then(|p| p.budget.clone()) {
+        statuses.push(BudgetStatus {
+            scope: BudgetScope::Repo,
+            budget,
+            used: month_usage(Some(repo))?,
+        });
+    }
+
+    if let Some(ref budget) = config.global.budget {
+        statuses.push(BudgetStatus {
+            scope: BudgetScope::Global,
+            budget: budget.clone(),
+            used: month_usage(None)?,
+        });
+    }
+
+    Ok(statuses)
+}
+
+/// Unix timestamp of the start of the current month in local time
+fn month_start() -> i64 {
+    let now = Local::now();
+    Local
+        .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .earliest()
+        .map(|start| start.timestamp())
+        .unwrap_or(0)
+}
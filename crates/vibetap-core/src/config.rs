@@ -5,9 +5,12 @@
 //! - Project config: .vibetap/config.json
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+use crate::budget::BudgetConfig;
+
 #[derive(Error, Debug)]
 pub enum ConfigError {
     #[error("Failed to read config file: {0}")]
@@ -40,6 +43,56 @@ pub struct AuthTokens {
 pub struct GlobalConfig {
     pub api_url: Option<String>,
     pub tokens: Option<AuthTokens>,
+    /// Plain-text output without spinners, borders, or emoji
+    #[serde(default)]
+    pub accessible: Option<bool>,
+    /// Repositories operated on together with `--workspace`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub workspace: Vec<PathBuf>,
+    /// Monthly usage budget across all repositories
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<BudgetConfig>,
+    /// Your own model provider key, for generating without the VibeTap API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byok: Option<ByokConfig>,
+    /// A local Ollama server, for generating without any network access
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ollama: Option<OllamaConfig>,
+    /// Report which suggestions are applied, edited, skipped or reverted
+    /// (on unless set to false; `VIBETAP_NO_FEEDBACK=1` also turns it off)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feedback: Option<bool>,
+}
+
+/// A model provider key used to generate directly (see `vibetap byok`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ByokConfig {
+    /// "openai" or "anthropic"
+    pub provider: String,
+    pub api_key: String,
+    /// Model to use instead of the provider default for the tier
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Endpoint to use instead of the provider's own, e.g. a proxy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Always generate directly, never through the VibeTap API
+    #[serde(default)]
+    pub always: bool,
+}
+
+/// A local Ollama server to generate with (`--backend ollama`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OllamaConfig {
+    /// Server address; defaults to OLLAMA_HOST, then http://localhost:11434
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Model to use, e.g. qwen2.5-coder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Always generate with Ollama, never through the VibeTap API
+    #[serde(default)]
+    pub always: bool,
 }
 
 /// Project-level configuration (stored in .vibetap/)
@@ -49,8 +102,24 @@ pub struct ProjectConfig {
     pub version: String,
     pub project_type: String,
     pub test_runner: String,
+    #[serde(alias = "watch_mode")]
     pub watch_mode: WatchModeConfig,
     pub generation: GenerationConfig,
+    /// Monthly usage budget for this repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<BudgetConfig>,
+    /// How changes are diffed before generation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff: Option<DiffConfig>,
+    /// Files to always or never send as context
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<ContextConfig>,
+    /// Defaults for `vibetap apply`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub apply: Option<ApplyConfig>,
+    /// Defaults for `vibetap run`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run: Option<RunConfig>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -58,6 +127,24 @@ pub struct ProjectConfig {
 pub struct WatchModeConfig {
     pub enabled: bool,
     pub debounce_ms: u64,
+    /// Show a desktop notification when watch gets suggestions
+    #[serde(default)]
+    pub notify: bool,
+    /// Directories watch looks at instead of the whole repository
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Generations watch may start in any hour, counting every generation
+    /// in the repository
+    #[serde(default, alias = "max_generations_per_hour", skip_serializing_if = "Option::is_none")]
+    pub max_generations_per_hour: Option<u32>,
+    /// Command run whenever watch gets suggestions, with a summary in
+    /// `VIBETAP_*` variables and the suggestions as JSON on stdin
+    #[serde(default, alias = "on_suggestions", skip_serializing_if = "Option::is_none")]
+    pub on_suggestions: Option<String>,
+    /// Extensions of the files whose edits trigger a generation, replacing
+    /// the built-in list of source extensions (empty: any file)
+    #[serde(default, alias = "trigger_extensions", skip_serializing_if = "Option::is_none")]
+    pub trigger_extensions: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -66,6 +153,96 @@ pub struct GenerationConfig {
     pub max_suggestions: u32,
     pub include_security: bool,
     pub include_negative_paths: bool,
+    /// Unchanged lines of context around each hunk sent for generation
+    #[serde(default, alias = "diff_context_lines", skip_serializing_if = "Option::is_none")]
+    pub diff_context_lines: Option<u32>,
+    /// Changes at most this many unchanged lines apart are sent as one hunk
+    #[serde(default, alias = "diff_interhunk_lines", skip_serializing_if = "Option::is_none")]
+    pub diff_interhunk_lines: Option<u32>,
+    /// Send recent commit messages so suggestions can follow the stated intent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_commit_messages: Option<bool>,
+    /// Model tier to request unless `--model` is given: cheap, default or enhanced
+    #[serde(default, alias = "model_tier", skip_serializing_if = "Option::is_none")]
+    pub model_tier: Option<String>,
+    /// Times a rate-limited generation is retried (0 turns retrying off)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+    /// Policy pack to apply unless `--policy` is given (see `vibetap policy list`)
+    #[serde(default, alias = "policy_pack", skip_serializing_if = "Option::is_none")]
+    pub policy_pack: Option<String>,
+    /// Suggestions below this confidence (0-1) are hidden unless `--min-confidence` is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_confidence: Option<f64>,
+    /// Requests in flight at once when a large diff is split (defaults to
+    /// [`crate::orchestrator::DEFAULT_CONCURRENCY`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub concurrency: Option<usize>,
+}
+
+/// Whitespace handling for diffs, so reformatting alone does not trigger suggestions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffConfig {
+    #[serde(default)]
+    pub ignore_whitespace: bool,
+    #[serde(default)]
+    pub ignore_whitespace_change: bool,
+    #[serde(default)]
+    pub ignore_blank_lines: bool,
+}
+
+/// Defaults for applying suggestions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyConfig {
+    /// Stage written test files unless `--no-stage` is given
+    #[serde(default)]
+    pub stage: bool,
+    /// Formatter run on written test files, with their paths appended, e.g.
+    /// "npx prettier --write"; detected from the project when unset, and an
+    /// empty string turns formatting off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format_command: Option<String>,
+    /// Where test files go, by language or extension, e.g.
+    /// "typescript": "src/{dir}/__tests__/{name}.test.ts"
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub test_path_templates: BTreeMap<String, String>,
+    /// Applied records kept for `vibetap revert`; older ones are dropped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_limit: Option<usize>,
+}
+
+/// Defaults for running tests
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunConfig {
+    /// Longest a whole run may take before it is stopped, e.g. "10m",
+    /// unless `--timeout` is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    /// Longest a single test may take, e.g. "30s", unless `--test-timeout`
+    /// is given
+    #[serde(default, alias = "test_timeout", skip_serializing_if = "Option::is_none")]
+    pub test_timeout: Option<String>,
+}
+
+/// Overrides for which files are sent as context alongside the diff
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextConfig {
+    /// Globs for files always sent, such as shared fixtures or types
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Globs for files never sent, such as generated code; wins over `include`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// Most context files sent (defaults to [`crate::context::DEFAULT_MAX_CONTEXT_FILES`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_files: Option<usize>,
+    /// Total context size in bytes (defaults to [`crate::context::DEFAULT_MAX_CONTEXT_BYTES`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<usize>,
 }
 
 /// Combined configuration from global and project sources
@@ -145,35 +322,48 @@ impl Config {
         serde_json::from_str(&content).map_err(|e| ConfigError::Parse(e.to_string()))
     }
 
-    /// Save authentication tokens
-    pub fn save_tokens(tokens: &AuthTokens, api_url: &str) -> Result<(), ConfigError> {
+    /// Save the global configuration file
+    pub fn save_global(config: &GlobalConfig) -> Result<(), ConfigError> {
         let dir = Self::global_config_dir();
         std::fs::create_dir_all(&dir)?;
 
-        let config = GlobalConfig {
-            api_url: Some(api_url.to_string()),
-            tokens: Some(tokens.clone()),
-        };
-
         let path = Self::global_config_path();
-        let content = toml::to_string_pretty(&config).map_err(|e| ConfigError::Parse(e.to_string()))?;
+        let content = toml::to_string_pretty(config).map_err(|e| ConfigError::Parse(e.to_string()))?;
         std::fs::write(path, content)?;
 
         Ok(())
     }
 
+    /// Save the project configuration file
+    pub fn save_project(config: &ProjectConfig) -> Result<(), ConfigError> {
+        std::fs::create_dir_all(".vibetap")?;
+        let content =
+            serde_json::to_string_pretty(config).map_err(|e| ConfigError::Parse(e.to_string()))?;
+        std::fs::write(".vibetap/config.json", content)?;
+
+        Ok(())
+    }
+
+    /// Save authentication tokens
+    pub fn save_tokens(tokens: &AuthTokens, api_url: &str) -> Result<(), ConfigError> {
+        // Preserve unrelated settings already in the global config
+        let mut config = Self::load_global().unwrap_or_default();
+        config.api_url = Some(api_url.to_string());
+        config.tokens = Some(tokens.clone());
+
+        Self::save_global(&config)
+    }
+
     /// Clear authentication tokens (logout)
     pub fn clear_tokens() -> Result<(), ConfigError> {
         let path = Self::global_config_path();
 
         if path.exists() {
-            let config = GlobalConfig {
-                api_url: None,
-                tokens: None,
-            };
+            let mut config = Self::load_global().unwrap_or_default();
+            config.api_url = None;
+            config.tokens = None;
 
-            let content = toml::to_string_pretty(&config).map_err(|e| ConfigError::Parse(e.to_string()))?;
-            std::fs::write(path, content)?;
+            Self::save_global(&config)?;
         }
 
         Ok(())
@@ -206,6 +396,11 @@ impl Config {
 
     /// Get a valid access token, refreshing if necessary
     pub async fn get_valid_access_token(&mut self) -> Result<String, ConfigError> {
+        // Replayed responses never reach the server, so no login or refresh is needed
+        if crate::replay::replay_path().is_some() {
+            return Ok(self.access_token().unwrap_or("replay").to_string());
+        }
+
         if !self.is_authenticated() {
             return Err(ConfigError::NotAuthenticated);
         }
@@ -338,12 +533,30 @@ impl Default for ProjectConfig {
             watch_mode: WatchModeConfig {
                 enabled: true,
                 debounce_ms: 2000,
+                notify: false,
+                paths: Vec::new(),
+                max_generations_per_hour: None,
+                on_suggestions: None,
+                trigger_extensions: None,
             },
             generation: GenerationConfig {
                 max_suggestions: 3,
                 include_security: true,
                 include_negative_paths: true,
+                diff_context_lines: None,
+                diff_interhunk_lines: None,
+                include_commit_messages: None,
+                model_tier: None,
+                max_retries: None,
+                policy_pack: None,
+                min_confidence: None,
+                concurrency: None,
             },
+            budget: None,
+            diff: None,
+            context: None,
+            apply: None,
+            run: None,
         }
     }
 }
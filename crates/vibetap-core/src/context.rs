@@ -0,0 +1,170 @@
+//! Fitting context files into a size budget
+//!
+//! Changed files get the budget first, then everything else (imports,
+//! dependents, includes). Within each group smaller files are served first
+//! and larger ones split what is left, so one huge file cannot crowd out the
+//! rest. Files that don't fit are cut at a line boundary, starting a little
+//! above the first changed line so the code under test survives.
+
+/// Default number of context files sent with a request
+pub const DEFAULT_MAX_CONTEXT_FILES: usize = 10;
+
+/// Default total size of context sent with a request
+pub const DEFAULT_MAX_CONTEXT_BYTES: usize = 256 * 1024;
+
+/// Lines kept above the first changed line when a changed file is cut
+const LINES_BEFORE_CHANGE: usize = 20;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ContextBudget {
+    pub max_files: usize,
+    pub max_bytes: usize,
+}
+
+impl Default for ContextBudget {
+    fn default() -> Self {
+        Self {
+            max_files: DEFAULT_MAX_CONTEXT_FILES,
+            max_bytes: DEFAULT_MAX_CONTEXT_BYTES,
+        }
+    }
+}
+
+/// A file that could be sent as context
+#[derive(Debug, Clone)]
+pub struct Candidate<'a> {
+    pub path: &'a str,
+    pub content: &'a str,
+    /// Whether the file is part of the diff
+    pub changed: bool,
+    /// First changed line (1-based) in the new version of the file
+    pub first_changed_line: Option<u32>,
+}
+
+/// The part of a file that fits the budget
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FittedFile {
+    pub path: String,
+    pub content: String,
+    /// Line the content starts at, when it does not start at the top of the file
+    pub start_line: Option<u32>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Allocation {
+    /// Files to send, in the order they were offered
+    pub files: Vec<FittedFile>,
+    /// Files sent only in part
+    pub truncated: Vec<String>,
+    /// Files left out entirely
+    pub dropped: Vec<String>,
+}
+
+/// Decide how much of each candidate to send
+pub fn allocate(candidates: &[Candidate], budget: &ContextBudget) -> Allocation {
+    // Changed files first, then smallest first
+    let mut order: Vec<usize> = (0..candidates.len()).collect();
+    order.sort_by_key(|&i| (!candidates[i].changed, candidates[i].content.len()));
+    let (kept, over_limit) = order.split_at(order.len().min(budget.max_files));
+
+    let mut shares = vec![0usize; candidates.len()];
+    let mut remaining = budget.max_bytes;
+    for changed in [true, false] {
+        let group: Vec<usize> = kept
+            .iter()
+            .copied()
+            .filter(|&i| candidates[i].changed == changed)
+            .collect();
+        for (served, &i) in group.iter().enumerate() {
+            let fair_share = remaining / (group.len() - served);
+            shares[i] = candidates[i].content.len().min(fair_share);
+            remaining -= shares[i];
+        }
+    }
+
+    let mut allocation = Allocation::default();
+    for (i, candidate) in candidates.iter().enumerate() {
+        if over_limit.contains(&i) {
+            allocation.dropped.push(candidate.path.to_string());
+            continue;
+        }
+        if shares[i] >= candidate.content.len() {
+            allocation.files.push(FittedFile {
+                path: candidate.path.to_string(),
+                content: candidate.content.to_string(),
+                start_line: None,
+                truncated: false,
+            });
+            continue;
+        }
+        match cut(candidate, shares[i]) {
+            Some(file) => {
+                allocation.truncated.push(file.path.clone());
+                allocation.files.push(file);
+            }
+            None => allocation.dropped.push(candidate.path.to_string()),
+        }
+    }
+    allocation
+}
+
+/// Whole lines of `candidate` fitting in `bytes`, around its first change
+fn cut(candidate: &Candidate, bytes: usize) -> Option<FittedFile> {
+    let lines: Vec<&str> = candidate.content.split_inclusive('\n').collect();
+    let mut start = candidate
+        .first_changed_line
+        .map(|line| (line as usize).saturating_sub(1 + LINES_BEFORE_CHANGE))
+        .unwrap_or(0)
+        .min(lines.len().saturating_sub(1));
+
+    let mut end = start;
+    let mut size = 0;
+    while end < lines.len() && size + lines[end].len() <= bytes {
+        size += lines[end].len();
+        end += 1;
+    }
+    // Near the end of the file, spend what is left on lines above the window
+    while start > 0 && size + lines[start - 1].len() <= bytes {
+        start -= 1;
+        size += lines[start].len();
+    }
+    if end == start {
+        return None;
+    }
+
+    Some(FittedFile {
+        path: candidate.path.to_string(),
+        content: lines[start..end].concat(),
+        start_line: (start > 0).then_some(start as u32 + 1),
+        truncated: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocate_prefers_changed_and_small_files() {
+        let big: String = (1..=100).map(|i| format!("line {}\n", i)).collect();
+        let small = "export const x = 1;\n";
+        let candidates = [
+            Candidate { path: "big.ts", content: &big, changed: true, first_changed_line: Some(90) },
+            Candidate { path: "small.ts", content: small, changed: true, first_changed_line: Some(1) },
+            Candidate { path: "extra.ts", content: small, changed: false, first_changed_line: None },
+        ];
+        let budget = ContextBudget { max_files: 2, max_bytes: 200 };
+
+        let allocation = allocate(&candidates, &budget);
+        assert_eq!(allocation.dropped, vec!["extra.ts"]);
+        assert_eq!(allocation.truncated, vec!["big.ts"]);
+        assert_eq!(allocation.files[1].content, small);
+
+        let fitted = &allocation.files[0];
+        assert!(fitted.content.len() <= 200 && fitted.content.ends_with('\n'));
+        // The window reaches the changed line instead of keeping the top of the file
+        assert!(fitted.content.contains("line 90\n"));
+        assert!(fitted.start_line.is_some_and(|line| line > 1));
+    }
+}
@@ -0,0 +1,338 @@
+//! Import analysis: what a changed file uses, and its "blast radius"
+//!
+//! Scans the project's JavaScript/TypeScript and Python sources for local
+//! imports in both directions, so for any changed file we can tell which
+//! project files it imports and which other files import it. Only relative
+//! JS/TS specifiers and project-local Python modules are resolved; packages
+//! are ignored. What a few files import can be found without scanning the
+//! rest of the project; what imports them can't.
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::LazyLock;
+
+/// Files larger than this are not scanned for imports
+const MAX_SCAN_BYTES: u64 = 1024 * 1024;
+
+const JS_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs", "mts", "cts"];
+
+static JS_IMPORT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?:import|export)\s[^'";]*?\bfrom\s*['"]([^'"]+)['"]|\bimport\s*['"]([^'"]+)['"]|\b(?:require|import)\(\s*['"]([^'"]+)['"]\s*\)"#,
+    )
+    .unwrap()
+});
+static PY_FROM_IMPORT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^\s*from\s+(\.*)([\w.]*)\s+import\s+\(?([\w, *]+)").unwrap()
+});
+static PY_IMPORT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*import\s+([\w., ]+)$").unwrap());
+
+/// Which project files import which
+///
+/// All paths are relative to the project root and use `/` separators.
+#[derive(Debug, Default)]
+pub struct ImportGraph {
+    /// Keyed by the imported file
+    importers: HashMap<String, BTreeSet<String>>,
+    /// Keyed by the importing file
+    imports: HashMap<String, BTreeSet<String>>,
+}
+
+impl ImportGraph {
+    /// Scan every source file under `root` not excluded by `.gitignore` or `.vibetapignore`
+    pub fn build(root: &Path) -> Self {
+        let files: Vec<String> = ignore::WalkBuilder::new(root)
+            .add_custom_ignore_filename(".vibetapignore")
+            .build()
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+            .filter(|e| is_source(e.path()))
+            .filter(|e| e.metadata().is_ok_and(|m| m.len() <= MAX_SCAN_BYTES))
+            .filter_map(|e| relative(root, e.path()))
+            .collect();
+        let known: HashSet<&str> = files.iter().map(String::as_str).collect();
+
+        let mut graph = ImportGraph::default();
+        for file in &files {
+            graph.scan(root, file, &|path| known.contains(path));
+        }
+        graph
+    }
+
+    /// Scan just `files`, relative to `root`, for what they import
+    ///
+    /// Enough for [`direct_imports`](Self::direct_imports) of those files;
+    /// files that import them are not found. Imports are resolved against
+    /// the files on disk, leaving out those ignored by the root
+    /// `.gitignore` or `.vibetapignore`.
+    pub fn of_files(root: &Path, files: &[String]) -> Self {
+        let ignored = root_ignore_rules(root);
+        let exists = |path: &str| {
+            is_source(Path::new(path))
+                && !ignored.matched_path_or_any_parents(path, false).is_ignore()
+                && std::fs::metadata(root.join(path))
+                    .is_ok_and(|m| m.is_file() && m.len() <= MAX_SCAN_BYTES)
+        };
+
+        let mut graph = ImportGraph::default();
+        for file in files.iter().map(|f| normalize_str(f)) {
+            if exists(&file) {
+                graph.scan(root, &file, &exists);
+            }
+        }
+        graph
+    }
+
+    /// Record what `file` imports, resolved against the files `known` accepts
+    fn scan(&mut self, root: &Path, file: &str, known: &dyn Fn(&str) -> bool) {
+        let Ok(content) = std::fs::read_to_string(root.join(file)) else {
+            return;
+        };
+        for target in imports_of(file, &content, known) {
+            if target != file {
+                self.imports.entry(file.to_string()).or_default().insert(target.clone());
+                self.importers.entry(target).or_default().insert(file.to_string());
+            }
+        }
+    }
+
+    /// Files that directly import `path`
+    pub fn dependents(&self, path: &str) -> Vec<&str> {
+        self.importers
+            .get(normalize_str(path).as_str())
+            .map(|set| set.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// Project files that `path` directly imports
+    pub fn imports(&self, path: &str) -> Vec<&str> {
+        self.imports
+            .get(normalize_str(path).as_str())
+            .map(|set| set.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// Files directly imported by any of `changed`, excluding the changed files themselves
+    ///
+    /// Ordered by how many of the changed files import them, most first.
+    pub fn direct_imports(&self, changed: &[String]) -> Vec<String> {
+        let changed: HashSet<String> = changed.iter().map(|p| normalize_str(p)).collect();
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for path in &changed {
+            for import in self.imports(path) {
+                if !changed.contains(import) {
+                    *counts.entry(import).or_default() += 1;
+                }
+            }
+        }
+        let mut result: Vec<(&str, usize)> = counts.into_iter().collect();
+        result.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        result.into_iter().map(|(path, _)| path.to_string()).collect()
+    }
+
+    /// Direct dependents of any of `changed`, excluding the changed files themselves
+    pub fn reverse_dependencies(&self, changed: &[String]) -> Vec<String> {
+        let changed: HashSet<String> = changed.iter().map(|p| normalize_str(p)).collect();
+        let mut result = BTreeSet::new();
+        for path in &changed {
+            for dependent in self.dependents(path) {
+                if !changed.contains(dependent) {
+                    result.insert(dependent.to_string());
+                }
+            }
+        }
+        result.into_iter().collect()
+    }
+}
+
+/// Whether imports are read from files like `path`
+fn is_source(path: &Path) -> bool {
+    let ext = path.extension().and_then(|x| x.to_str()).unwrap_or("");
+    ext == "py" || JS_EXTENSIONS.contains(&ext)
+}
+
+/// `.gitignore` and `.vibetapignore` rules at the top of `root`
+fn root_ignore_rules(root: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    for name in [".gitignore", ".vibetapignore"] {
+        let path = root.join(name);
+        if path.is_file() {
+            let _ = builder.add(path);
+        }
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+/// Project files imported by `file`, resolved against the files `known` accepts
+fn imports_of(file: &str, content: &str, known: &dyn Fn(&str) -> bool) -> Vec<String> {
+    let dir = Path::new(file).parent().unwrap_or(Path::new(""));
+    let extension = Path::new(file).extension().and_then(|e| e.to_str()).unwrap_or("");
+
+    let mut found = Vec::new();
+    if extension == "py" {
+        for caps in PY_FROM_IMPORT.captures_iter(content) {
+            let dots = caps[1].len();
+            let module = &caps[2];
+            let names: Vec<&str> = caps[3].split(',').map(|n| n.trim()).collect();
+            let bases = python_bases(dir, dots);
+            for base in &bases {
+                found.extend(resolve_python(base, module, known));
+                // `from pkg import module` imports a file too
+                for name in names.iter().filter(|n| !n.is_empty() && **n != "*") {
+                    let name = name.split_whitespace().next().unwrap_or("");
+                    let submodule = if module.is_empty() {
+                        name.to_string()
+                    } else {
+                        format!("{}.{}", module, name)
+                    };
+                    found.extend(resolve_python(base, &submodule, known));
+                }
+            }
+        }
+        for caps in PY_IMPORT.captures_iter(content) {
+            for item in caps[1].split(',') {
+                let module = item.split_whitespace().next().unwrap_or("");
+                for base in python_bases(dir, 0) {
+                    found.extend(resolve_python(&base, module, known));
+                }
+            }
+        }
+    } else {
+        for caps in JS_IMPORT.captures_iter(content) {
+            let spec = caps
+                .get(1)
+                .or_else(|| caps.get(2))
+                .or_else(|| caps.get(3))
+                .map(|m| m.as_str())
+                .unwrap_or("");
+            if spec.starts_with("./") || spec.starts_with("../") {
+                found.extend(resolve_js(&dir.join(spec), known));
+            }
+        }
+    }
+
+    found.sort();
+    found.dedup();
+    found
+}
+
+/// Directories a Python module path may be relative to
+fn python_bases(dir: &Path, dots: usize) -> Vec<PathBuf> {
+    if dots == 0 {
+        // Absolute imports resolve from the root or a src/ layout
+        return vec![PathBuf::new(), PathBuf::from("src")];
+    }
+    let mut base = dir.to_path_buf();
+    for _ in 1..dots {
+        base.pop();
+    }
+    vec![base]
+}
+
+fn resolve_python(base: &Path, module: &str, known: &dyn Fn(&str) -> bool) -> Option<String> {
+    if module.is_empty() {
+        return None;
+    }
+    let path = base.join(module.replace('.', "/"));
+    [path.with_extension("py"), path.join("__init__.py")]
+        .into_iter()
+        .map(|candidate| normalize(&candidate))
+        .find(|candidate| known(candidate))
+}
+
+fn resolve_js(spec: &Path, known: &dyn Fn(&str) -> bool) -> Option<String> {
+    let base = normalize(spec);
+    let mut candidates = vec![base.clone()];
+
+    // TypeScript sources are imported with the extension of their compiled output
+    for (compiled, source) in [(".js", &["ts", "tsx"][..]), (".mjs", &["mts"]), (".cjs", &["cts"])] {
+        if let Some(stem) = base.strip_suffix(compiled) {
+            candidates.extend(source.iter().map(|ext| format!("{}.{}", stem, ext)));
+        }
+    }
+    candidates.extend(JS_EXTENSIONS.iter().map(|ext| format!("{}.{}", base, ext)));
+    candidates.extend(JS_EXTENSIONS.iter().map(|ext| format!("{}/index.{}", base, ext)));
+
+    candidates.into_iter().find(|c| known(c))
+}
+
+fn relative(root: &Path, path: &Path) -> Option<String> {
+    path.strip_prefix(root).ok().map(normalize)
+}
+
+fn normalize_str(path: &str) -> String {
+    normalize(Path::new(path))
+}
+
+/// Resolve `.` and `..` lexically and join with `/`
+fn normalize(path: &Path) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+            Component::ParentDir => {
+                parts.pop();
+            }
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+        }
+    }
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolves_js_and_python_imports() {
+        let known: HashSet<&str> = [
+            "src/math.ts",
+            "src/util/index.ts",
+            "app/models.py",
+            "app/__init__.py",
+        ]
+        .into_iter()
+        .collect();
+
+        let js = "import { add } from './math.js';\nconst u = require('../src/util');\nimport React from 'react';\n";
+        assert_eq!(
+            imports_of("src/calc.ts", js, &|path| known.contains(path)),
+            vec!["src/math.ts", "src/util/index.ts"]
+        );
+
+        let py = "from .models import User\nimport app\nimport os\n";
+        assert_eq!(
+            imports_of("app/views.py", py, &|path| known.contains(path)),
+            vec!["app/__init__.py", "app/models.py"]
+        );
+    }
+
+    #[test]
+    fn test_of_files_reads_only_the_given_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write(
+            "src/calc.ts",
+            "import { add } from './math';\nimport { key } from './secret';\n",
+        );
+        write("src/math.ts", "export const add = (a, b) => a + b;\n");
+        write("src/secret.ts", "export const key = 'k';\n");
+        write("src/app.ts", "import { total } from './calc';\n");
+        write(".vibetapignore", "src/secret.ts\n");
+
+        let graph = ImportGraph::of_files(dir.path(), &["./src/calc.ts".to_string()]);
+        assert_eq!(
+            graph.direct_imports(&["src/calc.ts".to_string()]),
+            vec!["src/math.ts"]
+        );
+        // src/app.ts was never read
+        assert!(graph.dependents("src/calc.ts").is_empty());
+    }
+}
@@ -3,10 +3,30 @@
 //! Core functionality for VibeTap including:
 //! - API client for communicating with VibeTap SaaS
 //! - Configuration management
+//! - Record and replay of API traffic
+//! - Redacted debug dumps of generate calls
+//! - Generating directly with the user's own provider key or a local Ollama model
+//! - Local usage budgets
+//! - Reverse-dependency analysis of changed files
+//! - Fitting context files into a size budget
+//! - Splitting large diffs into concurrent generation requests
+//! - Finding the packages of a monorepo
 //! - Diff processing
 
 pub mod api;
+pub mod backend;
+pub mod budget;
 pub mod config;
+pub mod context;
+pub mod deps;
+pub mod dump;
+pub mod ollama;
+pub mod orchestrator;
+pub mod packages;
+pub mod prompt;
+pub mod provider;
+pub mod replay;
 
 pub use api::ApiClient;
-pub use config::{AuthTokens, Config, GlobalConfig};
+pub use backend::GenerateBackend;
+pub use config::{AuthTokens, ByokConfig, Config, GlobalConfig, OllamaConfig};
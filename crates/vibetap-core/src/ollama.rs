@@ -0,0 +1,164 @@
+//! Generating with a model served by a local Ollama instance
+//!
+//! Nothing leaves the machine: the prompt is built locally and sent to
+//! Ollama's chat endpoint, so this works without any network access.
+
+use futures::future::BoxFuture;
+
+use crate::api::{ApiError, GenerateRequest, GenerateResponse};
+use crate::backend::GenerateBackend;
+use crate::config::OllamaConfig;
+use crate::prompt::{parse_response, user_prompt, SYSTEM_PROMPT};
+
+/// Environment variable Ollama's own CLI reads the server address from
+pub const HOST_ENV: &str = "OLLAMA_HOST";
+
+/// Where Ollama listens unless configured otherwise
+pub const DEFAULT_URL: &str = "http://localhost:11434";
+
+/// Model used when none is configured or passed
+pub const DEFAULT_MODEL: &str = "qwen2.5-coder";
+
+/// Room left in the context window for the answer, in tokens
+const OUTPUT_TOKENS: usize = 8192;
+
+/// Largest context window requested, in tokens
+const MAX_CONTEXT_TOKENS: usize = 131_072;
+
+/// Client for an Ollama server
+pub struct OllamaClient {
+    client: reqwest::Client,
+    base_url: String,
+    model: String,
+}
+
+impl OllamaClient {
+    pub fn new(base_url: impl Into<String>, model: impl Into<String>) -> Self {
+        let base_url: String = base_url.into();
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.into(),
+        }
+    }
+
+    /// A client for the configured server and model
+    ///
+    /// The address falls back to `OLLAMA_HOST`, then the default port on
+    /// localhost; `model` overrides the configured model.
+    pub fn from_config(config: Option<&OllamaConfig>, model: Option<String>) -> Self {
+        let url = config
+            .and_then(|c| c.url.clone())
+            .or_else(|| std::env::var(HOST_ENV).ok().filter(|v| !v.is_empty()))
+            .map(|url| {
+                // OLLAMA_HOST is often just host:port
+                if url.contains("://") {
+                    url
+                } else {
+                    format!("http://{}", url)
+                }
+            })
+            .unwrap_or_else(|| DEFAULT_URL.to_string());
+        let model = model
+            .or_else(|| config.and_then(|c| c.model.clone()))
+            .unwrap_or_else(|| DEFAULT_MODEL.to_string());
+        Self::new(url, model)
+    }
+
+    async fn send(&self, request: &GenerateRequest) -> Result<GenerateResponse, ApiError> {
+        let prompt = user_prompt(request);
+        // Ollama silently truncates prompts longer than its small default
+        // context window, so ask for one that fits (about 3 bytes per token)
+        let context_tokens = ((SYSTEM_PROMPT.len() + prompt.len()) / 3 + OUTPUT_TOKENS)
+            .next_power_of_two()
+            .min(MAX_CONTEXT_TOKENS);
+
+        let response = self
+            .client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&serde_json::json!({
+                "model": self.model,
+                "stream": false,
+                "format": "json",
+                "messages": [
+                    { "role": "system", "content": SYSTEM_PROMPT },
+                    { "role": "user", "content": prompt },
+                ],
+                "options": { "num_ctx": context_tokens },
+            }))
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_connect() {
+                    ApiError::Api {
+                        code: "OLLAMA_UNREACHABLE".to_string(),
+                        message: format!(
+                            "Could not reach Ollama at {}. Is 'ollama serve' running?",
+                            self.base_url
+                        ),
+                    }
+                } else {
+                    ApiError::Request(e)
+                }
+            })?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        let parsed: serde_json::Value = serde_json::from_str(&body).unwrap_or_default();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Err(ApiError::Api {
+                code: "MODEL_NOT_FOUND".to_string(),
+                message: format!(
+                    "Ollama has no model '{}'. Run 'ollama pull {}' first.",
+                    self.model, self.model
+                ),
+            });
+        }
+        if !status.is_success() {
+            return Err(ApiError::Api {
+                code: "OLLAMA_ERROR".to_string(),
+                message: format!(
+                    "Ollama returned {}: {}",
+                    status,
+                    parsed["error"]
+                        .as_str()
+                        .map(str::to_string)
+                        .unwrap_or_else(|| body.chars().take(500).collect())
+                ),
+            });
+        }
+
+        let tokens = parsed["prompt_eval_count"].as_u64().unwrap_or(0)
+            + parsed["eval_count"].as_u64().unwrap_or(0);
+        let mut response = parse_response(
+            parsed["message"]["content"].as_str().unwrap_or(""),
+            request,
+            &self.model,
+            tokens as u32,
+        )
+        .map_err(|message| ApiError::Api {
+            code: "BAD_MODEL_OUTPUT".to_string(),
+            message,
+        })?;
+        // No provider key is involved
+        response.used_byok = false;
+        Ok(response)
+    }
+}
+
+impl GenerateBackend for OllamaClient {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    fn model_for(&self, _request: &GenerateRequest) -> String {
+        self.model.clone()
+    }
+
+    fn generate<'a>(
+        &'a self,
+        request: &'a GenerateRequest,
+    ) -> BoxFuture<'a, Result<GenerateResponse, ApiError>> {
+        Box::pin(self.send(request))
+    }
+}
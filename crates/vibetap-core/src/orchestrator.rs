@@ -0,0 +1,283 @@
+//! Generating suggestions for large diffs as several concurrent requests
+//!
+//! Changed files are grouped in path order, so files from the same package
+//! tend to share a chunk, and packed until a chunk's diff text reaches the
+//! limit. Chunks are streamed with bounded concurrency; their events are
+//! reported tagged with the chunk they came from, and their suggestions are
+//! merged back into a single response in chunk order, whatever order the
+//! chunks finished in.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinSet;
+
+use crate::api::{ApiClient, ApiError, DiffHunk, GenerateRequest, GenerateResponse, StreamEvent};
+
+/// Serialized request size above which a diff is split
+pub const CHUNK_THRESHOLD_BYTES: usize = 512 * 1024;
+
+/// Diff text packed into one chunk
+pub const CHUNK_DIFF_BYTES: usize = 128 * 1024;
+
+/// Chunks in flight at once unless configured otherwise
+pub const DEFAULT_CONCURRENCY: usize = 3;
+
+/// Something that happened to one chunk
+#[derive(Debug, Clone)]
+pub enum ChunkEvent {
+    /// An event from the chunk's stream
+    Stream {
+        chunk: usize,
+        event: Box<StreamEvent>,
+    },
+    /// The chunk's request finished, successfully or not
+    Finished { chunk: usize, ok: bool },
+}
+
+/// Group the changed files into chunks of roughly `max_bytes` of diff text each
+///
+/// A single file larger than the limit gets a chunk of its own.
+pub fn plan(hunks: &[DiffHunk], max_bytes: usize) -> Vec<Vec<String>> {
+    let mut files: Vec<(&str, usize)> = Vec::new();
+    for hunk in hunks {
+        match files.iter_mut().find(|(path, _)| *path == hunk.file_path) {
+            Some((_, size)) => *size += hunk.content.len(),
+            None => files.push((&hunk.file_path, hunk.content.len())),
+        }
+    }
+    files.sort_by_key(|(path, _)| *path);
+
+    let mut chunks: Vec<Vec<String>> = Vec::new();
+    let mut current_size = 0;
+    for (path, size) in files {
+        if chunks.is_empty() || current_size + size > max_bytes {
+            chunks.push(Vec::new());
+            current_size = 0;
+        }
+        current_size += size;
+        if let Some(chunk) = chunks.last_mut() {
+            chunk.push(path.to_string());
+        }
+    }
+    chunks
+}
+
+/// Streams chunk requests, at most `concurrency` at a time
+pub struct Orchestrator {
+    client: Arc<ApiClient>,
+    concurrency: usize,
+}
+
+impl Orchestrator {
+    pub fn new(client: Arc<ApiClient>, concurrency: usize) -> Self {
+        Self {
+            client,
+            concurrency: concurrency.max(1),
+        }
+    }
+
+    /// Send every request, calling `on_event` as chunks stream and finish
+    ///
+    /// Results come back in request order.
+    pub async fn run(
+        &self,
+        requests: Vec<GenerateRequest>,
+        mut on_event: impl FnMut(ChunkEvent),
+    ) -> Vec<Result<GenerateResponse, ApiError>> {
+        let semaphore = Arc::new(Semaphore::new(self.concurrency));
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let mut tasks = JoinSet::new();
+        let total = requests.len();
+
+        for (chunk, request) in requests.into_iter().enumerate() {
+            let client = self.client.clone();
+            let semaphore = semaphore.clone();
+            let sender = sender.clone();
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let result = client
+                    .generate_streaming(request, |event| {
+                        let _ = sender.send(ChunkEvent::Stream {
+                            chunk,
+                            event: Box::new(event),
+                        });
+                    })
+                    .await;
+                let _ = sender.send(ChunkEvent::Finished {
+                    chunk,
+                    ok: result.is_ok(),
+                });
+                (chunk, result)
+            });
+        }
+        // Only the tasks hold senders now, so the channel closes once they are done
+        drop(sender);
+
+        while let Some(event) = receiver.recv().await {
+            on_event(event);
+        }
+
+        let mut slots: Vec<Option<Result<GenerateResponse, ApiError>>> =
+            (0..total).map(|_| None).collect();
+        while let Some(joined) = tasks.join_next().await {
+            if let Ok((chunk, result)) = joined {
+                slots[chunk] = Some(result);
+            }
+        }
+
+        slots
+            .into_iter()
+            .map(|slot| {
+                slot.unwrap_or_else(|| {
+                    Err(ApiError::Api {
+                        code: "CHUNK_FAILED".to_string(),
+                        message: "Chunk task did not complete".to_string(),
+                    })
+                })
+            })
+            .collect()
+    }
+}
+
+/// Combine chunk responses into one, keeping suggestion ids unique
+pub fn merge(responses: Vec<GenerateResponse>) -> GenerateResponse {
+    let mut merged = GenerateResponse {
+        suggestions: Vec::new(),
+        summary: String::new(),
+        model_used: String::new(),
+        used_byok: false,
+        tokens_used: 0,
+        warning: None,
+    };
+    let mut summaries = Vec::new();
+    let mut warnings = Vec::new();
+
+    for response in responses {
+        for mut suggestion in response.suggestions {
+            if merged.suggestions.iter().any(|s| s.id == suggestion.id) {
+                suggestion.id = format!("{}-{}", suggestion.id, merged.suggestions.len() + 1);
+            }
+            merged.suggestions.push(suggestion);
+        }
+        if !response.summary.is_empty() {
+            summaries.push(response.summary);
+        }
+        if merged.model_used.is_empty() {
+            merged.model_used = response.model_used;
+        }
+        merged.used_byok |= response.used_byok;
+        merged.tokens_used += response.tokens_used;
+        warnings.extend(response.warning);
+    }
+
+    merged.summary = summaries.join("\n");
+    merged.warning = (!warnings.is_empty()).then(|| warnings.join("\n"));
+    merged
+}
+
+/// Keep the `max` most confident suggestions, in the order they came
+///
+/// Every chunk is asked for up to the limit, so a merged response can hold
+/// several times as many. Ties go to earlier chunks.
+pub fn keep_best(response: &mut GenerateResponse, max: usize) {
+    let suggestions = &mut response.suggestions;
+    if suggestions.len() <= max {
+        return;
+    }
+    let mut ranked: Vec<usize> = (0..suggestions.len()).collect();
+    ranked.sort_by(|&a, &b| {
+        suggestions[b]
+            .confidence
+            .total_cmp(&suggestions[a].confidence)
+    });
+    let keep: HashSet<usize> = ranked.into_iter().take(max).collect();
+    let mut index = 0;
+    suggestions.retain(|_| {
+        index += 1;
+        keep.contains(&(index - 1))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::TestSuggestion;
+
+    fn hunk(path: &str, size: usize) -> DiffHunk {
+        DiffHunk {
+            file_path: path.to_string(),
+            old_start: 1,
+            old_lines: 1,
+            new_start: 1,
+            new_lines: 1,
+            content: "+".repeat(size),
+            context_header: None,
+        }
+    }
+
+    fn response(ids: &[&str], tokens: u32) -> GenerateResponse {
+        GenerateResponse {
+            suggestions: ids
+                .iter()
+                .map(|id| TestSuggestion {
+                    id: id.to_string(),
+                    file_path: format!("{}.test.ts", id),
+                    test_runner: "vitest".to_string(),
+                    code: String::new(),
+                    description: String::new(),
+                    category: "unit".to_string(),
+                    confidence: 0.9,
+                    runtime_estimate: "fast".to_string(),
+                    risks_addressed: Vec::new(),
+                })
+                .collect(),
+            summary: String::new(),
+            model_used: "model".to_string(),
+            used_byok: false,
+            tokens_used: tokens,
+            warning: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_packs_files_in_path_order() {
+        let hunks = vec![
+            hunk("web/b.ts", 100 * 1024),
+            hunk("api/a.ts", 60 * 1024),
+            hunk("api/a.ts", 30 * 1024),
+            hunk("api/c.ts", 20 * 1024),
+            hunk("huge.ts", 300 * 1024),
+        ];
+
+        assert_eq!(
+            plan(&hunks, CHUNK_DIFF_BYTES),
+            vec![
+                vec!["api/a.ts".to_string(), "api/c.ts".to_string()],
+                vec!["huge.ts".to_string()],
+                vec!["web/b.ts".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_chunk_order_and_unique_ids() {
+        let merged = merge(vec![response(&["s1", "s2"], 10), response(&["s1"], 5)]);
+
+        let ids: Vec<&str> = merged.suggestions.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["s1", "s2", "s1-3"]);
+        assert_eq!(merged.tokens_used, 15);
+    }
+
+    #[test]
+    fn test_keep_best_keeps_most_confident_in_order() {
+        let mut merged = merge(vec![response(&["a", "b"], 10), response(&["c", "d"], 5)]);
+        for (suggestion, confidence) in merged.suggestions.iter_mut().zip([0.5, 0.9, 0.7, 0.9]) {
+            suggestion.confidence = confidence;
+        }
+
+        keep_best(&mut merged, 3);
+        let ids: Vec<&str> = merged.suggestions.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "c", "d"]);
+    }
+}
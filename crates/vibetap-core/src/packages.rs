@@ -0,0 +1,320 @@
+//! Packages of a monorepo
+//!
+//! Reads the workspace manifests at the repository root (`pnpm-workspace.yaml`,
+//! the `[workspace]` table of `Cargo.toml`, and `go.work`) and expands their
+//! member patterns to package directories. A directory only counts as a
+//! package when it has its own manifest (`package.json`, `Cargo.toml` or
+//! `go.mod`), so a pattern like `packages/*` skips stray folders.
+
+use std::path::Path;
+
+/// Directories never searched when expanding `**`
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "vendor", "dist", "build"];
+
+/// One package of a monorepo
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+    /// Directory relative to the repository root, using `/` separators
+    pub root: String,
+    /// The workspace that lists it: "pnpm", "cargo" or "go"
+    pub workspace: &'static str,
+}
+
+/// Every package listed by the workspace manifests under `repo_root`, sorted by path
+pub fn detect(repo_root: &Path) -> Vec<Package> {
+    let manifests = [
+        (
+            "pnpm",
+            "pnpm-workspace.yaml",
+            "package.json",
+            pnpm_patterns as fn(&str) -> Vec<String>,
+        ),
+        ("cargo", "Cargo.toml", "Cargo.toml", cargo_patterns),
+        ("go", "go.work", "go.mod", go_work_dirs),
+    ];
+
+    let mut packages: Vec<Package> = Vec::new();
+    for (workspace, file, manifest, parse) in manifests {
+        let Ok(content) = std::fs::read_to_string(repo_root.join(file)) else {
+            continue;
+        };
+        let patterns = parse(&content);
+        let (excluded, included): (Vec<&String>, Vec<&String>) =
+            patterns.iter().partition(|p| p.starts_with('!'));
+
+        let mut dirs = Vec::new();
+        for pattern in included {
+            expand(repo_root, &normalize(pattern), &mut dirs);
+        }
+        for dir in dirs {
+            let excluded = excluded
+                .iter()
+                .any(|p| matches_pattern(&normalize(&p[1..]), &dir));
+            if dir.is_empty()
+                || excluded
+                || !repo_root.join(&dir).join(manifest).is_file()
+                || packages.iter().any(|p| p.root == dir)
+            {
+                continue;
+            }
+            packages.push(Package {
+                root: dir,
+                workspace,
+            });
+        }
+    }
+    packages.sort_by(|a, b| a.root.cmp(&b.root));
+    packages
+}
+
+/// The innermost package containing `path` (relative to the repository root)
+pub fn owner<'a>(packages: &'a [Package], path: &str) -> Option<&'a Package> {
+    packages
+        .iter()
+        .filter(|p| {
+            path.strip_prefix(&p.root)
+                .is_some_and(|rest| rest.starts_with('/'))
+        })
+        .max_by_key(|p| p.root.len())
+}
+
+/// The `packages:` list of `pnpm-workspace.yaml`
+fn pnpm_patterns(content: &str) -> Vec<String> {
+    let mut patterns = Vec::new();
+    let mut in_packages = false;
+    for line in content.lines() {
+        let line = line.split(" #").next().unwrap_or("");
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with([' ', '\t', '-']) {
+            in_packages = trimmed == "packages:";
+            continue;
+        }
+        if let Some(item) = trimmed.strip_prefix('-').filter(|_| in_packages) {
+            patterns.push(unquote(item.trim()));
+        }
+    }
+    patterns
+}
+
+/// `workspace.members` of `Cargo.toml`, followed by `workspace.exclude` marked with `!`
+fn cargo_patterns(content: &str) -> Vec<String> {
+    let Ok(manifest) = content.parse::<toml::Table>() else {
+        return Vec::new();
+    };
+    let Some(workspace) = manifest.get("workspace").and_then(|w| w.as_table()) else {
+        return Vec::new();
+    };
+    let list = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(|v| v.as_array())
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|i| i.as_str())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let mut patterns = list("members");
+    patterns.extend(list("exclude").into_iter().map(|p| format!("!{}", p)));
+    patterns
+}
+
+/// The `use` directives of `go.work`, in either the single-line or block form
+fn go_work_dirs(content: &str) -> Vec<String> {
+    let mut dirs = Vec::new();
+    let mut in_block = false;
+    for line in content.lines() {
+        let line = line.split("//").next().unwrap_or("").trim();
+        if in_block {
+            if line == ")" {
+                in_block = false;
+            } else if !line.is_empty() {
+                dirs.push(unquote(line));
+            }
+        } else if let Some(rest) = line
+            .strip_prefix("use")
+            .filter(|rest| rest.starts_with([' ', '\t', '(']))
+        {
+            match rest.trim() {
+                "(" => in_block = true,
+                "" => {}
+                dir => dirs.push(unquote(dir)),
+            }
+        }
+    }
+    dirs
+}
+
+fn unquote(value: &str) -> String {
+    value.trim_matches(|c| c == '"' || c == '\'').to_string()
+}
+
+/// Drop `./` and trailing slashes, so patterns compare with package roots
+fn normalize(pattern: &str) -> String {
+    let pattern = pattern.trim().trim_end_matches('/');
+    let pattern = pattern.strip_prefix("./").unwrap_or(pattern);
+    if pattern == "." {
+        String::new()
+    } else {
+        pattern.to_string()
+    }
+}
+
+/// Add the directories under `root` matching `pattern` to `found`
+fn expand(root: &Path, pattern: &str, found: &mut Vec<String>) {
+    let parts: Vec<&str> = pattern.split('/').filter(|p| !p.is_empty()).collect();
+    expand_from(root, String::new(), &parts, found);
+}
+
+fn expand_from(root: &Path, dir: String, parts: &[&str], found: &mut Vec<String>) {
+    let Some((part, rest)) = parts.split_first() else {
+        found.push(dir);
+        return;
+    };
+    let join = |name: &str| {
+        if dir.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", dir, name)
+        }
+    };
+
+    if !part.contains(['*', '?']) {
+        let next = join(part);
+        if root.join(&next).is_dir() {
+            expand_from(root, next, rest, found);
+        }
+        return;
+    }
+    if *part == "**" {
+        // Zero directories, or one more and still inside the `**`
+        expand_from(root, dir.clone(), rest, found);
+    }
+    for name in subdirectories(&root.join(&dir)) {
+        if *part == "**" {
+            expand_from(root, join(&name), parts, found);
+        } else if wildcard(part, &name) {
+            expand_from(root, join(&name), rest, found);
+        }
+    }
+}
+
+fn subdirectories(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .filter_map(|e| e.file_name().to_str().map(str::to_string))
+        .filter(|name| !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_str()))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Whether the relative directory `dir` matches a member pattern
+fn matches_pattern(pattern: &str, dir: &str) -> bool {
+    fn matches(parts: &[&str], dirs: &[&str]) -> bool {
+        match (parts.split_first(), dirs.split_first()) {
+            (None, None) => true,
+            (Some((&"**", rest)), _) => {
+                matches(rest, dirs) || (!dirs.is_empty() && matches(parts, &dirs[1..]))
+            }
+            (Some((part, rest)), Some((name, names))) => {
+                wildcard(part, name) && matches(rest, names)
+            }
+            _ => false,
+        }
+    }
+    let parts: Vec<&str> = pattern.split('/').filter(|p| !p.is_empty()).collect();
+    let dirs: Vec<&str> = dir.split('/').filter(|d| !d.is_empty()).collect();
+    matches(&parts, &dirs)
+}
+
+/// Match one path component against a pattern with `*` and `?`
+fn wildcard(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // Position after the last `*`, and where in `name` it resumes matching
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((after, from)) => {
+                    p = after;
+                    n = from + 1;
+                    star = Some((after, from + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_workspace_manifests() {
+        let pnpm = "packages:\n  - 'packages/*'\n  - \"apps/**\" # apps\n  - '!**/test/**'\nonlyBuiltDependencies:\n  - esbuild\n";
+        assert_eq!(
+            pnpm_patterns(pnpm),
+            vec!["packages/*", "apps/**", "!**/test/**"]
+        );
+
+        let cargo =
+            "[workspace]\nmembers = [\"crates/*\", \"tools/gen\"]\nexclude = [\"crates/old\"]\n";
+        assert_eq!(
+            cargo_patterns(cargo),
+            vec!["crates/*", "tools/gen", "!crates/old"]
+        );
+
+        let go = "go 1.22\n\nuse (\n\t./api // service\n\t./worker\n)\nuse ./cli\n";
+        assert_eq!(go_work_dirs(go), vec!["./api", "./worker", "./cli"]);
+    }
+
+    #[test]
+    fn test_owner_and_patterns() {
+        let packages = vec![
+            Package {
+                root: "packages/ui".to_string(),
+                workspace: "pnpm",
+            },
+            Package {
+                root: "packages/ui/icons".to_string(),
+                workspace: "pnpm",
+            },
+        ];
+        assert_eq!(
+            owner(&packages, "packages/ui/icons/src/a.ts").map(|p| p.root.as_str()),
+            Some("packages/ui/icons")
+        );
+        assert_eq!(
+            owner(&packages, "packages/ui/src/b.ts").map(|p| p.root.as_str()),
+            Some("packages/ui")
+        );
+        assert!(owner(&packages, "packages/uikit/c.ts").is_none());
+
+        assert!(matches_pattern("**/test/**", "apps/web/test"));
+        assert!(matches_pattern("crates/*-cli", "crates/vibetap-cli"));
+        assert!(!matches_pattern("crates/*", "crates/a/b"));
+    }
+}
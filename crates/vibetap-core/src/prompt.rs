@@ -0,0 +1,232 @@
+//! Building the diff-to-test prompt locally
+//!
+//! Used when suggestions are generated by a model the user talks to
+//! directly instead of through the VibeTap API. The model is asked for a
+//! single JSON object, which is turned back into the same suggestions the
+//! API would have returned.
+
+use serde::Deserialize;
+use std::fmt::Write;
+
+use crate::api::{GenerateRequest, GenerateResponse, TestSuggestion};
+
+/// Start of the IDs given to suggestions generated without the VibeTap API
+pub const LOCAL_ID_PREFIX: &str = "local_";
+
+/// Instructions sent as the system prompt
+pub const SYSTEM_PROMPT: &str = "You are a senior engineer who writes focused, runnable tests for code changes. \
+You are given a diff, the files it touches, and project conventions. Suggest tests that would catch \
+regressions in the changed behavior. Only use imports and helpers that exist in the given files. \
+Answer with a single JSON object and nothing else, shaped like: \
+{\"summary\": string, \"suggestions\": [{\"filePath\": string, \"code\": string, \"description\": string, \
+\"category\": \"unit\" | \"integration\" | \"security\" | \"edge-case\", \"confidence\": number between 0 and 1, \
+\"runtimeEstimate\": \"fast\" | \"medium\" | \"slow\", \"risksAddressed\": [string]}]}. \
+`code` is the complete contents of the test file at `filePath`.";
+
+/// The user prompt describing the change and what to generate
+pub fn user_prompt(request: &GenerateRequest) -> String {
+    let options = &request.options;
+    let mut prompt = String::new();
+
+    let _ = writeln!(prompt, "Test runner: {}", options.test_runner);
+    let _ = writeln!(prompt, "Suggest at most {} test file(s).", options.max_suggestions);
+    if options.include_security {
+        let _ = writeln!(
+            prompt,
+            "Prioritize security tests: injection, authorization, secrets, unsafe input."
+        );
+    }
+    if options.include_negative_paths {
+        let _ = writeln!(prompt, "Cover error handling and invalid input, not just the happy path.");
+    }
+    if let Some(ref path) = options.test_file {
+        let _ = writeln!(prompt, "Write the tests to {}.", path);
+    }
+
+    if let Some(ref instructions) = request.project_instructions {
+        let _ = write!(prompt, "\n## Project conventions\n\n{}\n", instructions.trim_end());
+    }
+    if let Some(ref messages) = request.commit_messages {
+        let _ = write!(prompt, "\n## Recent commit messages\n\n");
+        for message in messages {
+            let _ = writeln!(prompt, "- {}", message.lines().next().unwrap_or(""));
+        }
+    }
+
+    let _ = write!(prompt, "\n## Diff\n\n");
+    for hunk in &request.diff.hunks {
+        let _ = writeln!(
+            prompt,
+            "--- {path}\n+++ {path}\n@@ -{},{} +{},{} @@{}",
+            hunk.old_start,
+            hunk.old_lines,
+            hunk.new_start,
+            hunk.new_lines,
+            hunk.context_header
+                .as_ref()
+                .map(|h| format!(" {}", h))
+                .unwrap_or_default(),
+            path = hunk.file_path,
+        );
+        let _ = write!(prompt, "{}", hunk.content);
+        if !hunk.content.ends_with('\n') {
+            prompt.push('\n');
+        }
+    }
+
+    if !request.context.is_empty() {
+        let _ = write!(prompt, "\n## Files\n");
+        for file in &request.context {
+            let location = match file.start_line {
+                Some(line) => format!("{} (from line {})", file.path, line),
+                None => file.path.clone(),
+            };
+            let _ = write!(prompt, "\n### {}\n\n```\n{}", location, file.content);
+            if !file.content.ends_with('\n') {
+                prompt.push('\n');
+            }
+            prompt.push_str("```\n");
+        }
+    }
+
+    if let Some(ref refine) = request.refine {
+        let _ = write!(
+            prompt,
+            "\n## Rework this earlier suggestion\n\nFile: {}\n\n```\n{}\n```\n\nChange it as follows: {}\n\
+             Return exactly one suggestion.\n",
+            refine.previous.file_path,
+            refine.previous.code.trim_end(),
+            refine.instruction
+        );
+    }
+
+    prompt
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelOutput {
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    suggestions: Vec<ModelSuggestion>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelSuggestion {
+    file_path: String,
+    code: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    confidence: Option<f64>,
+    #[serde(default)]
+    runtime_estimate: Option<String>,
+    #[serde(default)]
+    risks_addressed: Vec<String>,
+}
+
+/// Turn the model's answer into a response shaped like the API's
+///
+/// Tolerates prose or code fences around the JSON object.
+pub fn parse_response(
+    text: &str,
+    request: &GenerateRequest,
+    model_used: &str,
+    tokens_used: u32,
+) -> Result<GenerateResponse, String> {
+    let start = text.find('{').ok_or("the model did not answer with JSON")?;
+    let end = text.rfind('}').ok_or("the model did not answer with JSON")?;
+    let output: ModelOutput = serde_json::from_str(&text[start..=end])
+        .map_err(|e| format!("the model's JSON did not match the expected shape: {}", e))?;
+
+    let suggestions = output
+        .suggestions
+        .into_iter()
+        .filter(|s| !s.code.trim().is_empty())
+        .take(request.options.max_suggestions as usize)
+        .enumerate()
+        .map(|(i, s)| TestSuggestion {
+            id: format!("{}{}", LOCAL_ID_PREFIX, i + 1),
+            file_path: s.file_path,
+            test_runner: request.options.test_runner.clone(),
+            code: s.code,
+            description: s.description,
+            category: s.category.unwrap_or_else(|| "unit".to_string()),
+            confidence: s.confidence.unwrap_or(0.5).clamp(0.0, 1.0),
+            runtime_estimate: s.runtime_estimate.unwrap_or_else(|| "fast".to_string()),
+            risks_addressed: s.risks_addressed,
+        })
+        .collect();
+
+    Ok(GenerateResponse {
+        suggestions,
+        summary: output.summary,
+        model_used: model_used.to_string(),
+        used_byok: true,
+        tokens_used,
+        warning: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{DiffHunk, DiffPayload, GenerateOptions};
+
+    fn request() -> GenerateRequest {
+        GenerateRequest {
+            diff: DiffPayload {
+                hunks: vec![DiffHunk {
+                    file_path: "src/add.ts".to_string(),
+                    old_start: 1,
+                    old_lines: 1,
+                    new_start: 1,
+                    new_lines: 2,
+                    content: " export const a = 1;\n+export const add = (x, y) => x + y;\n".to_string(),
+                    context_header: None,
+                }],
+                base_branch: None,
+                head_commit: None,
+            },
+            context: Vec::new(),
+            options: GenerateOptions {
+                test_runner: "vitest".to_string(),
+                max_suggestions: 1,
+                include_security: false,
+                include_negative_paths: true,
+                model_tier: "default".to_string(),
+                test_file: None,
+            },
+            policy_pack_id: None,
+            repo_identifier: None,
+            commit_messages: None,
+            project_instructions: None,
+            refine: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_response_from_fenced_json() {
+        let request = request();
+        assert!(user_prompt(&request).contains("+++ src/add.ts\n@@ -1,1 +1,2 @@\n"));
+
+        let text = "Here you go:\n```json\n{\"summary\": \"Adds\", \"suggestions\": [\
+            {\"filePath\": \"src/add.test.ts\", \"code\": \"it('adds', () => {});\", \"confidence\": 1.4},\
+            {\"filePath\": \"src/other.test.ts\", \"code\": \"it('x', () => {});\"}]}\n```";
+        let response = parse_response(text, &request, "gpt-4o", 42).unwrap();
+
+        assert_eq!(response.suggestions.len(), 1);
+        let suggestion = &response.suggestions[0];
+        assert_eq!(suggestion.id, "local_1");
+        assert_eq!(suggestion.test_runner, "vitest");
+        assert_eq!(suggestion.category, "unit");
+        assert_eq!(suggestion.confidence, 1.0);
+        assert!(response.used_byok);
+
+        assert!(parse_response("no json here", &request, "m", 0).is_err());
+    }
+}
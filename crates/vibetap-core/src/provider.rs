@@ -0,0 +1,215 @@
+//! Generating directly with the user's own OpenAI or Anthropic key
+//!
+//! The prompt is built locally (see [`crate::prompt`]) and sent straight to
+//! the provider, so the diff never passes through the VibeTap API.
+
+use futures::future::BoxFuture;
+
+use crate::api::{ApiError, GenerateRequest, GenerateResponse};
+use crate::backend::GenerateBackend;
+use crate::config::ByokConfig;
+use crate::prompt::{parse_response, user_prompt, SYSTEM_PROMPT};
+
+/// Providers a key can be configured for
+pub const PROVIDERS: [&str; 2] = ["openai", "anthropic"];
+
+/// Longest answer requested from the model, in tokens
+const MAX_OUTPUT_TOKENS: u32 = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    OpenAi,
+    Anthropic,
+}
+
+impl Provider {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "openai" => Some(Self::OpenAi),
+            "anthropic" => Some(Self::Anthropic),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::OpenAi => "openai",
+            Self::Anthropic => "anthropic",
+        }
+    }
+
+    /// Environment variable the provider's own tools read the key from
+    pub fn key_env(&self) -> &'static str {
+        match self {
+            Self::OpenAi => "OPENAI_API_KEY",
+            Self::Anthropic => "ANTHROPIC_API_KEY",
+        }
+    }
+
+    /// Model used for a tier when none is configured
+    pub fn default_model(&self, tier: &str) -> &'static str {
+        match (self, tier) {
+            (Self::OpenAi, "cheap") => "gpt-4o-mini",
+            (Self::OpenAi, _) => "gpt-4o",
+            (Self::Anthropic, "cheap") => "claude-3-5-haiku-latest",
+            (Self::Anthropic, "enhanced") => "claude-opus-4-0",
+            (Self::Anthropic, _) => "claude-sonnet-4-0",
+        }
+    }
+
+    fn default_base_url(&self) -> &'static str {
+        match self {
+            Self::OpenAi => "https://api.openai.com/v1",
+            Self::Anthropic => "https://api.anthropic.com/v1",
+        }
+    }
+}
+
+/// Client for a model provider's API
+pub struct ProviderClient {
+    client: reqwest::Client,
+    provider: Provider,
+    api_key: String,
+    model: Option<String>,
+    base_url: String,
+}
+
+impl ProviderClient {
+    pub fn from_config(config: &ByokConfig) -> Result<Self, ApiError> {
+        let provider = Provider::parse(&config.provider).ok_or_else(|| ApiError::Api {
+            code: "UNKNOWN_PROVIDER".to_string(),
+            message: format!(
+                "Unknown provider '{}' (expected one of: {})",
+                config.provider,
+                PROVIDERS.join(", ")
+            ),
+        })?;
+        Ok(Self {
+            client: reqwest::Client::new(),
+            provider,
+            api_key: config.api_key.clone(),
+            model: config.model.clone(),
+            base_url: config
+                .base_url
+                .clone()
+                .unwrap_or_else(|| provider.default_base_url().to_string())
+                .trim_end_matches('/')
+                .to_string(),
+        })
+    }
+
+    /// Use `model` instead of the configured one, if given
+    pub fn with_model(mut self, model: Option<String>) -> Self {
+        if model.is_some() {
+            self.model = model;
+        }
+        self
+    }
+
+    pub fn provider(&self) -> Provider {
+        self.provider
+    }
+
+    async fn send(&self, request: &GenerateRequest) -> Result<GenerateResponse, ApiError> {
+        let model = self.model_for(request);
+        let prompt = user_prompt(request);
+
+        let builder = match self.provider {
+            Provider::OpenAi => self
+                .client
+                .post(format!("{}/chat/completions", self.base_url))
+                .bearer_auth(&self.api_key)
+                .json(&serde_json::json!({
+                    "model": model,
+                    "max_tokens": MAX_OUTPUT_TOKENS,
+                    "response_format": { "type": "json_object" },
+                    "messages": [
+                        { "role": "system", "content": SYSTEM_PROMPT },
+                        { "role": "user", "content": prompt },
+                    ],
+                })),
+            Provider::Anthropic => self
+                .client
+                .post(format!("{}/messages", self.base_url))
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", "2023-06-01")
+                .json(&serde_json::json!({
+                    "model": model,
+                    "max_tokens": MAX_OUTPUT_TOKENS,
+                    "system": SYSTEM_PROMPT,
+                    "messages": [{ "role": "user", "content": prompt }],
+                })),
+        };
+
+        let response = builder.send().await?;
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            return Err(ApiError::Unauthorized);
+        }
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = response
+                .headers()
+                .get("Retry-After")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(60);
+            return Err(ApiError::RateLimited { retry_after });
+        }
+
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(ApiError::Api {
+                code: "PROVIDER_ERROR".to_string(),
+                message: format!(
+                    "{} returned {}: {}",
+                    self.provider.as_str(),
+                    status,
+                    body.chars().take(500).collect::<String>()
+                ),
+            });
+        }
+
+        let parsed: serde_json::Value = serde_json::from_str(&body).map_err(|e| ApiError::Api {
+            code: "PARSE_ERROR".to_string(),
+            message: format!("Failed to parse {} response: {}", self.provider.as_str(), e),
+        })?;
+        let (text, tokens) = match self.provider {
+            Provider::OpenAi => (
+                parsed["choices"][0]["message"]["content"].as_str(),
+                parsed["usage"]["total_tokens"].as_u64().unwrap_or(0),
+            ),
+            Provider::Anthropic => (
+                parsed["content"][0]["text"].as_str(),
+                parsed["usage"]["input_tokens"].as_u64().unwrap_or(0)
+                    + parsed["usage"]["output_tokens"].as_u64().unwrap_or(0),
+            ),
+        };
+
+        parse_response(text.unwrap_or(""), request, &model, tokens as u32).map_err(|message| {
+            ApiError::Api {
+                code: "BAD_MODEL_OUTPUT".to_string(),
+                message,
+            }
+        })
+    }
+}
+
+impl GenerateBackend for ProviderClient {
+    fn name(&self) -> &str {
+        self.provider.as_str()
+    }
+
+    /// The configured model, or the provider's default for the request's tier
+    fn model_for(&self, request: &GenerateRequest) -> String {
+        self.model
+            .clone()
+            .unwrap_or_else(|| self.provider.default_model(&request.options.model_tier).to_string())
+    }
+
+    fn generate<'a>(
+        &'a self,
+        request: &'a GenerateRequest,
+    ) -> BoxFuture<'a, Result<GenerateResponse, ApiError>> {
+        Box::pin(self.send(request))
+    }
+}
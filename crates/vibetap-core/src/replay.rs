@@ -0,0 +1,166 @@
+//! Record and replay of API traffic
+//!
+//! Set `VIBETAP_RECORD=path` to append every API response (status, headers
+//! that matter, and body chunks exactly as received, including SSE streams)
+//! to a JSON Lines file. Set `VIBETAP_REPLAY=path` to serve responses from
+//! such a file instead of the network, in order, matched by method and path.
+//!
+//! Request bodies and credentials are never written to recordings.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::api::ApiError;
+
+/// Environment variable naming a file to record responses into
+pub const RECORD_ENV: &str = "VIBETAP_RECORD";
+
+/// Environment variable naming a recording to replay responses from
+pub const REPLAY_ENV: &str = "VIBETAP_REPLAY";
+
+/// Path of the active replay file, if replay mode is on
+pub fn replay_path() -> Option<PathBuf> {
+    std::env::var_os(REPLAY_ENV)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Path of the active record file, if record mode is on
+pub fn record_path() -> Option<PathBuf> {
+    std::env::var_os(RECORD_ENV)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+/// One recorded request/response pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Exchange {
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<String>,
+    /// Body as received, one entry per network chunk
+    pub chunks: Vec<String>,
+}
+
+/// Where API responses come from
+pub(crate) enum Transport {
+    Live { record_to: Option<PathBuf> },
+    Replay { source: PathBuf, exchanges: Mutex<VecDeque<Exchange>> },
+}
+
+impl Transport {
+    /// Pick live, record, or replay mode from the environment
+    pub(crate) fn from_env() -> Self {
+        if let Some(source) = replay_path() {
+            let exchanges = match load_recording(&source) {
+                Ok(e) => e,
+                Err(e) => {
+                    tracing::warn!("Could not load replay file {}: {}", source.display(), e);
+                    VecDeque::new()
+                }
+            };
+            return Transport::Replay {
+                source,
+                exchanges: Mutex::new(exchanges),
+            };
+        }
+
+        Transport::Live {
+            record_to: record_path(),
+        }
+    }
+
+    /// Take the next recorded exchange for a request
+    pub(crate) fn next_replayed(&self, method: &str, path: &str) -> Option<Result<Exchange, ApiError>> {
+        let Transport::Replay { source, exchanges } = self else {
+            return None;
+        };
+
+        let mut exchanges = exchanges.lock().unwrap_or_else(|e| e.into_inner());
+        let found = exchanges
+            .iter()
+            .position(|e| e.method == method && e.path == path)
+            .and_then(|i| exchanges.remove(i));
+
+        Some(found.ok_or_else(|| {
+            ApiError::Replay(format!(
+                "no recorded response for {} {} in {}",
+                method,
+                path,
+                source.display()
+            ))
+        }))
+    }
+}
+
+/// Read a JSON Lines recording
+pub fn load_recording(path: &Path) -> std::io::Result<VecDeque<Exchange>> {
+    let content = std::fs::read_to_string(path)?;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        })
+        .collect()
+}
+
+/// Append one exchange to a recording
+pub(crate) fn append_exchange(path: &Path, exchange: &Exchange) {
+    let result = (|| -> std::io::Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        let line = serde_json::to_string(exchange)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        writeln!(file, "{}", line)
+    })();
+
+    if let Err(e) = result {
+        tracing::warn!("Could not write recording {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exchange(path: &str, body: &str) -> Exchange {
+        Exchange {
+            method: "GET".to_string(),
+            path: path.to_string(),
+            status: 200,
+            retry_after: None,
+            chunks: vec![body.to_string()],
+        }
+    }
+
+    #[test]
+    fn test_replay_matches_in_order() {
+        let transport = Transport::Replay {
+            source: PathBuf::from("rec.jsonl"),
+            exchanges: Mutex::new(VecDeque::from(vec![
+                exchange("/api/v1/stats", "first"),
+                exchange("/api/v1/usage", "usage"),
+                exchange("/api/v1/stats", "second"),
+            ])),
+        };
+
+        let next = |path| transport.next_replayed("GET", path).unwrap().unwrap().chunks;
+        assert_eq!(next("/api/v1/stats"), vec!["first"]);
+        assert_eq!(next("/api/v1/stats"), vec!["second"]);
+        assert_eq!(next("/api/v1/usage"), vec!["usage"]);
+        assert!(transport.next_replayed("GET", "/api/v1/stats").unwrap().is_err());
+    }
+}